@@ -0,0 +1,195 @@
+//Measures the simulation throughput of the GPU and the CPU backends for every board size and several click rule radii.
+//Run with "cargo bench --bench throughput". The results are written to the JSON file from STAFRA_BENCH_OUTPUT
+//(benchmark_results.json by default). STAFRA_BENCH_TIME_MS sets the measuring time for each case (500 ms by default)
+
+use
+{
+    std::time::{Duration, Instant},
+    std::fmt::Write
+};
+
+use stafra::{app_state, stafra_state, stafra_cpu_state};
+use stafra::simulation_backend::SimulationBackend;
+
+//Radius 1 would be the center cell alone, so the smallest one is the default cross
+const CLICK_RULE_RADII: [u32; 4] = [2, 4, 8, 16];
+
+//Corresponds to 32767x32767
+const MAX_SIZE_INDEX: u32 = 14;
+
+//The number of generations submitted before waiting for them to finish
+const GENERATIONS_PER_BATCH: u32 = 16;
+
+struct BenchmarkResult
+{
+    backend:           &'static str,
+    board_size:        u32,
+    click_rule_radius: u32,
+    generations:       u32,
+    seconds:           f64
+}
+
+struct SkippedCase
+{
+    backend:    &'static str,
+    board_size: u32,
+    reason:     String
+}
+
+fn main()
+{
+    env_logger::init();
+
+    let output_path = std::env::var("STAFRA_BENCH_OUTPUT").unwrap_or("benchmark_results.json".to_string());
+    let time_budget = Duration::from_millis(std::env::var("STAFRA_BENCH_TIME_MS").ok().and_then(|value| value.parse::<u64>().ok()).unwrap_or(500));
+
+    let initial_size = app_state::AppState::board_size_from_index(0);
+
+    let mut backends: Vec<(&'static str, Box<dyn SimulationBackend>)> = Vec::new();
+    match futures::executor::block_on(stafra_state::StafraState::new_headless(initial_size, initial_size))
+    {
+        Ok(gpu_state) => backends.push(("gpu", Box::new(gpu_state))),
+        Err(message)  => println!("Skipping the GPU backend: {}", message)
+    }
+
+    backends.push(("cpu", Box::new(stafra_cpu_state::StafraCpuState::new(initial_size, initial_size))));
+
+    let mut results       = Vec::new();
+    let mut skipped_cases = Vec::new();
+    for (backend_name, backend) in &mut backends
+    {
+        //Only the simulation itself is measured, without saving the history
+        backend.set_history_depth(0, u32::MAX);
+
+        for size_index in 0..=MAX_SIZE_INDEX
+        {
+            let board_size = app_state::AppState::board_size_from_index(size_index);
+            if let Err(reason) = backend.resize_board(board_size, board_size)
+            {
+                println!("{:>4} {:>6}x{:<6} skipped: {}", backend_name, board_size, board_size, reason);
+                skipped_cases.push(SkippedCase{backend: backend_name, board_size, reason});
+                continue;
+            }
+
+            for click_rule_radius in CLICK_RULE_RADII
+            {
+                backend.reset_click_rule(&cross_click_rule(click_rule_radius));
+                backend.reset_board_standard(stafra_state::StandardResetBoardType::Corners);
+
+                let (generations, seconds) = measure_generations(backend.as_mut(), time_budget);
+                let result = BenchmarkResult{backend: backend_name, board_size, click_rule_radius, generations, seconds};
+
+                println!("{:>4} {:>6}x{:<6} radius {:>2}: {:>12.2} generations/s {:>16.0} cells/s", backend_name, board_size, board_size, click_rule_radius, generations_per_second(&result), cells_per_second(&result));
+                results.push(result);
+            }
+        }
+    }
+
+    let results_json = results_to_json(&results, &skipped_cases, time_budget);
+    match std::fs::write(&output_path, results_json)
+    {
+        Ok(_)      => println!("Results are written to {}", output_path),
+        Err(error) => println!("Cannot write the results to {}: {}", output_path, error)
+    }
+}
+
+//A cross with arms of radius - 1 cells. The click rule radius is the largest cell offset + 1, the same as in encode_click_rule_base64
+fn cross_click_rule(radius: u32) -> [u8; 32 * 32]
+{
+    let click_rule_size   = 32;
+    let click_rule_center = (click_rule_size - 1) / 2;
+
+    let mut click_rule_data = [0u8; 32 * 32];
+    for arm_offset in 0..radius as usize
+    {
+        click_rule_data[click_rule_center * click_rule_size + click_rule_center - arm_offset] = 1;
+        click_rule_data[click_rule_center * click_rule_size + click_rule_center + arm_offset] = 1;
+        click_rule_data[(click_rule_center - arm_offset) * click_rule_size + click_rule_center] = 1;
+        click_rule_data[(click_rule_center + arm_offset) * click_rule_size + click_rule_center] = 1;
+    }
+
+    click_rule_data
+}
+
+//Runs whole batches of generations until the time budget is spent. Returns the number of generations and the time they took
+fn measure_generations(backend: &mut dyn SimulationBackend, time_budget: Duration) -> (u32, f64)
+{
+    //Warm up to exclude the pipeline and memory setup
+    backend.update();
+    backend.wait_for_completion();
+
+    let start_time = Instant::now();
+
+    let mut generations = 0;
+    while start_time.elapsed() < time_budget
+    {
+        for _ in 0..GENERATIONS_PER_BATCH
+        {
+            backend.update();
+        }
+
+        backend.wait_for_completion();
+        generations += GENERATIONS_PER_BATCH;
+    }
+
+    (generations, start_time.elapsed().as_secs_f64())
+}
+
+fn generations_per_second(result: &BenchmarkResult) -> f64
+{
+    result.generations as f64 / result.seconds
+}
+
+fn cells_per_second(result: &BenchmarkResult) -> f64
+{
+    generations_per_second(result) * (result.board_size as f64) * (result.board_size as f64)
+}
+
+//The skip reasons are error messages and can contain newlines, which JSON only allows escaped
+fn escape_json(string: &str) -> String
+{
+    let mut escaped = String::with_capacity(string.len());
+    for character in string.chars()
+    {
+        match character
+        {
+            '\\'                    => escaped.push_str("\\\\"),
+            '"'                     => escaped.push_str("\\\""),
+            '\u{0000}'..='\u{001f}' => write!(escaped, "\\u{:04x}", character as u32).unwrap(),
+            _                       => escaped.push(character)
+        }
+    }
+
+    escaped
+}
+
+fn results_to_json(results: &[BenchmarkResult], skipped_cases: &[SkippedCase], time_budget: Duration) -> String
+{
+    let mut json = String::new();
+
+    writeln!(json, "{{").unwrap();
+    writeln!(json, "    \"version\": \"{}\",", env!("CARGO_PKG_VERSION")).unwrap();
+    writeln!(json, "    \"time_budget_ms\": {},", time_budget.as_millis()).unwrap();
+
+    writeln!(json, "    \"results\":").unwrap();
+    writeln!(json, "    [").unwrap();
+    for (result_index, result) in results.iter().enumerate()
+    {
+        let separator = if result_index + 1 < results.len() {","} else {""};
+        writeln!(json, "        {{\"backend\": \"{}\", \"board_size\": {}, \"click_rule_radius\": {}, \"generations\": {}, \"seconds\": {:.6}, \"generations_per_second\": {:.3}, \"cells_per_second\": {:.0}}}{}",
+                 result.backend, result.board_size, result.click_rule_radius, result.generations, result.seconds, generations_per_second(result), cells_per_second(result), separator).unwrap();
+    }
+    writeln!(json, "    ],").unwrap();
+
+    writeln!(json, "    \"skipped\":").unwrap();
+    writeln!(json, "    [").unwrap();
+    for (skipped_index, skipped_case) in skipped_cases.iter().enumerate()
+    {
+        let separator = if skipped_index + 1 < skipped_cases.len() {","} else {""};
+        writeln!(json, "        {{\"backend\": \"{}\", \"board_size\": {}, \"reason\": \"{}\"}}{}", skipped_case.backend, skipped_case.board_size, escape_json(&skipped_case.reason), separator).unwrap();
+    }
+    writeln!(json, "    ]").unwrap();
+
+    writeln!(json, "}}").unwrap();
+    json
+}
@@ -23,15 +23,15 @@ pub struct AppState
 }
 
 //Returns the position in string "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-+"
-fn decode_base_64_char(ch: char) -> u8
+pub fn decode_base_64_char(ch: char) -> u8
 {
     match ch
     {
         'A'..='Z' =>  0 + (ch as u8 - 'A' as u8),
         'a'..='z' => 26 + (ch as u8 - 'a' as u8),
         '0'..='9' => 52 + (ch as u8 - '0' as u8),
-        '+'       => 62,
-        '-'       => 63,
+        '-'       => 62,
+        '+'       => 63,
         _         => 0
     }
 }
@@ -80,6 +80,68 @@ pub fn parse_click_rule_base64(base64_click_rule: &str) -> [u8; 32 * 32]
     result
 }
 
+//The inverse of parse_click_rule_base64, the encoded square is the smallest one that contains all enabled cells
+pub fn encode_click_rule_base64(click_rule_data: &[u8; 32 * 32]) -> String
+{
+    let click_rule_size   = 32;
+    let click_rule_center = (click_rule_size - 1) / 2 as i32;
+
+    let mut click_rule_radius = 0;
+    for y in 0..32
+    {
+        for x in 0..32
+        {
+            let index = 32 * y + x;
+
+            if click_rule_data[index] != 0
+            {
+                let click_rule_offset_y = (y as i32 - click_rule_center).abs();
+                let click_rule_offset_x = (x as i32 - click_rule_center).abs();
+
+                let new_radius = std::cmp::max(click_rule_offset_x, click_rule_offset_y) + 1;
+                click_rule_radius = std::cmp::max(click_rule_radius, new_radius);
+            }
+        }
+    }
+
+    let click_rule_diameter = click_rule_radius * 2;
+
+    let click_rule_start = std::cmp::max(0, click_rule_center - click_rule_radius + 1);
+    let click_rule_end   = std::cmp::min(click_rule_start + click_rule_diameter, click_rule_size);
+
+    let mut curr_char_bits = 0u8;
+    let mut curr_char_bit_count = 0u8;
+    let mut result = String::with_capacity((((click_rule_diameter * click_rule_diameter) as f32) / 6.0).ceil() as usize);
+    for y in click_rule_start..click_rule_end
+    {
+        for x in click_rule_start..click_rule_end
+        {
+            if curr_char_bit_count == 6
+            {
+                result.push(encode_base_64_char(curr_char_bits));
+
+                curr_char_bits      = 0;
+                curr_char_bit_count = 0;
+            }
+
+            let index = y * click_rule_size + x;
+            if click_rule_data[index as usize] != 0
+            {
+                curr_char_bits |= 1 << curr_char_bit_count;
+            }
+
+            curr_char_bit_count += 1;
+        }
+    }
+
+    if curr_char_bit_count != 0
+    {
+        result.push(encode_base_64_char(curr_char_bits));
+    }
+
+    result
+}
+
 impl AppState
 {
     pub fn new(click_rule_init_data: ClickRuleInitData, last_frame: u32) -> Self
@@ -118,62 +180,6 @@ impl AppState
 
     pub fn encode_click_rule_base64(&self) -> String
     {
-        let click_rule_size   = 32;
-        let click_rule_center = (click_rule_size - 1) / 2 as i32;
-
-        let mut click_rule_radius = 0;
-        for y in 0..32
-        {
-            for x in 0..32
-            {
-                let index = 32 * y + x;
-
-                if self.click_rule_data[index] != 0
-                {
-                    let click_rule_offset_y = (y as i32 - click_rule_center).abs();
-                    let click_rule_offset_x = (x as i32 - click_rule_center).abs();
-
-                    let new_radius = std::cmp::max(click_rule_offset_x, click_rule_offset_y) + 1;
-                    click_rule_radius = std::cmp::max(click_rule_radius, new_radius);
-                }
-            }
-        }
-
-        let click_rule_diameter = click_rule_radius * 2;
-
-        let click_rule_start = std::cmp::max(0, click_rule_center - click_rule_radius + 1);
-        let click_rule_end   = std::cmp::min(click_rule_start + click_rule_diameter, click_rule_size);
-
-        let mut curr_char_bits = 0u8;
-        let mut curr_char_bit_count = 0u8;
-        let mut result = String::with_capacity((((click_rule_diameter * click_rule_diameter) as f32) / 6.0).ceil() as usize);
-        for y in click_rule_start..click_rule_end
-        {
-            for x in click_rule_start..click_rule_end
-            {
-                if curr_char_bit_count == 6
-                {
-                    result.push(encode_base_64_char(curr_char_bits));
-
-                    curr_char_bits      = 0;
-                    curr_char_bit_count = 0;
-                }
-
-                let index = y * click_rule_size + x;
-                if self.click_rule_data[index as usize] != 0
-                {
-                    curr_char_bits |= 1 << curr_char_bit_count;
-                }
-
-                curr_char_bit_count += 1;
-            }
-        }
-
-        if curr_char_bit_count != 0
-        {
-            result.push(encode_base_64_char(curr_char_bits));
-        }
-
-        result
+        encode_click_rule_base64(&self.click_rule_data)
     }
 }
\ No newline at end of file
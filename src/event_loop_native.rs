@@ -2,14 +2,21 @@
 
 use winit::
 {
-    event::{Event, WindowEvent},
+    event::{Event, WindowEvent, KeyboardInput, ElementState, VirtualKeyCode},
     event_loop::{ControlFlow, EventLoop},
-    window::{WindowBuilder},
+    window::{Window, WindowBuilder},
 };
 
-use super::stafra_state;
-use super::app_state;
-use crate::app_state::RunState;
+use std::sync::Arc;
+
+use stafra::stafra_state;
+use stafra::stafra_cpu_state;
+use stafra::app_state;
+use stafra::stafra_rule_exploration;
+use stafra::stafra_rule_search;
+use stafra::stafra_readback_pool;
+use stafra::app_state::RunState;
+use stafra::simulation_backend::SimulationBackend;
 
 enum ResetOption
 {
@@ -23,13 +30,116 @@ enum AppEvent
 {
 }
 
+//Page Up and Page Down move the timeline by this many frames
+const TIMELINE_PAGE_FRAMES: u32 = 64;
+
+const COMMAND_LINE_USAGE: &str = "Usage: stafra [options]
+
+Options:
+    --click-rule <code>               Click rule in the same base64 format as the click_rule query string parameter
+    --random-click-rule <seed>        Generate a random click rule from the seed
+    --click-rule-radius <1-15>        Largest offset of the random click rule, 2 by default
+    --click-rule-density <0-1>        Fraction of enabled cells of the random click rule, 0.5 by default
+    --click-rule-symmetry <symmetry>  none, mirror_x, 4fold or 8fold, none by default
+    --click-rule-center <y/n>         Whether the random click rule includes the center cell, y by default
+    --print-click-rule                Print the click rule code and exit
+    --explore <file>                  Simulate the click rules from the file, one code per line, and write a contact sheet and a CSV
+    --explore-random <count>          Also simulate count random click rules, with the seeds starting from the --random-click-rule one
+    --explore-board-size <size>       Board size for the exploration, 2^n - 1, 255 by default
+    --explore-frames <count>          Frames simulated for each click rule, 512 by default
+    --explore-columns <count>         Tiles in each row of the contact sheet, a square grid by default
+    --explore-output <prefix>         Write <prefix>.png and <prefix>.csv, exploration by default
+    --search <metric>                 Evolve click rules by fractal_dimension, stable_fraction, period or symmetry. The first generation is
+                                      the --explore rules and random click rules with the --random-click-rule seeds and constraints.
+                                      The boards are the same as in the exploration. Writes the best rules to <prefix>.png and <prefix>.csv,
+                                      and each generation to <prefix>_generations.csv
+    --search-population <count>       Click rules in each generation, 64 by default
+    --search-generations <count>      Generations after the first one, 32 by default
+    --search-mutation <0-1>           Probability to flip each cell within the --click-rule-radius, 0.02 by default
+    --search-best <count>             Number of the best rules to write, 16 by default
+    --readback-queue-depth <1-16>     Requests of each kind that can wait for the GPU readback at the same time, 4 by default
+    --help                            Print this message and exit
+
+Keys:
+    R generates a random click rule with the next seed and the same constraints";
+
+struct CommandLineParams
+{
+    click_rule_data:   app_state::ClickRuleInitData,
+    random_click_rule: stafra_state::RandomClickRuleParams,
+
+    //Exploration runs headless instead of opening the windows
+    explore_path:         Option<String>,
+    explore_random_count: u32,
+    exploration_params:   stafra_rule_exploration::ExplorationParams,
+    exploration_output:   String,
+
+    //The search seed and radius come from the random click rule
+    search_requested: bool,
+    search_params:    stafra_rule_search::SearchParams,
+
+    readback_queue_depth: u32,
+
+    print_click_rule: bool,
+    help:             bool
+}
+
 pub async fn run_event_loop()
 {
+    let command_line_params = match parse_command_line(std::env::args().skip(1))
+    {
+        Ok(command_line_params) => command_line_params,
+        Err(message) =>
+        {
+            eprintln!("{}\n\n{}", message, COMMAND_LINE_USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    if command_line_params.help
+    {
+        println!("{}", COMMAND_LINE_USAGE);
+        return;
+    }
+
+    if command_line_params.search_requested
+    {
+        if let Err(message) = run_search(&command_line_params).await
+        {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if command_line_params.explore_path.is_some() || command_line_params.explore_random_count != 0
+    {
+        if let Err(message) = run_exploration(&command_line_params).await
+        {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    let mut app_state = app_state::AppState::new(command_line_params.click_rule_data, u32::MAX);
+    if command_line_params.print_click_rule
+    {
+        println!("{}", app_state.encode_click_rule_base64());
+        return;
+    }
+
+    //R keeps generating click rules with the constraints from the command line
+    let mut random_click_rule = command_line_params.random_click_rule;
+
     //Create event loop
     let event_loop: EventLoop<AppEvent> = EventLoop::with_user_event();
 
-    let main_window       = WindowBuilder::new().build(&event_loop).unwrap();
-    let click_rule_window = WindowBuilder::new().build(&event_loop).unwrap();
+    //The windows are shown only after the state is created. The surfaces need the windows to exist, the CPU state keeps references to them
+    let main_window       = Arc::new(WindowBuilder::new().with_visible(false).build(&event_loop).unwrap());
+    let click_rule_window = Arc::new(WindowBuilder::new().with_visible(false).build(&event_loop).unwrap());
 
     main_window.set_inner_size(winit::dpi::LogicalSize {width: 768.0, height: 768.0});
     click_rule_window.set_inner_size(winit::dpi::LogicalSize {width: 256.0, height: 256.0});
@@ -39,11 +149,37 @@ pub async fn run_event_loop()
 
     let mut window_size = main_window.inner_size();
 
-    let app_state = app_state::AppState::new();
+    //Without a GPU adapter the simulation runs on the CPU
+    let mut main_state: Box<dyn SimulationBackend> = match stafra_state::StafraState::new_native(&main_window, &click_rule_window, initial_width, initial_height).await
+    {
+        Ok(main_state) => Box::new(main_state),
+        Err(message) =>
+        {
+            log::warn!("{}. Falling back to the CPU simulation", message);
+            Box::new(stafra_cpu_state::StafraCpuState::new_native(main_window.clone(), click_rule_window.clone(), initial_width, initial_height))
+        }
+    };
+
+    main_window.set_visible(true);
+    click_rule_window.set_visible(true);
 
-    let mut main_state = stafra_state::StafraState::new_native(&main_window, &click_rule_window, initial_width, initial_height).await;
+    main_state.set_readback_queue_depth(command_line_params.readback_queue_depth);
     main_state.reset_board_standard(stafra_state::StandardResetBoardType::Corners);
-    main_state.reset_click_rule();
+    main_state.reset_click_rule(&app_state.click_rule_data);
+    main_state.clear_restriction();
+
+    set_click_rule_window_title(&click_rule_window, &app_state);
+
+    //Pass timings are logged every second at the info level
+    let profiling_log_period = 60;
+    let mut frames_since_log = 0;
+    main_state.set_profiling_enabled(log::log_enabled!(log::Level::Info));
+
+    //Number keys switch between the patterns, minus and equals change the pattern size
+    let mut pattern_board = stafra_state::PatternBoardParams {kind: stafra_state::PatternBoardKind::Cross, size: 1, x: 0, y: 0};
+
+    //The window title shows the position on the timeline
+    let mut current_timeline = (u32::MAX, u32::MAX, u32::MAX);
 
     main_window.request_redraw();
     click_rule_window.request_redraw();
@@ -74,6 +210,13 @@ pub async fn run_event_loop()
                         main_state.resize(window_size.width, window_size.height);
                     }
 
+                    WindowEvent::KeyboardInput {input: KeyboardInput {state: ElementState::Pressed, virtual_keycode: Some(key_code), ..}, ..} =>
+                    {
+                        handle_timeline_key(*key_code, &mut app_state, main_state.as_mut());
+                        handle_initial_state_key(*key_code, &mut app_state, main_state.as_mut(), &mut pattern_board);
+                        handle_click_rule_key(*key_code, &mut app_state, main_state.as_mut(), &mut random_click_rule, &click_rule_window);
+                    }
+
                     _ => {}
                 }
             }
@@ -106,6 +249,14 @@ pub async fn run_event_loop()
                 main_state.update();
             }
 
+            let (timeline_start, timeline_end) = main_state.timeline_range();
+            let new_timeline = (main_state.frame_number(), timeline_start, timeline_end);
+            if new_timeline != current_timeline
+            {
+                current_timeline = new_timeline;
+                main_window.set_title(&format!("Stafra - frame {} / {}", new_timeline.0, timeline_end));
+            }
+
             match main_state.render()
             {
                 Ok(_) =>
@@ -124,7 +275,20 @@ pub async fn run_event_loop()
 
                 Err(error) =>
                 {
-                    web_sys::console::log_1(&format!("{:?}", error).into());
+                    log::error!("{:?}", error);
+                }
+            }
+
+            frames_since_log += 1;
+            if frames_since_log >= profiling_log_period && log::log_enabled!(log::Level::Info)
+            {
+                frames_since_log = 0;
+
+                let profiler_report = main_state.profiler_report();
+                let timing_source   = if profiler_report.timestamp_queries {"GPU timestamps"} else {"CPU wall-clock"};
+                for pass_timing in &profiler_report.passes
+                {
+                    log::info!("{} ({}): {:.3} ms", pass_timing.name, timing_source, pass_timing.average_ms);
                 }
             }
         }
@@ -141,4 +305,402 @@ pub async fn run_event_loop()
 
         _ => {}
     });
+}
+
+//Space plays and pauses, Escape stops. Left and Right step through the frames,
+//Page Up and Page Down move by whole pages, Home and End go to the start and the end of the timeline
+fn handle_timeline_key(key_code: VirtualKeyCode, app_state: &mut app_state::AppState, main_state: &mut dyn SimulationBackend)
+{
+    let (timeline_start, timeline_end) = main_state.timeline_range();
+    let frame_number = main_state.frame_number();
+
+    let target_frame = match key_code
+    {
+        VirtualKeyCode::Space =>
+        {
+            app_state.run_state = match app_state.run_state
+            {
+                RunState::Running => RunState::Paused,
+                _                 => RunState::Running
+            };
+
+            return;
+        }
+
+        VirtualKeyCode::Escape =>
+        {
+            app_state.run_state = RunState::Stopped;
+            main_state.reset_board_unchanged();
+            return;
+        }
+
+        VirtualKeyCode::Left     => frame_number.saturating_sub(1),
+        VirtualKeyCode::Right    => frame_number + 1,
+        VirtualKeyCode::PageUp   => frame_number.saturating_sub(TIMELINE_PAGE_FRAMES),
+        VirtualKeyCode::PageDown => std::cmp::min(frame_number + TIMELINE_PAGE_FRAMES, timeline_end),
+        VirtualKeyCode::Home     => timeline_start,
+        VirtualKeyCode::End      => timeline_end,
+        _                        => return
+    };
+
+    app_state.run_state = RunState::Paused;
+    if let Err(message) = main_state.seek_frame(std::cmp::max(target_frame, timeline_start))
+    {
+        log::error!("{}", message);
+    }
+}
+
+//1, 2 and 3 reset to the corners, the sides and the center. 4 to 0 reset to the patterns,
+//minus and equals change the size of the current pattern. The single cell pattern is only available in the web version
+fn handle_initial_state_key(key_code: VirtualKeyCode, app_state: &mut app_state::AppState, main_state: &mut dyn SimulationBackend, pattern_board: &mut stafra_state::PatternBoardParams)
+{
+    let standard_reset_type = match key_code
+    {
+        VirtualKeyCode::Key1 => Some(stafra_state::StandardResetBoardType::Corners),
+        VirtualKeyCode::Key2 => Some(stafra_state::StandardResetBoardType::Edges),
+        VirtualKeyCode::Key3 => Some(stafra_state::StandardResetBoardType::Center),
+        _                    => None
+    };
+
+    if let Some(reset_type) = standard_reset_type
+    {
+        app_state.run_state = RunState::Stopped;
+        main_state.reset_board_standard(reset_type);
+        return;
+    }
+
+    match key_code
+    {
+        VirtualKeyCode::Key4   => pattern_board.kind = stafra_state::PatternBoardKind::HorizontalLine,
+        VirtualKeyCode::Key5   => pattern_board.kind = stafra_state::PatternBoardKind::VerticalLine,
+        VirtualKeyCode::Key6   => pattern_board.kind = stafra_state::PatternBoardKind::Cross,
+        VirtualKeyCode::Key7   => pattern_board.kind = stafra_state::PatternBoardKind::Diagonals,
+        VirtualKeyCode::Key8   => pattern_board.kind = stafra_state::PatternBoardKind::Checkerboard,
+        VirtualKeyCode::Key9   => pattern_board.kind = stafra_state::PatternBoardKind::ConcentricSquares,
+        VirtualKeyCode::Key0   => pattern_board.kind = stafra_state::PatternBoardKind::ConcentricCircles,
+        VirtualKeyCode::Minus  => pattern_board.size = std::cmp::max(pattern_board.size - 1, 1),
+        VirtualKeyCode::Equals => pattern_board.size = pattern_board.size + 1,
+        _                      => return
+    }
+
+    app_state.run_state = RunState::Stopped;
+    main_state.reset_board_pattern(*pattern_board);
+}
+
+//R replaces the click rule with a random one. The board is reset, the same as when the click rule is changed in the web version
+fn handle_click_rule_key(key_code: VirtualKeyCode, app_state: &mut app_state::AppState, main_state: &mut dyn SimulationBackend, random_click_rule: &mut stafra_state::RandomClickRuleParams, click_rule_window: &Window)
+{
+    if key_code != VirtualKeyCode::R
+    {
+        return;
+    }
+
+    random_click_rule.seed = random_click_rule.seed.wrapping_add(1);
+    app_state.click_rule_data = random_click_rule.click_rule_data();
+
+    app_state.run_state = RunState::Stopped;
+    main_state.reset_board_unchanged();
+    main_state.reset_click_rule(&app_state.click_rule_data);
+
+    set_click_rule_window_title(click_rule_window, app_state);
+}
+
+//The code of the click rule is shown so it can be shared or passed with --click-rule
+fn set_click_rule_window_title(click_rule_window: &Window, app_state: &app_state::AppState)
+{
+    let click_rule_code = app_state.encode_click_rule_base64();
+    log::info!("Click rule {}", click_rule_code);
+
+    click_rule_window.set_title(&format!("Click rule {}", click_rule_code));
+}
+
+fn parse_command_line(args: impl Iterator<Item = String>) -> Result<CommandLineParams, String>
+{
+    let mut command_line_params = CommandLineParams
+    {
+        click_rule_data:   app_state::ClickRuleInitData::Default,
+        random_click_rule: stafra_state::RandomClickRuleParams {seed: 0, radius: 2, density: 0.5, symmetry: stafra_state::RandomBoardSymmetry::None, center: true},
+
+        explore_path:         None,
+        explore_random_count: 0,
+        exploration_params:   stafra_rule_exploration::ExplorationParams {board_size: 255, frame_count: 512, columns: 0},
+        exploration_output:   "exploration".to_string(),
+
+        search_requested: false,
+        search_params:    stafra_rule_search::SearchParams {metric: stafra_rule_search::SearchMetric::StableFraction, population_size: 64, generation_count: 32, mutation_rate: 0.02, radius: 2, best_count: 16, seed: 0},
+
+        readback_queue_depth: stafra_readback_pool::DEFAULT_READBACK_QUEUE_DEPTH,
+
+        print_click_rule: false,
+        help:             false
+    };
+
+    let mut random_click_rule_requested = false;
+
+    let mut args = args;
+    while let Some(arg) = args.next()
+    {
+        match arg.as_str()
+        {
+            "--print-click-rule" =>
+            {
+                command_line_params.print_click_rule = true;
+                continue;
+            }
+
+            "--help" | "-h" =>
+            {
+                command_line_params.help = true;
+                continue;
+            }
+
+            "--click-rule" | "--random-click-rule" | "--click-rule-radius" | "--click-rule-density" | "--click-rule-symmetry" | "--click-rule-center" => {}
+
+            "--explore" | "--explore-random" | "--explore-board-size" | "--explore-frames" | "--explore-columns" | "--explore-output" => {}
+
+            "--search" | "--search-population" | "--search-generations" | "--search-mutation" | "--search-best" => {}
+
+            "--readback-queue-depth" => {}
+
+            _ => return Err(format!("Unknown option {}", arg))
+        }
+
+        let value = args.next().ok_or(format!("Missing value for {}", arg))?;
+        let invalid_value_message = format!("Invalid value for {}: {}", arg, value);
+
+        let random_click_rule  = &mut command_line_params.random_click_rule;
+        let exploration_params = &mut command_line_params.exploration_params;
+        let search_params      = &mut command_line_params.search_params;
+        match arg.as_str()
+        {
+            "--click-rule" =>
+            {
+                command_line_params.click_rule_data = app_state::ClickRuleInitData::Custom(app_state::parse_click_rule_base64(&value));
+            }
+
+            "--random-click-rule" =>
+            {
+                random_click_rule.seed      = value.parse::<u32>().map_err(|_| invalid_value_message)?;
+                random_click_rule_requested = true;
+            }
+
+            "--click-rule-radius" =>
+            {
+                random_click_rule.radius = value.parse::<u32>().ok().filter(|radius| (1..=stafra_state::MAX_RANDOM_CLICK_RULE_RADIUS).contains(radius)).ok_or(invalid_value_message)?;
+            }
+
+            "--click-rule-density" =>
+            {
+                random_click_rule.density = value.parse::<f32>().ok().filter(|density| (0.0..=1.0).contains(density)).ok_or(invalid_value_message)?;
+            }
+
+            "--click-rule-symmetry" =>
+            {
+                random_click_rule.symmetry = match value.to_lowercase().as_str()
+                {
+                    "none"                  => stafra_state::RandomBoardSymmetry::None,
+                    "mirror_x" | "mirror-x" => stafra_state::RandomBoardSymmetry::MirrorX,
+                    "4fold"    | "4-fold"   => stafra_state::RandomBoardSymmetry::FourFold,
+                    "8fold"    | "8-fold"   => stafra_state::RandomBoardSymmetry::EightFold,
+                    _                       => return Err(invalid_value_message)
+                };
+            }
+
+            "--click-rule-center" =>
+            {
+                random_click_rule.center = match value.to_lowercase().as_str()
+                {
+                    "y" | "yes" | "1" | "true"  => true,
+                    "n" | "no"  | "0" | "false" => false,
+                    _                           => return Err(invalid_value_message)
+                };
+            }
+
+            "--explore" =>
+            {
+                command_line_params.explore_path = Some(value);
+            }
+
+            "--explore-random" =>
+            {
+                command_line_params.explore_random_count = value.parse::<u32>().map_err(|_| invalid_value_message)?;
+            }
+
+            "--explore-board-size" =>
+            {
+                exploration_params.board_size = value.parse::<u32>().ok().filter(|board_size| *board_size > 1 && (board_size + 1).is_power_of_two()).ok_or(invalid_value_message)?;
+            }
+
+            "--explore-frames" =>
+            {
+                exploration_params.frame_count = value.parse::<u32>().map_err(|_| invalid_value_message)?;
+            }
+
+            "--explore-columns" =>
+            {
+                exploration_params.columns = value.parse::<u32>().map_err(|_| invalid_value_message)?;
+            }
+
+            "--explore-output" =>
+            {
+                command_line_params.exploration_output = value;
+            }
+
+            "--search" =>
+            {
+                search_params.metric = stafra_rule_search::SearchMetric::from_name(&value).ok_or(invalid_value_message)?;
+                command_line_params.search_requested = true;
+            }
+
+            "--search-population" =>
+            {
+                search_params.population_size = value.parse::<u32>().ok().filter(|population_size| (2..=stafra_rule_search::MAX_SEARCH_POPULATION).contains(population_size)).ok_or(invalid_value_message)?;
+            }
+
+            "--search-generations" =>
+            {
+                search_params.generation_count = value.parse::<u32>().map_err(|_| invalid_value_message)?;
+            }
+
+            "--search-mutation" =>
+            {
+                search_params.mutation_rate = value.parse::<f32>().ok().filter(|mutation_rate| (0.0..=1.0).contains(mutation_rate)).ok_or(invalid_value_message)?;
+            }
+
+            "--search-best" =>
+            {
+                search_params.best_count = value.parse::<u32>().ok().filter(|best_count| *best_count > 0).ok_or(invalid_value_message)?;
+            }
+
+            "--readback-queue-depth" =>
+            {
+                command_line_params.readback_queue_depth = value.parse::<u32>().ok().filter(|depth| (1..=stafra_readback_pool::MAX_READBACK_QUEUE_DEPTH).contains(depth)).ok_or(invalid_value_message)?;
+            }
+
+            _ => {}
+        }
+    }
+
+    if random_click_rule_requested
+    {
+        command_line_params.click_rule_data = app_state::ClickRuleInitData::Custom(command_line_params.random_click_rule.click_rule_data());
+    }
+
+    command_line_params.search_params.seed   = command_line_params.random_click_rule.seed;
+    command_line_params.search_params.radius = command_line_params.random_click_rule.radius;
+
+    Ok(command_line_params)
+}
+
+//The rules from the --explore file, followed by random_count random click rules
+fn exploration_click_rules(command_line_params: &CommandLineParams, random_count: u32) -> Result<Vec<[u8; 32 * 32]>, String>
+{
+    let mut click_rules = Vec::new();
+    if let Some(explore_path) = &command_line_params.explore_path
+    {
+        let click_rule_list = std::fs::read_to_string(explore_path).map_err(|error| format!("Cannot read {}: {}", explore_path, error))?;
+        click_rules = stafra_rule_exploration::parse_click_rule_list(&click_rule_list).map_err(|message| format!("{}: {}", explore_path, message))?;
+    }
+
+    let mut random_click_rule = command_line_params.random_click_rule;
+    for _ in 0..random_count
+    {
+        click_rules.push(random_click_rule.click_rule_data());
+        random_click_rule.seed = random_click_rule.seed.wrapping_add(1);
+    }
+
+    Ok(click_rules)
+}
+
+//Runs on the GPU without windows, or on the CPU if there's no adapter
+async fn create_headless_backend(board_size: u32) -> Box<dyn SimulationBackend>
+{
+    match stafra_state::StafraState::new_headless(board_size, board_size).await
+    {
+        Ok(gpu_state) => Box::new(gpu_state),
+        Err(message) =>
+        {
+            log::warn!("{}. Falling back to the CPU simulation", message);
+            Box::new(stafra_cpu_state::StafraCpuState::new(board_size, board_size))
+        }
+    }
+}
+
+async fn run_exploration(command_line_params: &CommandLineParams) -> Result<(), String>
+{
+    let click_rules = exploration_click_rules(command_line_params, command_line_params.explore_random_count)?;
+    if click_rules.is_empty()
+    {
+        return Err("No click rules to explore".to_string());
+    }
+
+    let exploration_params = &command_line_params.exploration_params;
+
+    let mut backend = create_headless_backend(exploration_params.board_size).await;
+
+    let click_rule_count = click_rules.len();
+    let mut result_count = 0;
+    let results = stafra_rule_exploration::explore_click_rules(backend.as_mut(), &click_rules, exploration_params, |result|
+    {
+        result_count += 1;
+
+        let period_text = match result.period
+        {
+            Some(period) => format!("period {} after {} frames", period.period, period.transient),
+            None         => "no period".to_string()
+        };
+
+        println!("{:>5}/{} {}: {}, {:.2}% stable", result_count, click_rule_count, result.click_rule_code, period_text, result.stable_fraction * 100.0);
+    })?;
+
+    let contact_sheet_path = format!("{}.png", command_line_params.exploration_output);
+    let csv_path           = format!("{}.csv", command_line_params.exploration_output);
+
+    let contact_sheet = stafra_rule_exploration::contact_sheet_image(&results, exploration_params.columns)?;
+    image::save_buffer(&contact_sheet_path, &contact_sheet.pixel_data, contact_sheet.image_width, contact_sheet.image_height, image::ColorType::Rgba8).map_err(|error| format!("Cannot write {}: {}", contact_sheet_path, error))?;
+
+    std::fs::write(&csv_path, stafra_rule_exploration::results_to_csv(&results)).map_err(|error| format!("Cannot write {}: {}", csv_path, error))?;
+
+    println!("The contact sheet is written to {}, the results to {}", contact_sheet_path, csv_path);
+    Ok(())
+}
+
+async fn run_search(command_line_params: &CommandLineParams) -> Result<(), String>
+{
+    let exploration_params = &command_line_params.exploration_params;
+    let search_params      = &command_line_params.search_params;
+
+    let initial_population = exploration_click_rules(command_line_params, search_params.population_size)?;
+
+    let mut backend = create_headless_backend(exploration_params.board_size).await;
+
+    let search_result = stafra_rule_search::search_click_rules(backend.as_mut(), &initial_population, exploration_params, search_params, |summary|
+    {
+        println!("Generation {:>4}/{}: best {} {:.6}, mean {:.6}, {}", summary.generation, search_params.generation_count, search_params.metric.name(), summary.best_fitness, summary.mean_fitness, summary.best_click_rule);
+    })?;
+
+    let mut best_results = Vec::with_capacity(search_result.best_rules.len());
+    for candidate in &search_result.best_rules
+    {
+        let result = match candidate.start_period
+        {
+            Some(start_period) => stafra_rule_exploration::exploration_result_with_period(&candidate.click_rule_data, &candidate.stability, start_period),
+            None               => stafra_rule_exploration::exploration_result(&candidate.click_rule_data, &candidate.stability, exploration_params.frame_count)?
+        };
+
+        best_results.push(result);
+    }
+
+    let contact_sheet_path = format!("{}.png", command_line_params.exploration_output);
+    let csv_path           = format!("{}.csv", command_line_params.exploration_output);
+    let generations_path   = format!("{}_generations.csv", command_line_params.exploration_output);
+
+    let contact_sheet = stafra_rule_exploration::contact_sheet_image(&best_results, exploration_params.columns)?;
+    image::save_buffer(&contact_sheet_path, &contact_sheet.pixel_data, contact_sheet.image_width, contact_sheet.image_height, image::ColorType::Rgba8).map_err(|error| format!("Cannot write {}: {}", contact_sheet_path, error))?;
+
+    std::fs::write(&csv_path,         stafra_rule_search::best_rules_to_csv(&search_result.best_rules, &best_results)).map_err(|error| format!("Cannot write {}: {}", csv_path, error))?;
+    std::fs::write(&generations_path, stafra_rule_search::generations_to_csv(&search_result.generations)).map_err(|error| format!("Cannot write {}: {}", generations_path, error))?;
+
+    println!("The best rules are written to {} and {}, the generations to {}", contact_sheet_path, csv_path, generations_path);
+    Ok(())
 }
\ No newline at end of file
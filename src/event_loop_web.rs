@@ -5,26 +5,62 @@ use
     wasm_bindgen::closure::Closure,
     wasm_bindgen::{JsCast, JsValue, Clamped},
     std::rc::Rc,
-    std::cell::RefCell
+    std::cell::RefCell,
+    std::sync::{Arc, Mutex}
 };
 
 use super::stafra_state;
+use super::stafra_cpu_state;
+use super::stafra_profiler;
+use super::stafra_session;
+use super::stafra_history;
+use super::stafra_readback_pool;
+use super::stafra_expression;
+use super::stafra_image_import;
+use super::stafra_paint;
+use super::stafra_lights_out;
+use super::stafra_click_analysis;
+use super::stafra_inverse_problem;
+use super::stafra_click_rule_edit;
 use super::app_state;
 use super::video_record_state;
 
 use crate::app_state::RunState;
+use crate::simulation_backend::SimulationBackend;
 
 struct QueryStringParams
 {
-    initial_state: stafra_state::StandardResetBoardType,
+    initial_state: stafra_state::ResetBoardType,
     size_index:    u32,
 
+    random_board:  stafra_state::RandomBoardParams,
+    pattern_board: stafra_state::PatternBoardParams,
+
+    board_expression: String,
+    expression_seed:  u32,
+
     final_frame: u32,
 
     spawn:            u32,
     smooth_transform: bool,
 
     click_rule_data: app_state::ClickRuleInitData,
+
+    history_frames:  u32,
+    keyframe_period: u32,
+
+    readback_queue_depth: u32,
+
+    profile: bool,
+}
+
+//A mouse drag on the click rule canvas. The cells between the mouse positions are painted too
+#[derive(Copy, Clone)]
+struct ClickRuleStroke
+{
+    paint_value: u8,
+    last_x:      u32,
+    last_y:      u32
 }
 
 pub async fn run_event_loop()
@@ -48,8 +84,21 @@ pub async fn run_event_loop()
     //Initializing the state
     let board_size = app_state::AppState::board_size_from_index(state_params.size_index);
 
+    //Without a GPU adapter the simulation runs on the CPU
+    let stafra_state: Box<dyn SimulationBackend> = match stafra_state::StafraState::new_web(&main_canvas, &click_rule_canvas, board_size, board_size).await
+    {
+        Ok(stafra_state) => Box::new(stafra_state),
+        Err(message) =>
+        {
+            web_sys::console::warn_1(&format!("{}. Falling back to the CPU simulation", message).into());
+            Box::new(stafra_cpu_state::StafraCpuState::new_web(&main_canvas, &click_rule_canvas, board_size, board_size))
+        }
+    };
+
+    hide_unsupported_sizes(stafra_state.max_board_size());
+
     let app_state_rc          = Rc::new(RefCell::new(app_state::AppState::new(state_params.click_rule_data, state_params.final_frame)));
-    let stafra_state_rc       = Rc::new(RefCell::new(stafra_state::StafraState::new_web(&main_canvas, &click_rule_canvas, board_size, board_size).await));
+    let stafra_state_rc       = Rc::new(RefCell::new(stafra_state));
     let video_record_state_rc = Rc::new(RefCell::new(video_record_state::VideoRecordState::new()));
 
     let mut app_state      = app_state_rc.borrow_mut();
@@ -61,14 +110,37 @@ pub async fn run_event_loop()
         web_sys::console::warn_1(&"Warning: this browser does not support video recording with WebCodecs".into());
     }
 
-    stafra_state.reset_board_standard(state_params.initial_state);
+    stafra_state.set_history_depth(state_params.history_frames, state_params.keyframe_period);
+    stafra_state.set_readback_queue_depth(state_params.readback_queue_depth);
+    match state_params.initial_state
+    {
+        stafra_state::ResetBoardType::Standard {reset_type} => stafra_state.reset_board_standard(reset_type),
+        stafra_state::ResetBoardType::Random {params}       => stafra_state.reset_board_random(params),
+        stafra_state::ResetBoardType::Pattern {params}      => stafra_state.reset_board_pattern(params),
+        stafra_state::ResetBoardType::Custom                => {} //Custom boards can't be set from the query string
+
+        stafra_state::ResetBoardType::Expression =>
+        {
+            //An invalid expression from the link leaves the default board
+            let reset_result = stafra_state.reset_board_expression(&state_params.board_expression, state_params.expression_seed);
+            show_expression_error(reset_result.err());
+        }
+    }
+
     stafra_state.reset_click_rule(&app_state.click_rule_data);
     stafra_state.set_spawn_period(state_params.spawn);
     stafra_state.set_smooth_transform_enabled(state_params.smooth_transform);
     stafra_state.clear_restriction();
+    stafra_state.set_profiling_enabled(state_params.profile);
+
+    //The solution is filled in the session callback, which has to be Send
+    let lights_out_solution_arc = Arc::new(Mutex::new(None));
+
+    //The callback can't reset the board itself, the board is reset with the solution on the next refresh
+    let inverse_problem_solution_arc = Arc::new(Mutex::new(None));
 
     //Setting closures
-    create_closures(app_state_rc.clone(), stafra_state_rc.clone(), video_record_state_rc.clone());
+    create_closures(app_state_rc.clone(), stafra_state_rc.clone(), video_record_state_rc.clone(), lights_out_solution_arc.clone(), inverse_problem_solution_arc.clone());
 
     //Refresh handler
     let app_state_clone_for_refresh          = app_state_rc.clone();
@@ -81,6 +153,18 @@ pub async fn run_event_loop()
     let mut current_click_rule_canvas_width  = click_rule_canvas.width();
     let mut current_click_rule_canvas_height = click_rule_canvas.height();
 
+    //The performance overlay is updated twice a second to stay readable
+    let profiling_enabled                 = state_params.profile;
+    let performance_overlay_update_period = 30;
+    let mut frames_since_overlay_update   = 0;
+
+    //The timeline is only updated when it changes, so the slider can be dragged without interruptions
+    let mut current_timeline = (u32::MAX, u32::MAX, u32::MAX);
+
+    //The Lights Out status is only shown while the moves can be made
+    let lights_out_checkbox = document.get_element_by_id("lights_out_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let mut current_lights_out_status = None;
+
     let refresh_function: Rc<RefCell<Option<Closure<dyn FnMut()>>>> = Rc::new(RefCell::new(None));
     let refresh_function_copy = refresh_function.clone();
     *refresh_function_copy.borrow_mut() = Some(Closure::wrap(Box::new(move ||
@@ -101,10 +185,14 @@ pub async fn run_event_loop()
             let video_frame_channel = video_record_state.get_video_frame_channel();
 
             stafra_state.update();
-            stafra_state.post_video_frame_request(move |pixel_data, width, height|
+            stafra_state.post_video_frame_request(Box::new(move |video_frame_result|
             {
-                video_frame_channel.send(video_record_state::VideoFrameData{pixel_data, width, height}).unwrap();
-            });
+                match video_frame_result
+                {
+                    Ok(video_frame) => video_frame_channel.send(video_record_state::VideoFrameData{pixel_data: video_frame.pixel_data, width: video_frame.image_width, height: video_frame.image_height}).unwrap(),
+                    Err(message)    => web_sys::console::error_1(&message.into())
+                }
+            }));
 
             if stafra_state.frame_number() >= app_state.last_frame
             {
@@ -163,6 +251,47 @@ pub async fn run_event_loop()
             stafra_state.resize_click_rule(current_click_rule_canvas_width as u32, current_click_rule_canvas_height as u32);
         }
 
+        //The size selector already shows the new size, but the old board is kept if its allocation failed
+        if let Some(message) = stafra_state.take_allocation_error()
+        {
+            web_sys::console::error_1(&message.into());
+
+            let board_size  = stafra_state.board_size();
+            let size_select = window.document().unwrap().get_element_by_id("sizes").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+            let size_index  = (std::mem::size_of::<u32>() * 8) as u32 - board_size.leading_zeros() - 1;
+            size_select.set_selected_index(size_index as i32);
+
+            update_last_frame_with_size(board_size, &mut app_state);
+        }
+
+        let (timeline_start, timeline_end) = stafra_state.timeline_range();
+        let new_timeline = (stafra_state.frame_number(), timeline_start, timeline_end);
+        if new_timeline != current_timeline
+        {
+            current_timeline = new_timeline;
+            update_timeline(current_timeline.0, timeline_start, timeline_end);
+        }
+
+        let new_lights_out_status = if lights_out_checkbox.checked() && app_state.run_state == RunState::Stopped {Some(stafra_state.lights_out_status())} else {None};
+        if new_lights_out_status != current_lights_out_status
+        {
+            if lights_out_solution_stale(current_lights_out_status, new_lights_out_status)
+            {
+                *lights_out_solution_arc.lock().unwrap() = None;
+                update_lights_out_solution(None, "");
+            }
+
+            current_lights_out_status = new_lights_out_status;
+            update_lights_out_status(current_lights_out_status);
+        }
+
+        let inverse_problem_solution = inverse_problem_solution_arc.lock().unwrap().take();
+        if let Some(inverse_problem_solution) = inverse_problem_solution
+        {
+            let initial_board_image = inverse_problem_solution.initial_board_image();
+            load_board_image(&mut **stafra_state, &mut app_state, initial_board_image.pixel_data, initial_board_image.image_width, initial_board_image.image_height);
+        }
+
         stafra_state.update_visual_info();
 
         //Display state
@@ -192,6 +321,16 @@ pub async fn run_event_loop()
             }
         }
 
+        if profiling_enabled
+        {
+            frames_since_overlay_update += 1;
+            if frames_since_overlay_update >= performance_overlay_update_period
+            {
+                frames_since_overlay_update = 0;
+                update_performance_overlay(&stafra_state.profiler_report());
+            }
+        }
+
         window.request_animation_frame(refresh_function.borrow().as_ref().unwrap().as_ref().unchecked_ref()).expect("Request animation frame error");
     }) as Box<dyn FnMut()>));
 
@@ -207,15 +346,57 @@ pub async fn run_event_loop()
     window.request_animation_frame(refresh_function_copy.borrow().as_ref().unwrap().as_ref().unchecked_ref()).expect("Request animation frame error!");
 }
 
-fn create_closures(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>, video_record_state_rc: Rc<RefCell<video_record_state::VideoRecordState>>)
+fn create_closures(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, video_record_state_rc: Rc<RefCell<video_record_state::VideoRecordState>>, lights_out_solution_arc: Arc<Mutex<Option<stafra_lights_out::LightsOutSolution>>>, inverse_problem_solution_arc: Arc<Mutex<Option<stafra_inverse_problem::InverseProblemSolution>>>)
 {
-    create_click_rule_change_closure(app_state_rc.clone(), stafra_state_rc.clone());
+    //The value painted by the mouse drag on the click rule canvas, None if no drag is in progress
+    let click_rule_stroke_rc = Rc::new(RefCell::new(None));
+    create_click_rule_change_closure(app_state_rc.clone(), stafra_state_rc.clone(), click_rule_stroke_rc.clone());
+    create_click_rule_drag_closure(app_state_rc.clone(), stafra_state_rc.clone(), click_rule_stroke_rc.clone());
+    create_click_rule_drag_end_closure(click_rule_stroke_rc.clone());
+    create_click_rule_context_menu_closure();
+
+    for (button_id, read_transform) in click_rule_transform_buttons()
+    {
+        create_click_rule_transform_closure(app_state_rc.clone(), stafra_state_rc.clone(), button_id, read_transform);
+    }
+
+    create_random_click_rule_generate_closure(app_state_rc.clone(), stafra_state_rc.clone());
+    create_random_click_rule_new_seed_closure(app_state_rc.clone(), stafra_state_rc.clone());
+
+    //The stroke being drawn with the mouse, None if no stroke is in progress
+    let paint_stroke_rc = Rc::new(RefCell::new(None));
+    create_paint_start_closure(app_state_rc.clone(), stafra_state_rc.clone(), paint_stroke_rc.clone(), lights_out_solution_arc.clone());
+    create_paint_move_closure(stafra_state_rc.clone(), paint_stroke_rc.clone());
+    create_paint_end_closure(stafra_state_rc.clone(), paint_stroke_rc.clone());
+    create_paint_context_menu_closure();
+
+    create_lights_out_enable_closure(stafra_state_rc.clone());
+    create_lights_out_undo_closure(app_state_rc.clone(), stafra_state_rc.clone());
+    create_lights_out_solve_closure(app_state_rc.clone(), stafra_state_rc.clone(), lights_out_solution_arc.clone());
+    create_lights_out_hide_solution_closure(lights_out_solution_arc.clone());
+    create_lights_out_export_solution_closure(lights_out_solution_arc.clone());
+
+    //The analysis is filled in the session callback, which has to be Send
+    let click_rule_analysis_arc = Arc::new(Mutex::new(None));
+    create_click_rule_analyze_closure(app_state_rc.clone(), stafra_state_rc.clone(), click_rule_analysis_arc.clone());
+    create_click_rule_analysis_export_closure(click_rule_analysis_arc.clone());
+    create_quiet_pattern_view_closure(app_state_rc.clone(), stafra_state_rc.clone(), click_rule_analysis_arc.clone());
+    create_update_order_closure(app_state_rc.clone(), stafra_state_rc.clone());
+
+    create_upload_inverse_problem_target_closure();
+    create_inverse_problem_input_closure(app_state_rc.clone(), stafra_state_rc.clone(), inverse_problem_solution_arc);
 
     create_save_png_closure(stafra_state_rc.clone());
 
+    create_save_session_closure(stafra_state_rc.clone());
+    create_load_session_closure();
+    create_session_input_closure(app_state_rc.clone(), stafra_state_rc.clone());
+
     create_play_pause_closure(app_state_rc.clone(), stafra_state_rc.clone(), video_record_state_rc.clone());
     create_stop_closure(app_state_rc.clone(), stafra_state_rc.clone(), video_record_state_rc.clone());
     create_next_frame_closure(app_state_rc.clone(), stafra_state_rc.clone(), video_record_state_rc.clone());
+    create_prev_frame_closure(app_state_rc.clone(), stafra_state_rc.clone());
+    create_timeline_closure(app_state_rc.clone(), stafra_state_rc.clone());
 
     create_enable_last_frame_closure(app_state_rc.clone());
     create_change_last_frame_closure(app_state_rc.clone());
@@ -232,14 +413,20 @@ fn create_closures(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_
     create_upload_restriction_closure();
     create_clear_restriction_closure(stafra_state_rc.clone());
 
+    create_auto_threshold_closure();
+
     create_board_upload_input_closure(app_state_rc.clone(), stafra_state_rc.clone());
     create_upload_restriction_input_closure(stafra_state_rc.clone());
 
     create_select_initial_state_closure(stafra_state_rc.clone());
+    create_change_random_board_closure(stafra_state_rc.clone());
+    create_change_pattern_board_closure(stafra_state_rc.clone());
+    create_change_expression_board_closure(stafra_state_rc.clone());
+    create_new_seed_closure(stafra_state_rc.clone());
     create_select_size_closure(app_state_rc.clone(), stafra_state_rc.clone());
 }
 
-fn create_click_rule_change_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_click_rule_change_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, click_rule_stroke_rc: Rc<RefCell<Option<ClickRuleStroke>>>)
 {
     let document = web_sys::window().unwrap().document().unwrap();
     let click_rule_canvas = document.get_element_by_id("click_rule_canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
@@ -249,994 +436,3149 @@ fn create_click_rule_change_closure(app_state_rc: Rc<RefCell<app_state::AppState
         let mut app_state    = app_state_rc.borrow_mut();
         let mut stafra_state = stafra_state_rc.borrow_mut();
 
-        if app_state.run_state == RunState::Stopped
+        if app_state.run_state != RunState::Stopped
         {
-            let window       = web_sys::window().unwrap();
-            let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+            return;
+        }
 
-            let mouse_event       = event.dyn_into::<web_sys::MouseEvent>().unwrap();
-            let click_rule_canvas = mouse_event.target().unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+        let mouse_event = event.dyn_into::<web_sys::MouseEvent>().unwrap();
+        let (cell_x, cell_y) = click_rule_cell_from_mouse_event(&mouse_event);
 
-            let click_x = mouse_event.page_x() - click_rule_canvas.offset_left();
-            let click_y = mouse_event.page_y() - click_rule_canvas.offset_top();
+        //The first cell decides whether the drag paints or erases. The right button always erases
+        let click_rule_index = (cell_y * 32 + cell_x) as usize;
+        let paint_value      = if mouse_event.button() == 2 {0} else {(app_state.click_rule_data[click_rule_index] == 0) as u8};
 
-            let canvas_width  = click_rule_canvas.client_width();
-            let canvas_height = click_rule_canvas.client_height();
+        let stroke = ClickRuleStroke {paint_value, last_x: cell_x, last_y: cell_y};
+        paint_click_rule_from_ui(&mut app_state, stafra_state.as_mut(), &stroke, cell_x, cell_y);
 
-            let x_normalized = (click_x as f32) / (canvas_width  as f32);
-            let y_normalized = (click_y as f32) / (canvas_height as f32);
+        *click_rule_stroke_rc.borrow_mut() = Some(stroke);
+    })
+    as Box<dyn Fn(web_sys::Event)>);
 
-            let click_rule_size = 32;
-            let edit_index_x_unrestricted = (x_normalized * (click_rule_size as f32)) as i32;
-            let edit_index_y_unrestricted = (y_normalized * (click_rule_size as f32)) as i32;
+    click_rule_canvas.set_onmousedown(Some(click_rule_change_closure.as_ref().unchecked_ref()));
+    click_rule_change_closure.forget();
+}
 
-            let edit_index_x = edit_index_x_unrestricted.clamp(0, click_rule_size - 1);
-            let edit_index_y = edit_index_y_unrestricted.clamp(0, click_rule_size - 1);
+fn create_click_rule_drag_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, click_rule_stroke_rc: Rc<RefCell<Option<ClickRuleStroke>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let click_rule_canvas = document.get_element_by_id("click_rule_canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
 
-            let click_rule_index = (edit_index_y * click_rule_size + edit_index_x) as usize;
+    let click_rule_drag_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let mut click_rule_stroke = click_rule_stroke_rc.borrow_mut();
+        let stroke = match click_rule_stroke.as_mut()
+        {
+            Some(stroke) => stroke,
+            None         => return
+        };
 
-            let current_cell_state = app_state.click_rule_data[click_rule_index] != 0;
-            app_state.click_rule_data[click_rule_index] = (!current_cell_state) as u8;
+        let mut app_state    = app_state_rc.borrow_mut();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
 
-            stafra_state.reset_click_rule(&app_state.click_rule_data);
-            query_string.set("click_rule", &app_state.encode_click_rule_base64());
+        if app_state.run_state != RunState::Stopped
+        {
+            return;
+        }
 
-            let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
-            window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+        let mouse_event = event.dyn_into::<web_sys::MouseEvent>().unwrap();
+        let (cell_x, cell_y) = click_rule_cell_from_mouse_event(&mouse_event);
+        if cell_x == stroke.last_x && cell_y == stroke.last_y
+        {
+            return;
         }
+
+        paint_click_rule_from_ui(&mut app_state, stafra_state.as_mut(), stroke, cell_x, cell_y);
+
+        stroke.last_x = cell_x;
+        stroke.last_y = cell_y;
     })
     as Box<dyn Fn(web_sys::Event)>);
 
-    click_rule_canvas.set_onmousedown(Some(click_rule_change_closure.as_ref().unchecked_ref()));
-    click_rule_change_closure.forget();
+    click_rule_canvas.set_onmousemove(Some(click_rule_drag_closure.as_ref().unchecked_ref()));
+    click_rule_drag_closure.forget();
 }
 
-fn create_save_png_closure(stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_click_rule_drag_end_closure(click_rule_stroke_rc: Rc<RefCell<Option<ClickRuleStroke>>>)
 {
     let document = web_sys::window().unwrap().document().unwrap();
-    let save_png_button = document.get_element_by_id("button_save_png").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let click_rule_canvas = document.get_element_by_id("click_rule_canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
 
-    let save_png_closure = Closure::wrap(Box::new(move ||
+    let click_rule_drag_end_closure = Closure::wrap(Box::new(move |_event: web_sys::Event|
     {
-        let mut stafra_state = stafra_state_rc.borrow_mut();
-        stafra_state.post_save_png_request(move |pixel_data, width, height|
-        {
-            let image_data = web_sys::ImageData::new_with_u8_clamped_array_and_sh(Clamped(pixel_data.as_slice()), width, height).unwrap();
-            save_image_data(image_data);
-        });
+        *click_rule_stroke_rc.borrow_mut() = None;
     })
-    as Box<dyn Fn()>);
+    as Box<dyn Fn(web_sys::Event)>);
 
-    save_png_button.set_onclick(Some(save_png_closure.as_ref().unchecked_ref()));
-    save_png_closure.forget();
+    click_rule_canvas.set_onmouseup(Some(click_rule_drag_end_closure.as_ref().unchecked_ref()));
+    click_rule_canvas.set_onmouseleave(Some(click_rule_drag_end_closure.as_ref().unchecked_ref()));
+    click_rule_drag_end_closure.forget();
 }
 
-fn create_play_pause_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>, video_record_state_rc: Rc<RefCell<video_record_state::VideoRecordState>>)
+fn create_click_rule_context_menu_closure()
 {
     let document = web_sys::window().unwrap().document().unwrap();
-    let play_pause_button = document.get_element_by_id("button_play_pause").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let click_rule_canvas = document.get_element_by_id("click_rule_canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
 
-    let play_pause_closure = Closure::wrap(Box::new(move ||
+    //The right button is used for erasing
+    let click_rule_context_menu_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
     {
-        let mut app_state      = app_state_rc.borrow_mut();
-        let mut stafra_state   = stafra_state_rc.borrow_mut();
-        let video_record_state = video_record_state_rc.borrow();
+        event.prevent_default();
+    })
+    as Box<dyn Fn(web_sys::Event)>);
 
-        stafra_state.set_click_rule_read_only(true);
+    click_rule_canvas.set_oncontextmenu(Some(click_rule_context_menu_closure.as_ref().unchecked_ref()));
+    click_rule_context_menu_closure.forget();
+}
 
-        if video_record_state.is_recording_supported()
-        {
-            app_state.run_state = match app_state.run_state
-            {
-                RunState::Stopped | RunState::Paused => RunState::Running,
-                RunState::Running                    => RunState::Paused,
-                RunState::Recording                  => RunState::PausedRecording,
-                RunState::PausedRecording            => RunState::Recording,
-                RunState::SavePendingRecording       => RunState::SavePendingRecording
-            };
-        }
-        else
-        {
-            app_state.run_state = match app_state.run_state
-            {
-                RunState::Stopped | RunState::Paused => RunState::Running,
-                RunState::Running                    => RunState::Paused,
-                RunState::Recording                  => RunState::Paused,
-                RunState::PausedRecording            => RunState::Running,
-                RunState::SavePendingRecording       => RunState::SavePendingRecording
-            };
-        }
+fn create_click_rule_transform_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, button_id: &str, read_transform: fn() -> stafra_click_rule_edit::ClickRuleTransform)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let click_rule_transform_button = document.get_element_by_id(button_id).unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-        update_ui(app_state.run_state);
+    let click_rule_transform_closure = Closure::wrap(Box::new(move ||
+    {
+        let mut app_state    = app_state_rc.borrow_mut();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
 
-        if app_state.run_state == RunState::PausedRecording
+        if app_state.run_state == RunState::Stopped
         {
-            update_next_frame_button_paused_recording(!stafra_state.video_frame_queue_full());
+            app_state.click_rule_data = stafra_click_rule_edit::transform_click_rule(&app_state.click_rule_data, read_transform());
+            apply_click_rule_from_ui(&mut app_state, stafra_state.as_mut());
         }
-
     }) as Box<dyn Fn()>);
 
-    play_pause_button.set_onclick(Some(play_pause_closure.as_ref().unchecked_ref()));
-    play_pause_closure.forget();
+    click_rule_transform_button.set_onclick(Some(click_rule_transform_closure.as_ref().unchecked_ref()));
+    click_rule_transform_closure.forget();
 }
 
-fn create_stop_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>, video_record_state_rc: Rc<RefCell<video_record_state::VideoRecordState>>)
+fn create_random_click_rule_generate_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
 {
     let document = web_sys::window().unwrap().document().unwrap();
-    let stop_button = document.get_element_by_id("button_stop_record").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let random_click_rule_generate_button = document.get_element_by_id("button_random_click_rule_generate").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let stop_closure = Closure::wrap(Box::new(move ||
+    let random_click_rule_generate_closure = Closure::wrap(Box::new(move ||
     {
-        let mut app_state          = app_state_rc.borrow_mut();
-        let mut stafra_state       = stafra_state_rc.borrow_mut();
-        let mut video_record_state = video_record_state_rc.borrow_mut();
+        let mut app_state    = app_state_rc.borrow_mut();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
 
-        match app_state.run_state
+        if app_state.run_state == RunState::Stopped
         {
-            RunState::Stopped =>
-            {
-                if !video_record_state.pending() && video_record_state.is_recording_supported()
-                {
-                    video_record_state.restart().unwrap();
-                    app_state.run_state = RunState::Recording;
-                }
-            }
-
-            RunState::Recording | RunState::PausedRecording =>
-            {
-                app_state.run_state = RunState::Stopped;
+            generate_random_click_rule_from_ui(&mut app_state, stafra_state.as_mut());
+        }
+    }) as Box<dyn Fn()>);
 
-                stafra_state.reset_board_unchanged();
-                stafra_state.set_click_rule_read_only(false);
-            }
+    random_click_rule_generate_button.set_onclick(Some(random_click_rule_generate_closure.as_ref().unchecked_ref()));
+    random_click_rule_generate_closure.forget();
+}
 
-            RunState::Paused | RunState::Running =>
-            {
-                app_state.run_state = RunState::Stopped;
+fn create_random_click_rule_new_seed_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let random_click_rule_new_seed_button = document.get_element_by_id("button_random_click_rule_new_seed").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-                stafra_state.reset_board_unchanged();
-                stafra_state.set_click_rule_read_only(false);
-            }
+    let random_click_rule_new_seed_closure = Closure::wrap(Box::new(move ||
+    {
+        let mut app_state    = app_state_rc.borrow_mut();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
 
-            RunState::SavePendingRecording =>
-            {
-            }
-        };
+        if app_state.run_state == RunState::Stopped
+        {
+            let document = web_sys::window().unwrap().document().unwrap();
+            let random_click_rule_seed_input = document.get_element_by_id("random_click_rule_seed").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            random_click_rule_seed_input.set_value_as_number(generate_random_seed() as f64);
 
-        update_ui(app_state.run_state);
+            generate_random_click_rule_from_ui(&mut app_state, stafra_state.as_mut());
+        }
     }) as Box<dyn Fn()>);
 
-    stop_button.set_onclick(Some(stop_closure.as_ref().unchecked_ref()));
-    stop_closure.forget();
+    random_click_rule_new_seed_button.set_onclick(Some(random_click_rule_new_seed_closure.as_ref().unchecked_ref()));
+    random_click_rule_new_seed_closure.forget();
 }
 
-fn create_next_frame_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>, video_record_state_rc: Rc<RefCell<video_record_state::VideoRecordState>>)
+fn create_paint_start_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, paint_stroke_rc: Rc<RefCell<Option<stafra_paint::PaintStroke>>>, lights_out_solution_arc: Arc<Mutex<Option<stafra_lights_out::LightsOutSolution>>>)
 {
     let document = web_sys::window().unwrap().document().unwrap();
-    let next_frame_button = document.get_element_by_id("button_next_frame").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let main_canvas = document.get_element_by_id("stafra_canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
 
-    let next_frame_closure = Closure::wrap(Box::new(move ||
+    let paint_start_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
     {
-        let mut app_state      = app_state_rc.borrow_mut();
-        let mut stafra_state   = stafra_state_rc.borrow_mut();
-        let video_record_state = video_record_state_rc.borrow_mut();
+        let app_state        = app_state_rc.borrow();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
 
-        match app_state.run_state
+        if app_state.run_state != RunState::Stopped
         {
-            RunState::PausedRecording => if !stafra_state.video_frame_queue_full() && video_record_state.is_recording_supported()
-            {
-                let video_frame_channel = video_record_state.get_video_frame_channel();
+            return;
+        }
 
-                stafra_state.update();
-                stafra_state.post_video_frame_request(move |pixel_data: Vec<u8>, width: u32, height: u32|
-                {
-                    video_frame_channel.send(video_record_state::VideoFrameData{pixel_data, width, height}).unwrap();
-                });
+        let mouse_event = event.dyn_into::<web_sys::MouseEvent>().unwrap();
+        let (cell_x, cell_y) = paint_cell_from_mouse_event(&mouse_event, stafra_state.board_size());
 
-                update_next_frame_button_paused_recording(!stafra_state.video_frame_queue_full());
-                if app_state.last_frame == stafra_state.frame_number()
+        //In the Lights Out play mode clicks are moves instead of paint strokes
+        let document            = web_sys::window().unwrap().document().unwrap();
+        let lights_out_checkbox = document.get_element_by_id("lights_out_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        if lights_out_checkbox.checked()
+        {
+            if cell_x >= 0 && cell_y >= 0 && stafra_state.lights_out_click(cell_x as u32, cell_y as u32)
+            {
+                //The shown solution stays valid if the move is toggled in it
+                let mut lights_out_solution = lights_out_solution_arc.lock().unwrap();
+                if let Some(solution) = lights_out_solution.as_mut()
                 {
-                    app_state.run_state = RunState::Paused;
+                    solution.toggle_click(cell_x as u32, cell_y as u32);
+                    update_lights_out_solution(Some(solution), "");
                 }
             }
 
-            RunState::Paused =>
+            return;
+        }
+
+        //The right button paints with the disabled value
+        let stroke = match read_paint_stroke_ui(cell_x, cell_y, mouse_event.button() != 2)
+        {
+            Some(stroke) => stroke,
+            None         => return
+        };
+
+        match stroke.tool
+        {
+            stafra_paint::PaintTool::Pencil =>
             {
-                stafra_state.update();
+                paint_from_ui(stafra_state.as_mut(), stroke);
+                *paint_stroke_rc.borrow_mut() = Some(stroke);
             }
 
-            RunState::Stopped =>
+            stafra_paint::PaintTool::Fill =>
             {
-                app_state.run_state = RunState::Paused;
-                update_ui(app_state.run_state);
-
-                //Update twice to properly initialize the first frame
-                stafra_state.update();
-                stafra_state.update();
+                paint_from_ui(stafra_state.as_mut(), stroke);
             }
 
-            _ => {}
+            _ =>
+            {
+                *paint_stroke_rc.borrow_mut() = Some(stroke);
+            }
         }
-    }) as Box<dyn Fn()>);
+    })
+    as Box<dyn Fn(web_sys::Event)>);
 
-    next_frame_button.set_onclick(Some(next_frame_closure.as_ref().unchecked_ref()));
-    next_frame_closure.forget();
+    main_canvas.set_onmousedown(Some(paint_start_closure.as_ref().unchecked_ref()));
+    paint_start_closure.forget();
 }
 
-fn create_enable_last_frame_closure(app_state_rc: Rc<RefCell<app_state::AppState>>)
+fn create_paint_move_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, paint_stroke_rc: Rc<RefCell<Option<stafra_paint::PaintStroke>>>)
 {
-    let window = web_sys::window().unwrap();
-    let document = window.document().unwrap();
-    let last_frame_checkbox = document.get_element_by_id("last_frame_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let document = web_sys::window().unwrap().document().unwrap();
+    let main_canvas = document.get_element_by_id("stafra_canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
 
-    let enable_last_frame_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let paint_move_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
     {
-        let mut app_state = app_state_rc.borrow_mut();
-
-        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+        let mut paint_stroke = paint_stroke_rc.borrow_mut();
+        let stroke = match paint_stroke.as_mut()
+        {
+            Some(stroke) => stroke,
+            None         => return
+        };
 
-        let last_frame_input = document.get_element_by_id("last_frame_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
 
-        let last_frame_checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-        if last_frame_checkbox.checked()
+        let mouse_event = event.dyn_into::<web_sys::MouseEvent>().unwrap();
+        let (cell_x, cell_y) = paint_cell_from_mouse_event(&mouse_event, stafra_state.board_size());
+        if cell_x == stroke.end_x && cell_y == stroke.end_y
         {
-            let last_frame = last_frame_input.value_as_number();
-            if !last_frame.is_nan()
-            {
-                app_state.last_frame = last_frame as u32;
-                query_string.set("final_frame", &last_frame.to_string());
-            }
-            else
-            {
-                app_state.last_frame = u32::MAX;
-                query_string.delete("final_frame");
-            }
-
-            last_frame_input.set_disabled(false);
+            return;
         }
-        else
+
+        stroke.end_x = cell_x;
+        stroke.end_y = cell_y;
+
+        //Pencil draws a segment from the previous mouse position each time the mouse moves to a different cell
+        if stroke.tool == stafra_paint::PaintTool::Pencil
         {
-            app_state.last_frame = u32::MAX;
-            last_frame_input.set_disabled(true);
+            paint_from_ui(stafra_state.as_mut(), *stroke);
 
-            query_string.delete("final_frame");
+            stroke.start_x = cell_x;
+            stroke.start_y = cell_y;
         }
+    })
+    as Box<dyn Fn(web_sys::Event)>);
 
-        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
-        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
-
-    }) as Box<dyn Fn(web_sys::Event)>);
-
-    last_frame_checkbox.set_onclick(Some(enable_last_frame_closure.as_ref().unchecked_ref()));
-    enable_last_frame_closure.forget();
+    main_canvas.set_onmousemove(Some(paint_move_closure.as_ref().unchecked_ref()));
+    paint_move_closure.forget();
 }
 
-fn create_enable_spawn_closure(stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_paint_end_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, paint_stroke_rc: Rc<RefCell<Option<stafra_paint::PaintStroke>>>)
 {
-    let window = web_sys::window().unwrap();
-    let document = window.document().unwrap();
-    let spawn_checkbox = document.get_element_by_id("spawn_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let document = web_sys::window().unwrap().document().unwrap();
+    let main_canvas = document.get_element_by_id("stafra_canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
 
-    let enable_spawn_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    //Lines and shapes are drawn when the mouse button is released or the mouse leaves the canvas
+    let paint_end_closure = Closure::wrap(Box::new(move |_event: web_sys::Event|
     {
-        let mut stafra_state = stafra_state_rc.borrow_mut();
-
-        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+        let stroke = match paint_stroke_rc.borrow_mut().take()
+        {
+            Some(stroke) => stroke,
+            None         => return
+        };
 
-        let spawn_decrement_button    = document.get_element_by_id("decrement_spawn").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-        let spawn_increment_button    = document.get_element_by_id("increment_spawn").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-        let spawn_slider              = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-        let smooth_transform_checkbox = document.get_element_by_id("smooth_transform_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        if stroke.tool != stafra_paint::PaintTool::Pencil
+        {
+            let mut stafra_state = stafra_state_rc.borrow_mut();
+            paint_from_ui(stafra_state.as_mut(), stroke);
+        }
+    })
+    as Box<dyn Fn(web_sys::Event)>);
 
-        let spawn_checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    main_canvas.set_onmouseup(Some(paint_end_closure.as_ref().unchecked_ref()));
+    main_canvas.set_onmouseleave(Some(paint_end_closure.as_ref().unchecked_ref()));
+    paint_end_closure.forget();
+}
 
-        spawn_decrement_button.set_disabled(!spawn_checkbox.checked());
-        spawn_increment_button.set_disabled(!spawn_checkbox.checked());
-        spawn_slider.set_disabled(!spawn_checkbox.checked());
-        smooth_transform_checkbox.set_disabled(!spawn_checkbox.checked());
+fn create_paint_context_menu_closure()
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let main_canvas = document.get_element_by_id("stafra_canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
 
-        if spawn_checkbox.checked()
-        {
-            let spawn_period_input = document.get_element_by_id("spawn_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-            let spawn_period = spawn_period_input.value().parse::<u32>().expect("Not a number");
+    //The right button is used for painting with the disabled value
+    let paint_context_menu_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let document = web_sys::window().unwrap().document().unwrap();
 
-            stafra_state.set_spawn_period(spawn_period);
-            query_string.set("spawn_period", &spawn_period.to_string());
-        }
-        else
+        let paint_tool_select = document.get_element_by_id("paint_tool").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+        if paint_tool_select.value() != "none"
         {
-            stafra_state.set_spawn_period(u32::MAX);
-            query_string.delete("spawn_period");
+            event.prevent_default();
         }
+    })
+    as Box<dyn Fn(web_sys::Event)>);
 
-        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
-        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
-
-    }) as Box<dyn Fn(web_sys::Event)>);
-
-    spawn_checkbox.set_onclick(Some(enable_spawn_closure.as_ref().unchecked_ref()));
-    enable_spawn_closure.forget();
+    main_canvas.set_oncontextmenu(Some(paint_context_menu_closure.as_ref().unchecked_ref()));
+    paint_context_menu_closure.forget();
 }
 
-fn create_decrement_spawn_closure(stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_lights_out_enable_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
 {
-    let window = web_sys::window().unwrap();
-    let document = window.document().unwrap();
-    let decrement_spawn_button = document.get_element_by_id("decrement_spawn").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let document = web_sys::window().unwrap().document().unwrap();
+    let lights_out_checkbox = document.get_element_by_id("lights_out_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
 
-    let decrement_spawn_closure = Closure::wrap(Box::new(move ||
+    let lights_out_enable_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
     {
         let mut stafra_state = stafra_state_rc.borrow_mut();
 
-        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
-
-        let spawn_period_input = document.get_element_by_id("spawn_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-
-        let spawn_period_slider = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-        let new_spawn_period = ((spawn_period_input.value().parse::<u32>().expect("Not a number")) - 1).clamp(1, 255);
-
-        let spawn_period_string = new_spawn_period.to_string();
+        let lights_out_checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        stafra_state.set_lights_out_enabled(lights_out_checkbox.checked());
+    })
+    as Box<dyn Fn(web_sys::Event)>);
 
-        spawn_period_input.set_value(&spawn_period_string);
-        spawn_period_slider.set_value(&spawn_period_string);
+    lights_out_checkbox.set_onchange(Some(lights_out_enable_closure.as_ref().unchecked_ref()));
+    lights_out_enable_closure.forget();
+}
 
-        query_string.set("spawn_period", &spawn_period_string);
-        stafra_state.set_spawn_period(new_spawn_period);
+fn create_lights_out_undo_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let lights_out_undo_button = document.get_element_by_id("button_lights_out_undo").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
-        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+    let lights_out_undo_closure = Closure::wrap(Box::new(move ||
+    {
+        let app_state        = app_state_rc.borrow();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
 
-    }) as Box<dyn Fn()>);
+        if app_state.run_state == RunState::Stopped
+        {
+            stafra_state.lights_out_undo();
+        }
+    })
+    as Box<dyn Fn()>);
 
-    decrement_spawn_button.set_onclick(Some(decrement_spawn_closure.as_ref().unchecked_ref()));
-    decrement_spawn_closure.forget();
+    lights_out_undo_button.set_onclick(Some(lights_out_undo_closure.as_ref().unchecked_ref()));
+    lights_out_undo_closure.forget();
 }
 
-fn create_increment_spawn_closure(stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_lights_out_solve_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, lights_out_solution_arc: Arc<Mutex<Option<stafra_lights_out::LightsOutSolution>>>)
 {
-    let window = web_sys::window().unwrap();
-    let document = window.document().unwrap();
-    let increment_spawn_button = document.get_element_by_id("increment_spawn").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let document = web_sys::window().unwrap().document().unwrap();
+    let lights_out_solve_button = document.get_element_by_id("button_lights_out_solve").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let increment_spawn_closure = Closure::wrap(Box::new(move ||
+    let lights_out_solve_closure = Closure::wrap(Box::new(move ||
     {
+        let app_state        = app_state_rc.borrow();
         let mut stafra_state = stafra_state_rc.borrow_mut();
 
-        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+        if app_state.run_state != RunState::Stopped
+        {
+            return;
+        }
 
-        let spawn_period_input = document.get_element_by_id("spawn_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        //The session has everything the solver needs: the board, the restriction and the click rule
+        let lights_out_solution_arc = lights_out_solution_arc.clone();
+        stafra_state.post_save_session_request(Box::new(move |session_result|
+        {
+            let solve_result = session_result.and_then(|session| stafra_lights_out::solve_lights_out(session.board_size, &session.board, &session.restriction, &session.click_rule_data));
 
-        let spawn_period_slider = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-        let new_spawn_period = ((spawn_period_input.value().parse::<u32>().expect("Not a number")) + 1).clamp(1, 255);
+            let mut lights_out_solution = lights_out_solution_arc.lock().unwrap();
+            match solve_result
+            {
+                Ok(Some(solution)) =>
+                {
+                    update_lights_out_solution(Some(&solution), "");
+                    *lights_out_solution = Some(solution);
+                }
 
-        let spawn_period_string = new_spawn_period.to_string();
+                Ok(None) =>
+                {
+                    *lights_out_solution = None;
+                    update_lights_out_solution(None, "No solution");
+                }
 
-        spawn_period_input.set_value(&spawn_period_string);
-        spawn_period_slider.set_value(&spawn_period_string);
+                Err(message) =>
+                {
+                    *lights_out_solution = None;
+                    update_lights_out_solution(None, &message);
+                    web_sys::console::error_1(&message.into());
+                }
+            }
+        }));
+    })
+    as Box<dyn Fn()>);
 
-        query_string.set("spawn_period", &spawn_period_string);
-        stafra_state.set_spawn_period(new_spawn_period);
+    lights_out_solve_button.set_onclick(Some(lights_out_solve_closure.as_ref().unchecked_ref()));
+    lights_out_solve_closure.forget();
+}
 
-        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
-        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+fn create_lights_out_hide_solution_closure(lights_out_solution_arc: Arc<Mutex<Option<stafra_lights_out::LightsOutSolution>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let lights_out_hide_solution_button = document.get_element_by_id("button_lights_out_hide_solution").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    }) as Box<dyn Fn()>);
+    let lights_out_hide_solution_closure = Closure::wrap(Box::new(move ||
+    {
+        *lights_out_solution_arc.lock().unwrap() = None;
+        update_lights_out_solution(None, "");
+    })
+    as Box<dyn Fn()>);
 
-    increment_spawn_button.set_onclick(Some(increment_spawn_closure.as_ref().unchecked_ref()));
-    increment_spawn_closure.forget();
+    lights_out_hide_solution_button.set_onclick(Some(lights_out_hide_solution_closure.as_ref().unchecked_ref()));
+    lights_out_hide_solution_closure.forget();
 }
 
-fn create_change_spawn_closure(stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_lights_out_export_solution_closure(lights_out_solution_arc: Arc<Mutex<Option<stafra_lights_out::LightsOutSolution>>>)
 {
-    let window = web_sys::window().unwrap();
-    let document = window.document().unwrap();
-    let spawn_range = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let document = web_sys::window().unwrap().document().unwrap();
+    let lights_out_export_solution_button = document.get_element_by_id("button_lights_out_export_solution").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let spawn_query_string_timeout_closure = Closure::wrap(Box::new(move |spawn_value: &JsValue|
+    let lights_out_export_solution_closure = Closure::wrap(Box::new(move ||
     {
-        let window = web_sys::window().unwrap();
-        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
-
-        query_string.set("spawn_period", &spawn_value.as_string().unwrap());
+        if let Some(solution) = lights_out_solution_arc.lock().unwrap().as_ref()
+        {
+            let solution_image = solution.export_image();
+            let image_data = web_sys::ImageData::new_with_u8_clamped_array_and_sh(Clamped(solution_image.pixel_data.as_slice()), solution_image.image_width, solution_image.image_height).unwrap();
+            save_image_data(image_data, "LightsOutSolution.png");
+        }
+    })
+    as Box<dyn Fn()>);
 
-        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
-        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+    lights_out_export_solution_button.set_onclick(Some(lights_out_export_solution_closure.as_ref().unchecked_ref()));
+    lights_out_export_solution_closure.forget();
+}
 
-    }) as Box<dyn FnMut(&JsValue)>);
+fn create_click_rule_analyze_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, click_rule_analysis_arc: Arc<Mutex<Option<stafra_click_analysis::ClickRuleAnalysis>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let click_rule_analyze_button = document.get_element_by_id("button_click_rule_analyze").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let timeout_update_arguments = js_sys::Array::new_with_length(1);
-    let change_spawn_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let click_rule_analyze_closure = Closure::wrap(Box::new(move ||
     {
+        let app_state        = app_state_rc.borrow();
         let mut stafra_state = stafra_state_rc.borrow_mut();
 
-        let spawn_period_slider = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        //The restriction is taken from the session
+        let click_rule_data         = app_state.click_rule_data;
+        let click_rule_analysis_arc = click_rule_analysis_arc.clone();
+        stafra_state.post_save_session_request(Box::new(move |session_result|
+        {
+            let analysis_result = session_result.and_then(|session| stafra_click_analysis::analyze_click_rule(session.board_size, &session.restriction, &click_rule_data));
 
-        let spawn_period_input = document.get_element_by_id("spawn_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-        let new_spawn_period = spawn_period_slider.value_as_number() as u32;
+            let mut click_rule_analysis = click_rule_analysis_arc.lock().unwrap();
+            match analysis_result
+            {
+                Ok(analysis) =>
+                {
+                    update_click_rule_analysis(Some(&analysis), "");
+                    *click_rule_analysis = Some(analysis);
+                }
 
-        spawn_period_input.set_value(&new_spawn_period.to_string());
-        stafra_state.set_spawn_period(new_spawn_period);
+                Err(message) =>
+                {
+                    *click_rule_analysis = None;
+                    update_click_rule_analysis(None, &message);
+                    web_sys::console::error_1(&message.into());
+                }
+            }
+        }));
+    })
+    as Box<dyn Fn()>);
 
-        timeout_update_arguments.set(0, JsValue::from_str(&new_spawn_period.to_string()));
-        window.set_timeout_with_callback_and_timeout_and_arguments(&spawn_query_string_timeout_closure.as_ref().unchecked_ref(), 50, &timeout_update_arguments).unwrap();
+    click_rule_analyze_button.set_onclick(Some(click_rule_analyze_closure.as_ref().unchecked_ref()));
+    click_rule_analyze_closure.forget();
+}
 
-    }) as Box<dyn Fn(web_sys::Event)>);
+fn create_click_rule_analysis_export_closure(click_rule_analysis_arc: Arc<Mutex<Option<stafra_click_analysis::ClickRuleAnalysis>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let click_rule_analysis_export_button = document.get_element_by_id("button_click_rule_analysis_export").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    spawn_range.set_oninput(Some(change_spawn_closure.as_ref().unchecked_ref()));
-    change_spawn_closure.forget();
+    let click_rule_analysis_export_closure = Closure::wrap(Box::new(move ||
+    {
+        if let Some(analysis) = click_rule_analysis_arc.lock().unwrap().as_ref()
+        {
+            save_text_data(&analysis.to_json(), "ClickRuleAnalysis.json");
+        }
+    })
+    as Box<dyn Fn()>);
+
+    click_rule_analysis_export_button.set_onclick(Some(click_rule_analysis_export_closure.as_ref().unchecked_ref()));
+    click_rule_analysis_export_closure.forget();
 }
 
-fn create_change_smooth_transform_closure(stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_quiet_pattern_view_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, click_rule_analysis_arc: Arc<Mutex<Option<stafra_click_analysis::ClickRuleAnalysis>>>)
 {
-    let window   = web_sys::window().unwrap();
-    let document = window.document().unwrap();
-    let smooth_transform_checkbox = document.get_element_by_id("smooth_transform_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let document = web_sys::window().unwrap().document().unwrap();
+    let quiet_pattern_view_button = document.get_element_by_id("button_quiet_pattern_view").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let smooth_transform_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let quiet_pattern_view_closure = Closure::wrap(Box::new(move ||
     {
+        let mut app_state    = app_state_rc.borrow_mut();
         let mut stafra_state = stafra_state_rc.borrow_mut();
 
-        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
-
-        let smooth_transform_checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-        stafra_state.set_smooth_transform_enabled(smooth_transform_checkbox.checked());
+        let document            = web_sys::window().unwrap().document().unwrap();
+        let quiet_pattern_input = document.get_element_by_id("quiet_pattern_index").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
 
-        if smooth_transform_checkbox.checked()
-        {
-            query_string.set("smooth_transform", "y");
-        }
-        else
+        let quiet_pattern_image = match click_rule_analysis_arc.lock().unwrap().as_ref()
         {
-            query_string.delete("smooth_transform");
-        }
+            Some(analysis) => analysis.quiet_pattern_image(quiet_pattern_input.value_as_number() as usize),
+            None           => None
+        };
 
-        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
-        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+        let quiet_pattern_image = match quiet_pattern_image
+        {
+            Some(quiet_pattern_image) => quiet_pattern_image,
+            None                      => return
+        };
 
-    }) as Box<dyn Fn(web_sys::Event)>);
+        load_board_image(&mut **stafra_state, &mut app_state, quiet_pattern_image.pixel_data, quiet_pattern_image.image_width, quiet_pattern_image.image_height);
+    })
+    as Box<dyn Fn()>);
 
-    smooth_transform_checkbox.set_onclick(Some(smooth_transform_closure.as_ref().unchecked_ref()));
-    smooth_transform_closure.forget();
+    quiet_pattern_view_button.set_onclick(Some(quiet_pattern_view_closure.as_ref().unchecked_ref()));
+    quiet_pattern_view_closure.forget();
 }
 
-fn create_change_last_frame_closure(app_state_rc: Rc<RefCell<app_state::AppState>>)
+fn create_update_order_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
 {
-    let window = web_sys::window().unwrap();
-    let document = window.document().unwrap();
-    let last_frame_input = document.get_element_by_id("last_frame_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let document = web_sys::window().unwrap().document().unwrap();
+    let update_order_button = document.get_element_by_id("button_update_order").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let change_last_frame_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let update_order_closure = Closure::wrap(Box::new(move ||
     {
-        let mut app_state = app_state_rc.borrow_mut();
-
-        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+        let app_state        = app_state_rc.borrow();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
 
-        let last_frame_input = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-        let new_value = last_frame_input.value_as_number();
-        if !new_value.is_nan()
-        {
-            app_state.last_frame = new_value as u32;
-            query_string.set("final_frame", &new_value.to_string());
-        }
-        else
+        //The restriction is taken from the session
+        let click_rule_data = app_state.click_rule_data;
+        stafra_state.post_save_session_request(Box::new(move |session_result|
         {
-            app_state.last_frame = u32::MAX;
-            query_string.delete("final_frame");
-        }
+            let analysis_result = session_result.and_then(|session| stafra_click_analysis::analyze_update_order(session.board_size, &session.restriction, &click_rule_data));
 
-        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
-        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+            let status_text = match analysis_result
+            {
+                Ok(analysis) =>
+                {
+                    let mut status_text = format!("Every board (probably): {}, minimal polynomial degree {}", update_period_text(analysis.operator_period), analysis.minimal_polynomial_degree);
+                    for (reset_type, start_period) in analysis.start_periods
+                    {
+                        status_text += &format!("\n{}: {}", standard_reset_type_name(reset_type), update_period_text(start_period));
+                    }
 
-    }) as Box<dyn Fn(web_sys::Event)>);
+                    status_text
+                }
 
-    last_frame_input.set_oninput(Some(change_last_frame_closure.as_ref().unchecked_ref()));
-    change_last_frame_closure.forget();
+                Err(message) =>
+                {
+                    web_sys::console::error_1(&message.clone().into());
+                    message
+                }
+            };
+
+            let document = web_sys::window().unwrap().document().unwrap();
+            let update_order_status = document.get_element_by_id("update_order_status").unwrap();
+            update_order_status.set_text_content(Some(&status_text));
+        }));
+    })
+    as Box<dyn Fn()>);
+
+    update_order_button.set_onclick(Some(update_order_closure.as_ref().unchecked_ref()));
+    update_order_closure.forget();
 }
 
-fn create_show_grid_closure(stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_upload_inverse_problem_target_closure()
 {
     let document = web_sys::window().unwrap().document().unwrap();
-    let show_grid_checkbox = document.get_element_by_id("grid_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let upload_inverse_problem_target_button = document.get_element_by_id("button_inverse_problem_upload").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let show_grid_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let upload_inverse_problem_target_closure = Closure::wrap(Box::new(move |_event: web_sys::Event|
     {
-        let mut stafra_state = stafra_state_rc.borrow_mut();
+        let inverse_problem_upload_input = document.get_element_by_id("inverse_problem_input").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        inverse_problem_upload_input.click();
 
-        let show_grid_checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-        stafra_state.set_click_rule_grid_enabled(show_grid_checkbox.checked());
     }) as Box<dyn Fn(web_sys::Event)>);
 
-    show_grid_checkbox.set_onclick(Some(show_grid_closure.as_ref().unchecked_ref()));
-    show_grid_closure.forget();
+    upload_inverse_problem_target_button.set_onclick(Some(upload_inverse_problem_target_closure.as_ref().unchecked_ref()));
+    upload_inverse_problem_target_closure.forget();
 }
 
-fn create_board_upload_input_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_inverse_problem_input_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, inverse_problem_solution_arc: Arc<Mutex<Option<stafra_inverse_problem::InverseProblemSolution>>>)
 {
     let document = web_sys::window().unwrap().document().unwrap();
-    let initial_state_upload_input = document.get_element_by_id("board_input").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let inverse_problem_upload_input = document.get_element_by_id("inverse_problem_input").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
 
-    let board_upload_image_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let target_upload_image_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
     {
         let mut app_state    = app_state_rc.borrow_mut();
         let mut stafra_state = stafra_state_rc.borrow_mut();
 
-        let board_image = event.target().unwrap().dyn_into::<web_sys::HtmlImageElement>().unwrap();
+        let target_image = event.target().unwrap().dyn_into::<web_sys::HtmlImageElement>().unwrap();
 
         let document       = web_sys::window().unwrap().document().unwrap();
-        let canvas_board   = document.create_element("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
-        let canvas_context = canvas_board.get_context("2d").unwrap().unwrap().dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
+        let canvas_target  = document.create_element("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+        let canvas_context = canvas_target.get_context("2d").unwrap().unwrap().dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
 
-        canvas_board.set_width(board_image.width());
-        canvas_board.set_height(board_image.height());
+        canvas_target.set_width(target_image.width());
+        canvas_target.set_height(target_image.height());
 
-        canvas_context.draw_image_with_html_image_element(&board_image, 0.0, 0.0).expect("Draw image error!");
-        let image_data = canvas_context.get_image_data(0.0, 0.0, board_image.width() as f64, board_image.height() as f64).unwrap();
+        canvas_context.draw_image_with_html_image_element(&target_image, 0.0, 0.0).expect("Draw image error!");
+        let image_data = canvas_context.get_image_data(0.0, 0.0, target_image.width() as f64, target_image.height() as f64).unwrap();
 
-        let new_size = stafra_state.reset_board_custom(image_data.data().to_vec(), image_data.width(), image_data.height());
+        canvas_target.remove();
 
-        canvas_board.remove();
+        //The target is placed on the board the same way as an uploaded board, so the board is the target until the solution replaces it
+        stafra_state.set_image_import_options(read_image_import_options_ui());
+        let reset_result = stafra_state.reset_board_custom(image_data.data().to_vec(), image_data.width(), image_data.height());
 
+        let new_size = match reset_result
+        {
+            Ok(new_size) => new_size,
+            Err(message) =>
+            {
+                web_sys::console::error_1(&message.into());
+                return;
+            }
+        };
 
         let size_select = document.get_element_by_id("sizes").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
         let size_index = (std::mem::size_of::<u32>() * 8) as u32 - new_size.leading_zeros() - 1;
         size_select.set_selected_index(size_index as i32);
 
         update_last_frame_with_size(new_size, &mut app_state);
+
+        let steps_input = document.get_element_by_id("inverse_problem_steps").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        let step_count  = steps_input.value_as_number() as u32;
+
+        let click_rule_data              = app_state.click_rule_data;
+        let inverse_problem_solution_arc = inverse_problem_solution_arc.clone();
+        stafra_state.post_save_session_request(Box::new(move |session_result|
+        {
+            let solution_result = session_result.and_then(|session| stafra_inverse_problem::solve_inverse_problem(session.board_size, &session.board, &session.restriction, &click_rule_data, step_count));
+
+            let status_text = match solution_result
+            {
+                Ok(solution) =>
+                {
+                    let status_text = if solution.wrong_cells == 0
+                    {
+                        format!("Exact solution for {} steps", solution.step_count)
+                    }
+                    else
+                    {
+                        format!("No exact solution, {} cells differ from the target after {} steps", solution.wrong_cells, solution.step_count)
+                    };
+
+                    *inverse_problem_solution_arc.lock().unwrap() = Some(solution);
+                    status_text
+                }
+
+                Err(message) =>
+                {
+                    web_sys::console::error_1(&message.clone().into());
+                    message
+                }
+            };
+
+            let document = web_sys::window().unwrap().document().unwrap();
+            let inverse_problem_status = document.get_element_by_id("inverse_problem_status").unwrap();
+            inverse_problem_status.set_text_content(Some(&status_text));
+        }));
     }) as Box<dyn Fn(web_sys::Event)>);
 
-    let board_upload_image_element = web_sys::HtmlImageElement::new().unwrap();
-    board_upload_image_element.set_onload(Some(board_upload_image_closure.as_ref().unchecked_ref()));
+    let target_upload_image_element = web_sys::HtmlImageElement::new().unwrap();
+    target_upload_image_element.set_onload(Some(target_upload_image_closure.as_ref().unchecked_ref()));
 
-    let board_file_read_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let target_file_read_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
     {
         let file_reader      = event.target().unwrap().dyn_into::<web_sys::FileReader>().unwrap();
         let file_read_result = file_reader.result().unwrap();
 
         let file_data = file_read_result.as_string().unwrap();
-        board_upload_image_element.set_src(&file_data);
+        target_upload_image_element.set_src(&file_data);
     }) as Box<dyn Fn(web_sys::Event)>);
 
-    let board_file_reader = web_sys::FileReader::new().unwrap();
-    board_file_reader.set_onload(Some(board_file_read_closure.as_ref().unchecked_ref()));
+    let target_file_reader = web_sys::FileReader::new().unwrap();
+    target_file_reader.set_onload(Some(target_file_read_closure.as_ref().unchecked_ref()));
 
-    board_file_read_closure.forget();
-    board_upload_image_closure.forget();
+    target_file_read_closure.forget();
+    target_upload_image_closure.forget();
 
-    let initial_state_upload_input_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let inverse_problem_upload_input_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
     {
         let input_files = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap().files().unwrap();
         if input_files.length() > 0
         {
-            let image_file = input_files.item(0).unwrap();
-            let filename   = image_file.name();
-
-            //Change the select option text
-            let initial_state_select = document.get_element_by_id("initial_states").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
-            let custom_option_index  = find_select_option_index(&initial_state_select, "initial_state_custom_value");
-
-            let initial_state_options = initial_state_select.options();
-            let custom_option_element = initial_state_options.item(custom_option_index.try_into().unwrap()).unwrap().dyn_into::<web_sys::HtmlOptionElement>().unwrap();
-
-            custom_option_element.set_text(&filename);
-            initial_state_select.set_selected_index(custom_option_index);
-
-            //Read the data
-            board_file_reader.read_as_data_url(&image_file).expect("Read data url error!");
+            target_file_reader.read_as_data_url(&input_files.item(0).unwrap()).expect("Read data url error!");
         }
     }) as Box<dyn Fn(web_sys::Event)>);
 
-    initial_state_upload_input.set_onchange(Some(initial_state_upload_input_closure.as_ref().unchecked_ref()));
-    initial_state_upload_input_closure.forget();
+    inverse_problem_upload_input.set_onchange(Some(inverse_problem_upload_input_closure.as_ref().unchecked_ref()));
+    inverse_problem_upload_input_closure.forget();
 }
 
-fn create_upload_restriction_closure()
+fn create_save_png_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
 {
     let document = web_sys::window().unwrap().document().unwrap();
-    let upload_restriction_button = document.get_element_by_id("button_upload_restriction").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let save_png_button = document.get_element_by_id("button_save_png").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let upload_restriction_closure = Closure::wrap(Box::new(move |_event: web_sys::Event|
+    let save_png_closure = Closure::wrap(Box::new(move ||
     {
-        let restriction_upload_input = document.get_element_by_id("restriction_input").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-        restriction_upload_input.click();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+        stafra_state.post_save_png_request(Box::new(move |png_result|
+        {
+            match png_result
+            {
+                Ok(png_image) =>
+                {
+                    let image_data = web_sys::ImageData::new_with_u8_clamped_array_and_sh(Clamped(png_image.pixel_data.as_slice()), png_image.image_width, png_image.image_height).unwrap();
+                    save_image_data(image_data, "StabilityFractal.png");
+                }
 
-    }) as Box<dyn Fn(web_sys::Event)>);
+                Err(message) =>
+                {
+                    web_sys::console::error_1(&message.into());
+                }
+            }
+        }));
+    })
+    as Box<dyn Fn()>);
 
-    upload_restriction_button.set_onclick(Some(upload_restriction_closure.as_ref().unchecked_ref()));
-    upload_restriction_closure.forget();
+    save_png_button.set_onclick(Some(save_png_closure.as_ref().unchecked_ref()));
+    save_png_closure.forget();
 }
 
-fn create_upload_restriction_input_closure(stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_save_session_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
 {
     let document = web_sys::window().unwrap().document().unwrap();
-    let restriction_upload_input = document.get_element_by_id("restriction_input").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let save_session_button = document.get_element_by_id("button_save_session").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let restriction_upload_image_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let save_session_closure = Closure::wrap(Box::new(move ||
     {
         let mut stafra_state = stafra_state_rc.borrow_mut();
+        stafra_state.post_save_session_request(Box::new(move |session_result|
+        {
+            match session_result
+            {
+                Ok(session) =>
+                {
+                    save_session_data(session.to_bytes());
+                }
 
-        let restriction_image = event.target().unwrap().dyn_into::<web_sys::HtmlImageElement>().unwrap();
-
-        let canvas_restriction = document.create_element("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
-        let canvas_context     = canvas_restriction.get_context("2d").unwrap().unwrap().dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
-
-        canvas_restriction.set_width(restriction_image.width());
-        canvas_restriction.set_height(restriction_image.height());
-
-        canvas_context.draw_image_with_html_image_element(&restriction_image, 0.0, 0.0).expect("Draw image error!");
-        let image_data = canvas_context.get_image_data(0.0, 0.0, restriction_image.width() as f64, restriction_image.height() as f64).unwrap();
+                Err(message) =>
+                {
+                    web_sys::console::error_1(&message.into());
+                }
+            }
+        }));
+    })
+    as Box<dyn Fn()>);
 
-        stafra_state.upload_restriction(image_data.data().to_vec(), image_data.width(), image_data.height());
+    save_session_button.set_onclick(Some(save_session_closure.as_ref().unchecked_ref()));
+    save_session_closure.forget();
+}
 
-        canvas_restriction.remove();
+fn create_load_session_closure()
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let load_session_button = document.get_element_by_id("button_load_session").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-        let clear_restriction_button = document.get_element_by_id("button_clear_restriction").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-        clear_restriction_button.set_hidden(false);
+    let load_session_closure = Closure::wrap(Box::new(move |_event: web_sys::Event|
+    {
+        let session_input = document.get_element_by_id("session_input").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        session_input.click();
 
     }) as Box<dyn Fn(web_sys::Event)>);
 
-    let restriction_upload_image_element = web_sys::HtmlImageElement::new().unwrap();
-    restriction_upload_image_element.set_onload(Some(restriction_upload_image_closure.as_ref().unchecked_ref()));
+    load_session_button.set_onclick(Some(load_session_closure.as_ref().unchecked_ref()));
+    load_session_closure.forget();
+}
 
-    let restriction_file_read_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+fn create_session_input_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let session_input = document.get_element_by_id("session_input").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let session_file_read_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
     {
+        let mut app_state    = app_state_rc.borrow_mut();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
         let file_reader      = event.target().unwrap().dyn_into::<web_sys::FileReader>().unwrap();
         let file_read_result = file_reader.result().unwrap();
 
-        let file_data = file_read_result.as_string().unwrap();
-        restriction_upload_image_element.set_src(&file_data);
+        let session_data = js_sys::Uint8Array::new(&file_read_result).to_vec();
+        let load_result  = stafra_session::StafraSession::from_bytes(&session_data).and_then(|session|
+        {
+            stafra_state.load_session(&session).map(|_| session)
+        });
+
+        match load_result
+        {
+            Ok(session) =>
+            {
+                update_ui_from_session(&session, &mut app_state);
+            }
+
+            Err(message) =>
+            {
+                web_sys::console::error_1(&message.into());
+            }
+        }
     }) as Box<dyn Fn(web_sys::Event)>);
 
-    let restriction_file_reader = web_sys::FileReader::new().unwrap();
-    restriction_file_reader.set_onload(Some(restriction_file_read_closure.as_ref().unchecked_ref()));
+    let session_file_reader = web_sys::FileReader::new().unwrap();
+    session_file_reader.set_onload(Some(session_file_read_closure.as_ref().unchecked_ref()));
 
-    restriction_file_read_closure.forget();
-    restriction_upload_image_closure.forget();
+    session_file_read_closure.forget();
 
-    let restriction_upload_input_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let session_input_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
     {
-        let input_files = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap().files().unwrap();
+        let session_input = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        let input_files   = session_input.files().unwrap();
         if input_files.length() > 0
         {
-            let image_file = input_files.item(0).unwrap();
-            restriction_file_reader.read_as_data_url(&image_file).expect("Read data url error!");
+            let session_file = input_files.item(0).unwrap();
+            session_file_reader.read_as_array_buffer(&session_file).expect("Read array buffer error!");
         }
+
+        //Allow loading the same file again
+        session_input.set_value("");
     }) as Box<dyn Fn(web_sys::Event)>);
 
-    restriction_upload_input.set_onchange(Some(restriction_upload_input_closure.as_ref().unchecked_ref()));
-    restriction_upload_input_closure.forget();
+    session_input.set_onchange(Some(session_input_closure.as_ref().unchecked_ref()));
+    session_input_closure.forget();
 }
 
-fn create_clear_restriction_closure(stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_play_pause_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, video_record_state_rc: Rc<RefCell<video_record_state::VideoRecordState>>)
 {
     let document = web_sys::window().unwrap().document().unwrap();
-    let clear_restriction_button  = document.get_element_by_id("button_clear_restriction").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let play_pause_button = document.get_element_by_id("button_play_pause").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let clear_restriction_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let play_pause_closure = Closure::wrap(Box::new(move ||
     {
-        let mut stafra_state = stafra_state_rc.borrow_mut();
-        stafra_state.clear_restriction();
+        let mut app_state      = app_state_rc.borrow_mut();
+        let mut stafra_state   = stafra_state_rc.borrow_mut();
+        let video_record_state = video_record_state_rc.borrow();
 
-        let clear_restriction_button = event.target().unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-        clear_restriction_button.set_hidden(true);
-    }) as Box<dyn Fn(web_sys::Event)>);
+        stafra_state.set_click_rule_read_only(true);
 
-    clear_restriction_button.set_onclick(Some(clear_restriction_closure.as_ref().unchecked_ref()));
-    clear_restriction_closure.forget();
+        if video_record_state.is_recording_supported()
+        {
+            app_state.run_state = match app_state.run_state
+            {
+                RunState::Stopped | RunState::Paused => RunState::Running,
+                RunState::Running                    => RunState::Paused,
+                RunState::Recording                  => RunState::PausedRecording,
+                RunState::PausedRecording            => RunState::Recording,
+                RunState::SavePendingRecording       => RunState::SavePendingRecording
+            };
+        }
+        else
+        {
+            app_state.run_state = match app_state.run_state
+            {
+                RunState::Stopped | RunState::Paused => RunState::Running,
+                RunState::Running                    => RunState::Paused,
+                RunState::Recording                  => RunState::Paused,
+                RunState::PausedRecording            => RunState::Running,
+                RunState::SavePendingRecording       => RunState::SavePendingRecording
+            };
+        }
+
+        update_ui(app_state.run_state);
+
+        if app_state.run_state == RunState::PausedRecording
+        {
+            update_next_frame_button_paused_recording(!stafra_state.video_frame_queue_full());
+        }
+
+    }) as Box<dyn Fn()>);
+
+    play_pause_button.set_onclick(Some(play_pause_closure.as_ref().unchecked_ref()));
+    play_pause_closure.forget();
 }
 
-fn create_select_initial_state_closure(stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_stop_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, video_record_state_rc: Rc<RefCell<video_record_state::VideoRecordState>>)
 {
-    let window = web_sys::window().unwrap();
-    let document = window.document().unwrap();
-    let initial_state_select = document.get_element_by_id("initial_states").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let document = web_sys::window().unwrap().document().unwrap();
+    let stop_button = document.get_element_by_id("button_stop_record").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let select_initial_state_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let stop_closure = Closure::wrap(Box::new(move ||
     {
-        let mut stafra_state = stafra_state_rc.borrow_mut();
-
-        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+        let mut app_state          = app_state_rc.borrow_mut();
+        let mut stafra_state       = stafra_state_rc.borrow_mut();
+        let mut video_record_state = video_record_state_rc.borrow_mut();
 
-        let board_reset_select = event.target().unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
-        match board_reset_select.value().as_str()
+        match app_state.run_state
         {
-            "initial_state_corners" =>
+            RunState::Stopped =>
             {
-                stafra_state.reset_board_standard(stafra_state::StandardResetBoardType::Corners);
-                query_string.set("initial_state", "corners");
-            },
+                if !video_record_state.pending() && video_record_state.is_recording_supported()
+                {
+                    video_record_state.restart().unwrap();
+                    app_state.run_state = RunState::Recording;
+                }
+            }
 
-            "initial_state_sides" =>
+            RunState::Recording | RunState::PausedRecording =>
             {
-                stafra_state.reset_board_standard(stafra_state::StandardResetBoardType::Edges);
-                query_string.set("initial_state", "edges");
-            },
+                app_state.run_state = RunState::Stopped;
 
-            "initial_state_center" =>
-            {
-                stafra_state.reset_board_standard(stafra_state::StandardResetBoardType::Center);
-                query_string.set("initial_state", "center");
-            },
+                stafra_state.reset_board_unchanged();
+                stafra_state.set_click_rule_read_only(false);
+            }
 
-            "initial_state_custom" =>
+            RunState::Paused | RunState::Running =>
             {
-                let initial_state_upload_input = document.get_element_by_id("board_input").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-                initial_state_upload_input.click();
-
-                query_string.delete("initial_state");
-            },
+                app_state.run_state = RunState::Stopped;
 
-            _ => {}
-        }
+                stafra_state.reset_board_unchanged();
+                stafra_state.set_click_rule_read_only(false);
+            }
 
-        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
-        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+            RunState::SavePendingRecording =>
+            {
+            }
+        };
 
-    }) as Box<dyn Fn(web_sys::Event)>);
+        update_ui(app_state.run_state);
+    }) as Box<dyn Fn()>);
 
-    initial_state_select.set_onchange(Some(select_initial_state_closure.as_ref().unchecked_ref()));
-    select_initial_state_closure.forget();
+    stop_button.set_onclick(Some(stop_closure.as_ref().unchecked_ref()));
+    stop_closure.forget();
 }
 
-fn create_select_size_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<stafra_state::StafraState>>)
+fn create_next_frame_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>, video_record_state_rc: Rc<RefCell<video_record_state::VideoRecordState>>)
 {
-    let window = web_sys::window().unwrap();
-    let document = window.document().unwrap();
-    let size_select = document.get_element_by_id("sizes").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let document = web_sys::window().unwrap().document().unwrap();
+    let next_frame_button = document.get_element_by_id("button_next_frame").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let select_size_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    let next_frame_closure = Closure::wrap(Box::new(move ||
     {
-        let mut app_state    = app_state_rc.borrow_mut();
-        let mut stafra_state = stafra_state_rc.borrow_mut();
+        let mut app_state      = app_state_rc.borrow_mut();
+        let mut stafra_state   = stafra_state_rc.borrow_mut();
+        let video_record_state = video_record_state_rc.borrow_mut();
+
+        match app_state.run_state
+        {
+            RunState::PausedRecording => if !stafra_state.video_frame_queue_full() && video_record_state.is_recording_supported()
+            {
+                let video_frame_channel = video_record_state.get_video_frame_channel();
+
+                stafra_state.update();
+                stafra_state.post_video_frame_request(Box::new(move |video_frame_result|
+                {
+                    match video_frame_result
+                    {
+                        Ok(video_frame) => video_frame_channel.send(video_record_state::VideoFrameData{pixel_data: video_frame.pixel_data, width: video_frame.image_width, height: video_frame.image_height}).unwrap(),
+                        Err(message)    => web_sys::console::error_1(&message.into())
+                    }
+                }));
+
+                update_next_frame_button_paused_recording(!stafra_state.video_frame_queue_full());
+                if app_state.last_frame == stafra_state.frame_number()
+                {
+                    app_state.run_state = RunState::Paused;
+                }
+            }
+
+            RunState::Paused =>
+            {
+                stafra_state.update();
+            }
+
+            RunState::Stopped =>
+            {
+                app_state.run_state = RunState::Paused;
+                update_ui(app_state.run_state);
+
+                //Update twice to properly initialize the first frame
+                stafra_state.update();
+                stafra_state.update();
+            }
+
+            _ => {}
+        }
+    }) as Box<dyn Fn()>);
+
+    next_frame_button.set_onclick(Some(next_frame_closure.as_ref().unchecked_ref()));
+    next_frame_closure.forget();
+}
+
+fn create_prev_frame_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let prev_frame_button = document.get_element_by_id("button_prev_frame").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+
+    let prev_frame_closure = Closure::wrap(Box::new(move ||
+    {
+        let app_state        = app_state_rc.borrow();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        if app_state.run_state == RunState::Paused
+        {
+            if let Err(message) = stafra_state.step_back()
+            {
+                web_sys::console::error_1(&message.into());
+            }
+        }
+    }) as Box<dyn Fn()>);
+
+    prev_frame_button.set_onclick(Some(prev_frame_closure.as_ref().unchecked_ref()));
+    prev_frame_closure.forget();
+}
+
+fn create_timeline_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let timeline_slider = document.get_element_by_id("timeline_slider").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let timeline_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let app_state        = app_state_rc.borrow();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        if app_state.run_state == RunState::Paused
+        {
+            let timeline_slider = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            let new_frame = timeline_slider.value_as_number();
+            if !new_frame.is_nan()
+            {
+                if let Err(message) = stafra_state.seek_frame(new_frame as u32)
+                {
+                    web_sys::console::error_1(&message.into());
+                }
+            }
+        }
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    timeline_slider.set_oninput(Some(timeline_closure.as_ref().unchecked_ref()));
+    timeline_closure.forget();
+}
+
+fn create_enable_last_frame_closure(app_state_rc: Rc<RefCell<app_state::AppState>>)
+{
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let last_frame_checkbox = document.get_element_by_id("last_frame_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let enable_last_frame_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let mut app_state = app_state_rc.borrow_mut();
 
         let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
 
-        let size_select = event.target().unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
-        let board_size_selected_index = size_select.selected_index() as u32;
+        let last_frame_input = document.get_element_by_id("last_frame_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
 
-        let new_width  = app_state::AppState::board_size_from_index(board_size_selected_index);
-        let new_height = app_state::AppState::board_size_from_index(board_size_selected_index);
+        let last_frame_checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        if last_frame_checkbox.checked()
+        {
+            let last_frame = last_frame_input.value_as_number();
+            if !last_frame.is_nan()
+            {
+                app_state.last_frame = last_frame as u32;
+                query_string.set("final_frame", &last_frame.to_string());
+            }
+            else
+            {
+                app_state.last_frame = u32::MAX;
+                query_string.delete("final_frame");
+            }
 
-        update_last_frame_with_size(std::cmp::min(new_width, new_height), &mut app_state);
-        stafra_state.resize_board(new_width, new_height);
+            last_frame_input.set_disabled(false);
+        }
+        else
+        {
+            app_state.last_frame = u32::MAX;
+            last_frame_input.set_disabled(true);
 
-        query_string.set("size_index", &board_size_selected_index.to_string());
+            query_string.delete("final_frame");
+        }
+
+        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    last_frame_checkbox.set_onclick(Some(enable_last_frame_closure.as_ref().unchecked_ref()));
+    enable_last_frame_closure.forget();
+}
+
+fn create_enable_spawn_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let spawn_checkbox = document.get_element_by_id("spawn_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let enable_spawn_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+
+        let spawn_decrement_button    = document.get_element_by_id("decrement_spawn").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+        let spawn_increment_button    = document.get_element_by_id("increment_spawn").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+        let spawn_slider              = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        let smooth_transform_checkbox = document.get_element_by_id("smooth_transform_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+        let spawn_checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+        spawn_decrement_button.set_disabled(!spawn_checkbox.checked());
+        spawn_increment_button.set_disabled(!spawn_checkbox.checked());
+        spawn_slider.set_disabled(!spawn_checkbox.checked());
+        smooth_transform_checkbox.set_disabled(!spawn_checkbox.checked());
+
+        if spawn_checkbox.checked()
+        {
+            let spawn_period_input = document.get_element_by_id("spawn_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            let spawn_period = spawn_period_input.value().parse::<u32>().expect("Not a number");
+
+            stafra_state.set_spawn_period(spawn_period);
+            query_string.set("spawn_period", &spawn_period.to_string());
+        }
+        else
+        {
+            stafra_state.set_spawn_period(u32::MAX);
+            query_string.delete("spawn_period");
+        }
+
+        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    spawn_checkbox.set_onclick(Some(enable_spawn_closure.as_ref().unchecked_ref()));
+    enable_spawn_closure.forget();
+}
+
+fn create_decrement_spawn_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let decrement_spawn_button = document.get_element_by_id("decrement_spawn").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+
+    let decrement_spawn_closure = Closure::wrap(Box::new(move ||
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+
+        let spawn_period_input = document.get_element_by_id("spawn_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+        let spawn_period_slider = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        let new_spawn_period = ((spawn_period_input.value().parse::<u32>().expect("Not a number")) - 1).clamp(1, 255);
+
+        let spawn_period_string = new_spawn_period.to_string();
+
+        spawn_period_input.set_value(&spawn_period_string);
+        spawn_period_slider.set_value(&spawn_period_string);
+
+        query_string.set("spawn_period", &spawn_period_string);
+        stafra_state.set_spawn_period(new_spawn_period);
+
+        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+
+    }) as Box<dyn Fn()>);
+
+    decrement_spawn_button.set_onclick(Some(decrement_spawn_closure.as_ref().unchecked_ref()));
+    decrement_spawn_closure.forget();
+}
+
+fn create_increment_spawn_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let increment_spawn_button = document.get_element_by_id("increment_spawn").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+
+    let increment_spawn_closure = Closure::wrap(Box::new(move ||
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+
+        let spawn_period_input = document.get_element_by_id("spawn_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+        let spawn_period_slider = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        let new_spawn_period = ((spawn_period_input.value().parse::<u32>().expect("Not a number")) + 1).clamp(1, 255);
+
+        let spawn_period_string = new_spawn_period.to_string();
+
+        spawn_period_input.set_value(&spawn_period_string);
+        spawn_period_slider.set_value(&spawn_period_string);
+
+        query_string.set("spawn_period", &spawn_period_string);
+        stafra_state.set_spawn_period(new_spawn_period);
+
+        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+
+    }) as Box<dyn Fn()>);
+
+    increment_spawn_button.set_onclick(Some(increment_spawn_closure.as_ref().unchecked_ref()));
+    increment_spawn_closure.forget();
+}
+
+fn create_change_spawn_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let spawn_range = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let spawn_query_string_timeout_closure = Closure::wrap(Box::new(move |spawn_value: &JsValue|
+    {
+        let window = web_sys::window().unwrap();
+        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+
+        query_string.set("spawn_period", &spawn_value.as_string().unwrap());
 
         let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
         window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
 
-    }) as Box<dyn Fn(web_sys::Event)>);
+    }) as Box<dyn FnMut(&JsValue)>);
+
+    let timeout_update_arguments = js_sys::Array::new_with_length(1);
+    let change_spawn_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        let spawn_period_slider = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+        let spawn_period_input = document.get_element_by_id("spawn_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        let new_spawn_period = spawn_period_slider.value_as_number() as u32;
+
+        spawn_period_input.set_value(&new_spawn_period.to_string());
+        stafra_state.set_spawn_period(new_spawn_period);
+
+        timeout_update_arguments.set(0, JsValue::from_str(&new_spawn_period.to_string()));
+        window.set_timeout_with_callback_and_timeout_and_arguments(&spawn_query_string_timeout_closure.as_ref().unchecked_ref(), 50, &timeout_update_arguments).unwrap();
+
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    spawn_range.set_oninput(Some(change_spawn_closure.as_ref().unchecked_ref()));
+    change_spawn_closure.forget();
+}
+
+fn create_change_smooth_transform_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let window   = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let smooth_transform_checkbox = document.get_element_by_id("smooth_transform_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let smooth_transform_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+
+        let smooth_transform_checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        stafra_state.set_smooth_transform_enabled(smooth_transform_checkbox.checked());
+
+        if smooth_transform_checkbox.checked()
+        {
+            query_string.set("smooth_transform", "y");
+        }
+        else
+        {
+            query_string.delete("smooth_transform");
+        }
+
+        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    smooth_transform_checkbox.set_onclick(Some(smooth_transform_closure.as_ref().unchecked_ref()));
+    smooth_transform_closure.forget();
+}
+
+fn create_change_last_frame_closure(app_state_rc: Rc<RefCell<app_state::AppState>>)
+{
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let last_frame_input = document.get_element_by_id("last_frame_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let change_last_frame_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let mut app_state = app_state_rc.borrow_mut();
+
+        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+
+        let last_frame_input = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        let new_value = last_frame_input.value_as_number();
+        if !new_value.is_nan()
+        {
+            app_state.last_frame = new_value as u32;
+            query_string.set("final_frame", &new_value.to_string());
+        }
+        else
+        {
+            app_state.last_frame = u32::MAX;
+            query_string.delete("final_frame");
+        }
+
+        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    last_frame_input.set_oninput(Some(change_last_frame_closure.as_ref().unchecked_ref()));
+    change_last_frame_closure.forget();
+}
+
+fn create_show_grid_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let show_grid_checkbox = document.get_element_by_id("grid_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let show_grid_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        let show_grid_checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        stafra_state.set_click_rule_grid_enabled(show_grid_checkbox.checked());
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    show_grid_checkbox.set_onclick(Some(show_grid_closure.as_ref().unchecked_ref()));
+    show_grid_closure.forget();
+}
+
+fn create_board_upload_input_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let initial_state_upload_input = document.get_element_by_id("board_input").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let board_upload_image_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let mut app_state    = app_state_rc.borrow_mut();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        let board_image = event.target().unwrap().dyn_into::<web_sys::HtmlImageElement>().unwrap();
+
+        let document       = web_sys::window().unwrap().document().unwrap();
+        let canvas_board   = document.create_element("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+        let canvas_context = canvas_board.get_context("2d").unwrap().unwrap().dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
+
+        canvas_board.set_width(board_image.width());
+        canvas_board.set_height(board_image.height());
+
+        canvas_context.draw_image_with_html_image_element(&board_image, 0.0, 0.0).expect("Draw image error!");
+        let image_data = canvas_context.get_image_data(0.0, 0.0, board_image.width() as f64, board_image.height() as f64).unwrap();
+
+        stafra_state.set_image_import_options(read_image_import_options_ui());
+        let reset_result = stafra_state.reset_board_custom(image_data.data().to_vec(), image_data.width(), image_data.height());
+
+        canvas_board.remove();
+
+        let new_size = match reset_result
+        {
+            Ok(new_size) => new_size,
+            Err(message) =>
+            {
+                web_sys::console::error_1(&message.into());
+                return;
+            }
+        };
+
+        let size_select = document.get_element_by_id("sizes").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+        let size_index = (std::mem::size_of::<u32>() * 8) as u32 - new_size.leading_zeros() - 1;
+        size_select.set_selected_index(size_index as i32);
+
+        update_last_frame_with_size(new_size, &mut app_state);
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    let board_upload_image_element = web_sys::HtmlImageElement::new().unwrap();
+    board_upload_image_element.set_onload(Some(board_upload_image_closure.as_ref().unchecked_ref()));
+
+    let board_file_read_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let file_reader      = event.target().unwrap().dyn_into::<web_sys::FileReader>().unwrap();
+        let file_read_result = file_reader.result().unwrap();
+
+        let file_data = file_read_result.as_string().unwrap();
+        board_upload_image_element.set_src(&file_data);
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    let board_file_reader = web_sys::FileReader::new().unwrap();
+    board_file_reader.set_onload(Some(board_file_read_closure.as_ref().unchecked_ref()));
+
+    board_file_read_closure.forget();
+    board_upload_image_closure.forget();
+
+    let initial_state_upload_input_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let input_files = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap().files().unwrap();
+        if input_files.length() > 0
+        {
+            let image_file = input_files.item(0).unwrap();
+            let filename   = image_file.name();
+
+            //Change the select option text
+            let initial_state_select = document.get_element_by_id("initial_states").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+            let custom_option_index  = find_select_option_index(&initial_state_select, "initial_state_custom_value");
+
+            let initial_state_options = initial_state_select.options();
+            let custom_option_element = initial_state_options.item(custom_option_index.try_into().unwrap()).unwrap().dyn_into::<web_sys::HtmlOptionElement>().unwrap();
+
+            custom_option_element.set_text(&filename);
+            initial_state_select.set_selected_index(custom_option_index);
+
+            //Read the data
+            board_file_reader.read_as_data_url(&image_file).expect("Read data url error!");
+        }
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    initial_state_upload_input.set_onchange(Some(initial_state_upload_input_closure.as_ref().unchecked_ref()));
+    initial_state_upload_input_closure.forget();
+}
+
+fn create_upload_restriction_closure()
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let upload_restriction_button = document.get_element_by_id("button_upload_restriction").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+
+    let upload_restriction_closure = Closure::wrap(Box::new(move |_event: web_sys::Event|
+    {
+        let restriction_upload_input = document.get_element_by_id("restriction_input").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        restriction_upload_input.click();
+
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    upload_restriction_button.set_onclick(Some(upload_restriction_closure.as_ref().unchecked_ref()));
+    upload_restriction_closure.forget();
+}
+
+fn create_auto_threshold_closure()
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let auto_threshold_checkbox = document.get_element_by_id("import_auto_threshold").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    //The automatic threshold replaces the manual one
+    let auto_threshold_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let document = web_sys::window().unwrap().document().unwrap();
+
+        let auto_threshold_checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        let threshold_slider        = document.get_element_by_id("import_threshold").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        threshold_slider.set_disabled(auto_threshold_checkbox.checked());
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    auto_threshold_checkbox.set_onclick(Some(auto_threshold_closure.as_ref().unchecked_ref()));
+    auto_threshold_closure.forget();
+}
+
+fn create_upload_restriction_input_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let restriction_upload_input = document.get_element_by_id("restriction_input").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let restriction_upload_image_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        let restriction_image = event.target().unwrap().dyn_into::<web_sys::HtmlImageElement>().unwrap();
+
+        let canvas_restriction = document.create_element("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+        let canvas_context     = canvas_restriction.get_context("2d").unwrap().unwrap().dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
+
+        canvas_restriction.set_width(restriction_image.width());
+        canvas_restriction.set_height(restriction_image.height());
+
+        canvas_context.draw_image_with_html_image_element(&restriction_image, 0.0, 0.0).expect("Draw image error!");
+        let image_data = canvas_context.get_image_data(0.0, 0.0, restriction_image.width() as f64, restriction_image.height() as f64).unwrap();
+
+        stafra_state.set_image_import_options(read_image_import_options_ui());
+        stafra_state.upload_restriction(image_data.data().to_vec(), image_data.width(), image_data.height());
+
+        canvas_restriction.remove();
+
+        let clear_restriction_button = document.get_element_by_id("button_clear_restriction").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+        clear_restriction_button.set_hidden(false);
+
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    let restriction_upload_image_element = web_sys::HtmlImageElement::new().unwrap();
+    restriction_upload_image_element.set_onload(Some(restriction_upload_image_closure.as_ref().unchecked_ref()));
+
+    let restriction_file_read_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let file_reader      = event.target().unwrap().dyn_into::<web_sys::FileReader>().unwrap();
+        let file_read_result = file_reader.result().unwrap();
+
+        let file_data = file_read_result.as_string().unwrap();
+        restriction_upload_image_element.set_src(&file_data);
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    let restriction_file_reader = web_sys::FileReader::new().unwrap();
+    restriction_file_reader.set_onload(Some(restriction_file_read_closure.as_ref().unchecked_ref()));
+
+    restriction_file_read_closure.forget();
+    restriction_upload_image_closure.forget();
+
+    let restriction_upload_input_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let input_files = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap().files().unwrap();
+        if input_files.length() > 0
+        {
+            let image_file = input_files.item(0).unwrap();
+            restriction_file_reader.read_as_data_url(&image_file).expect("Read data url error!");
+        }
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    restriction_upload_input.set_onchange(Some(restriction_upload_input_closure.as_ref().unchecked_ref()));
+    restriction_upload_input_closure.forget();
+}
+
+fn create_clear_restriction_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let clear_restriction_button  = document.get_element_by_id("button_clear_restriction").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+
+    let clear_restriction_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+        stafra_state.clear_restriction();
+
+        let clear_restriction_button = event.target().unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+        clear_restriction_button.set_hidden(true);
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    clear_restriction_button.set_onclick(Some(clear_restriction_closure.as_ref().unchecked_ref()));
+    clear_restriction_closure.forget();
+}
+
+fn create_select_initial_state_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let initial_state_select = document.get_element_by_id("initial_states").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+
+    let select_initial_state_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+
+        let board_reset_select = event.target().unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+        match board_reset_select.value().as_str()
+        {
+            "initial_state_corners" =>
+            {
+                stafra_state.reset_board_standard(stafra_state::StandardResetBoardType::Corners);
+                query_string.set("initial_state", "corners");
+            },
+
+            "initial_state_sides" =>
+            {
+                stafra_state.reset_board_standard(stafra_state::StandardResetBoardType::Edges);
+                query_string.set("initial_state", "edges");
+            },
+
+            "initial_state_center" =>
+            {
+                stafra_state.reset_board_standard(stafra_state::StandardResetBoardType::Center);
+                query_string.set("initial_state", "center");
+            },
+
+            "initial_state_random" =>
+            {
+                let random_board_params = read_random_board_ui();
+                stafra_state.reset_board_random(random_board_params);
+
+                query_string.set("initial_state", "random");
+                set_random_board_query(&query_string, &random_board_params);
+            },
+
+            "initial_state_pattern" =>
+            {
+                let pattern_board_params = read_pattern_board_ui();
+                stafra_state.reset_board_pattern(pattern_board_params);
+
+                query_string.set("initial_state", "pattern");
+                set_pattern_board_query(&query_string, &pattern_board_params);
+            },
+
+            "initial_state_expression" =>
+            {
+                let (expression_source, expression_seed) = read_expression_board_ui();
+                let reset_result = stafra_state.reset_board_expression(&expression_source, expression_seed);
+                if reset_result.is_ok()
+                {
+                    query_string.set("initial_state", "expression");
+                    set_expression_board_query(&query_string, &expression_source, expression_seed);
+                }
+
+                show_expression_error(reset_result.err());
+            },
+
+            "initial_state_custom" =>
+            {
+                let initial_state_upload_input = document.get_element_by_id("board_input").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                initial_state_upload_input.click();
+
+                query_string.delete("initial_state");
+            },
+
+            _ => {}
+        }
+
+        let random_board_selected = board_reset_select.value() == "initial_state_random";
+        if !random_board_selected
+        {
+            delete_random_board_query(&query_string);
+        }
+
+        let pattern_board_selected = board_reset_select.value() == "initial_state_pattern";
+        if !pattern_board_selected
+        {
+            delete_pattern_board_query(&query_string);
+        }
+
+        let expression_board_selected = board_reset_select.value() == "initial_state_expression";
+        if !expression_board_selected
+        {
+            delete_expression_board_query(&query_string);
+        }
+
+        let random_board_div = document.get_element_by_id("random_board_div").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+        random_board_div.set_hidden(!random_board_selected);
+
+        let pattern_board_div = document.get_element_by_id("pattern_board_div").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+        pattern_board_div.set_hidden(!pattern_board_selected);
+
+        let expression_board_div = document.get_element_by_id("expression_board_div").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+        expression_board_div.set_hidden(!expression_board_selected);
+
+        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    initial_state_select.set_onchange(Some(select_initial_state_closure.as_ref().unchecked_ref()));
+    select_initial_state_closure.forget();
+}
+
+fn create_change_random_board_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let random_seed_input        = document.get_element_by_id("random_seed").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let random_density_slider    = document.get_element_by_id("random_density").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let random_symmetry_select   = document.get_element_by_id("random_symmetry").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let random_shape_select      = document.get_element_by_id("random_shape").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let random_shape_size_slider = document.get_element_by_id("random_shape_size").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    //All random board controls reset the board the same way
+    let change_random_board_closure = Closure::wrap(Box::new(move ||
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+        reset_random_board_from_ui(stafra_state.as_mut());
+    }) as Box<dyn Fn()>);
+
+    random_seed_input.set_onchange(Some(change_random_board_closure.as_ref().unchecked_ref()));
+    random_density_slider.set_onchange(Some(change_random_board_closure.as_ref().unchecked_ref()));
+    random_symmetry_select.set_onchange(Some(change_random_board_closure.as_ref().unchecked_ref()));
+    random_shape_select.set_onchange(Some(change_random_board_closure.as_ref().unchecked_ref()));
+    random_shape_size_slider.set_onchange(Some(change_random_board_closure.as_ref().unchecked_ref()));
+    change_random_board_closure.forget();
+}
+
+fn create_change_pattern_board_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let pattern_kind_select  = document.get_element_by_id("pattern_kind").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let pattern_size_input   = document.get_element_by_id("pattern_size").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let pattern_cell_x_input = document.get_element_by_id("pattern_cell_x").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let pattern_cell_y_input = document.get_element_by_id("pattern_cell_y").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    //All pattern board controls reset the board the same way
+    let change_pattern_board_closure = Closure::wrap(Box::new(move ||
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+        reset_pattern_board_from_ui(stafra_state.as_mut());
+    }) as Box<dyn Fn()>);
+
+    pattern_kind_select.set_onchange(Some(change_pattern_board_closure.as_ref().unchecked_ref()));
+    pattern_size_input.set_onchange(Some(change_pattern_board_closure.as_ref().unchecked_ref()));
+    pattern_cell_x_input.set_onchange(Some(change_pattern_board_closure.as_ref().unchecked_ref()));
+    pattern_cell_y_input.set_onchange(Some(change_pattern_board_closure.as_ref().unchecked_ref()));
+    change_pattern_board_closure.forget();
+}
+
+fn create_change_expression_board_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let board_expression_input = document.get_element_by_id("board_expression").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let expression_seed_input  = document.get_element_by_id("expression_seed").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let change_expression_board_closure = Closure::wrap(Box::new(move ||
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+        reset_expression_board_from_ui(stafra_state.as_mut());
+    }) as Box<dyn Fn()>);
+
+    board_expression_input.set_onchange(Some(change_expression_board_closure.as_ref().unchecked_ref()));
+    expression_seed_input.set_onchange(Some(change_expression_board_closure.as_ref().unchecked_ref()));
+    change_expression_board_closure.forget();
+}
+
+fn create_new_seed_closure(stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let new_seed_button = document.get_element_by_id("button_new_seed").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+
+    let new_seed_closure = Closure::wrap(Box::new(move ||
+    {
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        let document = web_sys::window().unwrap().document().unwrap();
+        let random_seed_input = document.get_element_by_id("random_seed").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        random_seed_input.set_value_as_number(generate_random_seed() as f64);
+
+        reset_random_board_from_ui(stafra_state.as_mut());
+    }) as Box<dyn Fn()>);
+
+    new_seed_button.set_onclick(Some(new_seed_closure.as_ref().unchecked_ref()));
+    new_seed_closure.forget();
+}
+
+fn create_select_size_closure(app_state_rc: Rc<RefCell<app_state::AppState>>, stafra_state_rc: Rc<RefCell<Box<dyn SimulationBackend>>>)
+{
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let size_select = document.get_element_by_id("sizes").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+
+    let select_size_closure = Closure::wrap(Box::new(move |event: web_sys::Event|
+    {
+        let mut app_state    = app_state_rc.borrow_mut();
+        let mut stafra_state = stafra_state_rc.borrow_mut();
+
+        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+
+        let size_select = event.target().unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+        let board_size_selected_index = size_select.selected_index() as u32;
+
+        let new_width  = app_state::AppState::board_size_from_index(board_size_selected_index);
+        let new_height = app_state::AppState::board_size_from_index(board_size_selected_index);
+
+        if let Err(message) = stafra_state.resize_board(new_width, new_height)
+        {
+            web_sys::console::error_1(&message.into());
+            return;
+        }
+
+        update_last_frame_with_size(std::cmp::min(new_width, new_height), &mut app_state);
+
+        query_string.set("size_index", &board_size_selected_index.to_string());
+
+        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+
+    }) as Box<dyn Fn(web_sys::Event)>);
+
+    size_select.set_onchange(Some(select_size_closure.as_ref().unchecked_ref()));
+    select_size_closure.forget();
+}
+
+fn parse_query_string(query_string: &str) -> QueryStringParams
+{
+    let search_params = web_sys::UrlSearchParams::new_with_str(query_string).unwrap();
+
+    //Without the seed in the query string a new one is generated
+    let seed = match search_params.get("seed")
+    {
+        Some(value) => value.parse::<u32>().unwrap_or_else(|_| generate_random_seed()),
+        None        => generate_random_seed()
+    };
+
+    let density = match search_params.get("density")
+    {
+        Some(value) => value.parse::<f32>().unwrap_or(0.5).clamp(0.0, 1.0),
+        None        => 0.5
+    };
+
+    let symmetry = match search_params.get("symmetry")
+    {
+        Some(value) => match value.to_lowercase().as_str()
+        {
+            "mirror_x" | "mirror-x" => stafra_state::RandomBoardSymmetry::MirrorX,
+            "4fold"    | "4-fold"   => stafra_state::RandomBoardSymmetry::FourFold,
+            "8fold"    | "8-fold"   => stafra_state::RandomBoardSymmetry::EightFold,
+            _                       => stafra_state::RandomBoardSymmetry::None
+        }
+
+        None => stafra_state::RandomBoardSymmetry::None
+    };
+
+    let shape = match search_params.get("shape")
+    {
+        Some(value) => match value.to_lowercase().as_str()
+        {
+            "disc"   => stafra_state::RandomBoardShape::Disc,
+            "square" => stafra_state::RandomBoardShape::Square,
+            _        => stafra_state::RandomBoardShape::Full
+        }
+
+        None => stafra_state::RandomBoardShape::Full
+    };
+
+    let shape_size = match search_params.get("shape_size")
+    {
+        Some(value) => value.parse::<f32>().unwrap_or(0.5).clamp(0.0, 1.0),
+        None        => 0.5
+    };
+
+    let random_board = stafra_state::RandomBoardParams{seed, density, symmetry, shape, shape_size};
+
+    let default_size_index = 9;  //Corresponds to 1023x1023
+    let minimum_size_index = 0;  //Corresponds to 1x1
+    let maximum_size_index = 14; //Corresponds to 32767x32767
+    let size_index = match search_params.get("size_index")
+    {
+        Some(value) => value.parse::<u32>().unwrap_or(default_size_index).clamp(minimum_size_index, maximum_size_index),
+        None        => default_size_index
+    };
+
+    let pattern_kind = match search_params.get("pattern")
+    {
+        Some(value) => match value.to_lowercase().as_str()
+        {
+            "hline"        => stafra_state::PatternBoardKind::HorizontalLine,
+            "vline"        => stafra_state::PatternBoardKind::VerticalLine,
+            "diagonals"    => stafra_state::PatternBoardKind::Diagonals,
+            "checkerboard" => stafra_state::PatternBoardKind::Checkerboard,
+            "squares"      => stafra_state::PatternBoardKind::ConcentricSquares,
+            "circles"      => stafra_state::PatternBoardKind::ConcentricCircles,
+            "cell"         => stafra_state::PatternBoardKind::SingleCell,
+            _              => stafra_state::PatternBoardKind::Cross
+        }
+
+        None => stafra_state::PatternBoardKind::Cross
+    };
+
+    let pattern_size = match search_params.get("pattern_size")
+    {
+        Some(value) => value.parse::<u32>().unwrap_or(1).clamp(1, u32::MAX),
+        None        => 1
+    };
+
+    //The single cell is in the center by default
+    let center_cell = (app_state::AppState::board_size_from_index(size_index) - 1) / 2;
+    let pattern_cell_x = match search_params.get("cell_x")
+    {
+        Some(value) => value.parse::<u32>().unwrap_or(center_cell),
+        None        => center_cell
+    };
+
+    let pattern_cell_y = match search_params.get("cell_y")
+    {
+        Some(value) => value.parse::<u32>().unwrap_or(center_cell),
+        None        => center_cell
+    };
+
+    let pattern_board = stafra_state::PatternBoardParams{kind: pattern_kind, size: pattern_size, x: pattern_cell_x, y: pattern_cell_y};
+
+    let board_expression = match search_params.get("expression")
+    {
+        Some(value) => value,
+        None        => stafra_expression::DEFAULT_BOARD_EXPRESSION.to_string()
+    };
+
+    let expression_seed = match search_params.get("expression_seed")
+    {
+        Some(value) => value.parse::<u32>().unwrap_or(0),
+        None        => 0
+    };
+
+    let initial_state = match search_params.get("initial_state")
+    {
+        Some(value) => match value.to_lowercase().as_str()
+        {
+            "corners"         => stafra_state::ResetBoardType::Standard {reset_type: stafra_state::StandardResetBoardType::Corners},
+            "sides" | "edges" => stafra_state::ResetBoardType::Standard {reset_type: stafra_state::StandardResetBoardType::Edges},
+            "center"          => stafra_state::ResetBoardType::Standard {reset_type: stafra_state::StandardResetBoardType::Center},
+            "random"          => stafra_state::ResetBoardType::Random {params: random_board},
+            "pattern"         => stafra_state::ResetBoardType::Pattern {params: pattern_board},
+            "expression"      => stafra_state::ResetBoardType::Expression,
+            _                 => stafra_state::ResetBoardType::Standard {reset_type: stafra_state::StandardResetBoardType::Corners}
+        }
+
+        None => stafra_state::ResetBoardType::Standard {reset_type: stafra_state::StandardResetBoardType::Corners}
+    };
+
+    let final_frame = match search_params.get("final_frame")
+    {
+        Some(value) => value.parse::<u32>().unwrap_or(u32::MAX).clamp(1, u32::MAX),
+        None        => u32::MAX
+    };
+
+    let spawn = match search_params.get("spawn_period")
+    {
+        Some(value) => value.parse::<u32>().unwrap_or(u32::MAX).clamp(1, 255),
+        None        => u32::MAX
+    };
+
+    let smooth_transform = match search_params.get("smooth_transform")
+    {
+        Some(value) => match value.to_lowercase().as_str()
+        {
+            "y" | "yes" | "1" | "true" => true,
+            _                          => false
+        }
+
+        None => false
+    };
+
+    let click_rule_data = match search_params.get("click_rule")
+    {
+        Some(value) => app_state::ClickRuleInitData::Custom(app_state::parse_click_rule_base64(value.as_str())),
+        None        => app_state::ClickRuleInitData::Default
+    };
+
+    let history_frames = match search_params.get("history_frames")
+    {
+        Some(value) => value.parse::<u32>().unwrap_or(stafra_history::DEFAULT_HISTORY_FRAMES),
+        None        => stafra_history::DEFAULT_HISTORY_FRAMES
+    };
+
+    let keyframe_period = match search_params.get("keyframe_period")
+    {
+        Some(value) => value.parse::<u32>().unwrap_or(stafra_history::DEFAULT_KEYFRAME_PERIOD).clamp(1, u32::MAX),
+        None        => stafra_history::DEFAULT_KEYFRAME_PERIOD
+    };
+
+    let readback_queue_depth = match search_params.get("readback_queue_depth")
+    {
+        Some(value) => value.parse::<u32>().unwrap_or(stafra_readback_pool::DEFAULT_READBACK_QUEUE_DEPTH).clamp(1, stafra_readback_pool::MAX_READBACK_QUEUE_DEPTH),
+        None        => stafra_readback_pool::DEFAULT_READBACK_QUEUE_DEPTH
+    };
+
+    let profile = match search_params.get("profile")
+    {
+        Some(value) => match value.to_lowercase().as_str()
+        {
+            "y" | "yes" | "1" | "true" => true,
+            _                          => false
+        }
+
+        None => false
+    };
+
+    QueryStringParams
+    {
+        initial_state,
+        size_index,
+
+        random_board,
+        pattern_board,
+
+        board_expression,
+        expression_seed,
+
+        final_frame,
+
+        spawn,
+        smooth_transform,
+
+        click_rule_data,
+
+        history_frames,
+        keyframe_period,
+
+        readback_queue_depth,
+
+        profile
+    }
+}
+
+fn update_performance_overlay(profiler_report: &stafra_profiler::ProfilerReport)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let performance_overlay = document.get_element_by_id("performance_overlay").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+
+    let timing_source = if profiler_report.timestamp_queries {"GPU timestamps"} else {"CPU wall-clock"};
+
+    let mut overlay_text = format!("{}\n", timing_source);
+    for pass_timing in &profiler_report.passes
+    {
+        overlay_text += &format!("{:<24}{:>8.3} ms\n", pass_timing.name, pass_timing.average_ms);
+    }
+
+    performance_overlay.set_inner_text(&overlay_text);
+}
+
+fn save_image_data(image_data: web_sys::ImageData, file_name: &str)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let canvas = document.create_element("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+    canvas.set_width(image_data.width());
+    canvas.set_height(image_data.height());
+
+    let canvas_context = canvas.get_context("2d").unwrap().unwrap().dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
+    canvas_context.put_image_data(&image_data, 0.0, 0.0).expect("Image data put error!");
+
+    let link = document.create_element("a").unwrap().dyn_into::<web_sys::HtmlAnchorElement>().unwrap();
+    link.set_href(&canvas.to_data_url_with_type("image/png").unwrap());
+    link.set_download(file_name);
+    link.click();
+
+    link.remove();
+    canvas.remove();
+}
+
+fn save_text_data(text: &str, file_name: &str)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let text_blob_parts = js_sys::Array::new_with_length(1);
+    text_blob_parts.set(0, JsValue::from_str(text));
+
+    let text_blob = web_sys::Blob::new_with_str_sequence(&text_blob_parts).unwrap();
+    let text_url  = web_sys::Url::create_object_url_with_blob(&text_blob).unwrap();
+
+    let link = document.create_element("a").unwrap().dyn_into::<web_sys::HtmlAnchorElement>().unwrap();
+    link.set_href(&text_url);
+    link.set_download(file_name);
+    link.click();
+
+    link.remove();
+    web_sys::Url::revoke_object_url(&text_url).unwrap();
+}
+
+fn save_session_data(session_data: Vec<u8>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let session_blob_parts = js_sys::Array::new_with_length(1);
+    session_blob_parts.set(0, js_sys::Uint8Array::from(session_data.as_slice()).into());
+
+    let session_blob = web_sys::Blob::new_with_u8_array_sequence(&session_blob_parts).unwrap();
+    let session_url  = web_sys::Url::create_object_url_with_blob(&session_blob).unwrap();
+
+    let link = document.create_element("a").unwrap().dyn_into::<web_sys::HtmlAnchorElement>().unwrap();
+    link.set_href(&session_url);
+    link.set_download(&"StabilityFractal.stafra");
+    link.click();
+
+    link.remove();
+    web_sys::Url::revoke_object_url(&session_url).unwrap();
+}
+
+fn find_select_option_index(select_element: &web_sys::HtmlSelectElement, value: &str) -> i32
+{
+    let options = select_element.options();
+
+    let mut option_index: i32 = 0;
+    while let Some(board_reset_custom_option) = options.item(option_index as u32)
+    {
+        let option_element = board_reset_custom_option.dyn_into::<web_sys::HtmlOptionElement>().unwrap();
+        if option_element.value() == value
+        {
+            return option_index;
+        }
+
+        option_index += 1;
+    }
+
+    return -1;
+}
+
+fn setup_initial_ui(state_params: &QueryStringParams)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let initial_state_select      = document.get_element_by_id("initial_states").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let size_select               = document.get_element_by_id("sizes").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let last_frame_checkbox       = document.get_element_by_id("last_frame_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let last_frame_input          = document.get_element_by_id("last_frame_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let spawn_checkbox            = document.get_element_by_id("spawn_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let smooth_transform_checkbox = document.get_element_by_id("smooth_transform_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let spawn_range               = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let spawn_input               = document.get_element_by_id("spawn_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let performance_overlay       = document.get_element_by_id("performance_overlay").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+    let random_board_div          = document.get_element_by_id("random_board_div").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+    let pattern_board_div         = document.get_element_by_id("pattern_board_div").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+    let expression_board_div      = document.get_element_by_id("expression_board_div").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+
+    let board_size = app_state::AppState::board_size_from_index(state_params.size_index);
+
+    performance_overlay.set_hidden(!state_params.profile);
+
+    initial_state_select.set_value(match state_params.initial_state
+    {
+        stafra_state::ResetBoardType::Standard {reset_type: stafra_state::StandardResetBoardType::Corners} => "initial_state_corners",
+        stafra_state::ResetBoardType::Standard {reset_type: stafra_state::StandardResetBoardType::Edges}   => "initial_state_sides",
+        stafra_state::ResetBoardType::Standard {reset_type: stafra_state::StandardResetBoardType::Center}  => "initial_state_center",
+        stafra_state::ResetBoardType::Random {..}                                                          => "initial_state_random",
+        stafra_state::ResetBoardType::Pattern {..}                                                         => "initial_state_pattern",
+        stafra_state::ResetBoardType::Expression                                                           => "initial_state_expression",
+        stafra_state::ResetBoardType::Custom                                                               => "initial_state_custom_value"
+    });
+
+    set_random_board_ui(&state_params.random_board);
+    set_pattern_board_ui(&state_params.pattern_board);
+
+    let pattern_board_selected = matches!(state_params.initial_state, stafra_state::ResetBoardType::Pattern {..});
+    pattern_board_div.set_hidden(!pattern_board_selected);
+
+    set_expression_board_ui(&state_params.board_expression, state_params.expression_seed);
+    expression_board_div.set_hidden(state_params.initial_state != stafra_state::ResetBoardType::Expression);
+
+    //The generated seed is stored right away, so the board can be reproduced from the link
+    let random_board_selected = matches!(state_params.initial_state, stafra_state::ResetBoardType::Random {..});
+    random_board_div.set_hidden(!random_board_selected);
+    if random_board_selected
+    {
+        let window       = web_sys::window().unwrap();
+        let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+        set_random_board_query(&query_string, &state_params.random_board);
+
+        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+    }
+
+    size_select.set_selected_index(state_params.size_index as i32);
+
+    if state_params.final_frame != u32::MAX
+    {
+        last_frame_checkbox.set_checked(true);
+        last_frame_input.set_value_as_number(state_params.final_frame as f64);
+    }
+    else
+    {
+        last_frame_checkbox.set_checked(false);
+        last_frame_input.set_value_as_number((board_size / 2) as f64);
+    }
+
+    if state_params.spawn != u32::MAX
+    {
+        spawn_checkbox.set_checked(true);
+        smooth_transform_checkbox.set_checked(state_params.smooth_transform);
+
+        spawn_range.set_value_as_number(state_params.spawn as f64);
+        spawn_input.set_value(&state_params.spawn.to_string());
+    }
+    else
+    {
+        spawn_checkbox.set_checked(false);
+        smooth_transform_checkbox.set_checked(false);
+
+        spawn_range.set_value_as_number(8 as f64);
+        spawn_input.set_value("8");
+    }
+}
+
+fn hide_unsupported_sizes(max_board_size: u32)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let size_select = document.get_element_by_id("sizes").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+
+    let selected_index = size_select.selected_index();
+
+    //Size options go in ascending order
+    for size_index in (0..size_select.length()).rev()
+    {
+        if app_state::AppState::board_size_from_index(size_index) > max_board_size
+        {
+            size_select.remove_with_index(size_index as i32);
+        }
+    }
+
+    if selected_index >= size_select.length() as i32
+    {
+        size_select.set_selected_index(size_select.length() as i32 - 1);
+    }
+}
+
+fn update_ui(run_state: RunState)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let play_pause_button = document.get_element_by_id("button_play_pause").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let stop_button       = document.get_element_by_id("button_stop_record").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+
+    if run_state == RunState::Running || run_state == RunState::Recording || run_state == RunState::SavePendingRecording
+    {
+        play_pause_button.set_text_content(Some("⏸️"));
+    }
+    else
+    {
+        play_pause_button.set_text_content(Some("▶️"));
+    }
+
+    if run_state == RunState::Stopped || run_state == RunState::SavePendingRecording
+    {
+        stop_button.set_text_content(Some("⏺️"));
+    }
+    else
+    {
+        stop_button.set_text_content(Some("⏹️"));
+    }
+
+    let stop_button = document.get_element_by_id("button_stop_record").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    stop_button.set_disabled(run_state == RunState::SavePendingRecording);
+
+    let play_pause_button = document.get_element_by_id("button_play_pause").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    play_pause_button.set_disabled(run_state == RunState::SavePendingRecording);
+
+    let next_frame_button = document.get_element_by_id("button_next_frame").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    next_frame_button.set_disabled(run_state == RunState::Running || run_state == RunState::Recording || run_state == RunState::PausedRecording || run_state == RunState::SavePendingRecording);
+
+    //Recorded videos only go forward
+    let prev_frame_button = document.get_element_by_id("button_prev_frame").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    prev_frame_button.set_disabled(run_state != RunState::Paused);
+
+    let timeline_slider = document.get_element_by_id("timeline_slider").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    timeline_slider.set_disabled(run_state != RunState::Paused);
+
+    let save_png_button = document.get_element_by_id("button_save_png").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    save_png_button.set_disabled(run_state == RunState::SavePendingRecording);
+
+    let save_session_button = document.get_element_by_id("button_save_session").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    save_session_button.set_disabled(run_state == RunState::SavePendingRecording);
+
+    let load_session_button = document.get_element_by_id("button_load_session").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    load_session_button.set_disabled(run_state != RunState::Stopped);
+
+    let initial_board_select = document.get_element_by_id("initial_states").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    initial_board_select.set_disabled(run_state != RunState::Stopped);
+
+    let size_select = document.get_element_by_id("sizes").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    size_select.set_disabled(run_state != RunState::Stopped);
+
+    for random_board_input_id in ["random_seed", "random_density", "random_shape_size"]
+    {
+        let random_board_input = document.get_element_by_id(random_board_input_id).unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        random_board_input.set_disabled(run_state != RunState::Stopped);
+    }
+
+    for random_board_select_id in ["random_symmetry", "random_shape"]
+    {
+        let random_board_select = document.get_element_by_id(random_board_select_id).unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+        random_board_select.set_disabled(run_state != RunState::Stopped);
+    }
+
+    let new_seed_button = document.get_element_by_id("button_new_seed").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    new_seed_button.set_disabled(run_state != RunState::Stopped);
+
+    for pattern_board_input_id in ["pattern_size", "pattern_cell_x", "pattern_cell_y"]
+    {
+        let pattern_board_input = document.get_element_by_id(pattern_board_input_id).unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        pattern_board_input.set_disabled(run_state != RunState::Stopped);
+    }
+
+    let pattern_kind_select = document.get_element_by_id("pattern_kind").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    pattern_kind_select.set_disabled(run_state != RunState::Stopped);
+
+    for expression_board_input_id in ["board_expression", "expression_seed"]
+    {
+        let expression_board_input = document.get_element_by_id(expression_board_input_id).unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+        expression_board_input.set_disabled(run_state != RunState::Stopped);
+    }
+
+    let last_frame_checkbox = document.get_element_by_id("last_frame_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    last_frame_checkbox.set_disabled(run_state == RunState::SavePendingRecording);
+
+    let spawn_checkbox = document.get_element_by_id("spawn_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    spawn_checkbox.set_disabled(run_state != RunState::Stopped);
+
+    let spawn_decrement_button = document.get_element_by_id("decrement_spawn").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    spawn_decrement_button.set_disabled(run_state != RunState::Stopped || !spawn_checkbox.checked());
+
+    let spawn_increment_button = document.get_element_by_id("increment_spawn").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    spawn_increment_button.set_disabled(run_state != RunState::Stopped || !spawn_checkbox.checked());
+
+    let spawn_slider = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    spawn_slider.set_disabled(run_state != RunState::Stopped || !spawn_checkbox.checked());
+
+    let smooth_transform_checkbox = document.get_element_by_id("smooth_transform_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    smooth_transform_checkbox.set_disabled(run_state != RunState::Stopped || !spawn_checkbox.checked());
+
+    let upload_restriction_button = document.get_element_by_id("button_upload_restriction").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    upload_restriction_button.set_disabled(run_state != RunState::Stopped);
+
+    let clear_restriction_button = document.get_element_by_id("button_clear_restriction").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    clear_restriction_button.set_disabled(run_state != RunState::Stopped);
+
+    let random_click_rule_generate_button = document.get_element_by_id("button_random_click_rule_generate").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    random_click_rule_generate_button.set_disabled(run_state != RunState::Stopped);
+
+    let random_click_rule_new_seed_button = document.get_element_by_id("button_random_click_rule_new_seed").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    random_click_rule_new_seed_button.set_disabled(run_state != RunState::Stopped);
+
+    for (button_id, _) in click_rule_transform_buttons()
+    {
+        let click_rule_transform_button = document.get_element_by_id(button_id).unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+        click_rule_transform_button.set_disabled(run_state != RunState::Stopped);
+    }
+
+    let inverse_problem_upload_button = document.get_element_by_id("button_inverse_problem_upload").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    inverse_problem_upload_button.set_disabled(run_state != RunState::Stopped);
+
+    let paint_tool_select = document.get_element_by_id("paint_tool").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    paint_tool_select.set_disabled(run_state != RunState::Stopped);
+}
+
+//Shows the settings of the loaded session in the controls and the query string
+fn update_ui_from_session(session: &stafra_session::StafraSession, app_state: &mut app_state::AppState)
+{
+    let window   = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+
+    let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+
+    let initial_state_select      = document.get_element_by_id("initial_states").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let size_select               = document.get_element_by_id("sizes").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let spawn_checkbox            = document.get_element_by_id("spawn_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let smooth_transform_checkbox = document.get_element_by_id("smooth_transform_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let spawn_range               = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let spawn_input               = document.get_element_by_id("spawn_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let clear_restriction_button  = document.get_element_by_id("button_clear_restriction").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+
+    match session.last_reset_type
+    {
+        stafra_state::ResetBoardType::Standard {reset_type} =>
+        {
+            let (select_value, query_value) = match reset_type
+            {
+                stafra_state::StandardResetBoardType::Corners => ("initial_state_corners", "corners"),
+                stafra_state::StandardResetBoardType::Edges   => ("initial_state_sides",   "edges"),
+                stafra_state::StandardResetBoardType::Center  => ("initial_state_center",  "center")
+            };
+
+            initial_state_select.set_value(select_value);
+            query_string.set("initial_state", query_value);
+            delete_random_board_query(&query_string);
+            delete_pattern_board_query(&query_string);
+            delete_expression_board_query(&query_string);
+        }
+
+        stafra_state::ResetBoardType::Random {params} =>
+        {
+            initial_state_select.set_value("initial_state_random");
+            query_string.set("initial_state", "random");
+
+            set_random_board_ui(&params);
+            set_random_board_query(&query_string, &params);
+            delete_pattern_board_query(&query_string);
+            delete_expression_board_query(&query_string);
+        }
+
+        stafra_state::ResetBoardType::Pattern {params} =>
+        {
+            initial_state_select.set_value("initial_state_pattern");
+            query_string.set("initial_state", "pattern");
+
+            set_pattern_board_ui(&params);
+            set_pattern_board_query(&query_string, &params);
+            delete_random_board_query(&query_string);
+            delete_expression_board_query(&query_string);
+        }
+
+        stafra_state::ResetBoardType::Expression =>
+        {
+            initial_state_select.set_value("initial_state_expression");
+            query_string.set("initial_state", "expression");
+
+            if let Some(board_expression) = &session.board_expression
+            {
+                set_expression_board_ui(board_expression.source(), board_expression.seed());
+                set_expression_board_query(&query_string, board_expression.source(), board_expression.seed());
+            }
+
+            delete_random_board_query(&query_string);
+            delete_pattern_board_query(&query_string);
+        }
+
+        stafra_state::ResetBoardType::Custom =>
+        {
+            let custom_option_index = find_select_option_index(&initial_state_select, "initial_state_custom_value");
+            initial_state_select.set_selected_index(custom_option_index);
+            query_string.delete("initial_state");
+            delete_random_board_query(&query_string);
+            delete_pattern_board_query(&query_string);
+            delete_expression_board_query(&query_string);
+        }
+    }
+
+    let random_board_div = document.get_element_by_id("random_board_div").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+    random_board_div.set_hidden(!matches!(session.last_reset_type, stafra_state::ResetBoardType::Random {..}));
+
+    let pattern_board_div = document.get_element_by_id("pattern_board_div").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+    pattern_board_div.set_hidden(!matches!(session.last_reset_type, stafra_state::ResetBoardType::Pattern {..}));
+
+    let expression_board_div = document.get_element_by_id("expression_board_div").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+    expression_board_div.set_hidden(session.last_reset_type != stafra_state::ResetBoardType::Expression);
+
+    let size_index = (std::mem::size_of::<u32>() * 8) as u32 - session.board_size.leading_zeros() - 1;
+    size_select.set_selected_index(size_index as i32);
+    query_string.set("size_index", &size_index.to_string());
+
+    update_last_frame_with_size(session.board_size, app_state);
+
+    let spawn_enabled = session.spawn_period != u32::MAX;
+    spawn_checkbox.set_checked(spawn_enabled);
+    smooth_transform_checkbox.set_checked(session.smooth_transform_enabled);
+    if spawn_enabled
+    {
+        spawn_range.set_value_as_number(session.spawn_period as f64);
+        spawn_input.set_value(&session.spawn_period.to_string());
+        query_string.set("spawn_period", &session.spawn_period.to_string());
+    }
+    else
+    {
+        query_string.delete("spawn_period");
+    }
+
+    if session.smooth_transform_enabled
+    {
+        query_string.set("smooth_transform", "y");
+    }
+    else
+    {
+        query_string.delete("smooth_transform");
+    }
+
+    app_state.click_rule_data = session.click_rule_data;
+    query_string.set("click_rule", &app_state.encode_click_rule_base64());
+
+    clear_restriction_button.set_hidden(!session.is_restricted());
+
+    let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+    window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+
+    update_ui(app_state.run_state);
+}
+
+fn generate_random_seed() -> u32
+{
+    (js_sys::Math::random() * u32::MAX as f64) as u32
+}
+
+fn read_random_board_ui() -> stafra_state::RandomBoardParams
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let random_seed_input        = document.get_element_by_id("random_seed").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let random_density_slider    = document.get_element_by_id("random_density").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let random_symmetry_select   = document.get_element_by_id("random_symmetry").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let random_shape_select      = document.get_element_by_id("random_shape").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let random_shape_size_slider = document.get_element_by_id("random_shape_size").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let seed_value = random_seed_input.value_as_number();
+    let seed       = if seed_value.is_nan() {0} else {seed_value.clamp(0.0, u32::MAX as f64) as u32};
+
+    let symmetry = match random_symmetry_select.value().as_str()
+    {
+        "mirror_x" => stafra_state::RandomBoardSymmetry::MirrorX,
+        "4fold"    => stafra_state::RandomBoardSymmetry::FourFold,
+        "8fold"    => stafra_state::RandomBoardSymmetry::EightFold,
+        _          => stafra_state::RandomBoardSymmetry::None
+    };
+
+    let shape = match random_shape_select.value().as_str()
+    {
+        "disc"   => stafra_state::RandomBoardShape::Disc,
+        "square" => stafra_state::RandomBoardShape::Square,
+        _        => stafra_state::RandomBoardShape::Full
+    };
+
+    stafra_state::RandomBoardParams
+    {
+        seed,
+        density:    (random_density_slider.value_as_number() / 100.0) as f32,
+        symmetry,
+        shape,
+        shape_size: (random_shape_size_slider.value_as_number() / 100.0) as f32
+    }
+}
+
+fn set_random_board_ui(params: &stafra_state::RandomBoardParams)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let random_seed_input        = document.get_element_by_id("random_seed").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let random_density_slider    = document.get_element_by_id("random_density").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let random_symmetry_select   = document.get_element_by_id("random_symmetry").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let random_shape_select      = document.get_element_by_id("random_shape").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let random_shape_size_slider = document.get_element_by_id("random_shape_size").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    random_seed_input.set_value_as_number(params.seed as f64);
+    random_density_slider.set_value_as_number((params.density * 100.0).round() as f64);
+    random_symmetry_select.set_value(random_board_symmetry_name(params.symmetry));
+    random_shape_select.set_value(random_board_shape_name(params.shape));
+    random_shape_size_slider.set_value_as_number((params.shape_size * 100.0).round() as f64);
+}
+
+fn random_board_symmetry_name(symmetry: stafra_state::RandomBoardSymmetry) -> &'static str
+{
+    match symmetry
+    {
+        stafra_state::RandomBoardSymmetry::None      => "none",
+        stafra_state::RandomBoardSymmetry::MirrorX   => "mirror_x",
+        stafra_state::RandomBoardSymmetry::FourFold  => "4fold",
+        stafra_state::RandomBoardSymmetry::EightFold => "8fold"
+    }
+}
+
+fn random_board_shape_name(shape: stafra_state::RandomBoardShape) -> &'static str
+{
+    match shape
+    {
+        stafra_state::RandomBoardShape::Full   => "full",
+        stafra_state::RandomBoardShape::Disc   => "disc",
+        stafra_state::RandomBoardShape::Square => "square"
+    }
+}
+
+fn set_random_board_query(query_string: &web_sys::UrlSearchParams, params: &stafra_state::RandomBoardParams)
+{
+    query_string.set("seed",       &params.seed.to_string());
+    query_string.set("density",    &params.density.to_string());
+    query_string.set("symmetry",   random_board_symmetry_name(params.symmetry));
+    query_string.set("shape",      random_board_shape_name(params.shape));
+    query_string.set("shape_size", &params.shape_size.to_string());
+}
+
+fn delete_random_board_query(query_string: &web_sys::UrlSearchParams)
+{
+    for random_board_param in ["seed", "density", "symmetry", "shape", "shape_size"]
+    {
+        query_string.delete(random_board_param);
+    }
+}
+
+fn reset_random_board_from_ui(stafra_state: &mut dyn SimulationBackend)
+{
+    let window       = web_sys::window().unwrap();
+    let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+
+    let random_board_params = read_random_board_ui();
+    stafra_state.reset_board_random(random_board_params);
+    set_random_board_query(&query_string, &random_board_params);
+
+    let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+    window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+}
+
+fn read_random_click_rule_ui() -> stafra_state::RandomClickRuleParams
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let random_click_rule_seed_input      = document.get_element_by_id("random_click_rule_seed").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let random_click_rule_radius_input    = document.get_element_by_id("random_click_rule_radius").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let random_click_rule_density_slider  = document.get_element_by_id("random_click_rule_density").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let random_click_rule_symmetry_select = document.get_element_by_id("random_click_rule_symmetry").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let random_click_rule_center_checkbox = document.get_element_by_id("random_click_rule_center_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let seed_value = random_click_rule_seed_input.value_as_number();
+    let seed       = if seed_value.is_nan() {0} else {seed_value.clamp(0.0, u32::MAX as f64) as u32};
+
+    let radius_value = random_click_rule_radius_input.value_as_number();
+    let radius       = if radius_value.is_nan() {1} else {radius_value.clamp(1.0, stafra_state::MAX_RANDOM_CLICK_RULE_RADIUS as f64) as u32};
+
+    let symmetry = match random_click_rule_symmetry_select.value().as_str()
+    {
+        "mirror_x" => stafra_state::RandomBoardSymmetry::MirrorX,
+        "4fold"    => stafra_state::RandomBoardSymmetry::FourFold,
+        "8fold"    => stafra_state::RandomBoardSymmetry::EightFold,
+        _          => stafra_state::RandomBoardSymmetry::None
+    };
+
+    stafra_state::RandomClickRuleParams
+    {
+        seed,
+        radius,
+        density: (random_click_rule_density_slider.value_as_number() / 100.0) as f32,
+        symmetry,
+        center:  random_click_rule_center_checkbox.checked()
+    }
+}
+
+//The code of the generated click rule is shown to be shared, the same code is in the query string
+fn generate_random_click_rule_from_ui(app_state: &mut app_state::AppState, stafra_state: &mut dyn SimulationBackend)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    app_state.click_rule_data = read_random_click_rule_ui().click_rule_data();
+    apply_click_rule_from_ui(app_state, stafra_state);
+
+    let random_click_rule_code_input = document.get_element_by_id("random_click_rule_code").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    random_click_rule_code_input.set_value(&app_state.encode_click_rule_base64());
+}
+
+fn read_pattern_board_ui() -> stafra_state::PatternBoardParams
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let pattern_kind_select  = document.get_element_by_id("pattern_kind").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let pattern_size_input   = document.get_element_by_id("pattern_size").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let pattern_cell_x_input = document.get_element_by_id("pattern_cell_x").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let pattern_cell_y_input = document.get_element_by_id("pattern_cell_y").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let kind = match pattern_kind_select.value().as_str()
+    {
+        "hline"        => stafra_state::PatternBoardKind::HorizontalLine,
+        "vline"        => stafra_state::PatternBoardKind::VerticalLine,
+        "diagonals"    => stafra_state::PatternBoardKind::Diagonals,
+        "checkerboard" => stafra_state::PatternBoardKind::Checkerboard,
+        "squares"      => stafra_state::PatternBoardKind::ConcentricSquares,
+        "circles"      => stafra_state::PatternBoardKind::ConcentricCircles,
+        "cell"         => stafra_state::PatternBoardKind::SingleCell,
+        _              => stafra_state::PatternBoardKind::Cross
+    };
+
+    let size_value   = pattern_size_input.value_as_number();
+    let cell_x_value = pattern_cell_x_input.value_as_number();
+    let cell_y_value = pattern_cell_y_input.value_as_number();
+
+    stafra_state::PatternBoardParams
+    {
+        kind,
+        size: if size_value.is_nan()   {1} else {size_value.clamp(1.0, u32::MAX as f64) as u32},
+        x:    if cell_x_value.is_nan() {0} else {cell_x_value.clamp(0.0, u32::MAX as f64) as u32},
+        y:    if cell_y_value.is_nan() {0} else {cell_y_value.clamp(0.0, u32::MAX as f64) as u32}
+    }
+}
+
+fn set_pattern_board_ui(params: &stafra_state::PatternBoardParams)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let pattern_kind_select  = document.get_element_by_id("pattern_kind").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let pattern_size_input   = document.get_element_by_id("pattern_size").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let pattern_cell_x_input = document.get_element_by_id("pattern_cell_x").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let pattern_cell_y_input = document.get_element_by_id("pattern_cell_y").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    pattern_kind_select.set_value(pattern_board_kind_name(params.kind));
+    pattern_size_input.set_value_as_number(params.size as f64);
+    pattern_cell_x_input.set_value_as_number(params.x as f64);
+    pattern_cell_y_input.set_value_as_number(params.y as f64);
+
+    update_pattern_board_controls(params.kind);
+}
+
+//The single cell only needs the position, all other patterns only need the size
+fn update_pattern_board_controls(kind: stafra_state::PatternBoardKind)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let pattern_size_div = document.get_element_by_id("pattern_size_div").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+    let pattern_cell_div = document.get_element_by_id("pattern_cell_div").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+
+    let single_cell = kind == stafra_state::PatternBoardKind::SingleCell;
+    pattern_size_div.set_hidden(single_cell);
+    pattern_cell_div.set_hidden(!single_cell);
+}
+
+fn pattern_board_kind_name(kind: stafra_state::PatternBoardKind) -> &'static str
+{
+    match kind
+    {
+        stafra_state::PatternBoardKind::HorizontalLine    => "hline",
+        stafra_state::PatternBoardKind::VerticalLine      => "vline",
+        stafra_state::PatternBoardKind::Cross             => "cross",
+        stafra_state::PatternBoardKind::Diagonals         => "diagonals",
+        stafra_state::PatternBoardKind::Checkerboard      => "checkerboard",
+        stafra_state::PatternBoardKind::ConcentricSquares => "squares",
+        stafra_state::PatternBoardKind::ConcentricCircles => "circles",
+        stafra_state::PatternBoardKind::SingleCell        => "cell"
+    }
+}
+
+//Only the parameters used by the pattern are stored
+fn set_pattern_board_query(query_string: &web_sys::UrlSearchParams, params: &stafra_state::PatternBoardParams)
+{
+    delete_pattern_board_query(query_string);
+
+    query_string.set("pattern", pattern_board_kind_name(params.kind));
+    if params.kind == stafra_state::PatternBoardKind::SingleCell
+    {
+        query_string.set("cell_x", &params.x.to_string());
+        query_string.set("cell_y", &params.y.to_string());
+    }
+    else
+    {
+        query_string.set("pattern_size", &params.size.to_string());
+    }
+}
+
+fn delete_pattern_board_query(query_string: &web_sys::UrlSearchParams)
+{
+    for pattern_board_param in ["pattern", "pattern_size", "cell_x", "cell_y"]
+    {
+        query_string.delete(pattern_board_param);
+    }
+}
+
+fn reset_pattern_board_from_ui(stafra_state: &mut dyn SimulationBackend)
+{
+    let window       = web_sys::window().unwrap();
+    let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
 
-    size_select.set_onchange(Some(select_size_closure.as_ref().unchecked_ref()));
-    select_size_closure.forget();
+    let pattern_board_params = read_pattern_board_ui();
+    stafra_state.reset_board_pattern(pattern_board_params);
+    set_pattern_board_query(&query_string, &pattern_board_params);
+    update_pattern_board_controls(pattern_board_params.kind);
+
+    let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+    window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
 }
 
-fn parse_query_string(query_string: &str) -> QueryStringParams
+fn read_image_import_options_ui() -> stafra_image_import::ImageImportOptions
 {
-    let search_params = web_sys::UrlSearchParams::new_with_str(query_string).unwrap();
+    let document = web_sys::window().unwrap().document().unwrap();
 
-    let initial_state = match search_params.get("initial_state")
+    let channel_select          = document.get_element_by_id("import_channel").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let threshold_slider        = document.get_element_by_id("import_threshold").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let auto_threshold_checkbox = document.get_element_by_id("import_auto_threshold").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let invert_checkbox         = document.get_element_by_id("import_invert").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let dithering_select        = document.get_element_by_id("import_dithering").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let placement_select        = document.get_element_by_id("import_placement").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let board_size_select       = document.get_element_by_id("import_board_size").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let offset_x_input          = document.get_element_by_id("import_offset_x").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let offset_y_input          = document.get_element_by_id("import_offset_y").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let channel = match channel_select.value().as_str()
     {
-        Some(value) => match value.to_lowercase().as_str()
-        {
-            "corners"         => stafra_state::StandardResetBoardType::Corners,
-            "sides" | "edges" => stafra_state::StandardResetBoardType::Edges,
-            "center"          => stafra_state::StandardResetBoardType::Center,
-            _                 => stafra_state::StandardResetBoardType::Corners
-        }
-
-        None => stafra_state::StandardResetBoardType::Corners
+        "red"   => stafra_image_import::ImportChannel::Red,
+        "green" => stafra_image_import::ImportChannel::Green,
+        "blue"  => stafra_image_import::ImportChannel::Blue,
+        "alpha" => stafra_image_import::ImportChannel::Alpha,
+        _       => stafra_image_import::ImportChannel::Luma
     };
 
-    let default_size_index = 9;  //Corresponds to 1023x1023
-    let minimum_size_index = 0;  //Corresponds to 1x1
-    let maximum_size_index = 13; //Corresponds to 16383x16383
-    let size_index = match search_params.get("size_index")
+    let dithering = match dithering_select.value().as_str()
     {
-        Some(value) => value.parse::<u32>().unwrap_or(default_size_index).clamp(minimum_size_index, maximum_size_index),
-        None        => default_size_index
+        "ordered"   => stafra_image_import::ImportDithering::Ordered,
+        "diffusion" => stafra_image_import::ImportDithering::ErrorDiffusion,
+        _           => stafra_image_import::ImportDithering::None
     };
 
-    let final_frame = match search_params.get("final_frame")
+    let placement = match placement_select.value().as_str()
     {
-        Some(value) => value.parse::<u32>().unwrap_or(u32::MAX).clamp(1, u32::MAX),
-        None        => u32::MAX
+        "fit"     => stafra_image_import::ImagePlacement::Fit,
+        "fill"    => stafra_image_import::ImagePlacement::Fill,
+        "stretch" => stafra_image_import::ImagePlacement::Stretch,
+        "tile"    => stafra_image_import::ImagePlacement::Tile,
+        _         => stafra_image_import::ImagePlacement::Crop
     };
 
-    let spawn = match search_params.get("spawn_period")
+    //Empty or invalid offsets are treated as 0
+    let read_offset = |input: &web_sys::HtmlInputElement|
     {
-        Some(value) => value.parse::<u32>().unwrap_or(u32::MAX).clamp(1, 255),
-        None        => u32::MAX
+        let offset = input.value_as_number();
+        if offset.is_finite() {offset.clamp(-32767.0, 32767.0) as i32} else {0}
     };
 
-    let smooth_transform = match search_params.get("smooth_transform")
+    stafra_image_import::ImageImportOptions
     {
-        Some(value) => match value.to_lowercase().as_str()
-        {
-            "y" | "yes" | "1" | "true" => true,
-            _                          => false
-        }
+        channel,
+        threshold:      (threshold_slider.value_as_number() / 100.0) as f32,
+        auto_threshold: auto_threshold_checkbox.checked(),
+        invert:         invert_checkbox.checked(),
+        dithering,
+        placement,
+        board_size:     board_size_select.value().parse::<u32>().unwrap_or(0),
+        offset_x:       read_offset(&offset_x_input),
+        offset_y:       read_offset(&offset_y_input)
+    }
+}
 
-        None => false
+fn read_paint_stroke_ui(cell_x: i32, cell_y: i32, primary_button: bool) -> Option<stafra_paint::PaintStroke>
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let tool_select     = document.get_element_by_id("paint_tool").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let target_select   = document.get_element_by_id("paint_target").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let radius_input    = document.get_element_by_id("paint_radius").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let filled_checkbox = document.get_element_by_id("paint_filled").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    //The eraser is a pencil that paints with the disabled value
+    let (tool, enabled) = match tool_select.value().as_str()
+    {
+        "pencil"    => (stafra_paint::PaintTool::Pencil,    primary_button),
+        "eraser"    => (stafra_paint::PaintTool::Pencil,    !primary_button),
+        "line"      => (stafra_paint::PaintTool::Line,      primary_button),
+        "rectangle" => (stafra_paint::PaintTool::Rectangle, primary_button),
+        "ellipse"   => (stafra_paint::PaintTool::Ellipse,   primary_button),
+        "fill"      => (stafra_paint::PaintTool::Fill,      primary_button),
+        _           => return None
     };
 
-    let click_rule_data = match search_params.get("click_rule")
+    let target = match target_select.value().as_str()
     {
-        Some(value) => app_state::ClickRuleInitData::Custom(app_state::parse_click_rule_base64(value.as_str())),
-        None        => app_state::ClickRuleInitData::Default
+        "restriction" => stafra_paint::PaintTarget::Restriction,
+        _             => stafra_paint::PaintTarget::Board
     };
 
-    QueryStringParams
+    let radius = radius_input.value_as_number();
+
+    Some(stafra_paint::PaintStroke
     {
-        initial_state,
-        size_index,
+        tool,
+        target,
+        start_x: cell_x,
+        start_y: cell_y,
+        end_x:   cell_x,
+        end_y:   cell_y,
+        radius:  if radius.is_finite() {radius.clamp(0.0, 256.0) as u32} else {0},
+        filled:  filled_checkbox.checked(),
+        enabled
+    })
+}
 
-        final_frame,
+//The board is stretched over the whole canvas
+fn paint_cell_from_mouse_event(mouse_event: &web_sys::MouseEvent, board_size: u32) -> (i32, i32)
+{
+    let main_canvas = mouse_event.target().unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
 
-        spawn,
-        smooth_transform,
+    let x_normalized = (mouse_event.offset_x() as f32) / (main_canvas.client_width()  as f32);
+    let y_normalized = (mouse_event.offset_y() as f32) / (main_canvas.client_height() as f32);
 
-        click_rule_data
-    }
+    ((x_normalized * board_size as f32).floor() as i32, (y_normalized * board_size as f32).floor() as i32)
 }
 
-fn save_image_data(image_data: web_sys::ImageData)
+fn paint_from_ui(stafra_state: &mut dyn SimulationBackend, stroke: stafra_paint::PaintStroke)
 {
-    let document = web_sys::window().unwrap().document().unwrap();
+    stafra_state.paint(stroke);
 
-    let canvas = document.create_element("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
-    canvas.set_width(image_data.width());
-    canvas.set_height(image_data.height());
+    if stroke.target == stafra_paint::PaintTarget::Restriction
+    {
+        let document = web_sys::window().unwrap().document().unwrap();
 
-    let canvas_context = canvas.get_context("2d").unwrap().unwrap().dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
-    canvas_context.put_image_data(&image_data, 0.0, 0.0).expect("Image data put error!");
+        let clear_restriction_button = document.get_element_by_id("button_clear_restriction").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+        clear_restriction_button.set_hidden(false);
+    }
+}
 
-    let link = document.create_element("a").unwrap().dyn_into::<web_sys::HtmlAnchorElement>().unwrap();
-    link.set_href(&canvas.to_data_url_with_type("image/png").unwrap());
-    link.set_download(&"StabilityFractal.png");
-    link.click();
+fn click_rule_cell_from_mouse_event(mouse_event: &web_sys::MouseEvent) -> (u32, u32)
+{
+    let click_rule_canvas = mouse_event.target().unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
 
-    link.remove();
-    canvas.remove();
+    let x_normalized = (mouse_event.offset_x() as f32) / (click_rule_canvas.client_width()  as f32);
+    let y_normalized = (mouse_event.offset_y() as f32) / (click_rule_canvas.client_height() as f32);
+
+    let click_rule_size = 32;
+    ((x_normalized * click_rule_size as f32).clamp(0.0, click_rule_size as f32 - 1.0) as u32, (y_normalized * click_rule_size as f32).clamp(0.0, click_rule_size as f32 - 1.0) as u32)
 }
 
-fn find_select_option_index(select_element: &web_sys::HtmlSelectElement, value: &str) -> i32
+//Paints the line from the last position of the stroke to (cell_x, cell_y) with the symmetry from the editor
+fn paint_click_rule_from_ui(app_state: &mut app_state::AppState, stafra_state: &mut dyn SimulationBackend, stroke: &ClickRuleStroke, cell_x: u32, cell_y: u32)
 {
-    let options = select_element.options();
+    let symmetry = read_click_rule_edit_symmetry_ui();
 
-    let mut option_index: i32 = 0;
-    while let Some(board_reset_custom_option) = options.item(option_index as u32)
+    let delta_x = cell_x as i32 - stroke.last_x as i32;
+    let delta_y = cell_y as i32 - stroke.last_y as i32;
+    let steps   = std::cmp::max(delta_x.abs(), delta_y.abs());
+
+    let mut click_rule_changed = false;
+    for step in 0..=steps
     {
-        let option_element = board_reset_custom_option.dyn_into::<web_sys::HtmlOptionElement>().unwrap();
-        if option_element.value() == value
-        {
-            return option_index;
-        }
+        let (step_x, step_y) = if steps == 0 {(0, 0)} else {((delta_x * step) / steps, (delta_y * step) / steps)};
 
-        option_index += 1;
+        let painted_x = (stroke.last_x as i32 + step_x) as u32;
+        let painted_y = (stroke.last_y as i32 + step_y) as u32;
+        click_rule_changed |= stafra_click_rule_edit::paint_click_rule_cell(&mut app_state.click_rule_data, painted_x, painted_y, stroke.paint_value, symmetry);
     }
 
-    return -1;
+    if click_rule_changed
+    {
+        apply_click_rule_from_ui(app_state, stafra_state);
+    }
 }
 
-fn setup_initial_ui(state_params: &QueryStringParams)
+//Every edit of the click rule ends here, so the simulation and the query string always get the new click rule
+fn apply_click_rule_from_ui(app_state: &mut app_state::AppState, stafra_state: &mut dyn SimulationBackend)
 {
-    let document = web_sys::window().unwrap().document().unwrap();
-
-    let initial_state_select      = document.get_element_by_id("initial_states").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
-    let size_select               = document.get_element_by_id("sizes").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
-    let last_frame_checkbox       = document.get_element_by_id("last_frame_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-    let last_frame_input          = document.get_element_by_id("last_frame_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-    let spawn_checkbox            = document.get_element_by_id("spawn_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-    let smooth_transform_checkbox = document.get_element_by_id("smooth_transform_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-    let spawn_range               = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-    let spawn_input               = document.get_element_by_id("spawn_number").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let window       = web_sys::window().unwrap();
+    let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
 
-    let board_size = app_state::AppState::board_size_from_index(state_params.size_index);
+    stafra_state.reset_click_rule(&app_state.click_rule_data);
+    query_string.set("click_rule", &app_state.encode_click_rule_base64());
 
-    initial_state_select.set_value(match state_params.initial_state
-    {
-        stafra_state::StandardResetBoardType::Corners => "initial_state_corners",
-        stafra_state::StandardResetBoardType::Edges   => "initial_state_sides",
-        stafra_state::StandardResetBoardType::Center  => "initial_state_center"
-    });
+    let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+    window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
+}
 
-    size_select.set_selected_index(state_params.size_index as i32);
+fn read_click_rule_edit_symmetry_ui() -> stafra_state::RandomBoardSymmetry
+{
+    let document = web_sys::window().unwrap().document().unwrap();
 
-    if state_params.final_frame != u32::MAX
-    {
-        last_frame_checkbox.set_checked(true);
-        last_frame_input.set_value_as_number(state_params.final_frame as f64);
-    }
-    else
+    let click_rule_edit_symmetry_select = document.get_element_by_id("click_rule_edit_symmetry").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    match click_rule_edit_symmetry_select.value().as_str()
     {
-        last_frame_checkbox.set_checked(false);
-        last_frame_input.set_value_as_number((board_size / 2) as f64);
+        "mirror_x" => stafra_state::RandomBoardSymmetry::MirrorX,
+        "4fold"    => stafra_state::RandomBoardSymmetry::FourFold,
+        "8fold"    => stafra_state::RandomBoardSymmetry::EightFold,
+        _          => stafra_state::RandomBoardSymmetry::None
     }
+}
 
-    if state_params.spawn != u32::MAX
-    {
-        spawn_checkbox.set_checked(true);
-        smooth_transform_checkbox.set_checked(state_params.smooth_transform);
+//The transform is read on each click, so the random seed and the symmetry are always new
+fn click_rule_transform_buttons() -> [(&'static str, fn() -> stafra_click_rule_edit::ClickRuleTransform); 10]
+{
+    [
+        ("button_click_rule_rotate",      || stafra_click_rule_edit::ClickRuleTransform::RotateClockwise),
+        ("button_click_rule_flip_x",      || stafra_click_rule_edit::ClickRuleTransform::FlipX),
+        ("button_click_rule_flip_y",      || stafra_click_rule_edit::ClickRuleTransform::FlipY),
+        ("button_click_rule_invert",      || stafra_click_rule_edit::ClickRuleTransform::Invert),
+        ("button_click_rule_clear",       || stafra_click_rule_edit::ClickRuleTransform::Clear),
+        ("button_click_rule_randomize",   || stafra_click_rule_edit::ClickRuleTransform::Randomize {seed: generate_random_seed(), symmetry: read_click_rule_edit_symmetry_ui()}),
+        ("button_click_rule_shift_left",  || stafra_click_rule_edit::ClickRuleTransform::Shift {offset_x: -1, offset_y:  0}),
+        ("button_click_rule_shift_up",    || stafra_click_rule_edit::ClickRuleTransform::Shift {offset_x:  0, offset_y: -1}),
+        ("button_click_rule_shift_down",  || stafra_click_rule_edit::ClickRuleTransform::Shift {offset_x:  0, offset_y:  1}),
+        ("button_click_rule_shift_right", || stafra_click_rule_edit::ClickRuleTransform::Shift {offset_x:  1, offset_y:  0})
+    ]
+}
 
-        spawn_range.set_value_as_number(state_params.spawn as f64);
-        spawn_input.set_value(&state_params.spawn.to_string());
-    }
-    else
-    {
-        spawn_checkbox.set_checked(false);
-        smooth_transform_checkbox.set_checked(false);
+fn read_expression_board_ui() -> (String, u32)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
 
-        spawn_range.set_value_as_number(8 as f64);
-        spawn_input.set_value("8");
-    }
+    let board_expression_input = document.get_element_by_id("board_expression").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let expression_seed_input  = document.get_element_by_id("expression_seed").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+
+    let seed_value = expression_seed_input.value_as_number();
+    (board_expression_input.value(), if seed_value.is_nan() {0} else {seed_value.clamp(0.0, u32::MAX as f64) as u32})
 }
 
-fn update_ui(run_state: RunState)
+fn set_expression_board_ui(expression_source: &str, expression_seed: u32)
 {
     let document = web_sys::window().unwrap().document().unwrap();
 
-    let play_pause_button = document.get_element_by_id("button_play_pause").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-    let stop_button       = document.get_element_by_id("button_stop_record").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let board_expression_input = document.get_element_by_id("board_expression").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let expression_seed_input  = document.get_element_by_id("expression_seed").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
 
-    if run_state == RunState::Running || run_state == RunState::Recording || run_state == RunState::SavePendingRecording
+    board_expression_input.set_value(expression_source);
+    expression_seed_input.set_value_as_number(expression_seed as f64);
+}
+
+//Parse errors are shown under the expression, the board stays the same
+fn show_expression_error(error_message: Option<String>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let expression_error_label = document.get_element_by_id("expression_error").unwrap().dyn_into::<web_sys::HtmlElement>().unwrap();
+
+    expression_error_label.set_hidden(error_message.is_none());
+    expression_error_label.set_inner_text(error_message.as_deref().unwrap_or(""));
+}
+
+fn set_expression_board_query(query_string: &web_sys::UrlSearchParams, expression_source: &str, expression_seed: u32)
+{
+    query_string.set("expression",      expression_source);
+    query_string.set("expression_seed", &expression_seed.to_string());
+}
+
+fn delete_expression_board_query(query_string: &web_sys::UrlSearchParams)
+{
+    query_string.delete("expression");
+    query_string.delete("expression_seed");
+}
+
+fn reset_expression_board_from_ui(stafra_state: &mut dyn SimulationBackend)
+{
+    let window       = web_sys::window().unwrap();
+    let query_string = web_sys::UrlSearchParams::new_with_str(window.location().search().unwrap().as_str()).unwrap();
+
+    let (expression_source, expression_seed) = read_expression_board_ui();
+    let reset_result = stafra_state.reset_board_expression(&expression_source, expression_seed);
+    if reset_result.is_ok()
     {
-        play_pause_button.set_text_content(Some("⏸️"));
+        set_expression_board_query(&query_string, &expression_source, expression_seed);
+
+        let new_search_state = window.location().pathname().unwrap() + "?" + &query_string.to_string().as_string().unwrap();
+        window.history().unwrap().replace_state_with_url(&JsValue::NULL, "", Some(&new_search_state)).unwrap();
     }
-    else
+
+    show_expression_error(reset_result.err());
+}
+
+fn update_timeline(frame_number: u32, timeline_start: u32, timeline_end: u32)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let timeline_slider = document.get_element_by_id("timeline_slider").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let timeline_label  = document.get_element_by_id("timeline_label").unwrap();
+
+    timeline_slider.set_min(&timeline_start.to_string());
+    timeline_slider.set_max(&timeline_end.to_string());
+    timeline_slider.set_value_as_number(frame_number as f64);
+
+    timeline_label.set_text_content(Some(&format!("{} / {}", frame_number, timeline_end)));
+}
+
+//None if the play mode is off or the simulation is not stopped
+fn update_lights_out_status(lights_out_status: Option<stafra_lights_out::LightsOutStatus>)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let lights_out_undo_button  = document.get_element_by_id("button_lights_out_undo").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let lights_out_solve_button = document.get_element_by_id("button_lights_out_solve").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let lights_out_status_label = document.get_element_by_id("lights_out_status").unwrap();
+
+    lights_out_solve_button.set_disabled(lights_out_status.is_none());
+
+    let status = match lights_out_status
     {
-        play_pause_button.set_text_content(Some("▶️"));
-    }
+        Some(status) => status,
+        None =>
+        {
+            lights_out_undo_button.set_disabled(true);
+            lights_out_status_label.set_text_content(Some("Turn all the lights off"));
+            return;
+        }
+    };
 
-    if run_state == RunState::Stopped || run_state == RunState::SavePendingRecording
+    lights_out_undo_button.set_disabled(status.move_count == 0);
+
+    let status_text = match status.lit_cells
     {
-        stop_button.set_text_content(Some("⏺️"));
-    }
-    else
+        Some(_) if status.solved() => format!("Solved in {} moves!", status.move_count),
+        Some(lit_cells)            => format!("Moves: {}, lights on: {}", status.move_count, lit_cells),
+        None                       => format!("Moves: {}", status.move_count)
+    };
+
+    lights_out_status_label.set_text_content(Some(&status_text));
+}
+
+fn update_period_text(update_period: stafra_click_analysis::UpdatePeriod) -> String
+{
+    format!("period {} after {} frames", update_period.period, update_period.transient)
+}
+
+fn standard_reset_type_name(reset_type: stafra_state::StandardResetBoardType) -> &'static str
+{
+    match reset_type
     {
-        stop_button.set_text_content(Some("⏹️"));
+        stafra_state::StandardResetBoardType::Corners => "Corners",
+        stafra_state::StandardResetBoardType::Edges   => "Sides",
+        stafra_state::StandardResetBoardType::Center  => "Center"
     }
+}
 
-    let stop_button = document.get_element_by_id("button_stop_record").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-    stop_button.set_disabled(run_state == RunState::SavePendingRecording);
+//The message is shown if there's no analysis
+fn update_click_rule_analysis(click_rule_analysis: Option<&stafra_click_analysis::ClickRuleAnalysis>, message: &str)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
 
-    let play_pause_button = document.get_element_by_id("button_play_pause").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-    play_pause_button.set_disabled(run_state == RunState::SavePendingRecording);
+    let analysis_export_button    = document.get_element_by_id("button_click_rule_analysis_export").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let analysis_status_label     = document.get_element_by_id("click_rule_analysis_status").unwrap();
+    let quiet_pattern_input       = document.get_element_by_id("quiet_pattern_index").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+    let quiet_pattern_view_button = document.get_element_by_id("button_quiet_pattern_view").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
 
-    let next_frame_button = document.get_element_by_id("button_next_frame").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-    next_frame_button.set_disabled(run_state == RunState::Running || run_state == RunState::Recording || run_state == RunState::PausedRecording || run_state == RunState::SavePendingRecording);
+    let quiet_pattern_count = click_rule_analysis.map_or(0, |analysis| analysis.quiet_patterns.len());
 
-    let save_png_button = document.get_element_by_id("button_save_png").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-    save_png_button.set_disabled(run_state == RunState::SavePendingRecording);
+    analysis_export_button.set_disabled(click_rule_analysis.is_none());
+    quiet_pattern_input.set_disabled(quiet_pattern_count == 0);
+    quiet_pattern_view_button.set_disabled(quiet_pattern_count == 0);
 
-    let initial_board_select = document.get_element_by_id("initial_states").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
-    initial_board_select.set_disabled(run_state != RunState::Stopped);
+    quiet_pattern_input.set_max(&quiet_pattern_count.saturating_sub(1).to_string());
+    quiet_pattern_input.set_value_as_number(0.0);
 
-    let size_select = document.get_element_by_id("sizes").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
-    size_select.set_disabled(run_state != RunState::Stopped);
+    let status_text = match click_rule_analysis
+    {
+        Some(analysis) => format!("Rank: {} of {}, kernel dimension: {}", analysis.rank, analysis.enabled_cells, analysis.kernel_dimension),
+        None           => message.to_string()
+    };
 
-    let last_frame_checkbox = document.get_element_by_id("last_frame_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-    last_frame_checkbox.set_disabled(run_state == RunState::SavePendingRecording);
+    analysis_status_label.set_text_content(Some(&status_text));
+}
 
-    let spawn_checkbox = document.get_element_by_id("spawn_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-    spawn_checkbox.set_disabled(run_state != RunState::Stopped);
+//Moves are toggled in the shown solution, any other change of the board makes it stale
+fn lights_out_solution_stale(old_status: Option<stafra_lights_out::LightsOutStatus>, new_status: Option<stafra_lights_out::LightsOutStatus>) -> bool
+{
+    match (old_status, new_status)
+    {
+        (Some(old_status), Some(new_status)) =>
+        {
+            if new_status.move_count != old_status.move_count
+            {
+                new_status.move_count < old_status.move_count
+            }
+            else
+            {
+                //The lit cell count arrives later than the move on the GPU
+                old_status.lit_cells.is_some() && new_status.lit_cells != old_status.lit_cells
+            }
+        }
 
-    let spawn_decrement_button = document.get_element_by_id("decrement_spawn").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-    spawn_decrement_button.set_disabled(run_state != RunState::Stopped || !spawn_checkbox.checked());
+        _ => true
+    }
+}
 
-    let spawn_increment_button = document.get_element_by_id("increment_spawn").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-    spawn_increment_button.set_disabled(run_state != RunState::Stopped || !spawn_checkbox.checked());
+//The message is shown if there's no solution
+fn update_lights_out_solution(lights_out_solution: Option<&stafra_lights_out::LightsOutSolution>, message: &str)
+{
+    let document = web_sys::window().unwrap().document().unwrap();
 
-    let spawn_slider = document.get_element_by_id("spawn_range").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-    spawn_slider.set_disabled(run_state != RunState::Stopped || !spawn_checkbox.checked());
+    let solution_canvas        = document.get_element_by_id("lights_out_solution_canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+    let hide_solution_button   = document.get_element_by_id("button_lights_out_hide_solution").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let export_solution_button = document.get_element_by_id("button_lights_out_export_solution").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
+    let solution_status_label  = document.get_element_by_id("lights_out_solution_status").unwrap();
 
-    let smooth_transform_checkbox = document.get_element_by_id("smooth_transform_checkbox").unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
-    smooth_transform_checkbox.set_disabled(run_state != RunState::Stopped || !spawn_checkbox.checked());
+    hide_solution_button.set_disabled(lights_out_solution.is_none());
+    export_solution_button.set_disabled(lights_out_solution.is_none());
 
-    let upload_restriction_button = document.get_element_by_id("button_upload_restriction").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-    upload_restriction_button.set_disabled(run_state != RunState::Stopped);
+    let solution = match lights_out_solution
+    {
+        Some(solution) => solution,
+        None =>
+        {
+            solution_canvas.set_hidden(true);
+            solution_status_label.set_text_content(Some(message));
+            return;
+        }
+    };
 
-    let clear_restriction_button = document.get_element_by_id("button_clear_restriction").unwrap().dyn_into::<web_sys::HtmlButtonElement>().unwrap();
-    clear_restriction_button.set_disabled(run_state != RunState::Stopped);
+    let overlay_image = solution.overlay_image();
+    let image_data    = web_sys::ImageData::new_with_u8_clamped_array_and_sh(Clamped(overlay_image.pixel_data.as_slice()), overlay_image.image_width, overlay_image.image_height).unwrap();
+
+    solution_canvas.set_width(overlay_image.image_width);
+    solution_canvas.set_height(overlay_image.image_height);
+
+    let canvas_context = solution_canvas.get_context("2d").unwrap().unwrap().dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
+    canvas_context.put_image_data(&image_data, 0.0, 0.0).expect("Image data put error!");
+
+    solution_canvas.set_hidden(false);
+    solution_status_label.set_text_content(Some(&format!("Solution: {} clicks", solution.click_count)));
 }
 
 fn update_next_frame_button_paused_recording(next_video_frame_available: bool)
@@ -1247,6 +3589,30 @@ fn update_next_frame_button_paused_recording(next_video_frame_available: bool)
     next_frame_button.set_disabled(!next_video_frame_available);
 }
 
+//The image must be black and white and have the board size, it's loaded unchanged
+fn load_board_image(stafra_state: &mut dyn SimulationBackend, app_state: &mut app_state::AppState, image_array: Vec<u8>, width: u32, height: u32)
+{
+    stafra_state.set_image_import_options(stafra_image_import::ImageImportOptions::default());
+    let reset_result = stafra_state.reset_board_custom(image_array, width, height);
+
+    let new_size = match reset_result
+    {
+        Ok(new_size) => new_size,
+        Err(message) =>
+        {
+            web_sys::console::error_1(&message.into());
+            return;
+        }
+    };
+
+    let document    = web_sys::window().unwrap().document().unwrap();
+    let size_select = document.get_element_by_id("sizes").unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+    let size_index  = (std::mem::size_of::<u32>() * 8) as u32 - new_size.leading_zeros() - 1;
+    size_select.set_selected_index(size_index as i32);
+
+    update_last_frame_with_size(new_size, app_state);
+}
+
 fn update_last_frame_with_size(new_size: u32, app_state: &mut app_state::AppState)
 {
     let document         = web_sys::window().unwrap().document().unwrap();
@@ -1,18 +1,43 @@
-#![cfg(target_arch = "wasm32")]
+//The web app is the library's entry point. The simulation modules are also used natively by stafra_native and the benchmark
 
 pub mod app_state;
+pub mod simulation_backend;
 pub mod stafra_state;
+pub mod stafra_cpu_state;
 pub mod stafra_static_state;
 pub mod stafra_static_state_bindings;
 pub mod stafra_board_state_bindings;
+pub mod stafra_batch_state_bindings;
+pub mod stafra_readback_pool;
+pub mod stafra_profiler;
+pub mod stafra_session;
+pub mod stafra_history;
+pub mod stafra_expression;
+pub mod stafra_image_import;
+pub mod stafra_paint;
+pub mod stafra_lights_out;
+pub mod stafra_click_analysis;
+pub mod stafra_gf2_polynomial;
+pub mod stafra_inverse_problem;
+pub mod stafra_click_rule_edit;
 pub mod stafra_initial_state_bindings;
 pub mod video_record_state;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod stafra_rule_exploration;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod stafra_rule_search;
+
+#[cfg(target_arch = "wasm32")]
 mod event_loop_web;
 
+#[cfg(test)]
+mod stafra_test_helpers;
+
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
-use console_log;
-use console_error_panic_hook;
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(start)]
 pub fn entry_point()
 {
@@ -1,8 +1,5 @@
 #![cfg(not(target_arch = "wasm32"))]
 
-pub mod app_state;
-pub mod stafra_state;
-pub mod video_record_state;
 mod event_loop_native;
 
 fn main()
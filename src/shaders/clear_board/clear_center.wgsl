@@ -1,17 +1,33 @@
 @group(0) @binding(0) var out_initial_board: texture_storage_2d<r32uint, write>;
 
+//The board can be split into several tiles, each one stored in its own texture
+struct TileInfo
+{
+    board_size:  vec2<u32>, //Size of the whole board in quads
+    tile_offset: vec2<u32>, //Offset of the tile in quads
+    halo_size:   u32,       //Number of halo quads on each side of the input board texture
+    padding_0:   u32,
+    padding_1:   u32,
+    padding_2:   u32
+};
+
+@group(1) @binding(0) var<uniform> tile_info: TileInfo;
+
 @compute @workgroup_size(16, 16)
 fn main(@builtin(global_invocation_id) global_thread_id: vec3<u32>)
 {
-    let board_size: vec2<i32> = textureDimensions(out_initial_board);
+    let tile_size: vec2<i32> = textureDimensions(out_initial_board);
 
-	if(global_thread_id.x >= u32(board_size.x) || global_thread_id.y >= u32(board_size.y))
+	if(global_thread_id.x >= u32(tile_size.x) || global_thread_id.y >= u32(tile_size.y))
 	{
 	    return;
 	}
 
+	let board_size: vec2<i32> = vec2<i32>(tile_info.board_size);
+	let quad_id:    vec2<u32> = global_thread_id.xy + tile_info.tile_offset;
+
     let center        = vec2<u32>(u32(board_size.x - 1) / 2u, u32(board_size.y - 1) / 2u);
-    let inside_center = u32(all(global_thread_id.xy == center));
+    let inside_center = u32(all(quad_id == center));
 
     //Each thread processes a single 2x2 quad. The quad is packed into uint32:
     // (Bits 0-7)   (Bits 8-15)
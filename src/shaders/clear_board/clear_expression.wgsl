@@ -0,0 +1,84 @@
+@group(0) @binding(0) var out_initial_board: texture_storage_2d<r32uint, write>;
+
+//The board can be split into several tiles, each one stored in its own texture
+struct TileInfo
+{
+    board_size:  vec2<u32>, //Size of the whole board in quads
+    tile_offset: vec2<u32>, //Offset of the tile in quads
+    halo_size:   u32,       //Number of halo quads on each side of the input board texture
+    padding_0:   u32,
+    padding_1:   u32,
+    padding_2:   u32
+};
+
+@group(1) @binding(0) var<uniform> tile_info: TileInfo;
+
+//Division and remainder never trap: x / 0 and x % 0 are 0, and the most negative number divided by -1 wraps around.
+//Must produce the same results as BoardExpression::cell_enabled
+fn expression_div(left: i32, right: i32) -> i32
+{
+    if(right == 0)
+    {
+        return 0;
+    }
+    else if(right == -1)
+    {
+        return -left;
+    }
+
+    return left / right;
+}
+
+fn expression_rem(left: i32, right: i32) -> i32
+{
+    if(right == 0 || right == -1)
+    {
+        return 0;
+    }
+
+    return left % right;
+}
+
+fn pcg_hash(value: u32) -> u32
+{
+    let state = value * 747796405u + 2891336453u;
+    let word  = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+//Generated from the board expression by BoardExpression::to_wgsl_function()
+BOARD_EXPRESSION_FUNCTION
+
+fn cell_enabled(cell: vec2<u32>, last_cell: vec2<u32>) -> u32
+{
+    //The last quad in each row and column has cells outside of the board
+    if(any(cell > last_cell))
+    {
+        return 0u;
+    }
+
+    let board_size = i32(last_cell.x + 1u);
+    return u32(board_expression(i32(cell.x), i32(cell.y), board_size) != 0);
+}
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_thread_id: vec3<u32>)
+{
+    let tile_size: vec2<i32> = textureDimensions(out_initial_board);
+
+	if(global_thread_id.x >= u32(tile_size.x) || global_thread_id.y >= u32(tile_size.y))
+	{
+	    return;
+	}
+
+    let last_cell:  vec2<u32> = tile_info.board_size * 2u - vec2<u32>(2u, 2u);
+	let quad_start: vec2<u32> = (global_thread_id.xy + tile_info.tile_offset) * 2u;
+
+	//Each thread processes a single 2x2 quad. The quad is packed into uint32:
+	// (Bits 0-7)   (Bits 8-15)
+	// (Bits 16-23) (Bits 24-31)
+	let packed_quad: u32 = (cell_enabled(quad_start + vec2<u32>(0u, 0u), last_cell) <<  0u) | (cell_enabled(quad_start + vec2<u32>(1u, 0u), last_cell) <<  8u)
+	                     | (cell_enabled(quad_start + vec2<u32>(0u, 1u), last_cell) << 16u) | (cell_enabled(quad_start + vec2<u32>(1u, 1u), last_cell) << 24u);
+
+    textureStore(out_initial_board, vec2<i32>(global_thread_id.xy), vec4<u32>(packed_quad));
+}
\ No newline at end of file
@@ -0,0 +1,126 @@
+@group(0) @binding(0) var out_initial_board: texture_storage_2d<r32uint, write>;
+
+//The board can be split into several tiles, each one stored in its own texture
+struct TileInfo
+{
+    board_size:  vec2<u32>, //Size of the whole board in quads
+    tile_offset: vec2<u32>, //Offset of the tile in quads
+    halo_size:   u32,       //Number of halo quads on each side of the input board texture
+    padding_0:   u32,
+    padding_1:   u32,
+    padding_2:   u32
+};
+
+@group(1) @binding(0) var<uniform> tile_info: TileInfo;
+
+//Must produce the same board as PatternBoardParams::cell_enabled
+struct PatternBoardData
+{
+    kind:   u32, //0 - horizontal line, 1 - vertical line, 2 - cross, 3 - diagonals, 4 - checkerboard, 5 - concentric squares, 6 - concentric circles, 7 - single cell
+    size:   u32, //Half-width of the lines, side of the checkerboard squares or distance between the rings
+    cell_x: u32, //Position of the single cell
+    cell_y: u32
+};
+
+@group(2) @binding(0) var<uniform> pattern_board_data: PatternBoardData;
+
+//Largest number whose square is not bigger than the value
+fn integer_sqrt(value: u32) -> u32
+{
+    var root = u32(sqrt(f32(value)));
+    loop
+    {
+        if(root * root <= value)
+        {
+            break;
+        }
+
+        root = root - 1u;
+    }
+
+    loop
+    {
+        if((root + 1u) * (root + 1u) > value)
+        {
+            break;
+        }
+
+        root = root + 1u;
+    }
+
+    return root;
+}
+
+fn cell_enabled(cell: vec2<u32>, last_cell: vec2<u32>) -> u32
+{
+    //The last quad in each row and column has cells outside of the board
+    if(any(cell > last_cell))
+    {
+        return 0u;
+    }
+
+    let center_cell     = last_cell / 2u;
+    let center_distance = max(cell, center_cell) - min(cell, center_cell);
+    let size            = max(pattern_board_data.size, 1u);
+
+    var enabled = false;
+    switch(pattern_board_data.kind)
+    {
+        case 0u:
+        {
+            enabled = center_distance.y < size;
+        }
+        case 1u:
+        {
+            enabled = center_distance.x < size;
+        }
+        case 2u:
+        {
+            enabled = center_distance.x < size || center_distance.y < size;
+        }
+        case 3u:
+        {
+            enabled = max(center_distance.x, center_distance.y) - min(center_distance.x, center_distance.y) < size;
+        }
+        case 4u:
+        {
+            enabled = (center_distance.x / size + center_distance.y / size) % 2u == 0u;
+        }
+        case 5u:
+        {
+            enabled = max(center_distance.x, center_distance.y) % size == 0u;
+        }
+        case 6u:
+        {
+            enabled = integer_sqrt(dot(center_distance, center_distance)) % size == 0u;
+        }
+        default:
+        {
+            enabled = all(cell == min(vec2<u32>(pattern_board_data.cell_x, pattern_board_data.cell_y), last_cell));
+        }
+    }
+
+    return u32(enabled);
+}
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_thread_id: vec3<u32>)
+{
+    let tile_size: vec2<i32> = textureDimensions(out_initial_board);
+
+	if(global_thread_id.x >= u32(tile_size.x) || global_thread_id.y >= u32(tile_size.y))
+	{
+	    return;
+	}
+
+    let last_cell:  vec2<u32> = tile_info.board_size * 2u - vec2<u32>(2u, 2u);
+	let quad_start: vec2<u32> = (global_thread_id.xy + tile_info.tile_offset) * 2u;
+
+	//Each thread processes a single 2x2 quad. The quad is packed into uint32:
+	// (Bits 0-7)   (Bits 8-15)
+	// (Bits 16-23) (Bits 24-31)
+	let packed_quad: u32 = (cell_enabled(quad_start + vec2<u32>(0u, 0u), last_cell) <<  0u) | (cell_enabled(quad_start + vec2<u32>(1u, 0u), last_cell) <<  8u)
+	                     | (cell_enabled(quad_start + vec2<u32>(0u, 1u), last_cell) << 16u) | (cell_enabled(quad_start + vec2<u32>(1u, 1u), last_cell) << 24u);
+
+    textureStore(out_initial_board, vec2<i32>(global_thread_id.xy), vec4<u32>(packed_quad));
+}
\ No newline at end of file
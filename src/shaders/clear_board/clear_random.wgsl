@@ -0,0 +1,106 @@
+@group(0) @binding(0) var out_initial_board: texture_storage_2d<r32uint, write>;
+
+//The board can be split into several tiles, each one stored in its own texture
+struct TileInfo
+{
+    board_size:  vec2<u32>, //Size of the whole board in quads
+    tile_offset: vec2<u32>, //Offset of the tile in quads
+    halo_size:   u32,       //Number of halo quads on each side of the input board texture
+    padding_0:   u32,
+    padding_1:   u32,
+    padding_2:   u32
+};
+
+@group(1) @binding(0) var<uniform> tile_info: TileInfo;
+
+//Must produce the same board as RandomBoardParams::cell_enabled
+struct RandomBoardData
+{
+    seed:              u32,
+    density_threshold: u32, //Cells with the hash below the threshold are enabled
+    symmetry:          u32, //0 - none, 1 - mirror x, 2 - 4-fold, 3 - 8-fold
+    shape:             u32, //0 - full board, 1 - central disc, 2 - central square
+    shape_radius:      u32, //Radius of the disc or half-size of the square in cells, not counting the center cell
+    padding_0:         u32,
+    padding_1:         u32,
+    padding_2:         u32
+};
+
+@group(2) @binding(0) var<uniform> random_board_data: RandomBoardData;
+
+fn pcg_hash(value: u32) -> u32
+{
+    let state = value * 747796405u + 2891336453u;
+    let word  = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn cell_enabled(cell: vec2<u32>, last_cell: vec2<u32>) -> u32
+{
+    //The last quad in each row and column has cells outside of the board
+    if(any(cell > last_cell))
+    {
+        return 0u;
+    }
+
+    let center_cell     = last_cell / 2u;
+    let center_distance = max(cell, center_cell) - min(cell, center_cell);
+    let shape_radius    = random_board_data.shape_radius;
+
+    var inside_shape = true;
+    if(random_board_data.shape == 1u)
+    {
+        inside_shape = dot(center_distance, center_distance) <= shape_radius * shape_radius;
+    }
+    else if(random_board_data.shape == 2u)
+    {
+        inside_shape = max(center_distance.x, center_distance.y) <= shape_radius;
+    }
+
+    if(!inside_shape)
+    {
+        return 0u;
+    }
+
+    //Symmetric cells share the hash
+    var hash_cell = cell;
+    if(random_board_data.symmetry >= 1u)
+    {
+        hash_cell.x = min(cell.x, last_cell.x - cell.x);
+    }
+
+    if(random_board_data.symmetry >= 2u)
+    {
+        hash_cell.y = min(cell.y, last_cell.y - cell.y);
+    }
+
+    if(random_board_data.symmetry >= 3u)
+    {
+        hash_cell = vec2<u32>(min(hash_cell.x, hash_cell.y), max(hash_cell.x, hash_cell.y));
+    }
+
+    let cell_hash = pcg_hash(hash_cell.x + pcg_hash(hash_cell.y + pcg_hash(random_board_data.seed)));
+    return u32(cell_hash < random_board_data.density_threshold);
+}
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_thread_id: vec3<u32>)
+{
+    let tile_size: vec2<i32> = textureDimensions(out_initial_board);
+
+	if(global_thread_id.x >= u32(tile_size.x) || global_thread_id.y >= u32(tile_size.y))
+	{
+	    return;
+	}
+
+    let last_cell:  vec2<u32> = tile_info.board_size * 2u - vec2<u32>(2u, 2u);
+	let quad_start: vec2<u32> = (global_thread_id.xy + tile_info.tile_offset) * 2u;
+
+	//Each thread processes a single 2x2 quad. The quad is packed into uint32:
+	// (Bits 0-7)   (Bits 8-15)
+	// (Bits 16-23) (Bits 24-31)
+	let packed_quad: u32 = (cell_enabled(quad_start + vec2<u32>(0u, 0u), last_cell) <<  0u) | (cell_enabled(quad_start + vec2<u32>(1u, 0u), last_cell) <<  8u)
+	                     | (cell_enabled(quad_start + vec2<u32>(0u, 1u), last_cell) << 16u) | (cell_enabled(quad_start + vec2<u32>(1u, 1u), last_cell) << 24u);
+
+    textureStore(out_initial_board, vec2<i32>(global_thread_id.xy), vec4<u32>(packed_quad));
+}
\ No newline at end of file
@@ -0,0 +1,128 @@
+//Makes a Lights Out move: toggles every cell the click rule covers around the clicked cell.
+//A lit cell at (x, y) changes the cells (x - xi, y - yi) on the next step, so the move toggles the same cells as a single lit cell at the clicked position.
+//Restricted cells are never toggled, and a click on a restricted cell doesn't toggle anything
+
+struct ClickRuleData
+{
+    header_packed:            vec4<u32>,
+    enabled_positions_packed: array<vec4<i32>, 512> //click_rule_width * click_rule_height / 2
+};
+
+@group(0) @binding(0) var in_board:          texture_2d<u32>;
+@group(0) @binding(1) var restriction:       texture_2d<u32>;
+@group(0) @binding(2) var click_restriction: texture_2d<u32>; //The restriction of the tile with the clicked cell
+@group(0) @binding(3) var out_board:         texture_storage_2d<r32uint, write>;
+
+@group(0) @binding(4) var<uniform> click_rule_data: ClickRuleData;
+
+//The board can be split into several tiles, each one stored in its own texture
+struct TileInfo
+{
+    board_size:  vec2<u32>, //Size of the whole board in quads
+    tile_offset: vec2<u32>, //Offset of the tile in quads
+    halo_size:   u32,       //Number of halo quads on each side of the input board texture
+    padding_0:   u32,
+    padding_1:   u32,
+    padding_2:   u32
+};
+
+@group(1) @binding(0) var<uniform> tile_info: TileInfo;
+
+struct LightsOutClickData
+{
+    click_x:             u32, //The clicked cell
+    click_y:             u32,
+    click_tile_offset_x: u32, //Offset of the tile with the clicked cell, in quads
+    click_tile_offset_y: u32,
+    region_min_x:        u32, //The region of the board the move can change, in quads
+    region_min_y:        u32,
+    region_max_x:        u32,
+    region_max_y:        u32
+};
+
+@group(2) @binding(0) var<uniform> lights_out_click_data: LightsOutClickData;
+
+fn quad_cell(packed_quad: u32, cell: vec2<u32>) -> u32
+{
+    let cell_shift = (cell.y % 2u) * 16u + (cell.x % 2u) * 8u;
+    return (packed_quad >> cell_shift) & 0xffu;
+}
+
+fn cell_toggled(cell_offset: vec2<i32>) -> bool
+{
+    let element_count: u32 = click_rule_data.header_packed.x;
+    let packed_element_count = i32(element_count / 2u);
+
+    for(var i: i32 = 0; i < packed_element_count; i = i + 1)
+    {
+        let offsets_packed: vec4<i32> = click_rule_data.enabled_positions_packed[i];
+        if(all(offsets_packed.xy == cell_offset) || all(offsets_packed.zw == cell_offset))
+        {
+            return true;
+        }
+    }
+
+    if((element_count % 2u) != 0u)
+    {
+        let last_offset: vec2<i32> = click_rule_data.enabled_positions_packed[packed_element_count].xy;
+        return all(last_offset == cell_offset);
+    }
+
+    return false;
+}
+
+fn clicked_cell(cell: vec2<u32>, last_cell: vec2<u32>, restriction_quad: u32, old_value: u32) -> u32
+{
+    //The last quad in each row and column has cells outside of the board
+    if(any(cell > last_cell) || quad_cell(restriction_quad, cell) == 0u)
+    {
+        return old_value;
+    }
+
+    let click_cell = vec2<u32>(lights_out_click_data.click_x, lights_out_click_data.click_y);
+    if(!cell_toggled(vec2<i32>(click_cell) - vec2<i32>(cell)))
+    {
+        return old_value;
+    }
+
+    return 1u - old_value;
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) global_thread_id: vec3<u32>)
+{
+    let tile_size: vec2<u32> = vec2<u32>(textureDimensions(out_board));
+
+    //The region is clamped to the tile on the CPU side in the same way
+    let region_min: vec2<u32> = max(vec2<u32>(lights_out_click_data.region_min_x, lights_out_click_data.region_min_y), tile_info.tile_offset);
+    let region_max: vec2<u32> = min(vec2<u32>(lights_out_click_data.region_max_x, lights_out_click_data.region_max_y), tile_info.tile_offset + tile_size - vec2<u32>(1u, 1u));
+
+    let board_quad: vec2<u32> = region_min + global_thread_id.xy;
+    if(any(board_quad > region_max))
+    {
+        return;
+    }
+
+    let click_cell       = vec2<u32>(lights_out_click_data.click_x, lights_out_click_data.click_y);
+    let click_tile_quad  = vec2<i32>(click_cell / 2u - vec2<u32>(lights_out_click_data.click_tile_offset_x, lights_out_click_data.click_tile_offset_y));
+    let click_restricted = quad_cell(textureLoad(click_restriction, click_tile_quad, 0).x, click_cell) == 0u;
+    if(click_restricted)
+    {
+        return;
+    }
+
+    let tile_quad:        vec2<i32> = vec2<i32>(board_quad - tile_info.tile_offset);
+    let old_quad:         u32       = textureLoad(in_board,    tile_quad, 0).x;
+    let restriction_quad: u32       = textureLoad(restriction, tile_quad, 0).x;
+
+    let last_cell:  vec2<u32> = tile_info.board_size * 2u - vec2<u32>(2u, 2u);
+    let quad_start: vec2<u32> = board_quad * 2u;
+
+    //The quad is packed into uint32:
+    // (Bits 0-7)   (Bits 8-15)
+    // (Bits 16-23) (Bits 24-31)
+    let packed_quad: u32 = (clicked_cell(quad_start + vec2<u32>(0u, 0u), last_cell, restriction_quad, (old_quad >>  0u) & 0xffu) <<  0u) | (clicked_cell(quad_start + vec2<u32>(1u, 0u), last_cell, restriction_quad, (old_quad >>  8u) & 0xffu) <<  8u)
+                         | (clicked_cell(quad_start + vec2<u32>(0u, 1u), last_cell, restriction_quad, (old_quad >> 16u) & 0xffu) << 16u) | (clicked_cell(quad_start + vec2<u32>(1u, 1u), last_cell, restriction_quad, (old_quad >> 24u) & 0xffu) << 24u);
+
+    textureStore(out_board, tile_quad, vec4<u32>(packed_quad));
+}
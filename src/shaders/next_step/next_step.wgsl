@@ -14,6 +14,12 @@
 //The largest click rule radius is 16, which means each workgroup might access eight more 16x16 blocks of values, surrounding the original one.
 //These blocks are packed into quads too, so we need to store up to 24x24 quads.
 
+//Big boards are split into tiles. In this case prev_board is a separate texture that contains the tile together with halo_size quads of its neighbors on each side.
+//For the boards that consist of a single tile, halo_size is 0 and prev_board is the board texture itself.
+
+//The boards are texture arrays, and each layer has its own click rule. The main board is a single layer, while batches of small boards
+//are simulated with one layer per board. Each workgroup processes a single layer, selected by the z coordinate of the workgroup.
+
 //The original formula for next_board[x, y] can be extended to quads. Instead of using prev_board[x - xi, y - yi], we calculate the quad to add from the elements of prev_board.
 //The formula to calculate this quad depends on the values of xi and yi:
 //- If both xi and yi are divisible by 2, we add the quad at prev_board[xi / 2, yi / 2] to the quad at next_board[xi / 2, yi / 2].
@@ -36,15 +42,29 @@ struct ClickRuleData
     enabled_positions_packed: array<vec4<i32>, 512> //click_rule_width * click_rule_height / 2
 };
 
-@group(0) @binding(0) var prev_board:     texture_2d<u32>;
-@group(0) @binding(1) var prev_stability: texture_2d<u32>;
+@group(0) @binding(0) var prev_board:     texture_2d_array<u32>;
+@group(0) @binding(1) var prev_stability: texture_2d_array<u32>;
 
-@group(0) @binding(2) var next_board:     texture_storage_2d<r32uint, write>;
-@group(0) @binding(3) var next_stability: texture_storage_2d<r32uint, write>;
+@group(0) @binding(2) var next_board:     texture_storage_2d_array<r32uint, write>;
+@group(0) @binding(3) var next_stability: texture_storage_2d_array<r32uint, write>;
 
+//The restriction is shared by all layers
 @group(0) @binding(4) var restriction: texture_2d<u32>;
 
-@group(0) @binding(5) var<uniform> click_rule_data: ClickRuleData;
+@group(0) @binding(5) var<storage, read> click_rules: array<ClickRuleData>;
+
+//The board can be split into several tiles, each one stored in its own texture
+struct TileInfo
+{
+    board_size:  vec2<u32>, //Size of the whole board in quads
+    tile_offset: vec2<u32>, //Offset of the tile in quads
+    halo_size:   u32,       //Number of halo quads on each side of the input board texture
+    padding_0:   u32,
+    padding_1:   u32,
+    padding_2:   u32
+};
+
+@group(1) @binding(0) var<uniform> tile_info: TileInfo;
 
 var<workgroup> shared_quad_states: array<u32, 576>; //(click_rule_data_width + workgroup_threads_x) * (click_rule_data_height + workgroup_threads_y)
 
@@ -159,18 +179,20 @@ fn calculate_quad(local_thread_id: vec2<u32>, click_rule_offset: vec2<i32>, extr
     }
 }
 
-fn update_quad_state(local_id: vec2<u32>, global_id: vec2<u32>, block_offset: vec2<i32>, board_size: vec2<i32>, extra_radius_quads: u32)
+fn update_quad_state(local_id: vec2<u32>, global_id: vec2<u32>, layer: i32, block_offset: vec2<i32>, board_size: vec2<i32>, extra_radius_quads: u32)
 {
     let extra_quad_state_index = calculate_quad_index(local_id, block_offset, extra_radius_quads);
-    let extra_quad_state = textureLoad(prev_board, vec2<i32>(global_id) + block_offset, 0).x;
-    shared_quad_states[extra_quad_state_index] = extra_quad_state & calculate_quad_mask(global_id, block_offset, board_size);
+    let extra_quad_state = textureLoad(prev_board, vec2<i32>(global_id + vec2<u32>(tile_info.halo_size)) + block_offset, layer, 0).x;
+    shared_quad_states[extra_quad_state_index] = extra_quad_state & calculate_quad_mask(global_id + tile_info.tile_offset, block_offset, board_size);
 }
 
 @compute @workgroup_size(8, 8)
-fn main(@builtin(local_invocation_id) local_thread_id: vec3<u32>, @builtin(global_invocation_id) global_thread_id: vec3<u32>)
+fn main(@builtin(local_invocation_id) local_thread_id: vec3<u32>, @builtin(global_invocation_id) global_thread_id: vec3<u32>, @builtin(workgroup_id) workgroup_id: vec3<u32>)
 {
-    let element_count: u32 = click_rule_data.header_packed.x;
-    let radius:        u32 = click_rule_data.header_packed.y;
+    let layer: i32 = i32(workgroup_id.z);
+
+    let element_count: u32 = click_rules[layer].header_packed.x;
+    let radius:        u32 = click_rules[layer].header_packed.y;
 
     if(radius == 0u)
     {
@@ -180,11 +202,12 @@ fn main(@builtin(local_invocation_id) local_thread_id: vec3<u32>, @builtin(globa
     let extra_radius:       u32 = radius - 1u;
     let extra_radius_quads: u32 = (extra_radius + 1u) / 2u;
 
-    let board_size     = textureDimensions(next_board);
-    let this_quad_mask = calculate_quad_mask(global_thread_id.xy, vec2<i32>(0, 0), board_size);
+    let tile_size      = textureDimensions(next_board);
+    let board_size     = vec2<i32>(tile_info.board_size);
+    let this_quad_mask = calculate_quad_mask(global_thread_id.xy + tile_info.tile_offset, vec2<i32>(0, 0), board_size);
 
     let quad_state_index: u32 = calculate_quad_index(local_thread_id.xy, vec2<i32>(0), extra_radius_quads);
-    let prev_board_quad:  u32 = textureLoad(prev_board, vec2<i32>(global_thread_id.xy), 0).x;
+    let prev_board_quad:  u32 = textureLoad(prev_board, vec2<i32>(global_thread_id.xy + vec2<u32>(tile_info.halo_size)), layer, 0).x;
     shared_quad_states[quad_state_index] = prev_board_quad & this_quad_mask;
 
     if(extra_radius_quads > 0u)
@@ -228,55 +251,55 @@ fn main(@builtin(local_invocation_id) local_thread_id: vec3<u32>, @builtin(globa
         if(in_blocks[0])
         {
             let block_offset: vec2<i32> = block_offsets[0];
-            update_quad_state(local_thread_id.xy, global_thread_id.xy, block_offset, board_size, extra_radius_quads);
+            update_quad_state(local_thread_id.xy, global_thread_id.xy, layer, block_offset, board_size, extra_radius_quads);
         }
 
         if(in_blocks[1])
         {
             let block_offset: vec2<i32> = block_offsets[1];
-            update_quad_state(local_thread_id.xy, global_thread_id.xy, block_offset, board_size, extra_radius_quads);
+            update_quad_state(local_thread_id.xy, global_thread_id.xy, layer, block_offset, board_size, extra_radius_quads);
         }
 
         if(in_blocks[2])
         {
             let block_offset: vec2<i32> = block_offsets[2];
-            update_quad_state(local_thread_id.xy, global_thread_id.xy, block_offset, board_size, extra_radius_quads);
+            update_quad_state(local_thread_id.xy, global_thread_id.xy, layer, block_offset, board_size, extra_radius_quads);
         }
 
         if(in_blocks[3])
         {
             let block_offset: vec2<i32> = block_offsets[3];
-            update_quad_state(local_thread_id.xy, global_thread_id.xy, block_offset, board_size, extra_radius_quads);
+            update_quad_state(local_thread_id.xy, global_thread_id.xy, layer, block_offset, board_size, extra_radius_quads);
         }
 
         if(in_blocks[4])
         {
             let block_offset: vec2<i32> = block_offsets[4];
-            update_quad_state(local_thread_id.xy, global_thread_id.xy, block_offset, board_size, extra_radius_quads);
+            update_quad_state(local_thread_id.xy, global_thread_id.xy, layer, block_offset, board_size, extra_radius_quads);
         }
 
         if(in_blocks[5])
         {
             let block_offset: vec2<i32> = block_offsets[5];
-            update_quad_state(local_thread_id.xy, global_thread_id.xy, block_offset, board_size, extra_radius_quads);
+            update_quad_state(local_thread_id.xy, global_thread_id.xy, layer, block_offset, board_size, extra_radius_quads);
         }
 
         if(in_blocks[6])
         {
             let block_offset: vec2<i32> = block_offsets[6];
-            update_quad_state(local_thread_id.xy, global_thread_id.xy, block_offset, board_size, extra_radius_quads);
+            update_quad_state(local_thread_id.xy, global_thread_id.xy, layer, block_offset, board_size, extra_radius_quads);
         }
 
         if(in_blocks[7])
         {
             let block_offset: vec2<i32> = block_offsets[7];
-            update_quad_state(local_thread_id.xy, global_thread_id.xy, block_offset, board_size, extra_radius_quads);
+            update_quad_state(local_thread_id.xy, global_thread_id.xy, layer, block_offset, board_size, extra_radius_quads);
         }
     }
 
     workgroupBarrier();
 
-    if(global_thread_id.x >= u32(board_size.x) || global_thread_id.y >= u32(board_size.y))
+    if(global_thread_id.x >= u32(tile_size.x) || global_thread_id.y >= u32(tile_size.y))
     {
         return;
     }
@@ -287,7 +310,7 @@ fn main(@builtin(local_invocation_id) local_thread_id: vec3<u32>, @builtin(globa
     var next_board_quad: u32 = 0x00000000u;
     for(var i: i32 = 0; i < packed_element_count; i = i + 1)
     {
-        let offsets_packed: vec4<i32> = click_rule_data.enabled_positions_packed[i];
+        let offsets_packed: vec4<i32> = click_rules[layer].enabled_positions_packed[i];
 
         let offset_1: vec2<i32> = offsets_packed.xy;
         let offset_2: vec2<i32> = offsets_packed.zw;
@@ -300,7 +323,7 @@ fn main(@builtin(local_invocation_id) local_thread_id: vec3<u32>, @builtin(globa
 
     if((element_count % 2u) != 0u)
     {
-        let last_offset: vec2<i32> = click_rule_data.enabled_positions_packed[packed_element_count].xy;
+        let last_offset: vec2<i32> = click_rules[layer].enabled_positions_packed[packed_element_count].xy;
         let prev_board_quad_offset: u32 = calculate_quad(local_thread_id.xy, last_offset, extra_radius_quads);
         next_board_quad = (next_board_quad + prev_board_quad_offset) & modulo_2_mask;
     }
@@ -315,7 +338,7 @@ fn main(@builtin(local_invocation_id) local_thread_id: vec3<u32>, @builtin(globa
     let prev_board_unpacked = unpack_quad(prev_board_quad);
     let next_board_unpacked = unpack_quad(next_board_quad);
 
-    let prev_stability_quad: u32 = textureLoad(prev_stability, vec2<i32>(global_thread_id.xy), 0).x;
+    let prev_stability_quad: u32 = textureLoad(prev_stability, vec2<i32>(global_thread_id.xy), layer, 0).x;
     let prev_stability_unpacked = unpack_quad(prev_stability_quad);
 
     let state_changed_flags   = vec4<u32>(prev_board_unpacked != next_board_unpacked);
@@ -327,6 +350,6 @@ fn main(@builtin(local_invocation_id) local_thread_id: vec3<u32>, @builtin(globa
     let next_stability_clamped = clamp(next_stability_unpacked, vec4<u32>(0u), vec4<u32>(255u));
     let next_stability_quad = pack_quad(next_stability_clamped);
 
-    textureStore(next_board,     vec2<i32>(global_thread_id.xy), vec4<u32>(next_board_quad));
-    textureStore(next_stability, vec2<i32>(global_thread_id.xy), vec4<u32>(next_stability_quad));
+    textureStore(next_board,     vec2<i32>(global_thread_id.xy), layer, vec4<u32>(next_board_quad));
+    textureStore(next_stability, vec2<i32>(global_thread_id.xy), layer, vec4<u32>(next_stability_quad));
 }
\ No newline at end of file
@@ -0,0 +1,137 @@
+//Draws a paint stroke into a rectangular region of the board or the restriction
+
+@group(0) @binding(0) var in_board:  texture_2d<u32>;
+@group(0) @binding(1) var out_board: texture_storage_2d<r32uint, write>;
+
+//The board can be split into several tiles, each one stored in its own texture
+struct TileInfo
+{
+    board_size:  vec2<u32>, //Size of the whole board in quads
+    tile_offset: vec2<u32>, //Offset of the tile in quads
+    halo_size:   u32,       //Number of halo quads on each side of the input board texture
+    padding_0:   u32,
+    padding_1:   u32,
+    padding_2:   u32
+};
+
+@group(1) @binding(0) var<uniform> tile_info: TileInfo;
+
+//Must draw the same shapes as PaintStroke::cell_covered
+struct PaintStrokeData
+{
+    tool:         u32, //0 - pencil or line, 1 - rectangle, 2 - ellipse
+    start_x:      i32,
+    start_y:      i32,
+    end_x:        i32,
+    end_y:        i32,
+    radius:       u32,
+    filled:       u32,
+    enabled:      u32,
+    region_min_x: u32, //The region of the board the stroke can change, in quads
+    region_min_y: u32,
+    region_max_x: u32,
+    region_max_y: u32
+};
+
+@group(2) @binding(0) var<uniform> paint_stroke_data: PaintStrokeData;
+
+fn inside_ellipse(offset: vec2<f32>, semi_axes: vec2<f32>) -> bool
+{
+    if(any(semi_axes <= vec2<f32>(0.0, 0.0)))
+    {
+        return false;
+    }
+
+    let normalized_offset = offset / semi_axes;
+    return dot(normalized_offset, normalized_offset) <= 1.0;
+}
+
+fn cell_covered(cell: vec2<i32>) -> bool
+{
+    let stroke_start = vec2<i32>(paint_stroke_data.start_x, paint_stroke_data.start_y);
+    let stroke_end   = vec2<i32>(paint_stroke_data.end_x,   paint_stroke_data.end_y);
+    let radius       = i32(paint_stroke_data.radius);
+
+    let min_cell = min(stroke_start, stroke_end);
+    let max_cell = max(stroke_start, stroke_end);
+
+    switch(paint_stroke_data.tool)
+    {
+        case 1u:
+        {
+            let inside_outer = all(cell >= min_cell - vec2<i32>(radius)) && all(cell <= max_cell + vec2<i32>(radius));
+            let inside_inner = all(cell >  min_cell + vec2<i32>(radius)) && all(cell <  max_cell - vec2<i32>(radius));
+            return inside_outer && (paint_stroke_data.filled != 0u || !inside_inner);
+        }
+        case 2u:
+        {
+            let center    = vec2<f32>(min_cell + max_cell) * 0.5;
+            let offset    = vec2<f32>(cell) - center;
+            let semi_axes = vec2<f32>(max_cell - min_cell) * 0.5;
+            let thickness = f32(radius) + 0.5;
+
+            let inside_outer = inside_ellipse(offset, semi_axes + vec2<f32>(thickness));
+            let inside_inner = inside_ellipse(offset, semi_axes - vec2<f32>(thickness));
+            return inside_outer && (paint_stroke_data.filled != 0u || !inside_inner);
+        }
+        default:
+        {
+            //Distance to the segment, measured from the cell centers
+            let segment     = vec2<f32>(stroke_end - stroke_start);
+            let cell_offset = vec2<f32>(cell - stroke_start);
+
+            let segment_length_sq = dot(segment, segment);
+
+            var t = 0.0;
+            if(segment_length_sq > 0.0)
+            {
+                t = clamp(dot(cell_offset, segment) / segment_length_sq, 0.0, 1.0);
+            }
+
+            let distance     = cell_offset - segment * t;
+            let max_distance = f32(radius) + 0.5;
+            return dot(distance, distance) <= max_distance * max_distance;
+        }
+    }
+}
+
+fn painted_cell(cell: vec2<u32>, last_cell: vec2<u32>, old_value: u32) -> u32
+{
+    //The last quad in each row and column has cells outside of the board
+    if(any(cell > last_cell) || !cell_covered(vec2<i32>(cell)))
+    {
+        return old_value;
+    }
+
+    return paint_stroke_data.enabled;
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) global_thread_id: vec3<u32>)
+{
+    let tile_size: vec2<u32> = vec2<u32>(textureDimensions(out_board));
+
+    //The region is clamped to the tile on the CPU side in the same way
+    let region_min: vec2<u32> = max(vec2<u32>(paint_stroke_data.region_min_x, paint_stroke_data.region_min_y), tile_info.tile_offset);
+    let region_max: vec2<u32> = min(vec2<u32>(paint_stroke_data.region_max_x, paint_stroke_data.region_max_y), tile_info.tile_offset + tile_size - vec2<u32>(1u, 1u));
+
+    let board_quad: vec2<u32> = region_min + global_thread_id.xy;
+	if(any(board_quad > region_max))
+	{
+	    return;
+	}
+
+    let tile_quad: vec2<i32> = vec2<i32>(board_quad - tile_info.tile_offset);
+    let old_quad:  u32       = textureLoad(in_board, tile_quad, 0).x;
+
+    let last_cell:  vec2<u32> = tile_info.board_size * 2u - vec2<u32>(2u, 2u);
+	let quad_start: vec2<u32> = board_quad * 2u;
+
+	//The quad is packed into uint32:
+	// (Bits 0-7)   (Bits 8-15)
+	// (Bits 16-23) (Bits 24-31)
+	let packed_quad: u32 = (painted_cell(quad_start + vec2<u32>(0u, 0u), last_cell, (old_quad >>  0u) & 0xffu) <<  0u) | (painted_cell(quad_start + vec2<u32>(1u, 0u), last_cell, (old_quad >>  8u) & 0xffu) <<  8u)
+	                     | (painted_cell(quad_start + vec2<u32>(0u, 1u), last_cell, (old_quad >> 16u) & 0xffu) << 16u) | (painted_cell(quad_start + vec2<u32>(1u, 1u), last_cell, (old_quad >> 24u) & 0xffu) << 24u);
+
+    textureStore(out_board, tile_quad, vec4<u32>(packed_quad));
+}
@@ -1,6 +1,19 @@
 @group(0) @binding(0) var board_tex:     texture_2d<f32>;
 @group(0) @binding(1) var board_sampler: sampler;
 
+//The board can be split into several tiles, each one stored in its own texture
+struct TileInfo
+{
+    board_size:  vec2<u32>, //Size of the whole board in quads
+    tile_offset: vec2<u32>, //Offset of the tile in quads
+    halo_size:   u32,       //Number of halo quads on each side of the input board texture
+    padding_0:   u32,
+    padding_1:   u32,
+    padding_2:   u32
+};
+
+@group(1) @binding(0) var<uniform> tile_info: TileInfo;
+
 struct FsInput
 {
     @builtin(position) clip_position: vec4<f32>,
@@ -11,12 +24,15 @@ struct FsInput
 fn main(fin: FsInput) -> @location(0) vec4<f32>
 {
     //We store the stability texture in 2x2 quads with one RGBA channel for each element of the quad.
-    //The last half-quads on the bottom and the right edges are always hidden.
+    //The last half-quads on the bottom and the right edges are always hidden. Only the rightmost and bottommost tiles contain these half-quads.
     //To display this correctly, we manually calculate the LOD to sample and mix the quad values
     let stability_color = vec4<f32>(1.0, 0.0, 1.0, 1.0);
 
-    let mip_0_size_padded     = vec2<f32>(textureDimensions(board_tex, 0));
-    let mip_0_size: vec2<f32> = mip_0_size_padded - vec2<f32>(0.5, 0.5);
+    let tile_size    = vec2<u32>(textureDimensions(board_tex, 0));
+    let is_last_tile = vec2<f32>(tile_info.tile_offset + tile_size >= tile_info.board_size);
+
+    let mip_0_size_padded     = vec2<f32>(tile_size);
+    let mip_0_size: vec2<f32> = mip_0_size_padded - vec2<f32>(0.5, 0.5) * is_last_tile;
 
 	let texcoord_big   = fin.texcoord * mip_0_size;
 	let d_texcoord_big = vec2<f32>(dpdx(texcoord_big.x), dpdy(texcoord_big.y)); //dfdx(y) and dfdy(x) are 0
@@ -0,0 +1,18 @@
+//Transforms the board itself into an image, lit cells are bright. Used instead of the stability in the Lights Out play mode
+//Mip generation is handled by another shader
+
+@group(0) @binding(0) var board:         texture_2d<u32>;
+@group(0) @binding(1) var out_tex_mip_0: texture_storage_2d<rgba8unorm, write>;
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_thread_id: vec3<u32>)
+{
+    let board_encoded: u32 = textureLoad(board, vec2<i32>(global_thread_id.xy), 0).x;
+
+    //Unpack the encoded quad
+    let board_quad = vec4<u32>((board_encoded >>  0u) & 0xffu, (board_encoded >>  8u) & 0xffu,
+                               (board_encoded >> 16u) & 0xffu, (board_encoded >> 24u) & 0xffu);
+
+    let tex_value = vec4<f32>(board_quad != vec4<u32>(0u));
+    textureStore(out_tex_mip_0, vec2<i32>(global_thread_id.xy), tex_value);
+}
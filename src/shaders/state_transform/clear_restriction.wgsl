@@ -2,9 +2,28 @@
 
 @group(0) @binding(0) var out_restriction: texture_storage_2d<r32uint, write>;
 
+//The board can be split into several tiles, each one stored in its own texture
+struct TileInfo
+{
+    board_size:  vec2<u32>, //Size of the whole board in quads
+    tile_offset: vec2<u32>, //Offset of the tile in quads
+    halo_size:   u32,       //Number of halo quads on each side of the input board texture
+    padding_0:   u32,
+    padding_1:   u32,
+    padding_2:   u32
+};
+
+@group(1) @binding(0) var<uniform> tile_info: TileInfo;
+
 @compute @workgroup_size(16, 16)
 fn main(@builtin(global_invocation_id) global_thread_id: vec3<u32>)
 {
+    let tile_size: vec2<i32> = textureDimensions(out_restriction);
+	if(global_thread_id.x >= u32(tile_size.x) || global_thread_id.y >= u32(tile_size.y))
+	{
+	    return;
+	}
+
     //Init every quad to "no restriction" (0xff in each of 4 bytes)
     let packed_restriction: u32 = 0xffffffffu;
 
@@ -13,8 +32,8 @@ fn main(@builtin(global_invocation_id) global_thread_id: vec3<u32>)
     let bottom_quad_mask: u32 = 0xffff0000u;
 
     //Mask out the rightmost and the bottommost values
-	let right_bottom_coordinates = vec2<u32>(textureDimensions(out_restriction) + vec2<i32>(-1, -1));
-	let on_right_bottom: vec2<bool> = (global_thread_id.xy == right_bottom_coordinates);
+	let right_bottom_coordinates = tile_info.board_size - vec2<u32>(1u, 1u);
+	let on_right_bottom: vec2<bool> = (global_thread_id.xy + tile_info.tile_offset == right_bottom_coordinates);
 
 	let right_bottom_mask = vec2<u32>(right_quad_mask, bottom_quad_mask) * vec2<u32>(on_right_bottom);
     textureStore(out_restriction, vec2<i32>(global_thread_id.xy), vec4<u32>(packed_restriction & ~(right_bottom_mask.x | right_bottom_mask.y)));
@@ -2,16 +2,32 @@
 
 @group(0) @binding(0) var out_initial_stability: texture_storage_2d<r32uint, write>;
 
+//The board can be split into several tiles, each one stored in its own texture
+struct TileInfo
+{
+    board_size:  vec2<u32>, //Size of the whole board in quads
+    tile_offset: vec2<u32>, //Offset of the tile in quads
+    halo_size:   u32,       //Number of halo quads on each side of the input board texture
+    padding_0:   u32,
+    padding_1:   u32,
+    padding_2:   u32
+};
+
+@group(1) @binding(0) var<uniform> tile_info: TileInfo;
+
 @compute @workgroup_size(16, 16)
 fn main(@builtin(global_invocation_id) global_thread_id: vec3<u32>)
 {
-    let board_size: vec2<i32> = textureDimensions(out_initial_stability);
+    let tile_size: vec2<i32> = textureDimensions(out_initial_stability);
 
-	if(global_thread_id.x >= u32(board_size.x) || global_thread_id.y >= u32(board_size.y))
+	if(global_thread_id.x >= u32(tile_size.x) || global_thread_id.y >= u32(tile_size.y))
 	{
 	    return;
 	}
 
+	let board_size: vec2<i32> = vec2<i32>(tile_info.board_size);
+	let quad_id:    vec2<u32> = global_thread_id.xy + tile_info.tile_offset;
+
     //Init every quad to "stable" (0 in each of 4 bytes)
     let packed_quad = 0u;
 
@@ -20,7 +36,7 @@ fn main(@builtin(global_invocation_id) global_thread_id: vec3<u32>)
     let bottom_quad_mask: u32 = 0xffff0000u;
 
 	let right_bottom_coordinates = vec2<u32>(board_size + vec2<i32>(-1, -1)); //Board size is always 2^n - 1. Mask out the bottom and right edge
-	let on_right_bottom: vec2<bool> = (global_thread_id.xy == right_bottom_coordinates);
+	let on_right_bottom: vec2<bool> = (quad_id == right_bottom_coordinates);
 
 	let right_bottom_mask = vec2<u32>(right_quad_mask, bottom_quad_mask) * vec2<u32>(on_right_bottom);
     textureStore(out_initial_stability, vec2<i32>(global_thread_id.xy), vec4<u32>(packed_quad | (right_bottom_mask.x | right_bottom_mask.y)));
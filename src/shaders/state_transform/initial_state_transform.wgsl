@@ -3,17 +3,36 @@
 @group(0) @binding(0) var initial_state_tex: texture_2d<f32>;
 @group(0) @binding(1) var out_initial_board: texture_storage_2d<r32uint, write>;
 
+//The board can be split into several tiles, each one stored in its own texture
+struct TileInfo
+{
+    board_size:  vec2<u32>, //Size of the whole board in quads
+    tile_offset: vec2<u32>, //Offset of the tile in quads
+    halo_size:   u32,       //Number of halo quads on each side of the input board texture
+    padding_0:   u32,
+    padding_1:   u32,
+    padding_2:   u32
+};
+
+@group(1) @binding(0) var<uniform> tile_info: TileInfo;
+
 @compute @workgroup_size(16, 16)
 fn main(@builtin(global_invocation_id) global_thread_id: vec3<u32>)
 {
     let initial_state_size: vec2<i32> = textureDimensions(initial_state_tex);
-    let board_size:         vec2<i32> = textureDimensions(out_initial_board);
+    let tile_size:          vec2<i32> = textureDimensions(out_initial_board);
+    let board_size:         vec2<i32> = vec2<i32>(tile_info.board_size);
+
+	if(global_thread_id.x >= u32(tile_size.x) || global_thread_id.y >= u32(tile_size.y))
+	{
+	    return;
+	}
 
     let real_board_size: vec2<i32> = board_size * 2 - vec2<i32>(1, 1);
     let offset: vec2<i32> = (initial_state_size - real_board_size) / 2;
 
 	let lum_factor = vec4<f32>(0.2126, 0.7152, 0.0722, 0.0);
-	let quad_start = vec2<i32>(global_thread_id.xy) * 2 + offset;
+	let quad_start = vec2<i32>(global_thread_id.xy + tile_info.tile_offset) * 2 + offset;
 
 	//4 packed values
     let top_left_quad_coord:     vec2<i32> = quad_start + vec2<i32>(0, 0);
@@ -0,0 +1,113 @@
+use super::stafra_state::{StandardResetBoardType, RandomBoardParams, PatternBoardParams, BatchParams};
+use super::stafra_board_state_bindings::ImageData;
+use super::stafra_profiler::ProfilerReport;
+use super::stafra_session::StafraSession;
+use super::stafra_image_import::ImageImportOptions;
+use super::stafra_paint::PaintStroke;
+use super::stafra_lights_out::LightsOutStatus;
+
+//Receives the requested image, or the error if it couldn't be read
+pub type ImageCallback = Box<dyn FnOnce(Result<ImageData, String>) + Send + 'static>;
+
+//Receives the saved session, or the error if it couldn't be read
+pub type SessionCallback = Box<dyn FnOnce(Result<StafraSession, String>) + Send + 'static>;
+
+//Receives the stability of each board of the batch in the order of the click rules, or the error if they couldn't be read
+pub type BatchCallback = Box<dyn FnOnce(Result<Vec<ImageData>, String>) + Send + 'static>;
+
+//Everything the front-ends need from a simulation implementation.
+//StafraState runs the simulation on the GPU, StafraCpuState runs it on the CPU when no adapter is available
+pub trait SimulationBackend
+{
+    fn frame_number(&self) -> u32;
+    fn board_size(&self) -> u32;
+    fn max_board_size(&self) -> u32;
+
+    fn resize_board(&mut self, new_width: u32, new_height: u32) -> Result<(), String>;
+
+    fn reset_board_unchanged(&mut self);
+    fn reset_board_standard(&mut self, reset_type: StandardResetBoardType);
+    fn reset_board_random(&mut self, params: RandomBoardParams);
+    fn reset_board_pattern(&mut self, params: PatternBoardParams);
+    fn reset_board_expression(&mut self, expression_source: &str, seed: u32) -> Result<(), String>;
+    fn reset_board_custom(&mut self, image_array: Vec<u8>, width: u32, height: u32) -> Result<u32, String>;
+
+    fn upload_restriction(&mut self, image_array: Vec<u8>, width: u32, height: u32);
+    fn clear_restriction(&mut self);
+
+    //Draws on the initial board or the restriction and resets the board to frame 0. The painted board becomes the new initial board,
+    //the strokes are kept until another kind of reset or a resize. A painted restriction is lost after resizing the board
+    fn paint(&mut self, stroke: PaintStroke);
+
+    //Lights Out play mode displays the board instead of the stability. Each move toggles the cells the click rule covers around the clicked cell,
+    //restricted cells are never toggled. Moves are kept for undo until the board changes in any other way.
+    //Clicks outside of the board, on restricted cells or with the play mode disabled are not moves and return false
+    fn set_lights_out_enabled(&mut self, enable: bool);
+    fn lights_out_click(&mut self, x: u32, y: u32) -> bool;
+    fn lights_out_undo(&mut self) -> bool;
+
+    //Reads the board back after each change, so the number of lit cells can be unknown for a few frames
+    fn lights_out_status(&mut self) -> LightsOutStatus;
+
+    //Used for the images imported by reset_board_custom and upload_restriction after this call.
+    //The restriction is always placed on the current board, the board size option only affects reset_board_custom
+    fn set_image_import_options(&mut self, options: ImageImportOptions);
+
+    fn reset_click_rule(&mut self, click_rule_data: &[u8; 32 * 32]);
+    fn set_spawn_period(&mut self, spawn_period: u32);
+    fn set_smooth_transform_enabled(&mut self, enable: bool);
+
+    //Calculates the next frame
+    fn update(&mut self);
+
+    //The latest history_frames frames are kept, and a keyframe every keyframe_period frames for the older ones.
+    //Both are limited by the memory budget, so big boards get shorter history
+    fn set_history_depth(&mut self, history_frames: u32, keyframe_period: u32);
+
+    //Goes back to the previous frame, or to any frame since the last reset. Going forward calculates the frames
+    fn step_back(&mut self) -> Result<(), String>;
+    fn seek_frame(&mut self, frame_number: u32) -> Result<(), String>;
+
+    //The first and the last frame of the timeline. The timeline starts again after each reset, and seeking inside it replays from the nearest snapshot
+    fn timeline_range(&self) -> (u32, u32);
+
+    //Blocks until all submitted work is finished
+    fn wait_for_completion(&self);
+
+    //Requests the stability value of each cell: 0 for "stable", 1 for "unstable", 2 for "unstable for 1 frame" and so on
+    fn post_stability_request(&mut self, callback: ImageCallback);
+
+    //Requests the final RGBA image of the whole board
+    fn post_save_png_request(&mut self, callback: ImageCallback);
+
+    //Simulates a separate small board for each click rule of the batch and requests the stability of each one, in the same format as post_stability_request.
+    //The main board, its click rule and its frame number are not affected
+    fn post_batch_request(&mut self, params: BatchParams, callback: BatchCallback);
+
+    //Requests the whole simulation state. Loading the session back continues the simulation exactly from the same frame
+    fn post_save_session_request(&mut self, callback: SessionCallback);
+    fn load_session(&mut self, session: &StafraSession) -> Result<(), String>;
+
+    //Presentation
+    fn resize(&mut self, new_width: u32, new_height: u32);
+    fn resize_click_rule(&mut self, new_width: u32, new_height: u32);
+    fn set_click_rule_grid_enabled(&mut self, enable: bool);
+    fn set_click_rule_read_only(&mut self, is_read_only: bool);
+    fn update_visual_info(&mut self);
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>;
+
+    //The number of requests of each kind that can wait for the readback at the same time
+    fn set_readback_queue_depth(&mut self, depth: u32);
+
+    //Video frames are 1024x1024 BGRA images of the board. No new frames should be requested while the queue is full
+    fn video_frame_queue_full(&self) -> bool;
+    fn post_video_frame_request(&mut self, callback: ImageCallback);
+
+    //WebGPU reports allocation failures only after resize_board, reset_board_custom or load_session return.
+    //The old board is kept until the allocation is checked, and stays if it failed
+    fn take_allocation_error(&mut self) -> Option<String>;
+
+    //Rolling averages of the time spent in each pass, measured only while profiling is enabled
+    fn set_profiling_enabled(&mut self, enable: bool);
+    fn profiler_report(&self) -> ProfilerReport;
+}
@@ -0,0 +1,397 @@
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+use super::stafra_static_state::{StafraStaticState, NextStepViews};
+use super::stafra_board_state_bindings::ImageBuffer;
+use super::stafra_state::StandardResetBoardType;
+use super::stafra_lights_out::click_rule_offsets;
+
+//The largest number of boards simulated by a single dispatch
+pub const MAX_BATCH_LAYERS: u32 = 256;
+
+//Board and stability textures of all layers, both current and next
+const BATCH_MEMORY_BUDGET: u64 = 256 * 1024 * 1024;
+
+//Header (element count, radius, padding) and the enabled positions, the same layout as ClickRuleData in next_step.wgsl
+const CLICK_RULE_DATA_SIZE: usize = 4 * std::mem::size_of::<u32>() + 32 * 32 * 2 * std::mem::size_of::<i32>();
+
+//Bindings for simulating many small boards at once. Every board is a layer of the board texture arrays and has its own click rule,
+//so a single next step dispatch updates all of them. The boards are never split into tiles, and have neither restriction nor final image
+pub struct StafraBatchBindings
+{
+    board_size:  u32,
+    layer_count: u32,
+
+    tile_info_bind_group:   wgpu::BindGroup,
+    next_step_bind_group_a: wgpu::BindGroup,
+    next_step_bind_group_b: wgpu::BindGroup,
+
+    click_rule_buffer: wgpu::Buffer,
+
+    current_board:     wgpu::Texture,
+    current_stability: wgpu::Texture,
+    next_stability:    wgpu::Texture,
+
+    //Only used through the bind groups, kept alive for as long as they are
+    _tile_info_buffer: wgpu::Buffer,
+    _next_board:       wgpu::Texture,
+    _restriction:      wgpu::Texture
+}
+
+impl StafraBatchBindings
+{
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, static_state: &StafraStaticState, board_size: u32, layer_count: u32) -> Result<Self, String>
+    {
+        if !(board_size + 1).is_power_of_two()
+        {
+            return Err(format!("Batch board size {} is not 2^n - 1", board_size));
+        }
+
+        if layer_count == 0
+        {
+            return Err("The batch has no boards".to_string());
+        }
+
+        let board_quads = (board_size + 1) / 2;
+
+        let board_texture_descriptor = wgpu::TextureDescriptor
+        {
+            label: Some("Batch board texture"),
+            size:  wgpu::Extent3d
+            {
+                width:                 board_quads,
+                height:                board_quads,
+                depth_or_array_layers: layer_count
+            },
+            mip_level_count: 1,
+            sample_count:    1,
+            dimension:       wgpu::TextureDimension::D2,
+            format:          wgpu::TextureFormat::R32Uint,
+            usage:           wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST
+        };
+
+        let restriction_texture_descriptor = wgpu::TextureDescriptor
+        {
+            label: Some("Batch restriction texture"),
+            size:  wgpu::Extent3d
+            {
+                width:                 board_quads,
+                height:                board_quads,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count:    1,
+            dimension:       wgpu::TextureDimension::D2,
+            format:          wgpu::TextureFormat::R32Uint,
+            usage:           wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+        };
+
+        let current_board     = device.create_texture(&board_texture_descriptor);
+        let next_board        = device.create_texture(&board_texture_descriptor);
+        let current_stability = device.create_texture(&board_texture_descriptor);
+        let next_stability    = device.create_texture(&board_texture_descriptor);
+        let restriction       = device.create_texture(&restriction_texture_descriptor);
+
+        //"No restriction" (0xff in each of 4 bytes) everywhere. The cells outside of the board are masked out by the next step
+        let restriction_data = vec![0xffu8; (board_quads * board_quads) as usize * std::mem::size_of::<u32>()];
+        queue.write_texture(wgpu::ImageCopyTexture
+        {
+            texture:   &restriction,
+            mip_level: 0,
+            origin:    wgpu::Origin3d::ZERO,
+            aspect:    wgpu::TextureAspect::All
+        },
+        restriction_data.as_slice(),
+        wgpu::ImageDataLayout
+        {
+            offset:         0,
+            bytes_per_row:  NonZeroU32::new(board_quads * std::mem::size_of::<u32>() as u32),
+            rows_per_image: NonZeroU32::new(board_quads)
+        },
+        wgpu::Extent3d
+        {
+            width:                 board_quads,
+            height:                board_quads,
+            depth_or_array_layers: 1
+        });
+
+        let click_rule_buffer = device.create_buffer(&wgpu::BufferDescriptor
+        {
+            label:              Some("Batch click rule buffer"),
+            size:               (CLICK_RULE_DATA_SIZE * layer_count as usize) as u64,
+            usage:              wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        //Board size in quads, tile offset in quads, halo size in quads, padding. Each layer is a single tile
+        let tile_info_data = [board_quads, board_quads, 0, 0, 0, 0, 0, 0];
+        let tile_info_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor
+        {
+            label:    Some("Batch tile info buffer"),
+            contents: &tile_info_data.iter().flat_map(|value: &u32| value.to_le_bytes()).collect::<Vec<u8>>(),
+            usage:    wgpu::BufferUsages::UNIFORM
+        });
+
+        let board_array_view_descriptor = wgpu::TextureViewDescriptor
+        {
+            label:             Some("Batch board array view"),
+            format:            Some(wgpu::TextureFormat::R32Uint),
+            dimension:         Some(wgpu::TextureViewDimension::D2Array),
+            aspect:            wgpu::TextureAspect::All,
+            base_mip_level:    0,
+            mip_level_count:   None,
+            base_array_layer:  0,
+            array_layer_count: None
+        };
+
+        let restriction_view_descriptor = wgpu::TextureViewDescriptor
+        {
+            label:             Some("Batch restriction view"),
+            format:            Some(wgpu::TextureFormat::R32Uint),
+            dimension:         Some(wgpu::TextureViewDimension::D2),
+            aspect:            wgpu::TextureAspect::All,
+            base_mip_level:    0,
+            mip_level_count:   None,
+            base_array_layer:  0,
+            array_layer_count: None
+        };
+
+        let current_board_view     = current_board.create_view(&board_array_view_descriptor);
+        let next_board_view        = next_board.create_view(&board_array_view_descriptor);
+        let current_stability_view = current_stability.create_view(&board_array_view_descriptor);
+        let next_stability_view    = next_stability.create_view(&board_array_view_descriptor);
+        let restriction_view       = restriction.create_view(&restriction_view_descriptor);
+
+        let tile_info_bind_group = static_state.create_tile_info_bind_group(device, &tile_info_buffer);
+
+        let next_step_views_a = NextStepViews
+        {
+            current_board:     &current_board_view,
+            current_stability: &current_stability_view,
+            next_board:        &next_board_view,
+            next_stability:    &next_stability_view,
+            restriction:       &restriction_view
+        };
+
+        let next_step_views_b = NextStepViews
+        {
+            current_board:     &next_board_view,
+            current_stability: &next_stability_view,
+            next_board:        &current_board_view,
+            next_stability:    &current_stability_view,
+            restriction:       &restriction_view
+        };
+
+        let next_step_bind_group_a = static_state.create_next_step_bind_group(device, next_step_views_a, click_rule_buffer.as_entire_buffer_binding());
+        let next_step_bind_group_b = static_state.create_next_step_bind_group(device, next_step_views_b, click_rule_buffer.as_entire_buffer_binding());
+
+        Ok(Self
+        {
+            board_size,
+            layer_count,
+
+            tile_info_bind_group,
+            next_step_bind_group_a,
+            next_step_bind_group_b,
+
+            click_rule_buffer,
+
+            current_board,
+            current_stability,
+            next_stability,
+
+            _tile_info_buffer: tile_info_buffer,
+            _next_board:       next_board,
+            _restriction:      restriction
+        })
+    }
+
+    //Limited by the texture array size and the memory budget. 0 if even a single board doesn't fit into a texture
+    pub fn max_layers(limits: &wgpu::Limits, board_size: u32) -> u32
+    {
+        let board_quads = (board_size + 1) / 2;
+        if board_quads > limits.max_texture_dimension_2d
+        {
+            return 0;
+        }
+
+        let layer_bytes = (board_quads as u64) * (board_quads as u64) * std::mem::size_of::<u32>() as u64 * 4;
+        std::cmp::min(std::cmp::min(MAX_BATCH_LAYERS, limits.max_texture_array_layers) as u64, BATCH_MEMORY_BUDGET / layer_bytes) as u32
+    }
+
+    pub fn board_size(&self) -> u32
+    {
+        self.board_size
+    }
+
+    pub fn layer_count(&self) -> u32
+    {
+        self.layer_count
+    }
+
+    //Every layer gets the standard reset and the click rule with the same index. The layers without a click rule get an empty one and never change
+    pub fn reset_layers(&self, queue: &wgpu::Queue, click_rules: &[[u8; 32 * 32]], reset_type: StandardResetBoardType)
+    {
+        assert!(click_rules.len() <= self.layer_count as usize);
+
+        let board_quads = (self.board_size + 1) / 2;
+
+        let mut board_data = vec![0u8; (board_quads * board_quads) as usize * std::mem::size_of::<u32>()];
+        for (cell_x, cell_y) in reset_type.enabled_cells(self.board_size)
+        {
+            let quad_index       = (cell_y / 2) * board_quads as usize + cell_x / 2;
+            let quad_value_index = (cell_y % 2) * 2 + cell_x % 2;
+            board_data[quad_index * 4 + quad_value_index] = 1;
+        }
+
+        let stability_data = vec![0u8; board_data.len()];
+
+        let mut click_rule_data = vec![0u8; CLICK_RULE_DATA_SIZE * self.layer_count as usize];
+        for (click_rule, layer_click_rule_data) in click_rules.iter().zip(click_rule_data.chunks_mut(CLICK_RULE_DATA_SIZE))
+        {
+            Self::pack_click_rule(click_rule, layer_click_rule_data);
+        }
+
+        queue.write_buffer(&self.click_rule_buffer, 0, &click_rule_data);
+
+        for layer in 0..self.layer_count
+        {
+            for (texture, texture_data) in [(&self.current_board, &board_data), (&self.current_stability, &stability_data)]
+            {
+                queue.write_texture(wgpu::ImageCopyTexture
+                {
+                    texture,
+                    mip_level: 0,
+                    origin:    wgpu::Origin3d
+                    {
+                        x: 0,
+                        y: 0,
+                        z: layer
+                    },
+                    aspect: wgpu::TextureAspect::All
+                },
+                texture_data.as_slice(),
+                wgpu::ImageDataLayout
+                {
+                    offset:         0,
+                    bytes_per_row:  NonZeroU32::new(board_quads * std::mem::size_of::<u32>() as u32),
+                    rows_per_image: NonZeroU32::new(board_quads)
+                },
+                wgpu::Extent3d
+                {
+                    width:                 board_quads,
+                    height:                board_quads,
+                    depth_or_array_layers: 1
+                });
+            }
+        }
+    }
+
+    pub fn calc_next_frame(&self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, frame_number: u32)
+    {
+        let board_quads   = (self.board_size + 1) / 2;
+        let thread_groups = std::cmp::max(board_quads / 8, 1u32);
+
+        let mut next_step_pass = static_state.create_next_step_pass(encoder);
+
+        let bind_group = if frame_number % 2 == 0 {&self.next_step_bind_group_a} else {&self.next_step_bind_group_b};
+        next_step_pass.set_bind_group(0, bind_group,                 &[]);
+        next_step_pass.set_bind_group(1, &self.tile_info_bind_group, &[]);
+        next_step_pass.dispatch_workgroups(thread_groups, thread_groups, self.layer_count);
+    }
+
+    //One staging buffer per board. The buffers are reused if they were created for the same number of boards of the same size
+    pub fn create_readback_buffers(&self, device: &wgpu::Device, image_buffers: &mut Vec<ImageBuffer>, board_count: usize)
+    {
+        let board_quads = (self.board_size + 1) / 2;
+
+        let buffers_match_boards = image_buffers.len() == board_count && image_buffers.iter().all(|image_buffer| image_buffer.raw_width == board_quads);
+        if buffers_match_boards
+        {
+            return;
+        }
+
+        let row_alignment: usize = 256;
+        let row_pitch     = ((board_quads as usize * std::mem::size_of::<u32>()) + (row_alignment - 1)) & (!(row_alignment - 1));
+
+        image_buffers.clear();
+        for _ in 0..board_count
+        {
+            let image_buffer = device.create_buffer(&wgpu::BufferDescriptor
+            {
+                label:              Some("Batch stability staging buffer"),
+                size:               (row_pitch * board_quads as usize) as u64,
+                usage:              wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false
+            });
+
+            image_buffers.push(ImageBuffer
+            {
+                image_buffer: Arc::new(image_buffer),
+                raw_width:    board_quads,
+                raw_height:   board_quads,
+                raw_offset_x: 0,
+                raw_offset_y: 0,
+                row_pitch
+            });
+        }
+    }
+
+    //Copies the stability of the first layers, one layer per buffer. The latest stability is in next_stability after odd frames
+    pub fn copy_stability_data(&self, buffer_copy_encoder: &mut wgpu::CommandEncoder, stability_buffers: &[ImageBuffer], frame_number: u32)
+    {
+        assert!(stability_buffers.len() <= self.layer_count as usize);
+
+        let stability_texture = if frame_number % 2 == 1 {&self.next_stability} else {&self.current_stability};
+        for (layer, stability_buffer) in stability_buffers.iter().enumerate()
+        {
+            buffer_copy_encoder.copy_texture_to_buffer(wgpu::ImageCopyTexture
+            {
+                texture:   stability_texture,
+                mip_level: 0,
+                origin:    wgpu::Origin3d
+                {
+                   x: 0,
+                   y: 0,
+                   z: layer as u32
+                },
+                aspect: wgpu::TextureAspect::All
+            },
+            wgpu::ImageCopyBuffer
+            {
+                buffer: &stability_buffer.image_buffer,
+                layout: wgpu::ImageDataLayout
+                {
+                   offset:         0,
+                   bytes_per_row:  NonZeroU32::new(stability_buffer.row_pitch as u32),
+                   rows_per_image: NonZeroU32::new(stability_buffer.raw_height)
+                },
+            },
+            wgpu::Extent3d
+            {
+                width:                 stability_buffer.raw_width,
+                height:                stability_buffer.raw_height,
+                depth_or_array_layers: 1
+            });
+        }
+    }
+
+    //The same data as bake_click_rule.wgsl writes, with the positions in row-major order
+    fn pack_click_rule(click_rule_data: &[u8; 32 * 32], packed_data: &mut [u8])
+    {
+        let offsets = click_rule_offsets(click_rule_data);
+        let radius  = offsets.iter().map(|(offset_x, offset_y)| std::cmp::max(offset_x.abs(), offset_y.abs()) + 1).max().unwrap_or(0);
+
+        let mut packed_words = vec![offsets.len() as u32, radius as u32, 0, 0];
+        for (offset_x, offset_y) in &offsets
+        {
+            packed_words.push(*offset_x as i32 as u32);
+            packed_words.push(*offset_y as i32 as u32);
+        }
+
+        for (word, packed_word_data) in packed_words.iter().zip(packed_data.chunks_mut(std::mem::size_of::<u32>()))
+        {
+            packed_word_data.copy_from_slice(&word.to_le_bytes());
+        }
+    }
+}
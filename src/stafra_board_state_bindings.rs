@@ -1,14 +1,55 @@
 use std::num::NonZeroU32;
-use super::stafra_static_state::StafraStaticState;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+use super::stafra_static_state::{StafraStaticState, NextStepViews};
 use super::stafra_static_state_bindings::StafraStaticBindings;
 use crate::stafra_initial_state_bindings::StafraInitialStateBindings;
+use crate::stafra_profiler::PassProfiler;
+use crate::stafra_paint::{PaintStroke, PaintTarget, PaintTool};
+use crate::stafra_lights_out::{LightsOutMove, LIGHTS_OUT_MAX_REACH};
 
-//Board bindings for the main stafra state. Re-initialized every time after resizing the board
+//The number of quads each tile borrows from its neighbors on every step. The largest click rule radius is 16, which is 8 quads
+const TILE_HALO_QUADS: u32 = 8;
+
+//The largest number of tiles on each side of the board
+const MAX_TILES_PER_SIDE: u32 = 8;
+
+//Board bindings for the main stafra state. Re-initialized every time after resizing the board.
+//Boards that don't fit into a single texture are split into a grid of square tiles, each one with its own set of textures
 pub struct StafraBoardBindings
 {
     board_width:  u32,
     board_height: u32,
 
+    tiles_x: u32,
+    tiles_y: u32,
+    tiles:   Vec<StafraBoardTile>,
+
+    video_frame: wgpu::Texture,
+}
+
+//The place of a tile on the board, in quads
+#[derive(Clone, Copy)]
+struct StafraTileLayout
+{
+    board_quads_x: u32,
+    board_quads_y: u32,
+    tile_offset_x: u32,
+    tile_offset_y: u32,
+    tile_width:    u32,
+    tile_height:   u32,
+    halo_quads:    u32
+}
+
+//A single tile of the board. All sizes and offsets are in quads
+struct StafraBoardTile
+{
+    tile_offset_x: u32,
+    tile_offset_y: u32,
+    tile_width:    u32,
+    tile_height:   u32,
+
+    tile_info_bind_group:          wgpu::BindGroup,
     main_render_state_bind_group:  wgpu::BindGroup,
     clear_default_bind_group:      wgpu::BindGroup,
     initial_transform_bind_group:  wgpu::BindGroup,
@@ -17,30 +58,43 @@ pub struct StafraBoardBindings
     next_step_bind_group_b:        wgpu::BindGroup,
     final_transform_bind_group_a:  wgpu::BindGroup,
     final_transform_bind_group_b:  wgpu::BindGroup,
+    board_image_bind_group_a:      wgpu::BindGroup,
+    board_image_bind_group_b:      wgpu::BindGroup,
     clear_stability_bind_group_a:  wgpu::BindGroup,
     clear_stability_bind_group_b:  wgpu::BindGroup,
     clear_restriction_bind_group:  wgpu::BindGroup,
     generate_mip_bind_groups:      Vec<wgpu::BindGroup>,
 
     #[allow(dead_code)]
+    tile_info_buffer: wgpu::Buffer,
+
     current_board:     wgpu::Texture,
-    #[allow(dead_code)]
     next_board:        wgpu::Texture,
-    #[allow(dead_code)]
     current_stability: wgpu::Texture,
-    #[allow(dead_code)]
     next_stability:    wgpu::Texture,
 
-    restriction:       wgpu::Texture,
-    final_state:       wgpu::Texture,
-    video_frame:       wgpu::Texture,
+    //The board of the tile together with TILE_HALO_QUADS quads of the neighbor tiles on each side. Only used if the board has more than one tile
+    halo_board: Option<wgpu::Texture>,
+
+    restriction: wgpu::Texture,
+    final_state: wgpu::Texture,
+}
+
+//Copies of the board and the stability of each tile, kept to go back to earlier frames
+pub struct BoardSnapshot
+{
+    tile_boards:      Vec<wgpu::Texture>,
+    tile_stabilities: Vec<wgpu::Texture>
 }
 
+//The buffer is shared so the readback pool can map it without holding the lock on the buffer list
 pub struct ImageBuffer
 {
-    pub image_buffer: wgpu::Buffer,
+    pub image_buffer: Arc<wgpu::Buffer>,
     pub raw_width:    u32,
     pub raw_height:   u32,
+    pub raw_offset_x: u32,
+    pub raw_offset_y: u32,
     pub row_pitch:    usize
 }
 
@@ -61,38 +115,40 @@ impl StafraBoardBindings
         let board_width  = width;
         let board_height = height;
 
-        let board_texture_descriptor = wgpu::TextureDescriptor
-        {
-            label: Some("Board texture"),
-            size:  wgpu::Extent3d
-            {
-                width:                 (board_width  + 1) / 2,
-                height:                (board_height + 1) / 2,
-                depth_or_array_layers: 1
-            },
-            mip_level_count: 1,
-            sample_count:    1,
-            dimension:       wgpu::TextureDimension::D2,
-            format:          wgpu::TextureFormat::R32Uint,
-            usage:           wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING
-        };
+        let board_quads_x = (board_width  + 1) / 2;
+        let board_quads_y = (board_height + 1) / 2;
 
-        let final_state_mips = (std::cmp::max(board_width, board_height) as f32).log2().ceil() as u32;
-        let final_state_texture_descriptor = wgpu::TextureDescriptor
+        let tile_size = Self::tile_size_for(&device.limits(), board_quads_x, board_quads_y);
+
+        let tile_width  = std::cmp::min(tile_size, board_quads_x);
+        let tile_height = std::cmp::min(tile_size, board_quads_y);
+
+        let tiles_x = board_quads_x / tile_width;
+        let tiles_y = board_quads_y / tile_height;
+
+        let halo_quads = if tiles_x * tiles_y > 1 {TILE_HALO_QUADS} else {0};
+
+        let initial_state_view = initial_state_bindings.create_initial_state_view();
+
+        let mut tiles = Vec::with_capacity((tiles_x * tiles_y) as usize);
+        for tile_y in 0..tiles_y
         {
-            label: Some("Final state texture"),
-            size:  wgpu::Extent3d
+            for tile_x in 0..tiles_x
             {
-                width:                 (board_width  + 1) / 2,
-                height:                (board_height + 1) / 2,
-                depth_or_array_layers: 1
-            },
-            mip_level_count: final_state_mips,
-            sample_count:    1,
-            dimension:       wgpu::TextureDimension::D2,
-            format:          wgpu::TextureFormat::Rgba8Unorm,
-            usage:           wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC
-        };
+                let tile_layout = StafraTileLayout
+                {
+                    board_quads_x,
+                    board_quads_y,
+                    tile_offset_x: tile_x * tile_width,
+                    tile_offset_y: tile_y * tile_height,
+                    tile_width,
+                    tile_height,
+                    halo_quads
+                };
+
+                tiles.push(StafraBoardTile::new(device, static_state, static_bindings, &initial_state_view, tile_layout));
+            }
+        }
 
         let video_frame_texture_descriptor = wgpu::TextureDescriptor
         {
@@ -110,248 +166,307 @@ impl StafraBoardBindings
             usage:           wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC
         };
 
-        let current_board      = device.create_texture(&board_texture_descriptor);
-        let next_board         = device.create_texture(&board_texture_descriptor);
-        let current_stability  = device.create_texture(&board_texture_descriptor);
-        let next_stability     = device.create_texture(&board_texture_descriptor);
-        let restriction        = device.create_texture(&board_texture_descriptor);
-        let final_state        = device.create_texture(&final_state_texture_descriptor);
-        let video_frame        = device.create_texture(&video_frame_texture_descriptor);
-
-        let board_view_descriptor = wgpu::TextureViewDescriptor
-        {
-            label:             Some("Board view"),
-            format:            Some(wgpu::TextureFormat::R32Uint),
-            dimension:         Some(wgpu::TextureViewDimension::D2),
-            aspect:            wgpu::TextureAspect::All,
-            base_mip_level:    0,
-            mip_level_count:   None,
-            base_array_layer:  0,
-            array_layer_count: None
-        };
+        let video_frame = device.create_texture(&video_frame_texture_descriptor);
 
-        let final_state_view_descriptor = wgpu::TextureViewDescriptor
+        Self
         {
-            label:             Some("Final state view"),
-            format:            Some(wgpu::TextureFormat::Rgba8Unorm),
-            dimension:         Some(wgpu::TextureViewDimension::D2),
-            aspect:            wgpu::TextureAspect::All,
-            base_mip_level:    0,
-            mip_level_count:   NonZeroU32::new(final_state_mips),
-            base_array_layer:  0,
-            array_layer_count: None
-        };
+            board_width,
+            board_height,
 
-        let initial_state_view     = initial_state_bindings.create_initial_state_view();
-        let current_board_view     = current_board.create_view(&board_view_descriptor);
-        let next_board_view        = next_board.create_view(&board_view_descriptor);
-        let current_stability_view = current_stability.create_view(&board_view_descriptor);
-        let next_stability_view    = next_stability.create_view(&board_view_descriptor);
-        let restriction_view       = restriction.create_view(&board_view_descriptor);
-        let final_state_view       = final_state.create_view(&final_state_view_descriptor);
+            tiles_x,
+            tiles_y,
+            tiles,
 
-        let mut final_state_mip_views = Vec::with_capacity(final_state_mips as usize);
-        for i in 0..final_state_mips
-        {
-            final_state_mip_views.push(final_state.create_view(&wgpu::TextureViewDescriptor
-            {
-                label:             Some(&format!("Final state mip {} view", i)),
-                format:            Some(wgpu::TextureFormat::Rgba8Unorm),
-                dimension:         Some(wgpu::TextureViewDimension::D2),
-                aspect:            wgpu::TextureAspect::All,
-                base_mip_level:    i,
-                mip_level_count:   NonZeroU32::new(1),
-                base_array_layer:  0,
-                array_layer_count: None
-            }));
+            video_frame
         }
+    }
 
-        let main_render_state_bind_group = static_state.create_render_main_bind_group(device, &final_state_view);
-
-        let clear_default_bind_group     = static_state.create_clear_default_bind_group(device,     &next_board_view);
-        let clear_stability_bind_group_a = static_state.create_clear_stability_bind_group(device,   &current_stability_view);
-        let clear_stability_bind_group_b = static_state.create_clear_stability_bind_group(device,   &next_stability_view);
-        let clear_restriction_bind_group = static_state.create_clear_restriction_bind_group(device, &restriction_view);
-
-        let initial_transform_bind_group  = static_state.create_initial_transform_bind_group(device, &initial_state_view, &next_board_view);
-        let filter_restriction_bind_group = static_state.create_filter_restriction_bind_group(device, &next_board_view, &restriction_view, &current_board_view);
+    //The largest board size supported with the given device limits
+    pub fn max_board_size(limits: &wgpu::Limits) -> u32
+    {
+        Self::max_tile_size(limits) * MAX_TILES_PER_SIDE * 2 - 1
+    }
 
-        let next_step_bind_group_a = static_state.create_next_step_bind_group(device, &current_board_view, &current_stability_view, &next_board_view, &next_stability_view, &restriction_view, static_bindings.click_rule_buffer_binding());
-        let next_step_bind_group_b = static_state.create_next_step_bind_group(device, &next_board_view, &next_stability_view, &current_board_view, &current_stability_view, &restriction_view, static_bindings.click_rule_buffer_binding());
+    //Boards that fit into a single texture entirely don't need to be split into tiles
+    fn tile_size_for(limits: &wgpu::Limits, board_quads_x: u32, board_quads_y: u32) -> u32
+    {
+        let board_quads = std::cmp::max(board_quads_x, board_quads_y);
+        if board_quads <= limits.max_texture_dimension_2d && Self::tile_fits_into_buffer(limits, board_quads)
+        {
+            return board_quads;
+        }
 
-        let final_transform_bind_group_a = static_state.create_final_transform_bind_group(device, &current_stability_view, &final_state_mip_views[0], static_bindings.spawn_buffer_binding());
-        let final_transform_bind_group_b = static_state.create_final_transform_bind_group(device, &next_stability_view,    &final_state_mip_views[0], static_bindings.spawn_buffer_binding());
+        Self::max_tile_size(limits)
+    }
 
-        let mut generate_mip_bind_groups = Vec::with_capacity(final_state_mips as usize - 1);
-        for i in 0..(final_state_mips - 1)
+    //The largest power of 2 tile size (in quads) that fits into a texture together with the halo and can be copied into a single buffer
+    fn max_tile_size(limits: &wgpu::Limits) -> u32
+    {
+        let mut max_tile_size = (limits.max_texture_dimension_2d - 2 * TILE_HALO_QUADS + 1).next_power_of_two() / 2;
+        while max_tile_size > 1 && !Self::tile_fits_into_buffer(limits, max_tile_size)
         {
-            generate_mip_bind_groups.push(static_state.create_generate_mip_bind_group(device, &final_state_mip_views[i as usize], &final_state_mip_views[i as usize + 1]));
+            max_tile_size /= 2;
         }
 
-        Self
-        {
-            board_width,
-            board_height,
+        max_tile_size
+    }
 
-            main_render_state_bind_group,
-            clear_default_bind_group,
-            initial_transform_bind_group,
-            filter_restriction_bind_group,
-            next_step_bind_group_a,
-            next_step_bind_group_b,
-            final_transform_bind_group_a,
-            final_transform_bind_group_b,
-            clear_stability_bind_group_a,
-            clear_stability_bind_group_b,
-            clear_restriction_bind_group,
-            generate_mip_bind_groups,
+    fn tile_fits_into_buffer(limits: &wgpu::Limits, tile_size: u32) -> bool
+    {
+        let row_alignment = 256 as u64;
+        let row_pitch     = ((tile_size as u64 * std::mem::size_of::<f32>() as u64) + (row_alignment - 1)) & (!(row_alignment - 1));
 
-            current_board,
-            next_board,
-            current_stability,
-            next_stability,
-            restriction,
-            final_state,
-            video_frame
-        }
+        row_pitch * tile_size as u64 <= limits.max_buffer_size
     }
 
     pub fn filter_restriction(&self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState)
     {
-        let thread_groups_x = std::cmp::max((self.board_width  + 1) / (2 * 16), 1u32);
-        let thread_groups_y = std::cmp::max((self.board_height + 1) / (2 * 16), 1u32);
-
+        for tile in &self.tiles
         {
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
+
             let mut filter_restriction_pass = static_state.create_filter_restriction_pass(encoder);
-            filter_restriction_pass.set_bind_group(0, &self.filter_restriction_bind_group, &[]);
+            filter_restriction_pass.set_bind_group(0, &tile.filter_restriction_bind_group, &[]);
             filter_restriction_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
         }
     }
 
     pub fn clear_stability(&self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState)
     {
-        let thread_groups_x = std::cmp::max((self.board_width  + 1) / (2 * 16), 1u32);
-        let thread_groups_y = std::cmp::max((self.board_height + 1) / (2 * 16), 1u32);
-
+        for tile in &self.tiles
         {
-            let mut clear_stability_pass_a = static_state.create_clear_stability_pass(encoder);
-            clear_stability_pass_a.set_bind_group(0, &self.clear_stability_bind_group_a, &[]);
-            clear_stability_pass_a.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
-        }
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
 
-        {
-            let mut clear_stability_pass_b = static_state.create_clear_stability_pass(encoder);
-            clear_stability_pass_b.set_bind_group(0, &self.clear_stability_bind_group_b, &[]);
-            clear_stability_pass_b.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+            {
+                let mut clear_stability_pass_a = static_state.create_clear_stability_pass(encoder);
+                clear_stability_pass_a.set_bind_group(0, &tile.clear_stability_bind_group_a, &[]);
+                clear_stability_pass_a.set_bind_group(1, &tile.tile_info_bind_group,         &[]);
+                clear_stability_pass_a.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+            }
+
+            {
+                let mut clear_stability_pass_b = static_state.create_clear_stability_pass(encoder);
+                clear_stability_pass_b.set_bind_group(0, &tile.clear_stability_bind_group_b, &[]);
+                clear_stability_pass_b.set_bind_group(1, &tile.tile_info_bind_group,         &[]);
+                clear_stability_pass_b.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+            }
         }
     }
 
     pub fn clear_restriction(&mut self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState)
     {
-        let thread_groups_x = std::cmp::max((self.board_width  + 1) / (2 * 16), 1u32);
-        let thread_groups_y = std::cmp::max((self.board_height + 1) / (2 * 16), 1u32);
-
+        for tile in &self.tiles
         {
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
+
             let mut clear_restriction_pass = static_state.create_clear_restriction_pass(encoder);
-            clear_restriction_pass.set_bind_group(0, &self.clear_restriction_bind_group, &[]);
+            clear_restriction_pass.set_bind_group(0, &tile.clear_restriction_bind_group, &[]);
+            clear_restriction_pass.set_bind_group(1, &tile.tile_info_bind_group,         &[]);
             clear_restriction_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
         }
     }
 
     pub fn reset_board_standard_corners(&mut self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState)
     {
-        let thread_groups_x = std::cmp::max((self.board_width + 1) / (2 * 16), 1u32);
-        let thread_groups_y = std::cmp::max((self.board_height + 1) / (2 * 16), 1u32);
-
+        for tile in &self.tiles
         {
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
+
             let mut reset_pass = static_state.create_clear_4_corners_pass(encoder);
-            reset_pass.set_bind_group(0, &self.clear_default_bind_group, &[]);
+            reset_pass.set_bind_group(0, &tile.clear_default_bind_group, &[]);
+            reset_pass.set_bind_group(1, &tile.tile_info_bind_group,     &[]);
             reset_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
         }
     }
 
     pub fn reset_board_standard_edges(&mut self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState)
     {
-        let thread_groups_x = std::cmp::max((self.board_width  + 1) / (2 * 16), 1u32);
-        let thread_groups_y = std::cmp::max((self.board_height + 1) / (2 * 16), 1u32);
-
+        for tile in &self.tiles
         {
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
+
             let mut reset_pass = static_state.create_clear_4_sides_pass(encoder);
-            reset_pass.set_bind_group(0, &self.clear_default_bind_group, &[]);
+            reset_pass.set_bind_group(0, &tile.clear_default_bind_group, &[]);
+            reset_pass.set_bind_group(1, &tile.tile_info_bind_group,     &[]);
             reset_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
         }
     }
 
     pub fn reset_board_standard_center(&mut self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState)
     {
-        let thread_groups_x = std::cmp::max((self.board_width  + 1) / (2 * 16), 1u32);
-        let thread_groups_y = std::cmp::max((self.board_height + 1) / (2 * 16), 1u32);
-
+        for tile in &self.tiles
         {
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
+
             let mut reset_pass = static_state.create_clear_center_pass(encoder);
-            reset_pass.set_bind_group(0, &self.clear_default_bind_group, &[]);
+            reset_pass.set_bind_group(0, &tile.clear_default_bind_group, &[]);
+            reset_pass.set_bind_group(1, &tile.tile_info_bind_group,     &[]);
             reset_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
         }
     }
 
-    pub fn reset_board_custom(&mut self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState)
+    pub fn reset_board_random(&mut self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, static_bindings: &StafraStaticBindings)
     {
-        let thread_groups_x = std::cmp::max((self.board_width  + 1) / (2 * 16), 1u32);
-        let thread_groups_y = std::cmp::max((self.board_height + 1) / (2 * 16), 1u32);
+        for tile in &self.tiles
+        {
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
+
+            let mut reset_pass = static_state.create_clear_random_pass(encoder);
+            reset_pass.set_bind_group(0, &tile.clear_default_bind_group,            &[]);
+            reset_pass.set_bind_group(1, &tile.tile_info_bind_group,                &[]);
+            reset_pass.set_bind_group(2, static_bindings.clear_random_bind_group(), &[]);
+            reset_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+        }
+    }
+
+    pub fn reset_board_pattern(&mut self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, static_bindings: &StafraStaticBindings)
+    {
+        for tile in &self.tiles
+        {
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
+
+            let mut reset_pass = static_state.create_clear_pattern_pass(encoder);
+            reset_pass.set_bind_group(0, &tile.clear_default_bind_group,             &[]);
+            reset_pass.set_bind_group(1, &tile.tile_info_bind_group,                 &[]);
+            reset_pass.set_bind_group(2, static_bindings.clear_pattern_bind_group(), &[]);
+            reset_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+        }
+    }
+
+    pub fn reset_board_expression(&mut self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, clear_expression_pipeline: &wgpu::ComputePipeline)
+    {
+        for tile in &self.tiles
+        {
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
+
+            let mut reset_pass = static_state.create_clear_expression_pass(encoder, clear_expression_pipeline);
+            reset_pass.set_bind_group(0, &tile.clear_default_bind_group, &[]);
+            reset_pass.set_bind_group(1, &tile.tile_info_bind_group,     &[]);
+            reset_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+        }
+    }
 
+    pub fn reset_board_custom(&mut self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState)
+    {
+        for tile in &self.tiles
         {
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
+
             let mut initial_transform_pass = static_state.create_initial_transform_pass(encoder);
-            initial_transform_pass.set_bind_group(0, &self.initial_transform_bind_group, &[]);
+            initial_transform_pass.set_bind_group(0, &tile.initial_transform_bind_group, &[]);
+            initial_transform_pass.set_bind_group(1, &tile.tile_info_bind_group,         &[]);
             initial_transform_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
         }
     }
 
-    pub fn draw_main_state(&self, encoder: &mut wgpu::CommandEncoder, main_frame_view: &wgpu::TextureView, static_state: &StafraStaticState)
+    pub fn draw_main_state(&self, encoder: &mut wgpu::CommandEncoder, main_frame_view: &wgpu::TextureView, frame_width: u32, frame_height: u32, static_state: &StafraStaticState, profiler: &mut PassProfiler)
     {
-        let mut main_render_pass = static_state.create_main_draw_pass(encoder, main_frame_view);
-        main_render_pass.set_bind_group(0, &self.main_render_state_bind_group, &[]);
-        main_render_pass.draw(0..3, 0..1);
+        profiler.begin_pass(encoder, "main_render");
+
+        {
+            let mut main_render_pass = static_state.create_main_draw_pass(encoder, main_frame_view);
+            self.draw_tiles(&mut main_render_pass, frame_width, frame_height);
+        }
+
+        profiler.end_pass(encoder);
     }
 
-    pub fn render_video_frame(&mut self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState)
+    pub fn render_video_frame(&mut self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, profiler: &mut PassProfiler)
     {
         let video_frame_view = self.video_frame.create_view(&wgpu::TextureViewDescriptor::default());
 
-        let mut main_render_pass = static_state.create_main_draw_pass(encoder, &video_frame_view);
-        main_render_pass.set_bind_group(0, &self.main_render_state_bind_group, &[]);
-        main_render_pass.draw(0..3, 0..1);
+        profiler.begin_pass(encoder, "video_frame_render");
+
+        {
+            let mut main_render_pass = static_state.create_main_draw_pass(encoder, &video_frame_view);
+            self.draw_tiles(&mut main_render_pass, 1024, 1024);
+        }
+
+        profiler.end_pass(encoder);
     }
 
-    pub fn calc_next_frame(&self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, frame_number: u32)
+    pub fn calc_next_frame(&self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, profiler: &mut PassProfiler, frame_number: u32)
     {
-        let thread_groups_x = std::cmp::max((self.board_width  + 1) / (2 * 8), 1u32);
-        let thread_groups_y = std::cmp::max((self.board_height + 1) / (2 * 8), 1u32);
+        if self.tiles.len() > 1
+        {
+            profiler.begin_pass(encoder, "halo_exchange");
+            self.exchange_halos(encoder, frame_number);
+            profiler.end_pass(encoder);
+        }
 
+        for tile in &self.tiles
         {
-            let mut next_step_pass = static_state.create_next_step_pass(encoder);
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(8);
 
-            let bind_group = if frame_number % 2 == 0 {&self.next_step_bind_group_a} else {&self.next_step_bind_group_b};
-            next_step_pass.set_bind_group(0, bind_group, &[]);
-            next_step_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+            profiler.begin_pass(encoder, "next_step");
+
+            {
+                let mut next_step_pass = static_state.create_next_step_pass(encoder);
+
+                let bind_group = if frame_number % 2 == 0 {&tile.next_step_bind_group_a} else {&tile.next_step_bind_group_b};
+                next_step_pass.set_bind_group(0, bind_group,                 &[]);
+                next_step_pass.set_bind_group(1, &tile.tile_info_bind_group, &[]);
+                next_step_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+            }
+
+            profiler.end_pass(encoder);
         }
     }
 
-    pub fn generate_final_image(&self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, frame_number: u32)
+    pub fn generate_final_image(&self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, profiler: &mut PassProfiler, frame_number: u32)
     {
-        let thread_groups_x = std::cmp::max((self.board_width  + 1) / (2 * 16), 1u32);
-        let thread_groups_y = std::cmp::max((self.board_height + 1) / (2 * 16), 1u32);
+        for tile in &self.tiles
+        {
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
+
+            profiler.begin_pass(encoder, "final_state_transform");
+
+            {
+                let mut final_transform_pass = static_state.create_generate_final_image_pass(encoder);
+
+                let bind_group = if frame_number % 2 == 0 {&tile.final_transform_bind_group_a} else {&tile.final_transform_bind_group_b};
+                final_transform_pass.set_bind_group(0, bind_group, &[]);
+                final_transform_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+            }
+
+            profiler.end_pass(encoder);
 
+            Self::generate_mips(encoder, static_state, profiler, tile);
+        }
+    }
+
+    //Shows the board itself instead of the stability. The latest board is in next_board after odd frames
+    pub fn generate_board_image(&self, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, profiler: &mut PassProfiler, frame_number: u32)
+    {
+        for tile in &self.tiles
         {
-            let mut final_transform_pass = static_state.create_generate_final_image_pass(encoder);
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
 
-            let bind_group = if frame_number % 2 == 0 {&self.final_transform_bind_group_a} else {&self.final_transform_bind_group_b};
-            final_transform_pass.set_bind_group(0, bind_group, &[]);
-            final_transform_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+            profiler.begin_pass(encoder, "board_image_transform");
+
+            {
+                let mut board_image_pass = static_state.create_generate_board_image_pass(encoder);
+
+                let bind_group = if frame_number % 2 == 0 {&tile.board_image_bind_group_a} else {&tile.board_image_bind_group_b};
+                board_image_pass.set_bind_group(0, bind_group, &[]);
+                board_image_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+            }
+
+            profiler.end_pass(encoder);
+
+            Self::generate_mips(encoder, static_state, profiler, tile);
         }
+    }
+
+    fn generate_mips(encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, profiler: &mut PassProfiler, tile: &StafraBoardTile)
+    {
+        let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
+
+        profiler.begin_pass(encoder, "generate_mips");
 
         let mut thread_groups_mip_x = std::cmp::max(thread_groups_x / 2, 1u32);
         let mut thread_groups_mip_y = std::cmp::max(thread_groups_y / 2, 1u32);
-        for gen_mip_bind_group in &self.generate_mip_bind_groups
+        for gen_mip_bind_group in &tile.generate_mip_bind_groups
         {
             let mut generate_mip_pass = static_state.create_generate_mip_pass(encoder);
             generate_mip_pass.set_bind_group(0, &gen_mip_bind_group, &[]);
@@ -360,89 +475,525 @@ impl StafraBoardBindings
             thread_groups_mip_x = std::cmp::max(thread_groups_mip_x / 2, 1u32);
             thread_groups_mip_y = std::cmp::max(thread_groups_mip_y / 2, 1u32);
         }
+
+        profiler.end_pass(encoder);
     }
 
     pub fn initial_transform_restriction(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, initial_restriction_view: &wgpu::TextureView, static_state: &StafraStaticState)
     {
-        let restriction_view = self.restriction.create_view(&wgpu::TextureViewDescriptor
+        for tile in &self.tiles
         {
-            label:             Some("Restriction view"),
-            format:            Some(wgpu::TextureFormat::R32Uint),
-            dimension:         Some(wgpu::TextureViewDimension::D2),
-            aspect:            wgpu::TextureAspect::All,
-            base_mip_level:    0,
-            mip_level_count:   None,
-            base_array_layer:  0,
-            array_layer_count: None
-        });
+            let restriction_view = tile.restriction.create_view(&wgpu::TextureViewDescriptor
+            {
+                label:             Some("Restriction view"),
+                format:            Some(wgpu::TextureFormat::R32Uint),
+                dimension:         Some(wgpu::TextureViewDimension::D2),
+                aspect:            wgpu::TextureAspect::All,
+                base_mip_level:    0,
+                mip_level_count:   None,
+                base_array_layer:  0,
+                array_layer_count: None
+            });
 
-        let initial_restriction_transform_bind_group = static_state.create_initial_restriction_transform_bind_group(device, &initial_restriction_view, &restriction_view);
+            let initial_restriction_transform_bind_group = static_state.create_initial_restriction_transform_bind_group(device, &initial_restriction_view, &restriction_view);
 
-        let thread_groups_x = std::cmp::max((self.board_width  + 1) / (2 * 16), 1u32);
-        let thread_groups_y = std::cmp::max((self.board_height + 1) / (2 * 16), 1u32);
+            let (thread_groups_x, thread_groups_y) = tile.thread_groups(16);
 
-        {
-            let mut initial_restriction_transform_pass = static_state.create_initial_restriction_transform_pass(encoder);
-            initial_restriction_transform_pass.set_bind_group(0, &initial_restriction_transform_bind_group, &[]);
-            initial_restriction_transform_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+            {
+                let mut initial_restriction_transform_pass = static_state.create_initial_restriction_transform_pass(encoder);
+                initial_restriction_transform_pass.set_bind_group(0, &initial_restriction_transform_bind_group, &[]);
+                initial_restriction_transform_pass.set_bind_group(1, &tile.tile_info_bind_group,                &[]);
+                initial_restriction_transform_pass.dispatch_workgroups(thread_groups_x, thread_groups_y, 1);
+            }
         }
     }
 
-    pub fn create_image_data_buffer(&self, device: &wgpu::Device, buffer_copy_encoder: &mut wgpu::CommandEncoder) -> ImageBuffer
+    //Draws the stroke into the pre-filter board (next_board) or into the restriction. current_board is used as a scratch texture,
+    //the board has to be filtered by the restriction and the stability cleared afterwards. Flood fill depends on the whole board and is done on the CPU
+    pub fn paint_stroke(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, stroke: &PaintStroke)
     {
-        let data_width  = (self.board_width  + 1) / 2;
-        let data_height = (self.board_height + 1) / 2;
+        let (min_cell_x, min_cell_y, max_cell_x, max_cell_y) = match stroke.cell_bounds(self.board_width)
+        {
+            Some(cell_bounds) => cell_bounds,
+            None              => return
+        };
 
-        let row_alignment = 256 as usize;
-        let row_pitch     = ((data_width as usize * std::mem::size_of::<f32>()) + (row_alignment - 1)) & (!(row_alignment - 1));
+        if stroke.tool == PaintTool::Fill
+        {
+            return;
+        }
 
-        let image_buffer = device.create_buffer(&wgpu::BufferDescriptor
+        let tool_index = match stroke.tool
         {
-            label: Some("PNG image staging buffer"),
-            size: (row_pitch * data_height as usize) as u64,
-            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false
+            PaintTool::Rectangle => 1,
+            PaintTool::Ellipse   => 2,
+            _                    => 0
+        };
+
+        let (region_min_x, region_min_y, region_max_x, region_max_y) = (min_cell_x / 2, min_cell_y / 2, max_cell_x / 2, max_cell_y / 2);
+
+        let paint_stroke_data = [tool_index, stroke.start_x as u32, stroke.start_y as u32, stroke.end_x as u32, stroke.end_y as u32, stroke.radius, stroke.filled as u32, stroke.enabled as u32, region_min_x, region_min_y, region_max_x, region_max_y];
+        let paint_stroke_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor
+        {
+            label:    Some("Paint stroke buffer"),
+            contents: &paint_stroke_data.iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<u8>>(),
+            usage:    wgpu::BufferUsages::UNIFORM
         });
 
-        buffer_copy_encoder.copy_texture_to_buffer(wgpu::ImageCopyTexture
+        let paint_stroke_data_bind_group = static_state.create_paint_stroke_data_bind_group(device, &paint_stroke_buffer);
+
+        for tile in &self.tiles
         {
-            texture:   &self.final_state,
-            mip_level: 0,
-            origin:    wgpu::Origin3d
+            //The part of the region inside the tile, in tile quads
+            let tile_min_x = std::cmp::max(region_min_x, tile.tile_offset_x);
+            let tile_min_y = std::cmp::max(region_min_y, tile.tile_offset_y);
+            let tile_max_x = std::cmp::min(region_max_x, tile.tile_offset_x + tile.tile_width  - 1);
+            let tile_max_y = std::cmp::min(region_max_y, tile.tile_offset_y + tile.tile_height - 1);
+            if tile_min_x > tile_max_x || tile_min_y > tile_max_y
             {
-               x: 0,
-               y: 0,
-               z: 0
-            },
-            aspect: wgpu::TextureAspect::All
-        },
-        wgpu::ImageCopyBuffer
-        {
-            buffer: &image_buffer,
-            layout: wgpu::ImageDataLayout
+                continue;
+            }
+
+            let target_texture = match stroke.target
             {
-               offset:         0,
-               bytes_per_row:  NonZeroU32::new(row_pitch as u32),
-               rows_per_image: NonZeroU32::new(data_height)
+                PaintTarget::Board       => &tile.next_board,
+                PaintTarget::Restriction => &tile.restriction
+            };
+
+            //The stroke pass reads the old values from the copy and writes only the region
+            let region_origin = wgpu::Origin3d
+            {
+                x: tile_min_x - tile.tile_offset_x,
+                y: tile_min_y - tile.tile_offset_y,
+                z: 0
+            };
+
+            let region_width  = tile_max_x - tile_min_x + 1;
+            let region_height = tile_max_y - tile_min_y + 1;
+
+            encoder.copy_texture_to_texture(wgpu::ImageCopyTexture
+            {
+                texture:   target_texture,
+                mip_level: 0,
+                origin:    region_origin,
+                aspect:    wgpu::TextureAspect::All
+            },
+            wgpu::ImageCopyTexture
+            {
+                texture:   &tile.current_board,
+                mip_level: 0,
+                origin:    region_origin,
+                aspect:    wgpu::TextureAspect::All
+            },
+            wgpu::Extent3d
+            {
+                width:                 region_width,
+                height:                region_height,
+                depth_or_array_layers: 1
+            });
+
+            let scratch_view = tile.current_board.create_view(&wgpu::TextureViewDescriptor::default());
+            let target_view  = target_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+            let paint_stroke_bind_group = static_state.create_paint_stroke_bind_group(device, &scratch_view, &target_view);
+
+            {
+                let mut paint_stroke_pass = static_state.create_paint_stroke_pass(encoder);
+                paint_stroke_pass.set_bind_group(0, &paint_stroke_bind_group,      &[]);
+                paint_stroke_pass.set_bind_group(1, &tile.tile_info_bind_group,    &[]);
+                paint_stroke_pass.set_bind_group(2, &paint_stroke_data_bind_group, &[]);
+                paint_stroke_pass.dispatch_workgroups((region_width + 7) / 8, (region_height + 7) / 8, 1);
+            }
+        }
+    }
+
+    //Makes a Lights Out move on the latest board. next_stability is used as a scratch texture,
+    //the stability has to be cleared afterwards
+    pub fn lights_out_click(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, static_state: &StafraStaticState, static_bindings: &StafraStaticBindings, lights_out_move: LightsOutMove, frame_number: u32)
+    {
+        let LightsOutMove{x: click_x, y: click_y} = lights_out_move;
+
+        let click_tile = self.tiles.iter().find(|tile|
+        {
+            click_x / 2 >= tile.tile_offset_x && click_x / 2 < tile.tile_offset_x + tile.tile_width && click_y / 2 >= tile.tile_offset_y && click_y / 2 < tile.tile_offset_y + tile.tile_height
+        });
+
+        let click_tile = match click_tile
+        {
+            Some(tile) => tile,
+            None       => return
+        };
+
+        let last_cell = self.board_width - 1;
+
+        let region_min_x = click_x.saturating_sub(LIGHTS_OUT_MAX_REACH) / 2;
+        let region_min_y = click_y.saturating_sub(LIGHTS_OUT_MAX_REACH) / 2;
+        let region_max_x = std::cmp::min(click_x + LIGHTS_OUT_MAX_REACH - 1, last_cell) / 2;
+        let region_max_y = std::cmp::min(click_y + LIGHTS_OUT_MAX_REACH - 1, last_cell) / 2;
+
+        let lights_out_click_data = [click_x, click_y, click_tile.tile_offset_x, click_tile.tile_offset_y, region_min_x, region_min_y, region_max_x, region_max_y];
+        let lights_out_click_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor
+        {
+            label:    Some("Lights Out click buffer"),
+            contents: &lights_out_click_data.iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<u8>>(),
+            usage:    wgpu::BufferUsages::UNIFORM
+        });
+
+        let lights_out_click_data_bind_group = static_state.create_lights_out_click_data_bind_group(device, &lights_out_click_buffer);
+        let click_restriction_view           = click_tile.restriction.create_view(&wgpu::TextureViewDescriptor::default());
+
+        for tile in &self.tiles
+        {
+            //The part of the region inside the tile, in tile quads
+            let tile_min_x = std::cmp::max(region_min_x, tile.tile_offset_x);
+            let tile_min_y = std::cmp::max(region_min_y, tile.tile_offset_y);
+            let tile_max_x = std::cmp::min(region_max_x, tile.tile_offset_x + tile.tile_width  - 1);
+            let tile_max_y = std::cmp::min(region_max_y, tile.tile_offset_y + tile.tile_height - 1);
+            if tile_min_x > tile_max_x || tile_min_y > tile_max_y
+            {
+                continue;
             }
+
+            let target_texture = if frame_number % 2 == 0 {&tile.current_board} else {&tile.next_board};
+
+            //The click pass reads the old values from the copy and writes only the region
+            let region_origin = wgpu::Origin3d
+            {
+                x: tile_min_x - tile.tile_offset_x,
+                y: tile_min_y - tile.tile_offset_y,
+                z: 0
+            };
+
+            let region_width  = tile_max_x - tile_min_x + 1;
+            let region_height = tile_max_y - tile_min_y + 1;
+
+            encoder.copy_texture_to_texture(wgpu::ImageCopyTexture
+            {
+                texture:   target_texture,
+                mip_level: 0,
+                origin:    region_origin,
+                aspect:    wgpu::TextureAspect::All
+            },
+            wgpu::ImageCopyTexture
+            {
+                texture:   &tile.next_stability,
+                mip_level: 0,
+                origin:    region_origin,
+                aspect:    wgpu::TextureAspect::All
+            },
+            wgpu::Extent3d
+            {
+                width:                 region_width,
+                height:                region_height,
+                depth_or_array_layers: 1
+            });
+
+            let scratch_view     = tile.next_stability.create_view(&wgpu::TextureViewDescriptor::default());
+            let restriction_view = tile.restriction.create_view(&wgpu::TextureViewDescriptor::default());
+            let target_view      = target_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+            let lights_out_click_bind_group = static_state.create_lights_out_click_bind_group(device, &scratch_view, &restriction_view, &click_restriction_view, &target_view, static_bindings.click_rule_buffer_binding());
+
+            {
+                let mut lights_out_click_pass = static_state.create_lights_out_click_pass(encoder);
+                lights_out_click_pass.set_bind_group(0, &lights_out_click_bind_group,      &[]);
+                lights_out_click_pass.set_bind_group(1, &tile.tile_info_bind_group,        &[]);
+                lights_out_click_pass.set_bind_group(2, &lights_out_click_data_bind_group, &[]);
+                lights_out_click_pass.dispatch_workgroups((region_width + 7) / 8, (region_height + 7) / 8, 1);
+            }
+        }
+    }
+
+    pub fn copy_image_data(&self, device: &wgpu::Device, buffer_copy_encoder: &mut wgpu::CommandEncoder, image_buffers: &mut Vec<ImageBuffer>)
+    {
+        self.copy_tile_data(device, buffer_copy_encoder, Some("PNG image staging buffer"), image_buffers, |tile| &tile.final_state)
+    }
+
+    //The latest stability is in next_stability after odd frames, the same as the board
+    pub fn copy_stability_data(&self, device: &wgpu::Device, buffer_copy_encoder: &mut wgpu::CommandEncoder, stability_buffers: &mut Vec<ImageBuffer>, frame_number: u32)
+    {
+        self.copy_tile_data(device, buffer_copy_encoder, Some("Stability staging buffer"), stability_buffers, |tile|
+        {
+            if frame_number % 2 == 1
+            {
+                &tile.next_stability
+            }
+            else
+            {
+                &tile.current_stability
+            }
+        })
+    }
+
+    //The latest board is in next_board after odd frames
+    pub fn copy_board_data(&self, device: &wgpu::Device, buffer_copy_encoder: &mut wgpu::CommandEncoder, board_buffers: &mut Vec<ImageBuffer>, frame_number: u32)
+    {
+        self.copy_tile_data(device, buffer_copy_encoder, Some("Board staging buffer"), board_buffers, |tile|
+        {
+            if frame_number % 2 == 1
+            {
+                &tile.next_board
+            }
+            else
+            {
+                &tile.current_board
+            }
+        })
+    }
+
+    pub fn copy_restriction_data(&self, device: &wgpu::Device, buffer_copy_encoder: &mut wgpu::CommandEncoder, restriction_buffers: &mut Vec<ImageBuffer>)
+    {
+        self.copy_tile_data(device, buffer_copy_encoder, Some("Restriction staging buffer"), restriction_buffers, |tile| &tile.restriction)
+    }
+
+    //The board of the frame 0 as painted, before filtering it by the restriction
+    pub fn copy_unfiltered_board_data(&self, device: &wgpu::Device, buffer_copy_encoder: &mut wgpu::CommandEncoder, board_buffers: &mut Vec<ImageBuffer>)
+    {
+        self.copy_tile_data(device, buffer_copy_encoder, Some("Unfiltered board staging buffer"), board_buffers, |tile| &tile.next_board)
+    }
+
+    //Writes the board state from arrays of one byte per cell. Both current and next textures get the same data, so it doesn't matter which one is read first
+    pub fn upload_board_state(&self, queue: &wgpu::Queue, board: &[u8], stability: &[u8], restriction: &[u8])
+    {
+        let board_quads       = self.pack_cells_to_quads(board);
+        let stability_quads   = self.pack_cells_to_quads(stability);
+        let restriction_quads = self.pack_cells_to_quads(restriction);
+
+        self.write_tile_data(queue, &board_quads,       |tile| &tile.current_board);
+        self.write_tile_data(queue, &board_quads,       |tile| &tile.next_board);
+        self.write_tile_data(queue, &stability_quads,   |tile| &tile.current_stability);
+        self.write_tile_data(queue, &stability_quads,   |tile| &tile.next_stability);
+        self.write_tile_data(queue, &restriction_quads, |tile| &tile.restriction);
+    }
+
+    //Writes the board only, the same way as upload_board_state. The queue writes happen before the commands of the encoders submitted after them
+    pub fn upload_board_cells(&self, queue: &wgpu::Queue, board: &[u8])
+    {
+        let board_quads = self.pack_cells_to_quads(board);
+
+        self.write_tile_data(queue, &board_quads, |tile| &tile.current_board);
+        self.write_tile_data(queue, &board_quads, |tile| &tile.next_board);
+    }
+
+    pub fn upload_restriction_cells(&self, queue: &wgpu::Queue, restriction: &[u8])
+    {
+        let restriction_quads = self.pack_cells_to_quads(restriction);
+        self.write_tile_data(queue, &restriction_quads, |tile| &tile.restriction);
+    }
+
+    pub fn snapshot_bytes(&self) -> u64
+    {
+        let board_quads = ((self.board_width + 1) / 2) as u64 * ((self.board_height + 1) / 2) as u64;
+        board_quads * std::mem::size_of::<u32>() as u64 * 2
+    }
+
+    pub fn create_snapshot(&self, device: &wgpu::Device) -> BoardSnapshot
+    {
+        let create_tile_texture = |tile: &StafraBoardTile, label|
+        {
+            device.create_texture(&wgpu::TextureDescriptor
+            {
+                label: Some(label),
+                size:  wgpu::Extent3d
+                {
+                    width:                 tile.tile_width,
+                    height:                tile.tile_height,
+                    depth_or_array_layers: 1
+                },
+                mip_level_count: 1,
+                sample_count:    1,
+                dimension:       wgpu::TextureDimension::D2,
+                format:          wgpu::TextureFormat::R32Uint,
+                usage:           wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST
+            })
+        };
+
+        BoardSnapshot
+        {
+            tile_boards:      self.tiles.iter().map(|tile| create_tile_texture(tile, "Board snapshot texture")).collect(),
+            tile_stabilities: self.tiles.iter().map(|tile| create_tile_texture(tile, "Stability snapshot texture")).collect()
+        }
+    }
+
+    //The latest board and stability are in the next textures after odd frames
+    pub fn save_snapshot(&self, encoder: &mut wgpu::CommandEncoder, snapshot: &BoardSnapshot, frame_number: u32)
+    {
+        for (tile_index, tile) in self.tiles.iter().enumerate()
+        {
+            let (board, stability) = if frame_number % 2 == 1 {(&tile.next_board, &tile.next_stability)} else {(&tile.current_board, &tile.current_stability)};
+
+            Self::copy_tile_texture(encoder, board,     &snapshot.tile_boards[tile_index],      tile);
+            Self::copy_tile_texture(encoder, stability, &snapshot.tile_stabilities[tile_index], tile);
+        }
+    }
+
+    //Both current and next textures get the snapshot, so it doesn't matter which one is read first
+    pub fn restore_snapshot(&self, encoder: &mut wgpu::CommandEncoder, snapshot: &BoardSnapshot)
+    {
+        for (tile_index, tile) in self.tiles.iter().enumerate()
+        {
+            Self::copy_tile_texture(encoder, &snapshot.tile_boards[tile_index],      &tile.current_board,     tile);
+            Self::copy_tile_texture(encoder, &snapshot.tile_boards[tile_index],      &tile.next_board,        tile);
+            Self::copy_tile_texture(encoder, &snapshot.tile_stabilities[tile_index], &tile.current_stability, tile);
+            Self::copy_tile_texture(encoder, &snapshot.tile_stabilities[tile_index], &tile.next_stability,    tile);
+        }
+    }
+
+    fn copy_tile_texture(encoder: &mut wgpu::CommandEncoder, source: &wgpu::Texture, destination: &wgpu::Texture, tile: &StafraBoardTile)
+    {
+        encoder.copy_texture_to_texture(wgpu::ImageCopyTexture
+        {
+            texture:   source,
+            mip_level: 0,
+            origin:    wgpu::Origin3d::ZERO,
+            aspect:    wgpu::TextureAspect::All
+        },
+        wgpu::ImageCopyTexture
+        {
+            texture:   destination,
+            mip_level: 0,
+            origin:    wgpu::Origin3d::ZERO,
+            aspect:    wgpu::TextureAspect::All
         },
         wgpu::Extent3d
         {
-            width:                 data_width,
-            height:                data_height,
+            width:                 tile.tile_width,
+            height:                tile.tile_height,
             depth_or_array_layers: 1
         });
+    }
 
-        ImageBuffer
+    //The inverse of decode_tile_buffers: packs one byte per cell into quads of the whole board
+    fn pack_cells_to_quads(&self, cells: &[u8]) -> Vec<u8>
+    {
+        let board_width  = self.board_width  as usize;
+        let board_height = self.board_height as usize;
+
+        let board_quads_x = (board_width  + 1) / 2;
+        let board_quads_y = (board_height + 1) / 2;
+
+        let mut quad_data = vec![0u8; board_quads_x * board_quads_y * 4];
+        for (cell_index, cell_value) in cells.iter().enumerate()
         {
-            image_buffer,
-            raw_width: data_width,
-            raw_height: data_height,
-            row_pitch
+            let cell_x = cell_index % board_width;
+            let cell_y = cell_index / board_width;
+
+            let quad_index       = (cell_y / 2) * board_quads_x + cell_x / 2;
+            let quad_value_index = (cell_y % 2) * 2 + cell_x % 2;
+            quad_data[quad_index * 4 + quad_value_index] = *cell_value;
         }
+
+        quad_data
     }
 
-    pub fn create_video_frame_data_buffer(&self, device: &wgpu::Device, buffer_copy_encoder: &mut wgpu::CommandEncoder) -> ImageBuffer
+    //Writes the part of the quad data of the whole board that belongs to each tile into the selected quad texture of the tile
+    fn write_tile_data(&self, queue: &wgpu::Queue, quad_data: &[u8], tile_texture: impl Fn(&StafraBoardTile) -> &wgpu::Texture)
+    {
+        let board_quads_x = (self.board_width + 1) / 2;
+        for tile in &self.tiles
+        {
+            let tile_data_offset = ((tile.tile_offset_y * board_quads_x + tile.tile_offset_x) as usize * std::mem::size_of::<u32>()) as u64;
+            queue.write_texture(wgpu::ImageCopyTexture
+            {
+                texture:   tile_texture(tile),
+                mip_level: 0,
+                origin:    wgpu::Origin3d::ZERO,
+                aspect:    wgpu::TextureAspect::All
+            },
+            quad_data,
+            wgpu::ImageDataLayout
+            {
+                offset:         tile_data_offset,
+                bytes_per_row:  NonZeroU32::new(board_quads_x * std::mem::size_of::<u32>() as u32),
+                rows_per_image: NonZeroU32::new(tile.tile_height)
+            },
+            wgpu::Extent3d
+            {
+                width:                 tile.tile_width,
+                height:                tile.tile_height,
+                depth_or_array_layers: 1
+            });
+        }
+    }
+
+    //Copies the mip 0 of the selected quad texture of each tile into its own staging buffer.
+    //The buffers are reused if they were created for the same tiles, otherwise they are re-created
+    fn copy_tile_data(&self, device: &wgpu::Device, buffer_copy_encoder: &mut wgpu::CommandEncoder, label: Option<&str>, image_buffers: &mut Vec<ImageBuffer>, tile_texture: impl Fn(&StafraBoardTile) -> &wgpu::Texture)
+    {
+        let buffers_match_tiles = image_buffers.len() == self.tiles.len() && image_buffers.iter().zip(self.tiles.iter()).all(|(image_buffer, tile)|
+        {
+               image_buffer.raw_width    == tile.tile_width
+            && image_buffer.raw_height   == tile.tile_height
+            && image_buffer.raw_offset_x == tile.tile_offset_x
+            && image_buffer.raw_offset_y == tile.tile_offset_y
+        });
+
+        if !buffers_match_tiles
+        {
+            image_buffers.clear();
+            for tile in &self.tiles
+            {
+                let data_width  = tile.tile_width;
+                let data_height = tile.tile_height;
+
+                let row_alignment = 256 as usize;
+                let row_pitch     = ((data_width as usize * std::mem::size_of::<f32>()) + (row_alignment - 1)) & (!(row_alignment - 1));
+
+                let image_buffer = device.create_buffer(&wgpu::BufferDescriptor
+                {
+                    label,
+                    size: (row_pitch * data_height as usize) as u64,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false
+                });
+
+                image_buffers.push(ImageBuffer
+                {
+                    image_buffer: Arc::new(image_buffer),
+                    raw_width:    data_width,
+                    raw_height:   data_height,
+                    raw_offset_x: tile.tile_offset_x,
+                    raw_offset_y: tile.tile_offset_y,
+                    row_pitch
+                });
+            }
+        }
+
+        for (tile, image_buffer) in self.tiles.iter().zip(image_buffers.iter())
+        {
+            buffer_copy_encoder.copy_texture_to_buffer(wgpu::ImageCopyTexture
+            {
+                texture:   tile_texture(tile),
+                mip_level: 0,
+                origin:    wgpu::Origin3d
+                {
+                   x: 0,
+                   y: 0,
+                   z: 0
+                },
+                aspect: wgpu::TextureAspect::All
+            },
+            wgpu::ImageCopyBuffer
+            {
+                buffer: &image_buffer.image_buffer,
+                layout: wgpu::ImageDataLayout
+                {
+                   offset:         0,
+                   bytes_per_row:  NonZeroU32::new(image_buffer.row_pitch as u32),
+                   rows_per_image: NonZeroU32::new(image_buffer.raw_height)
+                }
+            },
+            wgpu::Extent3d
+            {
+                width:                 image_buffer.raw_width,
+                height:                image_buffer.raw_height,
+                depth_or_array_layers: 1
+            });
+        }
+    }
+
+    //The video frame always has the same size, so the buffer is only created once
+    pub fn copy_video_frame_data(&self, device: &wgpu::Device, buffer_copy_encoder: &mut wgpu::CommandEncoder, video_frame_buffers: &mut Vec<ImageBuffer>)
     {
         let video_frame_width  = 1024;
         let video_frame_height = 1024;
@@ -450,13 +1001,26 @@ impl StafraBoardBindings
         let row_alignment = 256 as usize;
         let row_pitch     = (video_frame_width * 4 + row_alignment - 1) & (!(row_alignment - 1));
 
-        let video_frame_buffer = device.create_buffer(&wgpu::BufferDescriptor
+        if video_frame_buffers.is_empty()
         {
-            label:              Some("Video frame staging buffer"),
-            size:               (row_pitch * video_frame_height as usize) as u64,
-            usage:              wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false
-        });
+            let video_frame_buffer = device.create_buffer(&wgpu::BufferDescriptor
+            {
+                label:              Some("Video frame staging buffer"),
+                size:               (row_pitch * video_frame_height as usize) as u64,
+                usage:              wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false
+            });
+
+            video_frame_buffers.push(ImageBuffer
+            {
+                image_buffer: Arc::new(video_frame_buffer),
+                raw_width:    video_frame_width  as u32,
+                raw_height:   video_frame_height as u32,
+                raw_offset_x: 0,
+                raw_offset_y: 0,
+                row_pitch
+            });
+        }
 
         buffer_copy_encoder.copy_texture_to_buffer(wgpu::ImageCopyTexture
         {
@@ -472,7 +1036,7 @@ impl StafraBoardBindings
         },
         wgpu::ImageCopyBuffer
         {
-            buffer: &video_frame_buffer,
+            buffer: &video_frame_buffers[0].image_buffer,
             layout: wgpu::ImageDataLayout
             {
                offset:         0,
@@ -486,79 +1050,80 @@ impl StafraBoardBindings
             height:                video_frame_height as u32,
             depth_or_array_layers: 1
         });
+    }
 
-        ImageBuffer
+    //Stitches the mapped tile buffers into a single image of the whole board
+    pub fn get_image_buffers_mapped_data(image_buffers: &[ImageBuffer], image_width: u32, image_height: u32) -> ImageData
+    {
+        let mut image_array = vec![0u8; (image_width as usize) * (image_height as usize) * 4];
+        Self::decode_tile_buffers(image_buffers, image_width, image_height, |texel_index, quad_value|
         {
-            image_buffer: video_frame_buffer,
-            raw_width:    video_frame_width  as u32,
-            raw_height:   video_frame_height as u32,
-            row_pitch
+            let texel_start = texel_index * 4;
+            image_array[texel_start + 0] = quad_value; //Red
+            image_array[texel_start + 1] = 0u8;        //Green
+            image_array[texel_start + 2] = quad_value; //Blue
+            image_array[texel_start + 3] = 255u8;      //Alpha
+        });
+
+        ImageData
+        {
+            pixel_data: image_array,
+            image_width,
+            image_height
         }
     }
 
-    pub fn get_image_buffer_mapped_data(image_buffer: &wgpu::Buffer, raw_width: u32, raw_height: u32, row_pitch: usize) -> ImageData
+    //Stitches the mapped tile buffers of board, stability or restriction into a single array, one byte per cell
+    pub fn get_cell_buffers_mapped_data(cell_buffers: &[ImageBuffer], board_width: u32, board_height: u32) -> ImageData
     {
-        let image_width  = raw_width  * 2 - 1;
-        let image_height = raw_height * 2 - 1;
+        let mut cell_array = vec![0u8; (board_width as usize) * (board_height as usize)];
+        Self::decode_tile_buffers(cell_buffers, board_width, board_height, |cell_index, quad_value|
+        {
+            cell_array[cell_index] = quad_value;
+        });
+
+        ImageData
+        {
+            pixel_data:   cell_array,
+            image_width:  board_width,
+            image_height: board_height
+        }
+    }
 
-        let padded_width  = image_width  + 1;
-        let padded_height = image_height + 1;
-        let mut image_array = vec![0u8; (padded_width * padded_height * 4) as usize];
+    //Calls write_cell(y * image_width + x, value) for each cell (x, y) stored in the mapped quad buffers
+    fn decode_tile_buffers(image_buffers: &[ImageBuffer], image_width: u32, image_height: u32, mut write_cell: impl FnMut(usize, u8))
+    {
+        for image_buffer in image_buffers
         {
-            let image_buffer_view = image_buffer.slice(..).get_mapped_range();
-            for (quad_row_index, quad_row_chunk) in image_buffer_view.chunks(row_pitch).enumerate()
+            let image_buffer_view = image_buffer.image_buffer.slice(..).get_mapped_range();
+            for (quad_row_index, quad_row_chunk) in image_buffer_view.chunks(image_buffer.row_pitch).enumerate()
             {
-                let image_row_index = (quad_row_index * 2) as u32;
+                let image_row_index = (image_buffer.raw_offset_y as usize + quad_row_index) * 2;
                 for (quad_column_index, quad_bytes) in quad_row_chunk.chunks(4).enumerate()
                 {
-                    let image_column_index = (quad_column_index * 2) as u32;
-                    if image_column_index >= image_width
+                    if quad_column_index >= image_buffer.raw_width as usize
                     {
                         //Can get there if row_pitch is big enough
                         break;
                     }
 
-                    //Decode the quad
-                    let top_left     = quad_bytes[0];
-                    let top_right    = quad_bytes[1];
-                    let bottom_left  = quad_bytes[2];
-                    let bottom_right = quad_bytes[3];
-
-                    let top_left_texel_start     = (((image_row_index + 0) * image_width + image_column_index + 0) * 4) as usize;
-                    let top_right_texel_start    = (((image_row_index + 0) * image_width + image_column_index + 1) * 4) as usize;
-                    let bottom_left_texel_start  = (((image_row_index + 1) * image_width + image_column_index + 0) * 4) as usize;
-                    let bottom_right_texel_start = (((image_row_index + 1) * image_width + image_column_index + 1) * 4) as usize;
-
-                    image_array[bottom_right_texel_start + 0] = bottom_right; //Red
-                    image_array[bottom_right_texel_start + 1] = 0u8;          //Green
-                    image_array[bottom_right_texel_start + 2] = bottom_right; //Blue
-                    image_array[bottom_right_texel_start + 3] = 255u8;        //Alpha
-
-                    image_array[top_right_texel_start + 0] = top_right; //Red
-                    image_array[top_right_texel_start + 1] = 0u8;       //Green
-                    image_array[top_right_texel_start + 2] = top_right; //Blue
-                    image_array[top_right_texel_start + 3] = 255u8;     //Alpha
-
-                    image_array[bottom_left_texel_start + 0] = bottom_left; //Red
-                    image_array[bottom_left_texel_start + 1] = 0u8;         //Green
-                    image_array[bottom_left_texel_start + 2] = bottom_left; //Blue
-                    image_array[bottom_left_texel_start + 3] = 255u8;       //Alpha
-
-                    image_array[top_left_texel_start + 0] = top_left; //Red
-                    image_array[top_left_texel_start + 1] = 0u8;      //Green
-                    image_array[top_left_texel_start + 2] = top_left; //Blue
-                    image_array[top_left_texel_start + 3] = 255u8;    //Alpha
+                    let image_column_index = (image_buffer.raw_offset_x as usize + quad_column_index) * 2;
+
+                    //Decode the quad. The last row and column of quads only contain the top-left values
+                    for (quad_value_index, quad_value) in quad_bytes.iter().enumerate()
+                    {
+                        let texel_row    = image_row_index    + quad_value_index / 2;
+                        let texel_column = image_column_index + quad_value_index % 2;
+                        if texel_row >= image_height as usize || texel_column >= image_width as usize
+                        {
+                            continue;
+                        }
+
+                        write_cell(texel_row * image_width as usize + texel_column, *quad_value);
+                    }
                 }
             }
         }
-
-        image_array.truncate((image_width * image_height * 4) as usize);
-        ImageData
-        {
-            pixel_data: image_array,
-            image_width,
-            image_height
-        }
     }
 
     pub fn get_video_frame_buffer_mapped_data(video_frame_buffer: &wgpu::Buffer, raw_width: u32, raw_height: u32) -> ImageData
@@ -573,4 +1138,352 @@ impl StafraBoardBindings
             image_height: raw_height as u32
         }
     }
+
+    pub fn board_width(&self) -> u32
+    {
+        self.board_width
+    }
+
+    pub fn board_height(&self) -> u32
+    {
+        self.board_height
+    }
+
+    fn draw_tiles<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, frame_width: u32, frame_height: u32)
+    {
+        //Each tile is drawn into its own part of the frame
+        for tile in &self.tiles
+        {
+            let (viewport_left, viewport_right)  = Self::tile_viewport_range(tile.tile_offset_x, tile.tile_width,  self.board_width,  frame_width);
+            let (viewport_top,  viewport_bottom) = Self::tile_viewport_range(tile.tile_offset_y, tile.tile_height, self.board_height, frame_height);
+
+            render_pass.set_viewport(viewport_left, viewport_top, viewport_right - viewport_left, viewport_bottom - viewport_top, 0.0, 1.0);
+            render_pass.set_bind_group(0, &tile.main_render_state_bind_group, &[]);
+            render_pass.set_bind_group(1, &tile.tile_info_bind_group,         &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+
+    fn tile_viewport_range(tile_offset_quads: u32, tile_size_quads: u32, board_size: u32, frame_size: u32) -> (f32, f32)
+    {
+        let tile_start = (tile_offset_quads * 2) as u64;
+        let tile_end   = std::cmp::min((tile_offset_quads + tile_size_quads) as u64 * 2, board_size as u64);
+
+        let viewport_start = (tile_start * frame_size as u64) as f64 / board_size as f64;
+        let viewport_end   = if tile_end == board_size as u64 {frame_size as f64} else {(tile_end * frame_size as u64) as f64 / board_size as f64};
+
+        (viewport_start as f32, viewport_end as f32)
+    }
+
+    fn exchange_halos(&self, encoder: &mut wgpu::CommandEncoder, frame_number: u32)
+    {
+        //Copy the board of each tile and the borders of its 8 neighbors into the tile's halo texture:
+        //X X X
+        //X o X
+        //X X X
+        for tile_y in 0..self.tiles_y
+        {
+            for tile_x in 0..self.tiles_x
+            {
+                let tile      = &self.tiles[(tile_y * self.tiles_x + tile_x) as usize];
+                let halo_tile = tile.halo_board.as_ref().unwrap();
+
+                for neighbor_dy in -1i32..=1
+                {
+                    for neighbor_dx in -1i32..=1
+                    {
+                        let neighbor_x = tile_x as i32 + neighbor_dx;
+                        let neighbor_y = tile_y as i32 + neighbor_dy;
+                        if neighbor_x < 0 || neighbor_x >= self.tiles_x as i32 || neighbor_y < 0 || neighbor_y >= self.tiles_y as i32
+                        {
+                            //The halo outside of the board is masked out in the shader
+                            continue;
+                        }
+
+                        let neighbor = &self.tiles[(neighbor_y as u32 * self.tiles_x + neighbor_x as u32) as usize];
+                        let source   = if frame_number % 2 == 0 {&neighbor.current_board} else {&neighbor.next_board};
+
+                        let (source_x, dest_x, copy_width)  = Self::halo_copy_range(neighbor_dx, tile.tile_width);
+                        let (source_y, dest_y, copy_height) = Self::halo_copy_range(neighbor_dy, tile.tile_height);
+
+                        encoder.copy_texture_to_texture(wgpu::ImageCopyTexture
+                        {
+                            texture:   source,
+                            mip_level: 0,
+                            origin:    wgpu::Origin3d
+                            {
+                                x: source_x,
+                                y: source_y,
+                                z: 0
+                            },
+                            aspect: wgpu::TextureAspect::All
+                        },
+                        wgpu::ImageCopyTexture
+                        {
+                            texture:   halo_tile,
+                            mip_level: 0,
+                            origin:    wgpu::Origin3d
+                            {
+                                x: dest_x,
+                                y: dest_y,
+                                z: 0
+                            },
+                            aspect: wgpu::TextureAspect::All
+                        },
+                        wgpu::Extent3d
+                        {
+                            width:                 copy_width,
+                            height:                copy_height,
+                            depth_or_array_layers: 1
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    //Returns the source offset, destination offset and the size of the copy for one of the axes
+    fn halo_copy_range(neighbor_direction: i32, tile_size: u32) -> (u32, u32, u32)
+    {
+        match neighbor_direction
+        {
+            -1 => (tile_size - TILE_HALO_QUADS, 0,                           TILE_HALO_QUADS),
+            0  => (0,                           TILE_HALO_QUADS,             tile_size),
+            _  => (0,                           TILE_HALO_QUADS + tile_size, TILE_HALO_QUADS)
+        }
+    }
+}
+
+impl StafraBoardTile
+{
+    fn new(device: &wgpu::Device, static_state: &StafraStaticState, static_bindings: &StafraStaticBindings, initial_state_view: &wgpu::TextureView, tile_layout: StafraTileLayout) -> Self
+    {
+        let StafraTileLayout{board_quads_x, board_quads_y, tile_offset_x, tile_offset_y, tile_width, tile_height, halo_quads} = tile_layout;
+
+        let board_texture_descriptor = wgpu::TextureDescriptor
+        {
+            label: Some("Board texture"),
+            size:  wgpu::Extent3d
+            {
+                width:                 tile_width,
+                height:                tile_height,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count:    1,
+            dimension:       wgpu::TextureDimension::D2,
+            format:          wgpu::TextureFormat::R32Uint,
+            usage:           wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST
+        };
+
+        let halo_board_texture_descriptor = wgpu::TextureDescriptor
+        {
+            label: Some("Halo board texture"),
+            size:  wgpu::Extent3d
+            {
+                width:                 tile_width  + halo_quads * 2,
+                height:                tile_height + halo_quads * 2,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count:    1,
+            dimension:       wgpu::TextureDimension::D2,
+            format:          wgpu::TextureFormat::R32Uint,
+            usage:           wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+        };
+
+        let final_state_mips = (std::cmp::max(tile_width, tile_height) as f32).log2() as u32 + 1;
+        let final_state_texture_descriptor = wgpu::TextureDescriptor
+        {
+            label: Some("Final state texture"),
+            size:  wgpu::Extent3d
+            {
+                width:                 tile_width,
+                height:                tile_height,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: final_state_mips,
+            sample_count:    1,
+            dimension:       wgpu::TextureDimension::D2,
+            format:          wgpu::TextureFormat::Rgba8Unorm,
+            usage:           wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC
+        };
+
+        let current_board     = device.create_texture(&board_texture_descriptor);
+        let next_board        = device.create_texture(&board_texture_descriptor);
+        let current_stability = device.create_texture(&board_texture_descriptor);
+        let next_stability    = device.create_texture(&board_texture_descriptor);
+        let restriction       = device.create_texture(&board_texture_descriptor);
+        let final_state       = device.create_texture(&final_state_texture_descriptor);
+
+        let halo_board = if halo_quads > 0 {Some(device.create_texture(&halo_board_texture_descriptor))} else {None};
+
+        //Board size in quads, tile offset in quads, halo size in quads, padding
+        let tile_info_data = [board_quads_x, board_quads_y, tile_offset_x, tile_offset_y, halo_quads, 0, 0, 0];
+        let tile_info_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor
+        {
+            label:    Some("Tile info buffer"),
+            contents: &tile_info_data.iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<u8>>(),
+            usage:    wgpu::BufferUsages::UNIFORM
+        });
+
+        let board_view_descriptor = wgpu::TextureViewDescriptor
+        {
+            label:             Some("Board view"),
+            format:            Some(wgpu::TextureFormat::R32Uint),
+            dimension:         Some(wgpu::TextureViewDimension::D2),
+            aspect:            wgpu::TextureAspect::All,
+            base_mip_level:    0,
+            mip_level_count:   None,
+            base_array_layer:  0,
+            array_layer_count: None
+        };
+
+        let final_state_view_descriptor = wgpu::TextureViewDescriptor
+        {
+            label:             Some("Final state view"),
+            format:            Some(wgpu::TextureFormat::Rgba8Unorm),
+            dimension:         Some(wgpu::TextureViewDimension::D2),
+            aspect:            wgpu::TextureAspect::All,
+            base_mip_level:    0,
+            mip_level_count:   NonZeroU32::new(final_state_mips),
+            base_array_layer:  0,
+            array_layer_count: None
+        };
+
+        let current_board_view     = current_board.create_view(&board_view_descriptor);
+        let next_board_view        = next_board.create_view(&board_view_descriptor);
+        let current_stability_view = current_stability.create_view(&board_view_descriptor);
+        let next_stability_view    = next_stability.create_view(&board_view_descriptor);
+        let restriction_view       = restriction.create_view(&board_view_descriptor);
+        let final_state_view       = final_state.create_view(&final_state_view_descriptor);
+
+        //The next step works on texture arrays, the tile is a single layer of them
+        let board_array_view_descriptor = wgpu::TextureViewDescriptor
+        {
+            label:             Some("Board array view"),
+            format:            Some(wgpu::TextureFormat::R32Uint),
+            dimension:         Some(wgpu::TextureViewDimension::D2Array),
+            aspect:            wgpu::TextureAspect::All,
+            base_mip_level:    0,
+            mip_level_count:   None,
+            base_array_layer:  0,
+            array_layer_count: None
+        };
+
+        let current_board_array_view     = current_board.create_view(&board_array_view_descriptor);
+        let next_board_array_view        = next_board.create_view(&board_array_view_descriptor);
+        let current_stability_array_view = current_stability.create_view(&board_array_view_descriptor);
+        let next_stability_array_view    = next_stability.create_view(&board_array_view_descriptor);
+
+        let halo_board_array_view = halo_board.as_ref().map(|halo_board_tex| halo_board_tex.create_view(&board_array_view_descriptor));
+
+        let mut final_state_mip_views = Vec::with_capacity(final_state_mips as usize);
+        for i in 0..final_state_mips
+        {
+            final_state_mip_views.push(final_state.create_view(&wgpu::TextureViewDescriptor
+            {
+                label:             Some(&format!("Final state mip {} view", i)),
+                format:            Some(wgpu::TextureFormat::Rgba8Unorm),
+                dimension:         Some(wgpu::TextureViewDimension::D2),
+                aspect:            wgpu::TextureAspect::All,
+                base_mip_level:    i,
+                mip_level_count:   NonZeroU32::new(1),
+                base_array_layer:  0,
+                array_layer_count: None
+            }));
+        }
+
+        let tile_info_bind_group         = static_state.create_tile_info_bind_group(device, &tile_info_buffer);
+        let main_render_state_bind_group = static_state.create_render_main_bind_group(device, &final_state_view);
+
+        let clear_default_bind_group     = static_state.create_clear_default_bind_group(device,     &next_board_view);
+        let clear_stability_bind_group_a = static_state.create_clear_stability_bind_group(device,   &current_stability_view);
+        let clear_stability_bind_group_b = static_state.create_clear_stability_bind_group(device,   &next_stability_view);
+        let clear_restriction_bind_group = static_state.create_clear_restriction_bind_group(device, &restriction_view);
+
+        let initial_transform_bind_group  = static_state.create_initial_transform_bind_group(device, initial_state_view, &next_board_view);
+        let filter_restriction_bind_group = static_state.create_filter_restriction_bind_group(device, &next_board_view, &restriction_view, &current_board_view);
+
+        //With more than one tile, the previous board is read from the halo texture
+        let prev_board_view_a = halo_board_array_view.as_ref().unwrap_or(&current_board_array_view);
+        let prev_board_view_b = halo_board_array_view.as_ref().unwrap_or(&next_board_array_view);
+
+        let next_step_views_a = NextStepViews
+        {
+            current_board:     prev_board_view_a,
+            current_stability: &current_stability_array_view,
+            next_board:        &next_board_array_view,
+            next_stability:    &next_stability_array_view,
+            restriction:       &restriction_view
+        };
+
+        let next_step_views_b = NextStepViews
+        {
+            current_board:     prev_board_view_b,
+            current_stability: &next_stability_array_view,
+            next_board:        &current_board_array_view,
+            next_stability:    &current_stability_array_view,
+            restriction:       &restriction_view
+        };
+
+        let next_step_bind_group_a = static_state.create_next_step_bind_group(device, next_step_views_a, static_bindings.click_rule_buffer_binding());
+        let next_step_bind_group_b = static_state.create_next_step_bind_group(device, next_step_views_b, static_bindings.click_rule_buffer_binding());
+
+        let final_transform_bind_group_a = static_state.create_final_transform_bind_group(device, &current_stability_view, &final_state_mip_views[0], static_bindings.spawn_buffer_binding());
+        let final_transform_bind_group_b = static_state.create_final_transform_bind_group(device, &next_stability_view,    &final_state_mip_views[0], static_bindings.spawn_buffer_binding());
+
+        let board_image_bind_group_a = static_state.create_board_image_transform_bind_group(device, &current_board_view, &final_state_mip_views[0]);
+        let board_image_bind_group_b = static_state.create_board_image_transform_bind_group(device, &next_board_view,    &final_state_mip_views[0]);
+
+        let mut generate_mip_bind_groups = Vec::with_capacity(final_state_mips as usize - 1);
+        for i in 0..(final_state_mips - 1)
+        {
+            generate_mip_bind_groups.push(static_state.create_generate_mip_bind_group(device, &final_state_mip_views[i as usize], &final_state_mip_views[i as usize + 1]));
+        }
+
+        Self
+        {
+            tile_offset_x,
+            tile_offset_y,
+            tile_width,
+            tile_height,
+
+            tile_info_bind_group,
+            main_render_state_bind_group,
+            clear_default_bind_group,
+            initial_transform_bind_group,
+            filter_restriction_bind_group,
+            next_step_bind_group_a,
+            next_step_bind_group_b,
+            final_transform_bind_group_a,
+            final_transform_bind_group_b,
+            board_image_bind_group_a,
+            board_image_bind_group_b,
+            clear_stability_bind_group_a,
+            clear_stability_bind_group_b,
+            clear_restriction_bind_group,
+            generate_mip_bind_groups,
+
+            tile_info_buffer,
+
+            current_board,
+            next_board,
+            current_stability,
+            next_stability,
+
+            halo_board,
+
+            restriction,
+            final_state
+        }
+    }
+
+    fn thread_groups(&self, quads_per_group: u32) -> (u32, u32)
+    {
+        let thread_groups_x = std::cmp::max(self.tile_width  / quads_per_group, 1u32);
+        let thread_groups_y = std::cmp::max(self.tile_height / quads_per_group, 1u32);
+
+        (thread_groups_x, thread_groups_y)
+    }
 }
@@ -0,0 +1,459 @@
+//Linear algebra of the click rule over GF(2). A click toggles the cells (x - xi, y - yi), and one simulation step sums the same cells,
+//so a single operator describes both the Lights Out moves and the simulation on the board with the given size and restriction.
+//The kernel consists of quiet patterns: click sets that change nothing. Every solvable board has 2^kernel_dimension solutions
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use super::stafra_board_state_bindings::ImageData;
+use super::stafra_lights_out::{LightsOutChase, click_rule_offsets, gf2_bit_set, gf2_row_reduce};
+use super::stafra_gf2_polynomial::{Gf2Polynomial, berlekamp_massey};
+use super::stafra_cpu_state::xor_shifted_row;
+use super::stafra_state::{StandardResetBoardType, pcg_hash};
+
+//Only the first basis vectors of the kernel are kept, the basis can have as many patterns as the board has cells
+pub const MAX_QUIET_PATTERNS: usize = 64;
+
+//The minimal polynomial of each start needs a few times as many steps as there are enabled cells, so only small and medium boards are analyzed
+pub const MAX_UPDATE_ORDER_CELLS: u64 = 127 * 127;
+
+//The minimal polynomial of a random board misses each factor of the operator's one with a probability of at most 1/2
+const UPDATE_ORDER_RANDOM_STARTS: u32 = 16;
+
+//Each projection of the board sequence to a bit misses a factor with a probability of at most 1/2, the projections are repeated until the polynomial is verified
+const UPDATE_ORDER_PROJECTIONS: usize = 4;
+
+pub struct ClickRuleAnalysis
+{
+    pub board_size:      u32,
+    pub click_rule_data: [u8; 32 * 32],
+
+    pub enabled_cells:    u64, //The operator works on the enabled cells only
+    pub rank:             u64,
+    pub kernel_dimension: u64,
+
+    pub quiet_patterns: Vec<Vec<u8>> //1 for the clicked cells, one byte per cell
+}
+
+impl ClickRuleAnalysis
+{
+    //Clicked cells are white on black, the same as the board
+    pub fn quiet_pattern_image(&self, pattern_index: usize) -> Option<ImageData>
+    {
+        let quiet_pattern = self.quiet_patterns.get(pattern_index)?;
+
+        let mut pixel_data = Vec::with_capacity(quiet_pattern.len() * 4);
+        for click in quiet_pattern
+        {
+            let value = if *click != 0 {255} else {0};
+            pixel_data.extend_from_slice(&[value, value, value, 255]);
+        }
+
+        Some(ImageData
+        {
+            pixel_data,
+            image_width:  self.board_size,
+            image_height: self.board_size
+        })
+    }
+
+    //Cells are stored as [x, y] pairs, the click rule as the enabled offsets
+    pub fn to_json(&self) -> String
+    {
+        let click_rule_offsets = json_cell_list(click_rule_offsets(&self.click_rule_data).into_iter());
+
+        let board_size     = self.board_size as usize;
+        let quiet_patterns = self.quiet_patterns.iter().map(|quiet_pattern|
+        {
+            let clicked_cells = quiet_pattern.iter().enumerate().filter(|(_, click)| **click != 0).map(|(index, _)| ((index % board_size) as i64, (index / board_size) as i64));
+            format!("        [{}]", json_cell_list(clicked_cells))
+        }).collect::<Vec<String>>().join(",\n");
+
+        format!("{{\n    \"board_size\": {},\n    \"click_rule\": [{}],\n    \"enabled_cells\": {},\n    \"rank\": {},\n    \"kernel_dimension\": {},\n    \"quiet_patterns\":\n    [\n{}\n    ]\n}}\n",
+                self.board_size, click_rule_offsets, self.enabled_cells, self.rank, self.kernel_dimension, quiet_patterns)
+    }
+}
+
+fn json_cell_list(cells: impl Iterator<Item = (i64, i64)>) -> String
+{
+    cells.map(|(x, y)| format!("[{}, {}]", x, y)).collect::<Vec<String>>().join(", ")
+}
+
+//The click set is fully determined by the unknowns of the light chase, and the kernel is the set of unknowns for which the chase of an empty board ends with an empty board.
+//So the kernel dimension is the number of unknowns minus the rank of the chase equations
+pub fn analyze_click_rule(board_size: u32, restriction: &[u8], click_rule_data: &[u8; 32 * 32]) -> Result<ClickRuleAnalysis, String>
+{
+    let size = board_size as usize;
+    if restriction.len() != size * size
+    {
+        return Err(format!("Restriction data doesn't match the board size {}", board_size));
+    }
+
+    let enabled_cells = restriction.iter().filter(|restriction_cell| **restriction_cell != 0).count() as u64;
+
+    let mut analysis = ClickRuleAnalysis
+    {
+        board_size,
+        click_rule_data: *click_rule_data,
+
+        enabled_cells,
+        rank:             0,
+        kernel_dimension: enabled_cells,
+
+        quiet_patterns: Vec::new()
+    };
+
+    //Every click is quiet with an empty click rule
+    let chase = match LightsOutChase::new(board_size, restriction, click_rule_data)
+    {
+        Some(chase) => chase,
+        None =>
+        {
+            analysis.quiet_patterns = restriction.iter().enumerate().filter(|(_, restriction_cell)| **restriction_cell != 0).take(MAX_QUIET_PATTERNS).map(|(index, _)|
+            {
+                let mut quiet_pattern = vec![0u8; size * size];
+                quiet_pattern[index] = 1;
+                quiet_pattern
+            }).collect();
+
+            return Ok(analysis);
+        }
+    };
+
+    let empty_board   = vec![0u8; size * size];
+    let mut equations = chase.equations(&empty_board)?;
+
+    let pivot_unknowns = gf2_row_reduce(&mut equations, chase.unknown_count);
+
+    analysis.kernel_dimension = (chase.unknown_count - pivot_unknowns.len()) as u64;
+    analysis.rank             = enabled_cells - analysis.kernel_dimension;
+
+    //One basis vector for each unknown that isn't a pivot: the unknown itself, and the pivot unknowns that cancel it
+    let free_unknowns = (0..chase.unknown_count).filter(|unknown| pivot_unknowns.binary_search(unknown).is_err()).take(MAX_QUIET_PATTERNS);
+    for free_unknown in free_unknowns
+    {
+        let mut unknowns = vec![false; chase.unknown_count];
+        unknowns[free_unknown] = true;
+
+        for (row, pivot_unknown) in pivot_unknowns.iter().enumerate()
+        {
+            unknowns[*pivot_unknown] = gf2_bit_set(&equations[row], free_unknown);
+        }
+
+        let (quiet_pattern, cells) = chase.replay(empty_board.clone(), &unknowns);
+        if cells.iter().any(|cell| *cell != 0)
+        {
+            return Err("A quiet pattern changes the board".to_string());
+        }
+
+        analysis.quiet_patterns.push(quiet_pattern);
+    }
+
+    Ok(analysis)
+}
+
+//Every board eventually cycles: the board at frame transient + period is the same as at frame transient
+#[derive(Copy, Clone, PartialEq)]
+pub struct UpdatePeriod
+{
+    pub transient: u64,
+    pub period:    u128
+}
+
+//The spawn is ignored, it's the only part of the update that isn't linear
+pub struct UpdateOrderAnalysis
+{
+    pub board_size: u32,
+
+    //The operator's minimal polynomial and period are only probable, they can miss a factor that none of the sampled boards has
+    pub minimal_polynomial_degree: u64,
+
+    //The order of the update operator if the transient is 0. Every board repeats with a period dividing this one
+    pub operator_period: UpdatePeriod,
+
+    //Exact, the minimal polynomial of each start board is verified on the board
+    pub start_periods:   Vec<(StandardResetBoardType, UpdatePeriod)>
+}
+
+//The minimal polynomial x^a g(x), g(0) = 1, gives the transient a and the period equal to the order of g.
+//The minimal polynomial of a board comes from Berlekamp-Massey on bit projections of its sequence. The operator's one is the least common multiple over random boards, so it's exact only with a high probability.
+//Verifying it would take the sequences of all basis boards, too slow for the board sizes allowed here
+pub fn analyze_update_order(board_size: u32, restriction: &[u8], click_rule_data: &[u8; 32 * 32]) -> Result<UpdateOrderAnalysis, String>
+{
+    let size = board_size as usize;
+    if restriction.len() != size * size
+    {
+        return Err(format!("Restriction data doesn't match the board size {}", board_size));
+    }
+
+    let operator = UpdateOperator::new(board_size, restriction, click_rule_data);
+    if operator.enabled_cells > MAX_UPDATE_ORDER_CELLS
+    {
+        return Err(format!("The board has {} enabled cells, the period can only be computed for up to {}", operator.enabled_cells, MAX_UPDATE_ORDER_CELLS));
+    }
+
+    let mut operator_polynomial = Gf2Polynomial::one();
+    let mut start_periods       = Vec::new();
+
+    for reset_type in [StandardResetBoardType::Corners, StandardResetBoardType::Edges, StandardResetBoardType::Center]
+    {
+        let start_board      = operator.standard_start_board(reset_type);
+        let start_polynomial = operator.board_minimal_polynomial(&start_board, UPDATE_ORDER_RANDOM_STARTS + reset_type as u32);
+        start_periods.push((reset_type, polynomial_period(&start_polynomial)?));
+
+        operator_polynomial = operator_polynomial.lcm(&start_polynomial);
+    }
+
+    for start_index in 0..UPDATE_ORDER_RANDOM_STARTS
+    {
+        let random_board     = operator.random_board(pcg_hash(start_index));
+        let start_polynomial = operator.board_minimal_polynomial(&random_board, start_index);
+
+        operator_polynomial = operator_polynomial.lcm(&start_polynomial);
+    }
+
+    Ok(UpdateOrderAnalysis
+    {
+        board_size,
+
+        minimal_polynomial_degree: operator_polynomial.degree().unwrap_or(0) as u64,
+
+        operator_period: polynomial_period(&operator_polynomial)?,
+        start_periods
+    })
+}
+
+fn polynomial_period(minimal_polynomial: &Gf2Polynomial) -> Result<UpdatePeriod, String>
+{
+    let transient   = minimal_polynomial.trailing_zeros();
+    let cycle_part  = minimal_polynomial.div_rem(&Gf2Polynomial::monomial(transient)).0;
+
+    Ok(UpdatePeriod
+    {
+        transient: transient as u64,
+        period:    cycle_part.order()?
+    })
+}
+
+//The period of the board started with the standard reset, if the board repeats within max_frames frames.
+//Works for any board size, unlike analyze_update_order. The frames are compared by hashes, and a matching hash is confirmed by replaying the earlier frame
+pub fn find_start_period(board_size: u32, restriction: &[u8], click_rule_data: &[u8; 32 * 32], reset_type: StandardResetBoardType, max_frames: u32) -> Result<Option<UpdatePeriod>, String>
+{
+    let size = board_size as usize;
+    if restriction.len() != size * size
+    {
+        return Err(format!("Restriction data doesn't match the board size {}", board_size));
+    }
+
+    let operator    = UpdateOperator::new(board_size, restriction, click_rule_data);
+    let start_board = operator.standard_start_board(reset_type);
+
+    let mut frame_hashes: HashMap<u64, Vec<u64>> = HashMap::new();
+
+    let mut board      = start_board.clone();
+    let mut next_board = vec![0u64; board.len()];
+    for frame_number in 0..=max_frames as u64
+    {
+        let board_hash = hash_board(&board);
+
+        let earlier_frames = frame_hashes.entry(board_hash).or_default();
+        for earlier_frame in earlier_frames.iter()
+        {
+            let mut earlier_board = start_board.clone();
+            for _ in 0..*earlier_frame
+            {
+                operator.step(&earlier_board, &mut next_board);
+                std::mem::swap(&mut earlier_board, &mut next_board);
+            }
+
+            if earlier_board == board
+            {
+                return Ok(Some(UpdatePeriod {transient: *earlier_frame, period: (frame_number - earlier_frame) as u128}));
+            }
+        }
+
+        earlier_frames.push(frame_number);
+
+        operator.step(&board, &mut next_board);
+        std::mem::swap(&mut board, &mut next_board);
+    }
+
+    Ok(None)
+}
+
+fn hash_board(board: &[u64]) -> u64
+{
+    let mut hasher = DefaultHasher::new();
+    board.hash(&mut hasher);
+    hasher.finish()
+}
+
+//The same step as calc_next_rows, on rows of bits
+struct UpdateOperator
+{
+    board_size:    usize,
+    words_per_row: usize,
+    enabled_cells: u64,
+
+    restriction: Vec<u64>,
+    offsets:     Vec<(i64, i64)>
+}
+
+impl UpdateOperator
+{
+    fn new(board_size: u32, restriction: &[u8], click_rule_data: &[u8; 32 * 32]) -> Self
+    {
+        let size          = board_size as usize;
+        let words_per_row = (size + 63) / 64;
+
+        let mut restriction_rows = vec![0u64; words_per_row * size];
+        for (index, restriction_cell) in restriction.iter().enumerate()
+        {
+            if *restriction_cell != 0
+            {
+                let (x, y) = (index % size, index / size);
+                restriction_rows[y * words_per_row + x / 64] |= 1 << (x % 64);
+            }
+        }
+
+        Self
+        {
+            board_size:    size,
+            words_per_row,
+            enabled_cells: restriction.iter().filter(|restriction_cell| **restriction_cell != 0).count() as u64,
+
+            restriction: restriction_rows,
+            offsets:     click_rule_offsets(click_rule_data)
+        }
+    }
+
+    //The same cells as reset_board_standard
+    fn standard_start_board(&self, reset_type: StandardResetBoardType) -> Vec<u64>
+    {
+        let mut start_board = vec![0u64; self.words_per_row * self.board_size];
+        for (x, y) in reset_type.enabled_cells(self.board_size as u32)
+        {
+            start_board[y * self.words_per_row + x / 64] |= 1 << (x % 64);
+        }
+
+        for (board_word, restriction_word) in start_board.iter_mut().zip(self.restriction.iter())
+        {
+            *board_word &= restriction_word;
+        }
+
+        start_board
+    }
+
+    fn step(&self, board: &[u64], next_board: &mut [u64])
+    {
+        let size = self.board_size as i64;
+        for (y, next_row) in next_board.chunks_mut(self.words_per_row).enumerate()
+        {
+            //next_board[x, y] = SUM(prev_board[x + xi, y + yi]) mod 2 for all enabled (xi, yi)
+            next_row.fill(0);
+            for (offset_x, offset_y) in &self.offsets
+            {
+                let source_y = y as i64 + offset_y;
+                if source_y < 0 || source_y >= size
+                {
+                    continue;
+                }
+
+                let source_row_start = source_y as usize * self.words_per_row;
+                xor_shifted_row(next_row, &board[source_row_start..source_row_start + self.words_per_row], *offset_x as i32);
+            }
+
+            let row_start = y * self.words_per_row;
+            for (next_word, restriction_word) in next_row.iter_mut().zip(self.restriction[row_start..row_start + self.words_per_row].iter())
+            {
+                *next_word &= *restriction_word;
+            }
+        }
+    }
+
+    fn random_board(&self, seed: u32) -> Vec<u64>
+    {
+        let mut board = Vec::with_capacity(self.restriction.len());
+        for (word_index, restriction_word) in self.restriction.iter().enumerate()
+        {
+            let low_bits  = pcg_hash((word_index as u32).wrapping_mul(2).wrapping_add(seed)) as u64;
+            let high_bits = pcg_hash((word_index as u32).wrapping_mul(2).wrapping_add(1).wrapping_add(seed)) as u64;
+            board.push((low_bits | (high_bits << 32)) & restriction_word);
+        }
+
+        board
+    }
+
+    //The polynomial of the projections of the board sequence divides the board's minimal polynomial. The least common multiple of a few of them
+    //is checked by applying it to the board, and more projections are added until it turns the board to zero. A multiple of the minimal polynomial
+    //that divides it is the minimal polynomial itself, so the result is exact for this board
+    fn board_minimal_polynomial(&self, start_board: &[u64], seed: u32) -> Gf2Polynomial
+    {
+        let masked_board: Vec<u64> = start_board.iter().zip(self.restriction.iter()).map(|(board_word, restriction_word)| board_word & restriction_word).collect();
+
+        //The degree of the minimal polynomial is at most the number of enabled cells, and Berlekamp-Massey needs twice as many elements
+        let sequence_length = 2 * self.enabled_cells as usize + 2;
+
+        let mut minimal_polynomial = Gf2Polynomial::one();
+        let mut projection_round   = 0;
+        loop
+        {
+            let projections: Vec<Vec<u64>> = (0..UPDATE_ORDER_PROJECTIONS).map(|projection_index|
+            {
+                let projection_seed = pcg_hash(seed).wrapping_add(pcg_hash((projection_round * UPDATE_ORDER_PROJECTIONS + projection_index) as u32 + 1));
+                self.random_board(projection_seed)
+            }).collect();
+
+            let mut sequences = (0..UPDATE_ORDER_PROJECTIONS).map(|_| Vec::with_capacity(sequence_length)).collect::<Vec<Vec<bool>>>();
+
+            let mut board      = masked_board.clone();
+            let mut next_board = vec![0u64; board.len()];
+            for _ in 0..sequence_length
+            {
+                for (sequence, projection) in sequences.iter_mut().zip(projections.iter())
+                {
+                    let projected_bits: u32 = board.iter().zip(projection.iter()).map(|(board_word, projection_word)| (board_word & projection_word).count_ones()).sum();
+                    sequence.push(projected_bits % 2 != 0);
+                }
+
+                self.step(&board, &mut next_board);
+                std::mem::swap(&mut board, &mut next_board);
+            }
+
+            for sequence in &sequences
+            {
+                minimal_polynomial = minimal_polynomial.lcm(&berlekamp_massey(sequence));
+            }
+
+            if self.annihilates(&minimal_polynomial, &masked_board)
+            {
+                return minimal_polynomial;
+            }
+
+            projection_round += 1;
+        }
+    }
+
+    fn annihilates(&self, polynomial: &Gf2Polynomial, start_board: &[u64]) -> bool
+    {
+        let mut sum        = vec![0u64; start_board.len()];
+        let mut board      = start_board.to_vec();
+        let mut next_board = vec![0u64; start_board.len()];
+        for power in 0..=polynomial.degree().unwrap_or(0)
+        {
+            if polynomial.coefficient(power)
+            {
+                for (sum_word, board_word) in sum.iter_mut().zip(board.iter())
+                {
+                    *sum_word ^= *board_word;
+                }
+            }
+
+            self.step(&board, &mut next_board);
+            std::mem::swap(&mut board, &mut next_board);
+        }
+
+        sum.iter().all(|sum_word| *sum_word == 0)
+    }
+}
@@ -0,0 +1,159 @@
+//Editing tools for the click rule: painting cells together with their mirror images, and transforms of the whole rule.
+//All of them work around the center cell (15, 15), the same one bake_click_rule.wgsl uses. The cells moved outside of the 32x32 grid are lost
+
+use super::stafra_state::{RandomBoardSymmetry, RandomClickRuleParams, MAX_RANDOM_CLICK_RULE_RADIUS};
+
+const CLICK_RULE_SIZE: i32 = 32;
+const CLICK_RULE_CENTER: i32 = (CLICK_RULE_SIZE - 1) / 2;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ClickRuleTransform
+{
+    RotateClockwise,
+    FlipX,
+    FlipY,
+    Invert, //Only the cells inside the current radius, so the rule doesn't grow to the whole grid
+    Shift {offset_x: i32, offset_y: i32},
+    Clear,
+    Randomize {seed: u32, symmetry: RandomBoardSymmetry} //Random cells inside the current radius, the center cell is kept
+}
+
+//Sets the cell at (x, y) and the cells symmetric to it. Returns false if nothing changed
+pub fn paint_click_rule_cell(click_rule_data: &mut [u8; 32 * 32], x: u32, y: u32, value: u8, symmetry: RandomBoardSymmetry) -> bool
+{
+    let offset_x = x as i32 - CLICK_RULE_CENTER;
+    let offset_y = y as i32 - CLICK_RULE_CENTER;
+
+    let mut symmetric_offsets = vec![(offset_x, offset_y)];
+    match symmetry
+    {
+        RandomBoardSymmetry::None => {}
+
+        RandomBoardSymmetry::MirrorX =>
+        {
+            symmetric_offsets.push((-offset_x, offset_y));
+        }
+
+        RandomBoardSymmetry::FourFold =>
+        {
+            symmetric_offsets.extend_from_slice(&[(-offset_x, offset_y), (offset_x, -offset_y), (-offset_x, -offset_y)]);
+        }
+
+        RandomBoardSymmetry::EightFold =>
+        {
+            symmetric_offsets.extend_from_slice(&[(-offset_x, offset_y), (offset_x, -offset_y), (-offset_x, -offset_y)]);
+            symmetric_offsets.extend_from_slice(&[(offset_y, offset_x), (-offset_y, offset_x), (offset_y, -offset_x), (-offset_y, -offset_x)]);
+        }
+    }
+
+    let mut cell_changed = false;
+    for (symmetric_offset_x, symmetric_offset_y) in symmetric_offsets
+    {
+        if let Some(cell_index) = offset_cell_index(symmetric_offset_x, symmetric_offset_y)
+        {
+            cell_changed = cell_changed || click_rule_data[cell_index] != value;
+            click_rule_data[cell_index] = value;
+        }
+    }
+
+    cell_changed
+}
+
+pub fn transform_click_rule(click_rule_data: &[u8; 32 * 32], transform: ClickRuleTransform) -> [u8; 32 * 32]
+{
+    let radius = click_rule_radius(click_rule_data);
+
+    let mut transformed_data = [0u8; 32 * 32];
+    match transform
+    {
+        ClickRuleTransform::Invert =>
+        {
+            transformed_data = *click_rule_data;
+            for offset_y in -radius..=radius
+            {
+                for offset_x in -radius..=radius
+                {
+                    let cell_index = offset_cell_index(offset_x, offset_y).unwrap();
+                    transformed_data[cell_index] = (click_rule_data[cell_index] == 0) as u8;
+                }
+            }
+        }
+
+        ClickRuleTransform::Clear => {}
+
+        ClickRuleTransform::Randomize {seed, symmetry} =>
+        {
+            let center_index = offset_cell_index(0, 0).unwrap();
+            transformed_data = RandomClickRuleParams
+            {
+                seed,
+                radius:  radius as u32,
+                density: 0.5,
+                symmetry,
+                center:  click_rule_data[center_index] != 0
+            }.click_rule_data();
+        }
+
+        _ =>
+        {
+            for (cell_index, cell) in click_rule_data.iter().enumerate()
+            {
+                if *cell == 0
+                {
+                    continue;
+                }
+
+                let offset_x = cell_index as i32 % CLICK_RULE_SIZE - CLICK_RULE_CENTER;
+                let offset_y = cell_index as i32 / CLICK_RULE_SIZE - CLICK_RULE_CENTER;
+
+                let (new_offset_x, new_offset_y) = match transform
+                {
+                    ClickRuleTransform::RotateClockwise => (-offset_y, offset_x),
+                    ClickRuleTransform::FlipX           => (-offset_x, offset_y),
+                    ClickRuleTransform::FlipY           => (offset_x, -offset_y),
+                    ClickRuleTransform::Shift {offset_x: shift_x, offset_y: shift_y} =>
+                    {
+                        (offset_x + shift_x, offset_y + shift_y)
+                    }
+
+                    _ => (offset_x, offset_y)
+                };
+
+                if let Some(new_cell_index) = offset_cell_index(new_offset_x, new_offset_y)
+                {
+                    transformed_data[new_cell_index] = 1;
+                }
+            }
+        }
+    }
+
+    transformed_data
+}
+
+//The largest offset of an enabled cell, at least 1 and at most the largest random click rule radius
+fn click_rule_radius(click_rule_data: &[u8; 32 * 32]) -> i32
+{
+    let mut radius = 1;
+    for (cell_index, cell) in click_rule_data.iter().enumerate()
+    {
+        if *cell != 0
+        {
+            let offset_x = cell_index as i32 % CLICK_RULE_SIZE - CLICK_RULE_CENTER;
+            let offset_y = cell_index as i32 / CLICK_RULE_SIZE - CLICK_RULE_CENTER;
+            radius = std::cmp::max(radius, std::cmp::max(offset_x.abs(), offset_y.abs()));
+        }
+    }
+
+    std::cmp::min(radius, MAX_RANDOM_CLICK_RULE_RADIUS as i32)
+}
+
+fn offset_cell_index(offset_x: i32, offset_y: i32) -> Option<usize>
+{
+    let (x, y) = (CLICK_RULE_CENTER + offset_x, CLICK_RULE_CENTER + offset_y);
+    if x < 0 || y < 0 || x >= CLICK_RULE_SIZE || y >= CLICK_RULE_SIZE
+    {
+        return None;
+    }
+
+    Some((y * CLICK_RULE_SIZE + x) as usize)
+}
@@ -0,0 +1,1332 @@
+use std::cmp::min;
+
+use super::stafra_state::{StandardResetBoardType, ResetBoardType, RandomBoardParams, PatternBoardParams, BatchParams};
+use super::simulation_backend::{SimulationBackend, ImageCallback, SessionCallback, BatchCallback};
+use super::stafra_board_state_bindings::ImageData;
+use super::stafra_profiler::{PassProfiler, ProfilerReport};
+use super::stafra_session::StafraSession;
+use super::stafra_expression::BoardExpression;
+use super::stafra_image_import::{ImageImportOptions, import_board_size, import_image};
+use super::stafra_history::{FrameHistory, DEFAULT_HISTORY_FRAMES, DEFAULT_KEYFRAME_PERIOD};
+use super::stafra_paint::{PaintStroke, PaintTarget, PaintTool};
+use super::stafra_lights_out::{LightsOutMove, LightsOutStatus};
+
+#[cfg(target_arch = "wasm32")]
+use
+{
+    wasm_bindgen::{JsCast, JsValue, Clamped}
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use
+{
+    std::sync::Arc,
+    winit::window::Window
+};
+
+//Bigger boards are too slow to simulate on the CPU
+const MAX_CPU_BOARD_SIZE: u32 = 8191;
+
+const VIDEO_FRAME_SIZE: u32 = 1024;
+
+//An image kept to re-apply it after resizing the board
+struct SourceImage
+{
+    pixel_data: Vec<u8>,
+    width:      u32,
+    height:     u32
+}
+
+//The board and click rule shared by all threads of one step. Rows are stored as bits, words_per_row u64 words per row
+#[derive(Clone, Copy)]
+struct StepSource<'a>
+{
+    board:              &'a [u64],
+    restriction:        &'a [u64],
+    click_rule_offsets: &'a [(i32, i32)],
+    board_size:         usize,
+    words_per_row:      usize
+}
+
+//Board and stability of a past frame
+struct CpuSnapshot
+{
+    board:     Vec<u64>,
+    stability: Vec<u8>
+}
+
+//CPU implementation of the simulation, used when there's no GPU adapter.
+//The board and the restriction are stored as rows of bits, 64 cells per word. Rows are split between threads
+pub struct StafraCpuState
+{
+    board_size:    u32,
+    words_per_row: usize,
+    frame_number:  u32,
+
+    board:       Vec<u64>,
+    next_board:  Vec<u64>,
+    restriction: Vec<u64>,
+    stability:   Vec<u8>,
+
+    click_rule_data:    [u8; 32 * 32],
+    click_rule_offsets: Vec<(i32, i32)>,
+
+    spawn_period:             u32,
+    smooth_transform_enabled: bool,
+
+    click_rule_grid_enabled: bool,
+
+    profiler: PassProfiler,
+
+    history: FrameHistory<CpuSnapshot>,
+
+    last_reset_type:   ResetBoardType,
+    initial_image:     Option<SourceImage>,
+    restriction_image: Option<SourceImage>,
+    board_expression:  Option<BoardExpression>,
+
+    image_import_options: ImageImportOptions,
+
+    //Strokes painted on top of the last reset, replayed every time the board is reset unchanged
+    board_strokes: Vec<PaintStroke>,
+
+    //The board is displayed instead of the stability. The moves are kept until the board changes in any other way
+    lights_out_enabled: bool,
+    lights_out_moves:   Vec<LightsOutMove>,
+
+    #[cfg(target_arch = "wasm32")]
+    main_context: Option<web_sys::CanvasRenderingContext2d>,
+    #[cfg(target_arch = "wasm32")]
+    click_rule_context: Option<web_sys::CanvasRenderingContext2d>,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    main_context: Option<softbuffer::GraphicsContext<Arc<Window>>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    click_rule_context: Option<softbuffer::GraphicsContext<Arc<Window>>>
+}
+
+impl StafraCpuState
+{
+    //Headless, render() draws nothing
+    pub fn new(width: u32, height: u32) -> Self
+    {
+        let board_size = Self::cropped_board_size(width, height);
+
+        let mut cpu_state = Self
+        {
+            board_size:    0,
+            words_per_row: 0,
+            frame_number:  0,
+
+            board:       Vec::new(),
+            next_board:  Vec::new(),
+            restriction: Vec::new(),
+            stability:   Vec::new(),
+
+            click_rule_data:    [0u8; 32 * 32],
+            click_rule_offsets: Vec::new(),
+
+            spawn_period:             u32::MAX,
+            smooth_transform_enabled: false,
+
+            click_rule_grid_enabled: false,
+
+            profiler: PassProfiler::new_cpu(),
+
+            history: FrameHistory::new(DEFAULT_HISTORY_FRAMES, DEFAULT_KEYFRAME_PERIOD),
+
+            last_reset_type:   ResetBoardType::Standard{reset_type: StandardResetBoardType::Corners},
+            initial_image:     None,
+            restriction_image: None,
+            board_expression:  None,
+
+            image_import_options: ImageImportOptions::default(),
+
+            board_strokes: Vec::new(),
+
+            lights_out_enabled: false,
+            lights_out_moves:   Vec::new(),
+
+            main_context:       None,
+            click_rule_context: None
+        };
+
+        cpu_state.allocate_board(board_size);
+        cpu_state.clear_restriction_impl();
+        cpu_state
+    }
+
+    //Draws into 2D contexts of the canvases. The canvases are replaced with fresh ones if they already have a different context
+    #[cfg(target_arch = "wasm32")]
+    pub fn new_web(main_canvas: &web_sys::HtmlCanvasElement, click_rule_canvas: &web_sys::HtmlCanvasElement, width: u32, height: u32) -> Self
+    {
+        let mut cpu_state = Self::new(width, height);
+        cpu_state.main_context       = Self::create_canvas_context(main_canvas);
+        cpu_state.click_rule_context = Self::create_canvas_context(click_rule_canvas);
+        cpu_state
+    }
+
+    //Draws into the windows with softbuffer. A window that softbuffer can't draw into stays blank
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new_native(main_window: Arc<Window>, click_rule_window: Arc<Window>, width: u32, height: u32) -> Self
+    {
+        let mut cpu_state = Self::new(width, height);
+        cpu_state.main_context       = Self::create_window_context(main_window);
+        cpu_state.click_rule_context = Self::create_window_context(click_rule_window);
+        cpu_state
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn create_window_context(window: Arc<Window>) -> Option<softbuffer::GraphicsContext<Arc<Window>>>
+    {
+        //The context owns a reference to the window, so the window outlives it
+        match unsafe {softbuffer::GraphicsContext::new(window)}
+        {
+            Ok(context) => Some(context),
+            Err(error) =>
+            {
+                log::error!("Cannot draw into the window without a GPU adapter: {}", error);
+                None
+            }
+        }
+    }
+
+    //Shows the RGBA image in the window. The size is limited by softbuffer
+    #[cfg(not(target_arch = "wasm32"))]
+    fn present_native(context: &mut softbuffer::GraphicsContext<Arc<Window>>, image_data: &[u8], image_width: u16, image_height: u16)
+    {
+        let pixel_data: Vec<u32> = image_data.chunks(4).map(|pixel| ((pixel[0] as u32) << 16) | ((pixel[1] as u32) << 8) | (pixel[2] as u32)).collect();
+        context.set_buffer(&pixel_data, image_width, image_height);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn window_image_size(context: &softbuffer::GraphicsContext<Arc<Window>>) -> (u16, u16)
+    {
+        let window_size = context.window().inner_size();
+        (min(window_size.width, u16::MAX as u32) as u16, min(window_size.height, u16::MAX as u32) as u16)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn create_canvas_context(canvas: &web_sys::HtmlCanvasElement) -> Option<web_sys::CanvasRenderingContext2d>
+    {
+        if let Ok(Some(context)) = canvas.get_context("2d")
+        {
+            return context.dyn_into::<web_sys::CanvasRenderingContext2d>().ok();
+        }
+
+        let new_canvas = canvas.clone_node().ok()?.dyn_into::<web_sys::HtmlCanvasElement>().ok()?;
+        canvas.replace_with_with_node_1(&new_canvas).ok()?;
+
+        new_canvas.get_context("2d").ok()??.dyn_into::<web_sys::CanvasRenderingContext2d>().ok()
+    }
+
+    fn cropped_board_size(width: u32, height: u32) -> u32
+    {
+        //Crop to the largest possible square with sides of 2^n - 1
+        (min(min(width, height), MAX_CPU_BOARD_SIZE) + 2).next_power_of_two() / 2 - 1
+    }
+
+    fn allocate_board(&mut self, board_size: u32)
+    {
+        self.board_size    = board_size;
+        self.words_per_row = ((board_size + 63) / 64) as usize;
+
+        let board_words = self.words_per_row * board_size as usize;
+        self.board       = vec![0u64; board_words];
+        self.next_board  = vec![0u64; board_words];
+        self.restriction = vec![0u64; board_words];
+        self.stability   = vec![0u8;  (board_size * board_size) as usize];
+    }
+
+    fn set_cell(row: &mut [u64], x: usize)
+    {
+        row[x / 64] |= 1 << (x % 64);
+    }
+
+    fn cell_enabled(&self, rows: &[u64], x: usize, y: usize) -> bool
+    {
+        (rows[y * self.words_per_row + x / 64] >> (x % 64)) & 1 != 0
+    }
+
+    //Bit rows to one byte per cell
+    fn unpack_rows(&self, rows: &[u64]) -> Vec<u8>
+    {
+        let board_size = self.board_size as usize;
+
+        let mut cells = Vec::with_capacity(board_size * board_size);
+        for row in rows.chunks(self.words_per_row)
+        {
+            cells.extend((0..board_size).map(|x| ((row[x / 64] >> (x % 64)) & 1) as u8));
+        }
+
+        cells
+    }
+
+    //One byte per cell to bit rows
+    fn pack_rows(&self, cells: &[u8]) -> Vec<u64>
+    {
+        let board_size = self.board_size as usize;
+
+        let mut rows = vec![0u64; self.words_per_row * board_size];
+        for (row, row_cells) in rows.chunks_mut(self.words_per_row).zip(cells.chunks(board_size))
+        {
+            for (x, cell) in row_cells.iter().enumerate()
+            {
+                if *cell != 0
+                {
+                    Self::set_cell(row, x);
+                }
+            }
+        }
+
+        rows
+    }
+
+    fn image_luma_above_threshold(image: &SourceImage, x: i32, y: i32) -> Option<bool>
+    {
+        if x < 0 || y < 0 || x >= image.width as i32 || y >= image.height as i32
+        {
+            return None;
+        }
+
+        let texel_start = ((y as usize) * image.width as usize + x as usize) * 4;
+        let texel       = &image.pixel_data[texel_start..texel_start + 4];
+
+        let luma = (0.2126 * texel[0] as f32 + 0.7152 * texel[1] as f32 + 0.0722 * texel[2] as f32) / 255.0;
+        Some(luma > 0.15)
+    }
+
+    fn clear_restriction_impl(&mut self)
+    {
+        let board_size = self.board_size as usize;
+        for row in self.restriction.chunks_mut(self.words_per_row)
+        {
+            row.fill(0);
+            for x in 0..board_size
+            {
+                Self::set_cell(row, x);
+            }
+        }
+    }
+
+    fn apply_restriction_image(&mut self)
+    {
+        let restriction_image = match &self.restriction_image
+        {
+            Some(image) => image,
+            None        => return
+        };
+
+        //Centered, the same way as in initial_restriction_transform.wgsl. Everything outside the image is unrestricted
+        let board_size = self.board_size as i32;
+        let offset_x   = (restriction_image.width  as i32 - board_size) / 2;
+        let offset_y   = (restriction_image.height as i32 - board_size) / 2;
+
+        for (y, row) in self.restriction.chunks_mut(self.words_per_row).enumerate()
+        {
+            row.fill(0);
+            for x in 0..board_size
+            {
+                if Self::image_luma_above_threshold(restriction_image, x + offset_x, y as i32 + offset_y).unwrap_or(true)
+                {
+                    Self::set_cell(row, x as usize);
+                }
+            }
+        }
+    }
+
+    fn reset_board_standard_impl(&mut self, reset_type: StandardResetBoardType)
+    {
+        self.board.fill(0);
+
+        for (x, y) in reset_type.enabled_cells(self.board_size)
+        {
+            let row_start = y * self.words_per_row;
+            Self::set_cell(&mut self.board[row_start..row_start + self.words_per_row], x);
+        }
+
+        self.finish_reset();
+        self.last_reset_type = ResetBoardType::Standard {reset_type};
+    }
+
+    fn reset_board_random_impl(&mut self, params: RandomBoardParams)
+    {
+        self.board.fill(0);
+
+        let board_size = self.board_size;
+        for (y, row) in self.board.chunks_mut(self.words_per_row).enumerate()
+        {
+            for x in 0..board_size
+            {
+                if params.cell_enabled(board_size, x, y as u32)
+                {
+                    Self::set_cell(row, x as usize);
+                }
+            }
+        }
+
+        self.finish_reset();
+        self.last_reset_type = ResetBoardType::Random {params};
+    }
+
+    fn reset_board_pattern_impl(&mut self, params: PatternBoardParams)
+    {
+        self.board.fill(0);
+
+        let board_size = self.board_size;
+        for (y, row) in self.board.chunks_mut(self.words_per_row).enumerate()
+        {
+            for x in 0..board_size
+            {
+                if params.cell_enabled(board_size, x, y as u32)
+                {
+                    Self::set_cell(row, x as usize);
+                }
+            }
+        }
+
+        self.finish_reset();
+        self.last_reset_type = ResetBoardType::Pattern {params};
+    }
+
+    fn reset_board_expression_impl(&mut self)
+    {
+        self.board.fill(0);
+
+        if let Some(board_expression) = &self.board_expression
+        {
+            let board_size = self.board_size;
+            for (y, row) in self.board.chunks_mut(self.words_per_row).enumerate()
+            {
+                for x in 0..board_size
+                {
+                    if board_expression.cell_enabled(board_size, x, y as u32)
+                    {
+                        Self::set_cell(row, x as usize);
+                    }
+                }
+            }
+        }
+
+        self.finish_reset();
+        self.last_reset_type = ResetBoardType::Expression;
+    }
+
+    fn reset_board_custom_impl(&mut self)
+    {
+        self.board.fill(0);
+
+        if let Some(initial_image) = &self.initial_image
+        {
+            //The image is already placed on the board with the size of the board
+            for (y, row) in self.board.chunks_mut(self.words_per_row).enumerate()
+            {
+                for x in 0..self.board_size as usize
+                {
+                    if Self::image_luma_above_threshold(initial_image, x as i32, y as i32).unwrap_or(false)
+                    {
+                        Self::set_cell(row, x);
+                    }
+                }
+            }
+        }
+
+        self.finish_reset();
+        self.last_reset_type = ResetBoardType::Custom;
+    }
+
+    fn finish_reset(&mut self)
+    {
+        for stroke in &self.board_strokes
+        {
+            paint_rows(&mut self.board, self.board_size as usize, self.words_per_row, stroke);
+        }
+
+        for (board_word, restriction_word) in self.board.iter_mut().zip(self.restriction.iter())
+        {
+            *board_word &= *restriction_word;
+        }
+
+        self.stability.fill(0);
+        self.frame_number = 0;
+        self.lights_out_moves.clear();
+
+        self.restart_history();
+    }
+
+    //The same cells as in lights_out_click.wgsl. Toggling them again undoes the move
+    fn lights_out_toggle(&mut self, lights_out_move: LightsOutMove)
+    {
+        let board_size = self.board_size as i32;
+        for &(offset_x, offset_y) in &self.click_rule_offsets
+        {
+            let x = lights_out_move.x as i32 - offset_x;
+            let y = lights_out_move.y as i32 - offset_y;
+            if x < 0 || y < 0 || x >= board_size || y >= board_size
+            {
+                continue;
+            }
+
+            let word_index = y as usize * self.words_per_row + x as usize / 64;
+            self.board[word_index] ^= self.restriction[word_index] & (1 << (x % 64));
+        }
+
+        self.stability.fill(0);
+        self.restart_history();
+    }
+
+    fn snapshot_bytes(&self) -> u64
+    {
+        (self.board.len() * std::mem::size_of::<u64>() + self.stability.len()) as u64
+    }
+
+    //Drops the history and starts it again from the current frame
+    fn restart_history(&mut self)
+    {
+        self.history.reset(self.snapshot_bytes());
+        self.record_history();
+    }
+
+    fn record_history(&mut self)
+    {
+        let board     = &self.board;
+        let stability = &self.stability;
+
+        self.history.record(self.frame_number, |reused_snapshot|
+        {
+            match reused_snapshot
+            {
+                Some(mut snapshot) =>
+                {
+                    snapshot.board.copy_from_slice(board);
+                    snapshot.stability.copy_from_slice(stability);
+                    snapshot
+                }
+
+                None => CpuSnapshot{board: board.clone(), stability: stability.clone()}
+            }
+        });
+    }
+
+    fn thread_count() -> usize
+    {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            std::thread::available_parallelism().map(|count| count.get()).unwrap_or(1)
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            1
+        }
+    }
+
+    fn calc_next_frame(&mut self)
+    {
+        //Same as next_step.wgsl, an empty click rule doesn't change anything
+        if self.click_rule_offsets.is_empty()
+        {
+            return;
+        }
+
+        let board_size     = self.board_size as usize;
+        let words_per_row  = self.words_per_row;
+        let rows_per_chunk = (board_size + Self::thread_count() - 1) / Self::thread_count();
+
+        let step_source = StepSource
+        {
+            board:              &self.board,
+            restriction:        &self.restriction,
+            click_rule_offsets: &self.click_rule_offsets,
+            board_size,
+            words_per_row
+        };
+
+        let next_board_chunks = self.next_board.chunks_mut(rows_per_chunk * words_per_row);
+        let stability_chunks  = self.stability.chunks_mut(rows_per_chunk * board_size);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::scope(|scope|
+        {
+            for (chunk_index, (next_board_chunk, stability_chunk)) in next_board_chunks.zip(stability_chunks).enumerate()
+            {
+                scope.spawn(move ||
+                {
+                    calc_next_rows(step_source, chunk_index * rows_per_chunk, next_board_chunk, stability_chunk);
+                });
+            }
+        });
+
+        #[cfg(target_arch = "wasm32")]
+        for (chunk_index, (next_board_chunk, stability_chunk)) in next_board_chunks.zip(stability_chunks).enumerate()
+        {
+            calc_next_rows(step_source, chunk_index * rows_per_chunk, next_board_chunk, stability_chunk);
+        }
+
+        std::mem::swap(&mut self.board, &mut self.next_board);
+        self.frame_number += 1;
+
+        self.record_history();
+    }
+
+    //The same transform as final_state_transform.wgsl
+    fn final_cell_value(&self, stability: u8) -> u8
+    {
+        let clamped_stability = min((stability as u32).wrapping_sub(1), self.spawn_period);
+        if !self.smooth_transform_enabled
+        {
+            if clamped_stability == self.spawn_period {255} else {0}
+        }
+        else
+        {
+            (clamped_stability as f32 / self.spawn_period as f32 * 255.0).round() as u8
+        }
+    }
+
+    //Nearest-neighbor scaled image of the board. The channel order is either RGBA or BGRA, which for the magenta color is the same
+    fn scaled_final_image(&self, image_width: u32, image_height: u32) -> Vec<u8>
+    {
+        let board_size = self.board_size as usize;
+
+        let mut image_data = vec![0u8; (image_width * image_height * 4) as usize];
+        for (pixel_index, pixel) in image_data.chunks_mut(4).enumerate()
+        {
+            let pixel_x = pixel_index % image_width as usize;
+            let pixel_y = pixel_index / image_width as usize;
+
+            let cell_x = pixel_x * board_size / image_width as usize;
+            let cell_y = pixel_y * board_size / image_height as usize;
+
+            let value = if self.lights_out_enabled
+            {
+                if self.cell_enabled(&self.board, cell_x, cell_y) {255} else {0}
+            }
+            else
+            {
+                self.final_cell_value(self.stability[cell_y * board_size + cell_x])
+            };
+
+            pixel.copy_from_slice(&[value, 0, value, 255]);
+        }
+
+        image_data
+    }
+
+    //RGBA image of the click rule with the same colors as draw_click_rule
+    #[cfg(not(target_arch = "wasm32"))]
+    fn scaled_click_rule_image(&self, image_width: u32, image_height: u32) -> Vec<u8>
+    {
+        let click_rule_size = 32;
+
+        let mut image_data = vec![0u8; (image_width * image_height * 4) as usize];
+        for (pixel_index, pixel) in image_data.chunks_mut(4).enumerate()
+        {
+            let pixel_x = pixel_index % image_width as usize;
+            let pixel_y = pixel_index / image_width as usize;
+
+            let cell_x = pixel_x * click_rule_size / image_width as usize;
+            let cell_y = pixel_y * click_rule_size / image_height as usize;
+
+            //Grid lines are drawn on the first pixel of each cell
+            let on_grid_line = cell_x * image_width as usize / click_rule_size == pixel_x || cell_y * image_height as usize / click_rule_size == pixel_y;
+
+            let value = if self.click_rule_grid_enabled && on_grid_line
+            {
+                128
+            }
+            else if self.click_rule_data[cell_y * click_rule_size + cell_x] != 0
+            {
+                255
+            }
+            else
+            {
+                0
+            };
+
+            pixel.copy_from_slice(&[value, value, value, 255]);
+        }
+
+        image_data
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn draw_click_rule(&self, click_rule_context: &web_sys::CanvasRenderingContext2d)
+    {
+        let click_rule_canvas = match click_rule_context.canvas()
+        {
+            Some(canvas) => canvas,
+            None         => return
+        };
+
+        let click_rule_size = 32;
+        let cell_width  = click_rule_canvas.width()  as f64 / click_rule_size as f64;
+        let cell_height = click_rule_canvas.height() as f64 / click_rule_size as f64;
+
+        for y in 0..click_rule_size
+        {
+            for x in 0..click_rule_size
+            {
+                let cell_color = if self.click_rule_data[y * click_rule_size + x] != 0 {"#ffffff"} else {"#000000"};
+                click_rule_context.set_fill_style(&JsValue::from_str(cell_color));
+                click_rule_context.fill_rect(x as f64 * cell_width, y as f64 * cell_height, cell_width, cell_height);
+
+                if self.click_rule_grid_enabled
+                {
+                    click_rule_context.set_stroke_style(&JsValue::from_str("#808080"));
+                    click_rule_context.stroke_rect(x as f64 * cell_width, y as f64 * cell_height, cell_width, cell_height);
+                }
+            }
+        }
+    }
+}
+
+//Calculates the rows of the next board starting from first_row, and updates their stability
+fn calc_next_rows(step_source: StepSource, first_row: usize, next_rows: &mut [u64], stability_rows: &mut [u8])
+{
+    let StepSource{board, restriction, click_rule_offsets, board_size, words_per_row} = step_source;
+
+    for (row_index, next_row) in next_rows.chunks_mut(words_per_row).enumerate()
+    {
+        let y = first_row + row_index;
+
+        //next_board[x, y] = SUM(prev_board[x + xi, y + yi]) mod 2 for all enabled (xi, yi)
+        next_row.fill(0);
+        for &(offset_x, offset_y) in click_rule_offsets
+        {
+            let source_y = y as i32 + offset_y;
+            if source_y < 0 || source_y >= board_size as i32
+            {
+                continue;
+            }
+
+            let source_row_start = source_y as usize * words_per_row;
+            xor_shifted_row(next_row, &board[source_row_start..source_row_start + words_per_row], offset_x);
+        }
+
+        let row_start = y * words_per_row;
+        for (next_word, restriction_word) in next_row.iter_mut().zip(restriction[row_start..row_start + words_per_row].iter())
+        {
+            *next_word &= *restriction_word;
+        }
+
+        //0 for "stable", 1 for "unstable", 2 for "unstable for 1 frame" and so on
+        let prev_row      = &board[row_start..row_start + words_per_row];
+        let stability_row = &mut stability_rows[row_index * board_size..(row_index + 1) * board_size];
+        for (word_index, (prev_word, next_word)) in prev_row.iter().zip(next_row.iter()).enumerate()
+        {
+            let changed_word = prev_word ^ next_word;
+
+            let word_start = word_index * 64;
+            let word_end   = min(word_start + 64, board_size);
+            for (bit_index, stability) in stability_row[word_start..word_end].iter_mut().enumerate()
+            {
+                if (changed_word >> bit_index) & 1 != 0
+                {
+                    *stability = 1;
+                }
+                else if *stability != 0
+                {
+                    *stability = stability.saturating_add(1);
+                }
+            }
+        }
+    }
+}
+
+//Draws the stroke into the bit rows. Flood fill is the same as PaintStroke::flood_fill_cells, which the GPU state uses
+fn paint_rows(rows: &mut [u64], board_size: usize, words_per_row: usize, stroke: &PaintStroke)
+{
+    let (min_x, min_y, max_x, max_y) = match stroke.cell_bounds(board_size as u32)
+    {
+        Some(cell_bounds) => cell_bounds,
+        None              => return
+    };
+
+    let get_cell = |rows: &[u64], x: usize, y: usize| (rows[y * words_per_row + x / 64] >> (x % 64)) & 1 != 0;
+    let set_cell = |rows: &mut [u64], x: usize, y: usize, enabled: bool|
+    {
+        let word = &mut rows[y * words_per_row + x / 64];
+        if enabled {*word |= 1 << (x % 64);} else {*word &= !(1 << (x % 64));}
+    };
+
+    if stroke.tool != PaintTool::Fill
+    {
+        for y in min_y as usize..=max_y as usize
+        {
+            for x in min_x as usize..=max_x as usize
+            {
+                if stroke.cell_covered(x as i32, y as i32)
+                {
+                    set_cell(rows, x, y, stroke.enabled);
+                }
+            }
+        }
+
+        return;
+    }
+
+    //4-connected flood fill from the start cell
+    let seed_x     = stroke.start_x as usize;
+    let seed_y     = stroke.start_y as usize;
+    let seed_value = get_cell(rows, seed_x, seed_y);
+    if seed_value == stroke.enabled
+    {
+        return;
+    }
+
+    let mut pending_cells = vec![(seed_x, seed_y)];
+    set_cell(rows, seed_x, seed_y, stroke.enabled);
+    while let Some((x, y)) = pending_cells.pop()
+    {
+        let neighbors = [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)];
+        for (neighbor_x, neighbor_y) in neighbors
+        {
+            if neighbor_x < board_size && neighbor_y < board_size && get_cell(rows, neighbor_x, neighbor_y) == seed_value
+            {
+                set_cell(rows, neighbor_x, neighbor_y, stroke.enabled);
+                pending_cells.push((neighbor_x, neighbor_y));
+            }
+        }
+    }
+}
+
+//dest[x] ^= source[x + shift] for each bit x. Bits outside of the source row are treated as 0
+pub fn xor_shifted_row(dest: &mut [u64], source: &[u64], shift: i32)
+{
+    let word_shift = shift.div_euclid(64) as isize;
+    let bit_shift  = shift.rem_euclid(64) as u32;
+
+    let source_word = |index: isize| -> u64
+    {
+        if index < 0 || index >= source.len() as isize {0} else {source[index as usize]}
+    };
+
+    for (dest_index, dest_word) in dest.iter_mut().enumerate()
+    {
+        let low_index = dest_index as isize + word_shift;
+        if bit_shift == 0
+        {
+            *dest_word ^= source_word(low_index);
+        }
+        else
+        {
+            *dest_word ^= (source_word(low_index) >> bit_shift) | (source_word(low_index + 1) << (64 - bit_shift));
+        }
+    }
+}
+
+impl SimulationBackend for StafraCpuState
+{
+    fn frame_number(&self) -> u32
+    {
+        self.frame_number
+    }
+
+    fn board_size(&self) -> u32
+    {
+        self.board_size
+    }
+
+    fn max_board_size(&self) -> u32
+    {
+        MAX_CPU_BOARD_SIZE
+    }
+
+    fn resize_board(&mut self, new_width: u32, new_height: u32) -> Result<(), String>
+    {
+        let cropped_size = (min(new_width, new_height) + 2).next_power_of_two() / 2 - 1;
+        if cropped_size > MAX_CPU_BOARD_SIZE
+        {
+            return Err(format!("Board size {}x{} is not supported, the largest supported size is {}x{}", cropped_size, cropped_size, MAX_CPU_BOARD_SIZE, MAX_CPU_BOARD_SIZE));
+        }
+
+        self.allocate_board(cropped_size);
+
+        self.clear_restriction_impl();
+        self.apply_restriction_image();
+
+        //The strokes are drawn in the cells of the old board size
+        self.board_strokes.clear();
+
+        self.reset_board_unchanged();
+        Ok(())
+    }
+
+    fn reset_board_unchanged(&mut self)
+    {
+        match self.last_reset_type
+        {
+            ResetBoardType::Standard {reset_type} => self.reset_board_standard_impl(reset_type),
+            ResetBoardType::Random {params}       => self.reset_board_random_impl(params),
+            ResetBoardType::Pattern {params}      => self.reset_board_pattern_impl(params),
+            ResetBoardType::Expression            => self.reset_board_expression_impl(),
+            ResetBoardType::Custom                => self.reset_board_custom_impl()
+        }
+    }
+
+    fn reset_board_standard(&mut self, reset_type: StandardResetBoardType)
+    {
+        self.board_strokes.clear();
+        self.reset_board_standard_impl(reset_type);
+    }
+
+    fn reset_board_random(&mut self, params: RandomBoardParams)
+    {
+        self.board_strokes.clear();
+        self.reset_board_random_impl(params);
+    }
+
+    fn reset_board_pattern(&mut self, params: PatternBoardParams)
+    {
+        self.board_strokes.clear();
+        self.reset_board_pattern_impl(params);
+    }
+
+    fn reset_board_expression(&mut self, expression_source: &str, seed: u32) -> Result<(), String>
+    {
+        self.board_expression = Some(BoardExpression::parse(expression_source, seed)?);
+        self.board_strokes.clear();
+        self.reset_board_expression_impl();
+
+        Ok(())
+    }
+
+    fn reset_board_custom(&mut self, image_array: Vec<u8>, width: u32, height: u32) -> Result<u32, String>
+    {
+        let image_bytes = (width as usize).checked_mul(height as usize).and_then(|pixel_count| pixel_count.checked_mul(4));
+        if width == 0 || height == 0 || image_bytes.is_none_or(|image_bytes| image_array.len() < image_bytes)
+        {
+            return Err(format!("Invalid initial state image of size {}x{}", width, height));
+        }
+
+        let cropped_size = import_board_size(width, height, &self.image_import_options, MAX_CPU_BOARD_SIZE);
+        self.allocate_board(cropped_size);
+
+        self.clear_restriction_impl();
+        self.apply_restriction_image();
+
+        let image_array = import_image(&image_array, width, height, cropped_size, false, &self.image_import_options);
+        self.initial_image = Some(SourceImage{pixel_data: image_array, width: cropped_size, height: cropped_size});
+        self.board_strokes.clear();
+        self.reset_board_custom_impl();
+
+        Ok(cropped_size)
+    }
+
+    fn upload_restriction(&mut self, image_array: Vec<u8>, width: u32, height: u32)
+    {
+        //Everything outside the image is unrestricted
+        let image_array = import_image(&image_array, width, height, self.board_size, true, &self.image_import_options);
+        self.restriction_image = Some(SourceImage{pixel_data: image_array, width: self.board_size, height: self.board_size});
+        self.apply_restriction_image();
+        self.reset_board_unchanged();
+    }
+
+    fn clear_restriction(&mut self)
+    {
+        self.restriction_image = None;
+        self.clear_restriction_impl();
+        self.reset_board_unchanged();
+    }
+
+    fn paint(&mut self, stroke: PaintStroke)
+    {
+        match stroke.target
+        {
+            PaintTarget::Board       => self.board_strokes.push(stroke),
+            PaintTarget::Restriction => paint_rows(&mut self.restriction, self.board_size as usize, self.words_per_row, &stroke)
+        }
+
+        self.reset_board_unchanged();
+    }
+
+    fn set_lights_out_enabled(&mut self, enable: bool)
+    {
+        self.lights_out_enabled = enable;
+    }
+
+    fn lights_out_click(&mut self, x: u32, y: u32) -> bool
+    {
+        if !self.lights_out_enabled || x >= self.board_size || y >= self.board_size || !self.cell_enabled(&self.restriction, x as usize, y as usize)
+        {
+            return false;
+        }
+
+        let lights_out_move = LightsOutMove{x, y};
+        self.lights_out_toggle(lights_out_move);
+        self.lights_out_moves.push(lights_out_move);
+        true
+    }
+
+    fn lights_out_undo(&mut self) -> bool
+    {
+        match self.lights_out_moves.pop()
+        {
+            Some(lights_out_move) =>
+            {
+                self.lights_out_toggle(lights_out_move);
+                true
+            }
+
+            None => false
+        }
+    }
+
+    fn lights_out_status(&mut self) -> LightsOutStatus
+    {
+        LightsOutStatus
+        {
+            move_count: self.lights_out_moves.len() as u32,
+            lit_cells:  Some(self.board.iter().map(|board_word| board_word.count_ones() as u64).sum())
+        }
+    }
+
+    fn reset_click_rule(&mut self, click_rule_data: &[u8; 32 * 32])
+    {
+        //The same offsets as in bake_click_rule.wgsl
+        let click_rule_size = 32;
+        let max_radius      = (click_rule_size as i32 - 1) / 2;
+
+        self.click_rule_data = *click_rule_data;
+        self.click_rule_offsets.clear();
+        for (index, click_rule_cell) in click_rule_data.iter().enumerate()
+        {
+            if *click_rule_cell != 0
+            {
+                let offset_x = (index % click_rule_size) as i32 - max_radius;
+                let offset_y = (index / click_rule_size) as i32 - max_radius;
+                self.click_rule_offsets.push((offset_x, offset_y));
+            }
+        }
+    }
+
+    fn set_spawn_period(&mut self, spawn_period: u32)
+    {
+        self.spawn_period = spawn_period;
+    }
+
+    fn set_smooth_transform_enabled(&mut self, enable: bool)
+    {
+        self.smooth_transform_enabled = enable;
+    }
+
+    fn set_image_import_options(&mut self, options: ImageImportOptions)
+    {
+        self.image_import_options = options;
+    }
+
+    fn update(&mut self)
+    {
+        self.lights_out_moves.clear();
+
+        self.profiler.begin_cpu_pass("next_step");
+        self.calc_next_frame();
+        self.profiler.end_cpu_pass();
+    }
+
+    fn set_history_depth(&mut self, history_frames: u32, keyframe_period: u32)
+    {
+        self.history.set_depth(history_frames, keyframe_period, self.snapshot_bytes());
+        self.record_history();
+    }
+
+    fn step_back(&mut self) -> Result<(), String>
+    {
+        if self.frame_number == 0
+        {
+            return Err("Cannot step back from the first frame".to_string());
+        }
+
+        self.seek_frame(self.frame_number - 1)
+    }
+
+    fn seek_frame(&mut self, frame_number: u32) -> Result<(), String>
+    {
+        self.lights_out_moves.clear();
+
+        if frame_number < self.frame_number
+        {
+            let snapshot_frame = match self.history.nearest_snapshot(frame_number)
+            {
+                Some((snapshot_frame, snapshot)) =>
+                {
+                    self.board.copy_from_slice(&snapshot.board);
+                    self.stability.copy_from_slice(&snapshot.stability);
+                    snapshot_frame
+                }
+
+                None =>
+                {
+                    let first_frame = self.history.first_frame().unwrap_or(self.frame_number);
+                    return Err(format!("Frame {} is not in the history, the earliest frame is {}", frame_number, first_frame));
+                }
+            };
+
+            self.history.truncate_after(snapshot_frame);
+            self.frame_number = snapshot_frame;
+        }
+
+        //Replay the frames after the snapshot, recording them again. The frame number doesn't change with an empty click rule
+        for _ in self.frame_number..frame_number
+        {
+            self.calc_next_frame();
+        }
+
+        Ok(())
+    }
+
+    fn timeline_range(&self) -> (u32, u32)
+    {
+        self.history.timeline_range(self.frame_number)
+    }
+
+    fn wait_for_completion(&self)
+    {
+        //All work is done immediately
+    }
+
+    fn post_stability_request(&mut self, callback: ImageCallback)
+    {
+        callback(Ok(ImageData
+        {
+            pixel_data:   self.stability.clone(),
+            image_width:  self.board_size,
+            image_height: self.board_size
+        }));
+    }
+
+    fn post_save_png_request(&mut self, callback: ImageCallback)
+    {
+        callback(Ok(ImageData
+        {
+            pixel_data:   self.scaled_final_image(self.board_size, self.board_size),
+            image_width:  self.board_size,
+            image_height: self.board_size
+        }));
+    }
+
+    //The boards are simulated one by one on a separate state
+    fn post_batch_request(&mut self, params: BatchParams, callback: BatchCallback)
+    {
+        if !(params.board_size + 1).is_power_of_two() || params.board_size < 3 || params.board_size > MAX_CPU_BOARD_SIZE
+        {
+            return callback(Err(format!("Batch board size {} is not supported", params.board_size)));
+        }
+
+        let mut batch_state = Self::new(params.board_size, params.board_size);
+        batch_state.set_history_depth(0, u32::MAX);
+
+        let mut stabilities = Vec::with_capacity(params.click_rules.len());
+        for click_rule in &params.click_rules
+        {
+            batch_state.reset_click_rule(click_rule);
+            batch_state.reset_board_standard(params.reset_type);
+            for _ in 0..params.frame_count
+            {
+                batch_state.update();
+            }
+
+            stabilities.push(ImageData
+            {
+                pixel_data:   batch_state.stability.clone(),
+                image_width:  batch_state.board_size,
+                image_height: batch_state.board_size
+            });
+        }
+
+        callback(Ok(stabilities));
+    }
+
+    fn post_save_session_request(&mut self, callback: SessionCallback)
+    {
+        callback(Ok(StafraSession
+        {
+            board_size:   self.board_size,
+            frame_number: self.frame_number,
+
+            spawn_period:             self.spawn_period,
+            smooth_transform_enabled: self.smooth_transform_enabled,
+
+            last_reset_type: self.last_reset_type,
+            click_rule_data: self.click_rule_data,
+
+            board:       self.unpack_rows(&self.board),
+            stability:   self.stability.clone(),
+            restriction: self.unpack_rows(&self.restriction),
+
+            initial_image: self.initial_image.as_ref().map(|initial_image| ImageData
+            {
+                pixel_data:   initial_image.pixel_data.clone(),
+                image_width:  initial_image.width,
+                image_height: initial_image.height
+            }),
+
+            board_expression: self.board_expression.clone(),
+            board_strokes:    self.board_strokes.clone()
+        }));
+    }
+
+    fn load_session(&mut self, session: &StafraSession) -> Result<(), String>
+    {
+        if session.board_size > MAX_CPU_BOARD_SIZE
+        {
+            return Err(format!("Board size {}x{} is not supported, the largest supported size is {}x{}", session.board_size, session.board_size, MAX_CPU_BOARD_SIZE, MAX_CPU_BOARD_SIZE));
+        }
+
+        self.reset_click_rule(&session.click_rule_data);
+        self.spawn_period             = session.spawn_period;
+        self.smooth_transform_enabled = session.smooth_transform_enabled;
+
+        self.allocate_board(session.board_size);
+
+        self.board       = self.pack_rows(&session.board);
+        self.restriction = self.pack_rows(&session.restriction);
+        self.stability.copy_from_slice(&session.stability);
+
+        //Keep the images to re-apply them after resizing the board
+        self.initial_image = session.initial_image.as_ref().map(|initial_image| SourceImage
+        {
+            pixel_data: initial_image.pixel_data.clone(),
+            width:      initial_image.image_width,
+            height:     initial_image.image_height
+        });
+
+        self.board_expression = session.board_expression.clone();
+
+        self.restriction_image = if session.is_restricted()
+        {
+            let restriction_image = session.restriction_image();
+            Some(SourceImage{pixel_data: restriction_image.pixel_data, width: restriction_image.image_width, height: restriction_image.image_height})
+        }
+        else
+        {
+            None
+        };
+
+        self.last_reset_type = session.last_reset_type;
+        self.frame_number    = session.frame_number;
+        self.board_strokes   = session.board_strokes.clone();
+
+        self.lights_out_moves.clear();
+
+        self.restart_history();
+        Ok(())
+    }
+
+    fn resize(&mut self, _new_width: u32, _new_height: u32)
+    {
+        //The image is scaled to the canvas size on each render
+    }
+
+    fn resize_click_rule(&mut self, _new_width: u32, _new_height: u32)
+    {
+        //The click rule is scaled to the canvas size on each render
+    }
+
+    fn set_click_rule_grid_enabled(&mut self, enable: bool)
+    {
+        self.click_rule_grid_enabled = enable;
+    }
+
+    fn set_click_rule_read_only(&mut self, _is_read_only: bool)
+    {
+        //The read-only state is not displayed
+    }
+
+    fn update_visual_info(&mut self)
+    {
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>
+    {
+        self.profiler.begin_cpu_pass("main_render");
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            if let Some(main_canvas) = self.main_context.as_ref().and_then(|main_context| main_context.canvas())
+            {
+                let canvas_width  = main_canvas.width();
+                let canvas_height = main_canvas.height();
+
+                let pixel_data = self.scaled_final_image(canvas_width, canvas_height);
+                if let Ok(image_data) = web_sys::ImageData::new_with_u8_clamped_array_and_sh(Clamped(pixel_data.as_slice()), canvas_width, canvas_height)
+                {
+                    self.main_context.as_ref().unwrap().put_image_data(&image_data, 0.0, 0.0).unwrap_or(());
+                }
+            }
+
+            if let Some(click_rule_context) = &self.click_rule_context
+            {
+                self.draw_click_rule(click_rule_context);
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(mut main_context) = self.main_context.take()
+            {
+                let (window_width, window_height) = Self::window_image_size(&main_context);
+                if window_width != 0 && window_height != 0
+                {
+                    let pixel_data = self.scaled_final_image(window_width as u32, window_height as u32);
+                    Self::present_native(&mut main_context, &pixel_data, window_width, window_height);
+                }
+
+                self.main_context = Some(main_context);
+            }
+
+            if let Some(mut click_rule_context) = self.click_rule_context.take()
+            {
+                let (window_width, window_height) = Self::window_image_size(&click_rule_context);
+                if window_width != 0 && window_height != 0
+                {
+                    let pixel_data = self.scaled_click_rule_image(window_width as u32, window_height as u32);
+                    Self::present_native(&mut click_rule_context, &pixel_data, window_width, window_height);
+                }
+
+                self.click_rule_context = Some(click_rule_context);
+            }
+        }
+
+        self.profiler.end_cpu_pass();
+        self.profiler.finish_frame();
+
+        Ok(())
+    }
+
+    fn set_readback_queue_depth(&mut self, _depth: u32)
+    {
+        //All requests are completed immediately
+    }
+
+    fn video_frame_queue_full(&self) -> bool
+    {
+        false
+    }
+
+    fn take_allocation_error(&mut self) -> Option<String>
+    {
+        //The CPU boards are allocated right away
+        None
+    }
+
+    fn post_video_frame_request(&mut self, callback: ImageCallback)
+    {
+        callback(Ok(ImageData
+        {
+            pixel_data:   self.scaled_final_image(VIDEO_FRAME_SIZE, VIDEO_FRAME_SIZE),
+            image_width:  VIDEO_FRAME_SIZE,
+            image_height: VIDEO_FRAME_SIZE
+        }));
+    }
+
+    fn set_profiling_enabled(&mut self, enable: bool)
+    {
+        self.profiler.set_enabled(enable);
+    }
+
+    fn profiler_report(&self) -> ProfilerReport
+    {
+        self.profiler.report()
+    }
+}
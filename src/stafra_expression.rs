@@ -0,0 +1,639 @@
+//Board expressions: integer formulas over the cell coordinates that become the initial board.
+//Everything is a wrapping 32-bit signed integer, comparisons and logical operators return 0 or 1, and the cell is enabled if the result is not 0.
+//Variables: x, y (cell coordinates), n (board size in cells), seed. Functions: abs(a), min(a, b), max(a, b), hash(a).
+//Operators, from the lowest precedence: ?:, ||, &&, |, ^, &, == !=, < <= > >=, << >>, + -, * / %, unary - ! ~.
+//Division and remainder by 0 return 0, shifts only use the lowest 5 bits of the shift amount
+
+use super::stafra_state::pcg_hash;
+
+pub const DEFAULT_BOARD_EXPRESSION: &str = "(x * y) % 7 == 0 || (x ^ y) & 16";
+
+//Keeps the query strings and the generated shaders small
+const MAX_EXPRESSION_LENGTH: usize = 1024;
+const MAX_EXPRESSION_DEPTH:  u32   = 64;
+
+#[derive(Clone)]
+pub struct BoardExpression
+{
+    source: String,
+    seed:   u32,
+    root:   ExpressionNode
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum ExpressionVariable
+{
+    X,
+    Y,
+    BoardSize,
+    Seed
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum ExpressionFunction
+{
+    Abs,
+    Min,
+    Max,
+    Hash
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum UnaryOperator
+{
+    Negate,
+    LogicalNot,
+    BitwiseNot
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum BinaryOperator
+{
+    LogicalOr,
+    LogicalAnd,
+    BitwiseOr,
+    BitwiseXor,
+    BitwiseAnd,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    ShiftLeft,
+    ShiftRight,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder
+}
+
+#[derive(Clone)]
+enum ExpressionNode
+{
+    Constant(i32),
+    Variable(ExpressionVariable),
+    Unary(UnaryOperator, Box<ExpressionNode>),
+    Binary(BinaryOperator, Box<ExpressionNode>, Box<ExpressionNode>),
+    Conditional(Box<ExpressionNode>, Box<ExpressionNode>, Box<ExpressionNode>),
+    Call(ExpressionFunction, Vec<ExpressionNode>)
+}
+
+#[derive(Clone, PartialEq)]
+enum Token
+{
+    Number(i32),
+    Identifier(String),
+    Symbol(&'static str)
+}
+
+//Longer symbols go first so that "<=" is not read as "<" and "="
+const EXPRESSION_SYMBOLS: [&str; 26] =
+[
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>",
+    "?", ":", "|", "^", "&", "<", ">", "+", "-", "*", "/", "%", "!", "~", "(", ")", ",", "="
+];
+
+struct ExpressionParser
+{
+    tokens:   Vec<(Token, usize)>, //The token and its character column, counted from 0
+    position: usize,
+    depth:    u32
+}
+
+impl BoardExpression
+{
+    pub fn parse(source: &str, seed: u32) -> Result<Self, String>
+    {
+        if source.chars().count() > MAX_EXPRESSION_LENGTH
+        {
+            return Err(format!("The expression is too long, the maximum length is {} characters", MAX_EXPRESSION_LENGTH));
+        }
+
+        let mut parser = ExpressionParser
+        {
+            tokens:   tokenize(source)?,
+            position: 0,
+            depth:    0
+        };
+
+        let root = parser.parse_expression()?;
+        if let Some((token, offset)) = parser.tokens.get(parser.position)
+        {
+            return Err(format!("Unexpected {} at position {}", token_description(token), offset + 1));
+        }
+
+        Ok(Self
+        {
+            source: source.to_string(),
+            seed,
+            root
+        })
+    }
+
+    pub fn source(&self) -> &str
+    {
+        &self.source
+    }
+
+    pub fn seed(&self) -> u32
+    {
+        self.seed
+    }
+
+    pub fn cell_enabled(&self, board_size: u32, x: u32, y: u32) -> bool
+    {
+        let variables = [x as i32, y as i32, board_size as i32, self.seed as i32];
+        evaluate(&self.root, &variables) != 0
+    }
+
+    //The board_expression() function for clear_expression.wgsl. The seed is baked into the function
+    pub fn to_wgsl_function(&self) -> String
+    {
+        format!("fn board_expression(x: i32, y: i32, n: i32) -> i32\n{{\n    let seed = bitcast<i32>({}u);\n    return {};\n}}", self.seed, to_wgsl(&self.root))
+    }
+}
+
+//Error positions are character columns, not byte offsets
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, String>
+{
+    let mut tokens = Vec::new();
+
+    let char_column = |byte_offset: usize| source[..byte_offset].chars().count();
+
+    let source_bytes = source.as_bytes();
+    let mut offset = 0;
+    while offset < source_bytes.len()
+    {
+        let current_byte = source_bytes[offset];
+        if current_byte.is_ascii_whitespace()
+        {
+            offset += 1;
+        }
+        else if current_byte.is_ascii_digit()
+        {
+            let token_start = offset;
+            while offset < source_bytes.len() && source_bytes[offset].is_ascii_alphanumeric()
+            {
+                offset += 1;
+            }
+
+            let number_text = &source[token_start..offset];
+            let parse_result = match number_text.strip_prefix("0x").or_else(|| number_text.strip_prefix("0X"))
+            {
+                Some(hex_digits) => u32::from_str_radix(hex_digits, 16).map(|value| value as i32).ok(),
+                None             => number_text.parse::<i32>().ok()
+            };
+
+            match parse_result
+            {
+                Some(value) => tokens.push((Token::Number(value), char_column(token_start))),
+                None        => return Err(format!("Invalid number {} at position {}", number_text, char_column(token_start) + 1))
+            }
+        }
+        else if current_byte.is_ascii_alphabetic() || current_byte == b'_'
+        {
+            let token_start = offset;
+            while offset < source_bytes.len() && (source_bytes[offset].is_ascii_alphanumeric() || source_bytes[offset] == b'_')
+            {
+                offset += 1;
+            }
+
+            tokens.push((Token::Identifier(source[token_start..offset].to_lowercase()), char_column(token_start)));
+        }
+        else
+        {
+            match EXPRESSION_SYMBOLS.iter().find(|symbol| source_bytes[offset..].starts_with(symbol.as_bytes()))
+            {
+                Some(symbol) =>
+                {
+                    tokens.push((Token::Symbol(symbol), char_column(offset)));
+                    offset += symbol.len();
+                }
+
+                None =>
+                {
+                    let unknown_char = source[offset..].chars().next().unwrap_or('?');
+                    return Err(format!("Unexpected character '{}' at position {}", unknown_char, char_column(offset) + 1));
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn token_description(token: &Token) -> String
+{
+    match token
+    {
+        Token::Number(value)     => format!("number {}", value),
+        Token::Identifier(name)  => format!("name {}", name),
+        Token::Symbol(symbol)    => format!("'{}'", symbol)
+    }
+}
+
+//Binary operators for each precedence level, from the lowest
+const BINARY_PRECEDENCE_LEVELS: [&[(&str, BinaryOperator)]; 10] =
+[
+    &[("||", BinaryOperator::LogicalOr)],
+    &[("&&", BinaryOperator::LogicalAnd)],
+    &[("|",  BinaryOperator::BitwiseOr)],
+    &[("^",  BinaryOperator::BitwiseXor)],
+    &[("&",  BinaryOperator::BitwiseAnd)],
+    &[("==", BinaryOperator::Equal), ("!=", BinaryOperator::NotEqual)],
+    &[("<",  BinaryOperator::Less),  ("<=", BinaryOperator::LessEqual), (">", BinaryOperator::Greater), (">=", BinaryOperator::GreaterEqual)],
+    &[("<<", BinaryOperator::ShiftLeft), (">>", BinaryOperator::ShiftRight)],
+    &[("+",  BinaryOperator::Add),       ("-",  BinaryOperator::Subtract)],
+    &[("*",  BinaryOperator::Multiply),  ("/",  BinaryOperator::Divide), ("%", BinaryOperator::Remainder)]
+];
+
+impl ExpressionParser
+{
+    fn peek_symbol(&self) -> Option<&'static str>
+    {
+        match self.tokens.get(self.position)
+        {
+            Some((Token::Symbol(symbol), _)) => Some(symbol),
+            _                                => None
+        }
+    }
+
+    fn expect_symbol(&mut self, expected_symbol: &str) -> Result<(), String>
+    {
+        match self.tokens.get(self.position)
+        {
+            Some((Token::Symbol(symbol), _)) if *symbol == expected_symbol =>
+            {
+                self.position += 1;
+                Ok(())
+            }
+
+            Some((token, offset)) => Err(format!("Expected '{}' at position {}, found {}", expected_symbol, offset + 1, token_description(token))),
+            None                  => Err(format!("Expected '{}' at the end of the expression", expected_symbol))
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<ExpressionNode, String>
+    {
+        self.depth += 1;
+        if self.depth > MAX_EXPRESSION_DEPTH
+        {
+            return Err("The expression is nested too deeply".to_string());
+        }
+
+        let condition = self.parse_binary(0)?;
+
+        let expression = if self.peek_symbol() == Some("?")
+        {
+            self.position += 1;
+            let true_expression = self.parse_expression()?;
+            self.expect_symbol(":")?;
+            let false_expression = self.parse_expression()?;
+
+            ExpressionNode::Conditional(Box::new(condition), Box::new(true_expression), Box::new(false_expression))
+        }
+        else
+        {
+            condition
+        };
+
+        self.depth -= 1;
+        Ok(expression)
+    }
+
+    fn parse_binary(&mut self, precedence_level: usize) -> Result<ExpressionNode, String>
+    {
+        if precedence_level >= BINARY_PRECEDENCE_LEVELS.len()
+        {
+            return self.parse_unary();
+        }
+
+        let mut left_expression = self.parse_binary(precedence_level + 1)?;
+        while let Some(operator) = self.peek_symbol().and_then(|symbol| BINARY_PRECEDENCE_LEVELS[precedence_level].iter().find(|(operator_symbol, _)| *operator_symbol == symbol))
+        {
+            self.position += 1;
+            let right_expression = self.parse_binary(precedence_level + 1)?;
+            left_expression = ExpressionNode::Binary(operator.1, Box::new(left_expression), Box::new(right_expression));
+        }
+
+        Ok(left_expression)
+    }
+
+    fn parse_unary(&mut self) -> Result<ExpressionNode, String>
+    {
+        let unary_operator = match self.peek_symbol()
+        {
+            Some("-") => Some(UnaryOperator::Negate),
+            Some("!") => Some(UnaryOperator::LogicalNot),
+            Some("~") => Some(UnaryOperator::BitwiseNot),
+            _         => None
+        };
+
+        match unary_operator
+        {
+            Some(operator) =>
+            {
+                self.position += 1;
+
+                self.depth += 1;
+                if self.depth > MAX_EXPRESSION_DEPTH
+                {
+                    return Err("The expression is nested too deeply".to_string());
+                }
+
+                let operand = self.parse_unary()?;
+                self.depth -= 1;
+
+                Ok(ExpressionNode::Unary(operator, Box::new(operand)))
+            }
+
+            None => self.parse_primary()
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<ExpressionNode, String>
+    {
+        let (token, offset) = match self.tokens.get(self.position)
+        {
+            Some((token, offset)) => (token.clone(), *offset),
+            None                  => return Err("Unexpected end of the expression".to_string())
+        };
+
+        self.position += 1;
+        match token
+        {
+            Token::Number(value) => Ok(ExpressionNode::Constant(value)),
+
+            Token::Symbol("(") =>
+            {
+                let expression = self.parse_expression()?;
+                self.expect_symbol(")")?;
+                Ok(expression)
+            }
+
+            Token::Identifier(name) =>
+            {
+                let variable = match name.as_str()
+                {
+                    "x"    => Some(ExpressionVariable::X),
+                    "y"    => Some(ExpressionVariable::Y),
+                    "n"    => Some(ExpressionVariable::BoardSize),
+                    "seed" => Some(ExpressionVariable::Seed),
+                    _      => None
+                };
+
+                if let Some(variable) = variable
+                {
+                    return Ok(ExpressionNode::Variable(variable));
+                }
+
+                let (function, argument_count) = match name.as_str()
+                {
+                    "abs"  => (ExpressionFunction::Abs,  1),
+                    "min"  => (ExpressionFunction::Min,  2),
+                    "max"  => (ExpressionFunction::Max,  2),
+                    "hash" => (ExpressionFunction::Hash, 1),
+                    _      => return Err(format!("Unknown name {} at position {}", name, offset + 1))
+                };
+
+                self.expect_symbol("(")?;
+
+                let mut arguments = Vec::new();
+                for argument_index in 0..argument_count
+                {
+                    if argument_index > 0
+                    {
+                        self.expect_symbol(",")?;
+                    }
+
+                    arguments.push(self.parse_expression()?);
+                }
+
+                self.expect_symbol(")")?;
+                Ok(ExpressionNode::Call(function, arguments))
+            }
+
+            Token::Symbol(symbol) => Err(format!("Unexpected '{}' at position {}", symbol, offset + 1))
+        }
+    }
+}
+
+//The same calculation as in clear_expression.wgsl
+fn evaluate(node: &ExpressionNode, variables: &[i32; 4]) -> i32
+{
+    match node
+    {
+        ExpressionNode::Constant(value) => *value,
+
+        ExpressionNode::Variable(variable) => match variable
+        {
+            ExpressionVariable::X         => variables[0],
+            ExpressionVariable::Y         => variables[1],
+            ExpressionVariable::BoardSize => variables[2],
+            ExpressionVariable::Seed      => variables[3]
+        },
+
+        ExpressionNode::Unary(operator, operand) =>
+        {
+            let value = evaluate(operand, variables);
+            match operator
+            {
+                UnaryOperator::Negate     => value.wrapping_neg(),
+                UnaryOperator::LogicalNot => (value == 0) as i32,
+                UnaryOperator::BitwiseNot => !value
+            }
+        }
+
+        ExpressionNode::Binary(operator, left_operand, right_operand) =>
+        {
+            let left  = evaluate(left_operand,  variables);
+            let right = evaluate(right_operand, variables);
+            match operator
+            {
+                BinaryOperator::LogicalOr    => (left != 0 || right != 0) as i32,
+                BinaryOperator::LogicalAnd   => (left != 0 && right != 0) as i32,
+                BinaryOperator::BitwiseOr    => left | right,
+                BinaryOperator::BitwiseXor   => left ^ right,
+                BinaryOperator::BitwiseAnd   => left & right,
+                BinaryOperator::Equal        => (left == right) as i32,
+                BinaryOperator::NotEqual     => (left != right) as i32,
+                BinaryOperator::Less         => (left <  right) as i32,
+                BinaryOperator::LessEqual    => (left <= right) as i32,
+                BinaryOperator::Greater      => (left >  right) as i32,
+                BinaryOperator::GreaterEqual => (left >= right) as i32,
+                BinaryOperator::ShiftLeft    => left.wrapping_shl(right as u32),
+                BinaryOperator::ShiftRight   => left.wrapping_shr(right as u32),
+                BinaryOperator::Add          => left.wrapping_add(right),
+                BinaryOperator::Subtract     => left.wrapping_sub(right),
+                BinaryOperator::Multiply     => left.wrapping_mul(right),
+                BinaryOperator::Divide       => if right == 0 {0} else {left.wrapping_div(right)},
+                BinaryOperator::Remainder    => if right == 0 {0} else {left.wrapping_rem(right)}
+            }
+        }
+
+        ExpressionNode::Conditional(condition, true_operand, false_operand) =>
+        {
+            if evaluate(condition, variables) != 0
+            {
+                evaluate(true_operand, variables)
+            }
+            else
+            {
+                evaluate(false_operand, variables)
+            }
+        }
+
+        ExpressionNode::Call(function, arguments) =>
+        {
+            let first_argument = evaluate(&arguments[0], variables);
+            match function
+            {
+                ExpressionFunction::Abs  => first_argument.wrapping_abs(),
+                ExpressionFunction::Min  => std::cmp::min(first_argument, evaluate(&arguments[1], variables)),
+                ExpressionFunction::Max  => std::cmp::max(first_argument, evaluate(&arguments[1], variables)),
+                ExpressionFunction::Hash => pcg_hash(first_argument as u32) as i32
+            }
+        }
+    }
+}
+
+//Every subexpression is parenthesized, so WGSL precedence rules never matter
+fn to_wgsl(node: &ExpressionNode) -> String
+{
+    match node
+    {
+        ExpressionNode::Constant(value) =>
+        {
+            format!("bitcast<i32>({}u)", *value as u32)
+        }
+
+        ExpressionNode::Variable(variable) => match variable
+        {
+            ExpressionVariable::X         => "x".to_string(),
+            ExpressionVariable::Y         => "y".to_string(),
+            ExpressionVariable::BoardSize => "n".to_string(),
+            ExpressionVariable::Seed      => "seed".to_string()
+        },
+
+        ExpressionNode::Unary(operator, operand) =>
+        {
+            let operand_code = to_wgsl(operand);
+            match operator
+            {
+                UnaryOperator::Negate     => format!("(-{})", operand_code),
+                UnaryOperator::LogicalNot => format!("i32({} == 0)", operand_code),
+                UnaryOperator::BitwiseNot => format!("(~{})", operand_code)
+            }
+        }
+
+        ExpressionNode::Binary(operator, left_operand, right_operand) =>
+        {
+            let left  = to_wgsl(left_operand);
+            let right = to_wgsl(right_operand);
+            match operator
+            {
+                BinaryOperator::LogicalOr    => format!("i32({} != 0 || {} != 0)", left, right),
+                BinaryOperator::LogicalAnd   => format!("i32({} != 0 && {} != 0)", left, right),
+                BinaryOperator::BitwiseOr    => format!("({} | {})",  left, right),
+                BinaryOperator::BitwiseXor   => format!("({} ^ {})",  left, right),
+                BinaryOperator::BitwiseAnd   => format!("({} & {})",  left, right),
+                BinaryOperator::Equal        => format!("i32({} == {})", left, right),
+                BinaryOperator::NotEqual     => format!("i32({} != {})", left, right),
+                BinaryOperator::Less         => format!("i32({} < {})",  left, right),
+                BinaryOperator::LessEqual    => format!("i32({} <= {})", left, right),
+                BinaryOperator::Greater      => format!("i32({} > {})",  left, right),
+                BinaryOperator::GreaterEqual => format!("i32({} >= {})", left, right),
+                BinaryOperator::ShiftLeft    => format!("({} << (bitcast<u32>({}) & 31u))", left, right),
+                BinaryOperator::ShiftRight   => format!("({} >> (bitcast<u32>({}) & 31u))", left, right),
+                BinaryOperator::Add          => format!("({} + {})", left, right),
+                BinaryOperator::Subtract     => format!("({} - {})", left, right),
+                BinaryOperator::Multiply     => format!("({} * {})", left, right),
+                BinaryOperator::Divide       => format!("expression_div({}, {})", left, right),
+                BinaryOperator::Remainder    => format!("expression_rem({}, {})", left, right)
+            }
+        }
+
+        ExpressionNode::Conditional(condition, true_operand, false_operand) =>
+        {
+            format!("select({}, {}, {} != 0)", to_wgsl(false_operand), to_wgsl(true_operand), to_wgsl(condition))
+        }
+
+        ExpressionNode::Call(function, arguments) => match function
+        {
+            ExpressionFunction::Abs  => format!("abs({})", to_wgsl(&arguments[0])),
+            ExpressionFunction::Min  => format!("min({}, {})", to_wgsl(&arguments[0]), to_wgsl(&arguments[1])),
+            ExpressionFunction::Max  => format!("max({}, {})", to_wgsl(&arguments[0]), to_wgsl(&arguments[1])),
+            ExpressionFunction::Hash => format!("bitcast<i32>(pcg_hash(bitcast<u32>({})))", to_wgsl(&arguments[0]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn evaluate_source(source: &str) -> i32
+    {
+        let expression = BoardExpression::parse(source, 0).unwrap_or_else(|message| panic!("{}: {}", source, message));
+        evaluate(&expression.root, &[0, 0, 0, 0])
+    }
+
+    fn parse_error(source: &str) -> String
+    {
+        match BoardExpression::parse(source, 0)
+        {
+            Ok(_)        => panic!("{} should not parse", source),
+            Err(message) => message
+        }
+    }
+
+    #[test]
+    fn operator_precedence()
+    {
+        assert_eq!(evaluate_source("1 + 2 * 3"),         7);
+        assert_eq!(evaluate_source("(1 + 2) * 3"),       9);
+        assert_eq!(evaluate_source("10 - 4 - 3"),        3);
+        assert_eq!(evaluate_source("-2 * 3 + 7 % 4"),   -3);
+        assert_eq!(evaluate_source("1 << 2 + 1"),        8);
+        assert_eq!(evaluate_source("1 | 2 ^ 3 & 1"),     3);
+        assert_eq!(evaluate_source("2 < 3 == 1"),        1);
+        assert_eq!(evaluate_source("1 == 1 & 0"),        0);
+        assert_eq!(evaluate_source("!0 + 1"),            2);
+        assert_eq!(evaluate_source("~0 && 0 || 1"),      1);
+        assert_eq!(evaluate_source("0 ? 1 : 2 ? 3 : 4"), 3);
+        assert_eq!(evaluate_source("1 || 0 ? 5 : 6"),    5);
+    }
+
+    //The same results as expression_div and expression_rem in clear_expression.wgsl
+    #[test]
+    fn division_and_remainder()
+    {
+        assert_eq!(evaluate_source("7 / 0"),  0);
+        assert_eq!(evaluate_source("7 % 0"),  0);
+        assert_eq!(evaluate_source("-7 / 2"), -3);
+        assert_eq!(evaluate_source("-7 % 2"), -1);
+        assert_eq!(evaluate_source("7 % -2"),  1);
+
+        assert_eq!(evaluate_source("0x80000000 / -1"), i32::MIN);
+        assert_eq!(evaluate_source("0x80000000 % -1"), 0);
+        assert_eq!(evaluate_source("5 / -1"),          -5);
+        assert_eq!(evaluate_source("5 % -1"),           0);
+    }
+
+    #[test]
+    fn error_positions()
+    {
+        assert_eq!(parse_error("x + $"),         "Unexpected character '$' at position 5");
+        assert_eq!(parse_error("x + 12a"),       "Invalid number 12a at position 5");
+        assert_eq!(parse_error("x + z"),         "Unknown name z at position 5");
+        assert_eq!(parse_error("(x + y"),        "Expected ')' at the end of the expression");
+        assert_eq!(parse_error("min(x y)"),      "Expected ',' at position 7, found name y");
+        assert_eq!(parse_error("x y"),           "Unexpected name y at position 3");
+        assert_eq!(parse_error("x + * y"),       "Unexpected '*' at position 5");
+        assert_eq!(parse_error("x +"),           "Unexpected end of the expression");
+        assert_eq!(parse_error("x + \u{e9} * y"), "Unexpected character '\u{e9}' at position 5");
+    }
+}
@@ -0,0 +1,709 @@
+//Polynomials over GF(2) for the analysis of the update operator. Coefficients are packed into bits, the bit i is the coefficient of x^i
+
+use std::cmp::{min, max};
+
+#[derive(Clone, PartialEq)]
+pub struct Gf2Polynomial
+{
+    words: Vec<u64> //No trailing zero words, so equal polynomials have equal representations
+}
+
+impl Gf2Polynomial
+{
+    pub fn zero() -> Self
+    {
+        Self {words: Vec::new()}
+    }
+
+    pub fn one() -> Self
+    {
+        Self::monomial(0)
+    }
+
+    pub fn monomial(degree: usize) -> Self
+    {
+        let mut polynomial = Self {words: vec![0; degree / 64 + 1]};
+        polynomial.words[degree / 64] = 1 << (degree % 64);
+        polynomial
+    }
+
+    //The coefficient of x^i is the bit i
+    pub fn from_coefficients(coefficients: &[bool]) -> Self
+    {
+        let mut polynomial = Self {words: vec![0; (coefficients.len() + 63) / 64]};
+        for (index, coefficient) in coefficients.iter().enumerate()
+        {
+            if *coefficient
+            {
+                polynomial.words[index / 64] |= 1 << (index % 64);
+            }
+        }
+
+        polynomial.normalize();
+        polynomial
+    }
+
+    pub fn is_zero(&self) -> bool
+    {
+        self.words.is_empty()
+    }
+
+    pub fn is_one(&self) -> bool
+    {
+        self.words.len() == 1 && self.words[0] == 1
+    }
+
+    //None for the zero polynomial
+    pub fn degree(&self) -> Option<usize>
+    {
+        let last_word = self.words.last()?;
+        Some((self.words.len() - 1) * 64 + 63 - last_word.leading_zeros() as usize)
+    }
+
+    pub fn coefficient(&self, index: usize) -> bool
+    {
+        index / 64 < self.words.len() && (self.words[index / 64] >> (index % 64)) & 1 != 0
+    }
+
+    //The largest power of x that divides the polynomial
+    pub fn trailing_zeros(&self) -> usize
+    {
+        match self.words.iter().position(|word| *word != 0)
+        {
+            Some(word_index) => word_index * 64 + self.words[word_index].trailing_zeros() as usize,
+            None             => 0
+        }
+    }
+
+    pub fn add(&self, other: &Self) -> Self
+    {
+        let mut sum = Self {words: vec![0; max(self.words.len(), other.words.len())]};
+        for (index, sum_word) in sum.words.iter_mut().enumerate()
+        {
+            *sum_word = self.words.get(index).copied().unwrap_or(0) ^ other.words.get(index).copied().unwrap_or(0);
+        }
+
+        sum.normalize();
+        sum
+    }
+
+    //Adds other * x^shift in place
+    pub fn add_shifted(&mut self, other: &Self, shift: usize)
+    {
+        let other_degree = match other.degree()
+        {
+            Some(other_degree) => other_degree,
+            None               => return
+        };
+
+        let required_words = (other_degree + shift) / 64 + 1;
+        if self.words.len() < required_words
+        {
+            self.words.resize(required_words, 0);
+        }
+
+        let word_shift = shift / 64;
+        let bit_shift  = shift % 64;
+        for (index, other_word) in other.words.iter().enumerate()
+        {
+            self.words[index + word_shift] ^= other_word << bit_shift;
+            if bit_shift != 0 && index + word_shift + 1 < self.words.len()
+            {
+                self.words[index + word_shift + 1] ^= other_word >> (64 - bit_shift);
+            }
+        }
+
+        self.normalize();
+    }
+
+    pub fn mul(&self, other: &Self) -> Self
+    {
+        let mut product = Self::zero();
+        if let Some(degree) = self.degree()
+        {
+            for index in 0..=degree
+            {
+                if self.coefficient(index)
+                {
+                    product.add_shifted(other, index);
+                }
+            }
+        }
+
+        product
+    }
+
+    //Squaring only spreads the coefficients, (a + b)^2 = a^2 + b^2 over GF(2)
+    pub fn square(&self) -> Self
+    {
+        let mut coefficients = vec![false; self.words.len() * 128];
+        for index in 0..self.words.len() * 64
+        {
+            coefficients[index * 2] = self.coefficient(index);
+        }
+
+        Self::from_coefficients(&coefficients)
+    }
+
+    //The inverse of square() for the polynomials that are squares
+    pub fn square_root(&self) -> Self
+    {
+        let coefficients: Vec<bool> = (0..(self.words.len() * 64 + 1) / 2).map(|index| self.coefficient(index * 2)).collect();
+        Self::from_coefficients(&coefficients)
+    }
+
+    pub fn derivative(&self) -> Self
+    {
+        //Only the odd powers survive, x^(2k + 1) becomes x^(2k)
+        let coefficients: Vec<bool> = (0..self.words.len() * 64).map(|index| index % 2 == 0 && self.coefficient(index + 1)).collect();
+        Self::from_coefficients(&coefficients)
+    }
+
+    //Returns the quotient and the remainder. The divisor must be nonzero
+    pub fn div_rem(&self, divisor: &Self) -> (Self, Self)
+    {
+        let divisor_degree = divisor.degree().expect("Division by the zero polynomial");
+
+        let mut quotient  = Self::zero();
+        let mut remainder = self.clone();
+        while let Some(remainder_degree) = remainder.degree()
+        {
+            if remainder_degree < divisor_degree
+            {
+                break;
+            }
+
+            quotient.add_shifted(&Self::one(), remainder_degree - divisor_degree);
+            remainder.add_shifted(divisor, remainder_degree - divisor_degree);
+        }
+
+        (quotient, remainder)
+    }
+
+    pub fn rem(&self, divisor: &Self) -> Self
+    {
+        self.div_rem(divisor).1
+    }
+
+    pub fn gcd(&self, other: &Self) -> Self
+    {
+        let mut a = self.clone();
+        let mut b = other.clone();
+        while !b.is_zero()
+        {
+            let remainder = a.rem(&b);
+            a = b;
+            b = remainder;
+        }
+
+        a
+    }
+
+    pub fn lcm(&self, other: &Self) -> Self
+    {
+        if self.is_zero() || other.is_zero()
+        {
+            return Self::zero();
+        }
+
+        self.div_rem(&self.gcd(other)).0.mul(other)
+    }
+
+    //x^exponent mod modulus with square-and-multiply
+    pub fn pow_x_mod(exponent: u128, modulus: &Self) -> Self
+    {
+        let mut result = Self::one().rem(modulus);
+        for bit in (0..128 - exponent.leading_zeros()).rev()
+        {
+            result = result.square().rem(modulus);
+            if (exponent >> bit) & 1 != 0
+            {
+                result = result.mul(&Self::monomial(1)).rem(modulus);
+            }
+        }
+
+        result
+    }
+
+    //The product of the distinct irreducible factors
+    pub fn radical(&self) -> Self
+    {
+        if self.degree().unwrap_or(0) == 0
+        {
+            return Self::one();
+        }
+
+        //Every exponent is even if the derivative is zero, so the polynomial is a square
+        let derivative = self.derivative();
+        if derivative.is_zero()
+        {
+            return self.square_root().radical();
+        }
+
+        //gcd(f, f') keeps p^(e - 1) of the factors with odd e and p^e of the ones with even e, so it's a square. The quotient is the product of the factors with odd e
+        let common_part  = self.gcd(&derivative);
+        let odd_factors  = self.div_rem(&common_part).0;
+        odd_factors.lcm(&common_part.square_root().radical())
+    }
+
+    //Splits a squarefree polynomial into the products of all its irreducible factors of the same degree, as (degree, product) pairs
+    pub fn distinct_degree_factors(&self) -> Vec<(usize, Self)>
+    {
+        let mut factors   = Vec::new();
+        let mut remaining = self.clone();
+
+        //x^(2^d) - x is the product of all irreducible polynomials with degrees dividing d
+        let mut x_power = Self::monomial(1);
+        let mut degree  = 1;
+        while remaining.degree().unwrap_or(0) >= 2 * degree
+        {
+            x_power = x_power.square().rem(&remaining);
+
+            let factor = remaining.gcd(&x_power.add(&Self::monomial(1)));
+            if !factor.is_one()
+            {
+                remaining = remaining.div_rem(&factor).0;
+                x_power   = x_power.rem(&remaining);
+                factors.push((degree, factor));
+            }
+
+            degree += 1;
+        }
+
+        //What's left has no factors of degree below half of its own, so it's irreducible
+        if let Some(remaining_degree) = remaining.degree()
+        {
+            if remaining_degree > 0
+            {
+                factors.push((remaining_degree, remaining));
+            }
+        }
+
+        factors
+    }
+
+    //The smallest positive n such that x^n = 1 mod the polynomial. The polynomial must not be divisible by x.
+    //Each irreducible factor of degree d has the order dividing 2^d - 1, and a factor with the exponent e multiplies it by the smallest power of 2 not below e
+    pub fn order(&self) -> Result<u128, String>
+    {
+        if self.degree().unwrap_or(0) == 0
+        {
+            return Ok(1);
+        }
+
+        if !self.coefficient(0)
+        {
+            return Err("The polynomial is divisible by x and has no order".to_string());
+        }
+
+        let mut order: u128 = 1;
+        for (degree, factor) in self.radical().distinct_degree_factors()
+        {
+            if degree > 128
+            {
+                return Err(format!("The polynomial has an irreducible factor of degree {}, its order is too large to compute", degree));
+            }
+
+            let full_order = if degree == 128 {u128::MAX} else {(1u128 << degree) - 1};
+
+            let mut factor_order = full_order;
+            for prime in prime_factors(full_order)
+            {
+                while factor_order % prime == 0 && Self::pow_x_mod(factor_order / prime, &factor).is_one()
+                {
+                    factor_order /= prime;
+                }
+            }
+
+            order = lcm_u128(order, factor_order).ok_or_else(|| "The order is larger than 2^128".to_string())?;
+        }
+
+        let mut x_power = Self::pow_x_mod(order, self);
+        while !x_power.is_one()
+        {
+            x_power = x_power.square().rem(self);
+            order   = order.checked_mul(2).ok_or_else(|| "The order is larger than 2^128".to_string())?;
+        }
+
+        Ok(order)
+    }
+
+    //64 coefficients starting from the given one
+    fn bits_at(&self, start: usize) -> u64
+    {
+        let word = |index: usize| self.words.get(index).copied().unwrap_or(0);
+
+        let word_index = start / 64;
+        let bit_shift  = start % 64;
+        if bit_shift == 0
+        {
+            word(word_index)
+        }
+        else
+        {
+            (word(word_index) >> bit_shift) | (word(word_index + 1) << (64 - bit_shift))
+        }
+    }
+
+    fn normalize(&mut self)
+    {
+        while self.words.last() == Some(&0)
+        {
+            self.words.pop();
+        }
+    }
+}
+
+//The minimal polynomial of a linear recurrent sequence with the Berlekamp-Massey algorithm. It's exact if the sequence is at least twice as long as the polynomial's degree
+pub fn berlekamp_massey(sequence: &[bool]) -> Gf2Polynomial
+{
+    //The connection polynomial C(x) = 1 + c_1 x + ... + c_l x^l, with s_n = c_1 s_(n - 1) + ... + c_l s_(n - l)
+    let mut connection      = Gf2Polynomial::one();
+    let mut prev_connection = Gf2Polynomial::one();
+    let mut length          = 0;
+    let mut shift           = 1;
+
+    //The sequence is stored reversed, so the sum of c_i s_(n - i) is the parity of the connection ANDed with a contiguous range of bits
+    let sequence_length   = sequence.len();
+    let reversed_sequence = Gf2Polynomial::from_coefficients(&sequence.iter().rev().copied().collect::<Vec<bool>>());
+
+    for index in 0..sequence_length
+    {
+        let window_start    = sequence_length - 1 - index;
+        let mut discrepancy = false;
+        for (word_index, connection_word) in connection.words.iter().enumerate()
+        {
+            discrepancy ^= (connection_word & reversed_sequence.bits_at(window_start + word_index * 64)).count_ones() % 2 != 0;
+        }
+
+        if !discrepancy
+        {
+            shift += 1;
+        }
+        else if 2 * length <= index
+        {
+            let old_connection = connection.clone();
+            connection.add_shifted(&prev_connection, shift);
+
+            length          = index + 1 - length;
+            prev_connection = old_connection;
+            shift           = 1;
+        }
+        else
+        {
+            connection.add_shifted(&prev_connection, shift);
+            shift += 1;
+        }
+    }
+
+    //The minimal polynomial is the reciprocal x^l C(1/x). C can have a lower degree than l, then the minimal polynomial is divisible by x
+    let coefficients: Vec<bool> = (0..=length).map(|index| connection.coefficient(length - index)).collect();
+    Gf2Polynomial::from_coefficients(&coefficients)
+}
+
+fn lcm_u128(a: u128, b: u128) -> Option<u128>
+{
+    (a / gcd_u128(a, b)).checked_mul(b)
+}
+
+fn gcd_u128(a: u128, b: u128) -> u128
+{
+    let mut x = a;
+    let mut y = b;
+    while y != 0
+    {
+        let remainder = x % y;
+        x = y;
+        y = remainder;
+    }
+
+    x
+}
+
+//Distinct prime factors with Pollard's rho
+fn prime_factors(value: u128) -> Vec<u128>
+{
+    let mut factors   = Vec::new();
+    let mut remaining = value;
+
+    for small_prime in SMALL_PRIMES
+    {
+        if remaining % small_prime == 0
+        {
+            factors.push(small_prime);
+            while remaining % small_prime == 0
+            {
+                remaining /= small_prime;
+            }
+        }
+    }
+
+    let mut composites = vec![remaining];
+    while let Some(composite) = composites.pop()
+    {
+        if composite == 1
+        {
+            continue;
+        }
+
+        if is_prime(composite)
+        {
+            if !factors.contains(&composite)
+            {
+                factors.push(composite);
+            }
+
+            continue;
+        }
+
+        let divisor = pollard_rho(composite);
+        composites.push(divisor);
+        composites.push(composite / divisor);
+    }
+
+    factors.sort_unstable();
+    factors
+}
+
+//Miller-Rabin with these bases is exact below 3.3 * 10^24 and almost certain above
+const SMALL_PRIMES: [u128; 20] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71];
+
+//Both arguments must be below the modulus
+fn add_mod(a: u128, b: u128, modulus: u128) -> u128
+{
+    let (sum, overflow) = a.overflowing_add(b);
+    if overflow || sum >= modulus {sum.wrapping_sub(modulus)} else {sum}
+}
+
+fn mul_mod(a: u128, b: u128, modulus: u128) -> u128
+{
+    //The product fits into 128 bits for the moduli below 2^64
+    if modulus <= u64::MAX as u128
+    {
+        return (a % modulus) * (b % modulus) % modulus;
+    }
+
+    let mut result = 0;
+    let mut addend = a % modulus;
+    let mut rest   = b;
+    while rest != 0
+    {
+        if rest & 1 != 0
+        {
+            result = add_mod(result, addend, modulus);
+        }
+
+        addend = add_mod(addend, addend, modulus);
+        rest >>= 1;
+    }
+
+    result
+}
+
+fn pow_mod(base: u128, exponent: u128, modulus: u128) -> u128
+{
+    let mut result = 1 % modulus;
+    let mut power  = base % modulus;
+    let mut rest   = exponent;
+    while rest != 0
+    {
+        if rest & 1 != 0
+        {
+            result = mul_mod(result, power, modulus);
+        }
+
+        power = mul_mod(power, power, modulus);
+        rest >>= 1;
+    }
+
+    result
+}
+
+fn is_prime(value: u128) -> bool
+{
+    if value < 2
+    {
+        return false;
+    }
+
+    for base in SMALL_PRIMES
+    {
+        if value % base == 0
+        {
+            return value == base;
+        }
+    }
+
+    let odd_part  = (value - 1) >> (value - 1).trailing_zeros();
+    let two_power = (value - 1).trailing_zeros();
+    'witness_loop: for base in SMALL_PRIMES
+    {
+        let mut x = pow_mod(base, odd_part, value);
+        if x == 1 || x == value - 1
+        {
+            continue;
+        }
+
+        for _ in 1..two_power
+        {
+            x = mul_mod(x, x, value);
+            if x == value - 1
+            {
+                continue 'witness_loop;
+            }
+        }
+
+        return false;
+    }
+
+    true
+}
+
+//Finds a nontrivial divisor of an odd composite number. Brent's variant, the differences are multiplied together to take fewer gcds
+fn pollard_rho(value: u128) -> u128
+{
+    let batch_size = 128;
+
+    let mut increment = 1;
+    loop
+    {
+        let step = |x: u128| add_mod(mul_mod(x, x, value), increment, value);
+
+        let mut fast       = 2;
+        let mut slow       = fast;
+        let mut saved_fast = fast;
+        let mut product    = 1;
+        let mut divisor    = 1;
+        let mut cycle      = 1;
+        while divisor == 1
+        {
+            slow = fast;
+            for _ in 0..cycle
+            {
+                fast = step(fast);
+            }
+
+            let mut steps_done = 0;
+            while steps_done < cycle && divisor == 1
+            {
+                saved_fast = fast;
+                for _ in 0..min(batch_size, cycle - steps_done)
+                {
+                    fast    = step(fast);
+                    product = mul_mod(product, slow.abs_diff(fast), value);
+                }
+
+                divisor     = gcd_u128(product, value);
+                steps_done += batch_size;
+            }
+
+            cycle *= 2;
+        }
+
+        //The whole batch hit the divisor at once, repeat it one step at a time
+        if divisor == value
+        {
+            loop
+            {
+                saved_fast = step(saved_fast);
+                divisor    = gcd_u128(slow.abs_diff(saved_fast), value);
+                if divisor > 1
+                {
+                    break;
+                }
+            }
+        }
+
+        if divisor != value
+        {
+            return divisor;
+        }
+
+        increment += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn polynomial(degrees: &[usize]) -> Gf2Polynomial
+    {
+        degrees.iter().fold(Gf2Polynomial::zero(), |sum, degree| sum.add(&Gf2Polynomial::monomial(*degree)))
+    }
+
+    //Row i of the matrix is the bit mask of the nonzero columns of row i
+    fn mul_matrix_vector(matrix: &[u64], vector: u64) -> u64
+    {
+        matrix.iter().enumerate().fold(0, |product, (row, row_mask)| product | ((((row_mask & vector).count_ones() % 2) as u64) << row))
+    }
+
+    //The minimal polynomial of the matrix is the lcm of the minimal polynomials of the sequences e_i^T A^k e_j
+    fn matrix_minimal_polynomial(matrix: &[u64]) -> Gf2Polynomial
+    {
+        let size = matrix.len();
+
+        let mut minimal_polynomial = Gf2Polynomial::one();
+        for column in 0..size
+        {
+            let mut vectors = vec![1u64 << column];
+            for _ in 1..2 * size
+            {
+                vectors.push(mul_matrix_vector(matrix, *vectors.last().unwrap()));
+            }
+
+            for row in 0..size
+            {
+                let sequence: Vec<bool> = vectors.iter().map(|vector| (vector >> row) & 1 != 0).collect();
+                minimal_polynomial = minimal_polynomial.lcm(&berlekamp_massey(&sequence));
+            }
+        }
+
+        minimal_polynomial
+    }
+
+    #[test]
+    fn berlekamp_massey_finds_the_recurrence()
+    {
+        //s_(n + 4) = s_(n + 1) + s_n, the sequence of the primitive polynomial x^4 + x + 1
+        let mut sequence = vec![true, false, false, false];
+        for index in 4..30
+        {
+            let next = sequence[index - 3] ^ sequence[index - 4];
+            sequence.push(next);
+        }
+
+        let minimal_polynomial = berlekamp_massey(&sequence);
+        assert!(minimal_polynomial == polynomial(&[0, 1, 4]));
+        assert_eq!(minimal_polynomial.order(), Ok(15));
+    }
+
+    #[test]
+    fn matrix_minimal_polynomial_round_trip()
+    {
+        //Block diagonal: the companion matrix of x^3 + x + 1, a 2x2 nilpotent Jordan block and a 2x2 Jordan block for the eigenvalue 1.
+        //The minimal polynomial is (x^3 + x + 1) x^2 (x + 1)^2
+        let matrix: Vec<u64> = vec!
+        [
+            0b0000100, //Companion block on the columns 0..3: e_0 -> e_1, e_1 -> e_2, e_2 -> e_0 + e_1
+            0b0000101,
+            0b0000010,
+            0b0010000, //Nilpotent block on the columns 3..5: e_3 -> 0, e_4 -> e_3
+            0b0000000,
+            0b1100000, //Unipotent block on the columns 5..7: e_5 -> e_5, e_6 -> e_5 + e_6
+            0b1000000,
+        ];
+
+        let expected = polynomial(&[0, 1, 3]).mul(&polynomial(&[2])).mul(&polynomial(&[0, 2]));
+
+        let minimal_polynomial = matrix_minimal_polynomial(&matrix);
+        assert!(minimal_polynomial == expected);
+        assert_eq!(minimal_polynomial.trailing_zeros(), 2);
+        assert!(minimal_polynomial.div_rem(&polynomial(&[0, 1, 3])).1.is_zero());
+
+        //The cycle part without the x^2 factor has the order lcm(7, 2)
+        let cycle_part = minimal_polynomial.div_rem(&Gf2Polynomial::monomial(2)).0;
+        assert_eq!(cycle_part.order(), Ok(14));
+    }
+}
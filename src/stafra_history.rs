@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+
+pub const DEFAULT_HISTORY_FRAMES:  u32 = 64;
+pub const DEFAULT_KEYFRAME_PERIOD: u32 = 64;
+
+//All snapshots together must fit into this amount of memory. Big boards get shorter history
+const HISTORY_MEMORY_BUDGET: u64 = 512 * 1024 * 1024;
+
+//When there are more keyframes, every second one is dropped and the keyframe period is doubled
+const MAX_KEYFRAMES: usize = 32;
+
+//Past frames of the simulation: a ring of the latest frames and keyframes for older ones.
+//Frames between keyframes are restored by replaying the simulation from the nearest older keyframe.
+//The snapshot type depends on the backend: GPU textures for StafraState, bit rows for StafraCpuState
+pub struct FrameHistory<Snapshot>
+{
+    requested_ring_depth:      usize,
+    requested_keyframe_period: u32,
+
+    ring_depth:      usize,
+    max_keyframes:   usize,
+    keyframe_period: u32,
+
+    ring:      VecDeque<(u32, Snapshot)>,
+    keyframes: Vec<(u32, Snapshot)>,
+
+    //The furthest recorded frame. Truncating the history doesn't change it, the frames up to it can be calculated again
+    last_frame: Option<u32>
+}
+
+impl<Snapshot> FrameHistory<Snapshot>
+{
+    pub fn new(ring_depth: u32, keyframe_period: u32) -> Self
+    {
+        Self
+        {
+            requested_ring_depth:      ring_depth as usize,
+            requested_keyframe_period: std::cmp::max(keyframe_period, 1),
+
+            ring_depth:      0,
+            max_keyframes:   0,
+            keyframe_period: std::cmp::max(keyframe_period, 1),
+
+            ring:      VecDeque::new(),
+            keyframes: Vec::new(),
+
+            last_frame: None
+        }
+    }
+
+    pub fn set_depth(&mut self, ring_depth: u32, keyframe_period: u32, snapshot_bytes: u64)
+    {
+        self.requested_ring_depth      = ring_depth as usize;
+        self.requested_keyframe_period = std::cmp::max(keyframe_period, 1);
+        self.reset(snapshot_bytes);
+    }
+
+    //Drops all snapshots. Must be called after the board is reset or resized, since the snapshots don't match the board anymore
+    pub fn reset(&mut self, snapshot_bytes: u64)
+    {
+        //Half of the budget goes to the ring, the rest to the keyframes
+        let max_snapshots = (HISTORY_MEMORY_BUDGET / std::cmp::max(snapshot_bytes, 1)) as usize;
+        self.ring_depth      = std::cmp::min(self.requested_ring_depth, max_snapshots / 2);
+        self.max_keyframes   = std::cmp::min(MAX_KEYFRAMES, max_snapshots - self.ring_depth);
+        self.keyframe_period = self.requested_keyframe_period;
+
+        self.ring.clear();
+        self.keyframes.clear();
+        self.last_frame = None;
+    }
+
+    //Adds the frame to the history. save_snapshot gets an old snapshot to overwrite, if there is one, and returns the saved snapshot
+    pub fn record(&mut self, frame_number: u32, mut save_snapshot: impl FnMut(Option<Snapshot>) -> Snapshot)
+    {
+        self.last_frame = Some(self.last_frame.map_or(frame_number, |last_frame| std::cmp::max(last_frame, frame_number)));
+
+        if self.ring_depth > 0
+        {
+            let reused_snapshot = if self.ring.len() >= self.ring_depth {self.ring.pop_front().map(|(_, snapshot)| snapshot)} else {None};
+            self.ring.push_back((frame_number, save_snapshot(reused_snapshot)));
+        }
+
+        if self.max_keyframes > 0 && self.is_keyframe(frame_number)
+        {
+            self.keyframes.push((frame_number, save_snapshot(None)));
+            if self.keyframes.len() > self.max_keyframes
+            {
+                self.keyframe_period *= 2;
+
+                let first_frame     = self.keyframes[0].0;
+                let keyframe_period = self.keyframe_period;
+                self.keyframes.retain(|(keyframe_number, _)| (keyframe_number - first_frame) % keyframe_period == 0);
+            }
+        }
+    }
+
+    //The first recorded frame is always a keyframe, the rest are counted from it
+    fn is_keyframe(&self, frame_number: u32) -> bool
+    {
+        match self.keyframes.first()
+        {
+            Some((first_frame, _)) => frame_number > *first_frame && (frame_number - first_frame) % self.keyframe_period == 0,
+            None                   => true
+        }
+    }
+
+    //The earliest frame that can be restored
+    pub fn first_frame(&self) -> Option<u32>
+    {
+        let first_ring_frame = self.ring.front().map(|(frame_number, _)| *frame_number);
+        let first_keyframe   = self.keyframes.first().map(|(frame_number, _)| *frame_number);
+        first_ring_frame.into_iter().chain(first_keyframe).min()
+    }
+
+    //The frames the timeline can seek to: from the earliest snapshot to the furthest frame calculated since the reset
+    pub fn timeline_range(&self, current_frame: u32) -> (u32, u32)
+    {
+        let first_frame = self.first_frame().map_or(current_frame, |first_frame| std::cmp::min(first_frame, current_frame));
+        let last_frame  = self.last_frame.map_or(current_frame,   |last_frame|  std::cmp::max(last_frame,  current_frame));
+        (first_frame, last_frame)
+    }
+
+    //Drops everything after the frame. The simulation is deterministic, so these frames are recorded again when it goes forward
+    pub fn truncate_after(&mut self, frame_number: u32)
+    {
+        while self.ring.back().is_some_and(|(ring_frame, _)| *ring_frame > frame_number)
+        {
+            self.ring.pop_back();
+        }
+
+        self.keyframes.retain(|(keyframe_number, _)| *keyframe_number <= frame_number);
+    }
+
+    //The latest snapshot not after the frame, together with its frame number. The frames in between have to be replayed
+    pub fn nearest_snapshot(&self, frame_number: u32) -> Option<(u32, &Snapshot)>
+    {
+        self.ring.iter().chain(self.keyframes.iter())
+            .filter(|(snapshot_frame, _)| *snapshot_frame <= frame_number)
+            .max_by_key(|(snapshot_frame, _)| *snapshot_frame)
+            .map(|(snapshot_frame, snapshot)| (*snapshot_frame, snapshot))
+    }
+}
@@ -0,0 +1,300 @@
+//Converts imported images to black and white board-sized images before they become the initial board or the restriction.
+//Error diffusion and the automatic threshold need the whole image, so this is done on the CPU for both backends.
+//The transform shaders then see only pure black and white pixels
+
+use std::cmp::{min, max};
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ImportChannel
+{
+    Luma,
+    Red,
+    Green,
+    Blue,
+    Alpha
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ImportDithering
+{
+    None,
+    Ordered,
+    ErrorDiffusion
+}
+
+//How the image is placed on the board. All scaling is nearest-neighbor
+#[derive(Copy, Clone, PartialEq)]
+pub enum ImagePlacement
+{
+    Crop,    //No scaling, the center of the image is cropped
+    Fit,     //The whole image is scaled to fit the board, the rest of the board is left empty
+    Fill,    //The image is scaled to cover the whole board, the parts that don't fit are cropped
+    Stretch, //Both sides are scaled to the board size separately
+    Tile     //No scaling, the image is repeated starting from the top left corner
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct ImageImportOptions
+{
+    pub channel:        ImportChannel,
+    pub threshold:      f32,  //Pixels brighter than the threshold are enabled
+    pub auto_threshold: bool, //Otsu's method, replaces the threshold
+    pub invert:         bool,
+    pub dithering:      ImportDithering,
+    pub placement:      ImagePlacement,
+    pub board_size:     u32,  //Board size for the imported initial state, 0 to derive it from the image size
+    pub offset_x:       i32,  //Offset of the placed image in board cells
+    pub offset_y:       i32
+}
+
+impl Default for ImageImportOptions
+{
+    //The same conversion as before the options were added: Rec.709 luma with the threshold of 0.15, cropped to the board size
+    fn default() -> Self
+    {
+        Self
+        {
+            channel:        ImportChannel::Luma,
+            threshold:      0.15,
+            auto_threshold: false,
+            invert:         false,
+            dithering:      ImportDithering::None,
+            placement:      ImagePlacement::Crop,
+            board_size:     0,
+            offset_x:       0,
+            offset_y:       0
+        }
+    }
+}
+
+//8x8 Bayer matrix for ordered dithering
+const BAYER_MATRIX_8X8: [[u8; 8]; 8] =
+[
+    [ 0, 32,  8, 40,  2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44,  4, 36, 14, 46,  6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [ 3, 35, 11, 43,  1, 33,  9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47,  7, 39, 13, 45,  5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21]
+];
+
+//The board size for the imported initial state: the chosen one or the largest one that fits into the image, with sides of 2^n - 1
+pub fn import_board_size(width: u32, height: u32, options: &ImageImportOptions, max_board_size: u32) -> u32
+{
+    let board_size = if options.board_size != 0 {options.board_size} else {min(width, height)};
+    (min(max(board_size, 1), max_board_size) + 2).next_power_of_two() / 2 - 1
+}
+
+//Returns the RGBA image of board_size x board_size, with every pixel either black or white.
+//The parts of the board not covered by the image are white if background_enabled is set and black otherwise
+pub fn import_image(pixel_data: &[u8], width: u32, height: u32, board_size: u32, background_enabled: bool, options: &ImageImportOptions) -> Vec<u8>
+{
+    let board_size = board_size as usize;
+
+    let source_columns: Vec<Option<usize>> = (0..board_size).map(|x| placed_source_coordinate(options, x as i64 - options.offset_x as i64, board_size, width, height, true)).collect();
+    let source_rows:    Vec<Option<usize>> = (0..board_size).map(|y| placed_source_coordinate(options, y as i64 - options.offset_y as i64, board_size, width, height, false)).collect();
+
+    //None for the pixels not covered by the image
+    let mut values: Vec<Option<f32>> = Vec::with_capacity(board_size * board_size);
+    for source_row in &source_rows
+    {
+        for source_column in &source_columns
+        {
+            values.push(source_row.zip(*source_column).map(|(source_y, source_x)|
+            {
+                let texel_start = (source_y * width as usize + source_x) * 4;
+                let texel       = &pixel_data[texel_start..texel_start + 4];
+
+                let channel_value = match options.channel
+                {
+                    ImportChannel::Luma  => (0.2126 * texel[0] as f32 + 0.7152 * texel[1] as f32 + 0.0722 * texel[2] as f32) / 255.0,
+                    ImportChannel::Red   => texel[0] as f32 / 255.0,
+                    ImportChannel::Green => texel[1] as f32 / 255.0,
+                    ImportChannel::Blue  => texel[2] as f32 / 255.0,
+                    ImportChannel::Alpha => texel[3] as f32 / 255.0
+                };
+
+                if options.invert {1.0 - channel_value} else {channel_value}
+            }));
+        }
+    }
+
+    let threshold = if options.auto_threshold {otsu_threshold(&values)} else {options.threshold};
+
+    let mut enabled_pixels = vec![background_enabled; values.len()];
+    match options.dithering
+    {
+        ImportDithering::None =>
+        {
+            for (enabled, value) in enabled_pixels.iter_mut().zip(values.iter())
+            {
+                if let Some(value) = value
+                {
+                    *enabled = *value > threshold;
+                }
+            }
+        }
+
+        ImportDithering::Ordered =>
+        {
+            //The threshold shifts the dithering pattern, 0.5 gives the usual ordered dithering
+            for (index, (enabled, value)) in enabled_pixels.iter_mut().zip(values.iter()).enumerate()
+            {
+                if let Some(value) = value
+                {
+                    let x = index % board_size;
+                    let y = index / board_size;
+
+                    let bayer_offset = (BAYER_MATRIX_8X8[y % 8][x % 8] as f32 + 0.5) / 64.0 - 0.5;
+                    *enabled = *value + bayer_offset > threshold;
+                }
+            }
+        }
+
+        ImportDithering::ErrorDiffusion =>
+        {
+            //Floyd-Steinberg. The error is only diffused inside the image
+            let width  = board_size;
+            let height = board_size;
+            for y in 0..height
+            {
+                for x in 0..width
+                {
+                    let index = y * width + x;
+
+                    let value = match values[index]
+                    {
+                        Some(value) => value,
+                        None        => continue
+                    };
+
+                    let enabled = value > threshold;
+                    let error   = value - if enabled {1.0} else {0.0};
+                    enabled_pixels[index] = enabled;
+
+                    let mut diffuse_error = |target_index: usize, weight: f32|
+                    {
+                        if let Some(target_value) = &mut values[target_index]
+                        {
+                            *target_value += error * weight / 16.0;
+                        }
+                    };
+
+                    if x + 1 < width
+                    {
+                        diffuse_error(index + 1, 7.0);
+                    }
+
+                    if y + 1 < height
+                    {
+                        if x > 0
+                        {
+                            diffuse_error(index + width - 1, 3.0);
+                        }
+
+                        diffuse_error(index + width, 5.0);
+                        if x + 1 < width
+                        {
+                            diffuse_error(index + width + 1, 1.0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    enabled_pixels.iter().flat_map(|enabled|
+    {
+        let color = if *enabled {255} else {0};
+        [color, color, color, 255]
+    }).collect()
+}
+
+//Maps a board coordinate (with the offset already subtracted) to the image coordinate along one axis
+fn placed_source_coordinate(options: &ImageImportOptions, board_coord: i64, board_size: usize, width: u32, height: u32, horizontal: bool) -> Option<usize>
+{
+    let board_size  = board_size as i64;
+    let source_size = if horizontal {width as i64} else {height as i64};
+
+    //The size of the whole image scaled for the board, and the position of its top left corner
+    let (scaled_size, origin) = match options.placement
+    {
+        ImagePlacement::Crop =>
+        {
+            (source_size, -((source_size - board_size) / 2))
+        }
+
+        ImagePlacement::Fit =>
+        {
+            let scaled_size = max(source_size * board_size / max(width, height) as i64, 1);
+            (scaled_size, (board_size - scaled_size) / 2)
+        }
+
+        ImagePlacement::Fill =>
+        {
+            let scaled_size = source_size * board_size / min(width, height) as i64;
+            (scaled_size, (board_size - scaled_size) / 2)
+        }
+
+        ImagePlacement::Stretch => (board_size, 0),
+
+        ImagePlacement::Tile =>
+        {
+            return Some(board_coord.rem_euclid(source_size) as usize);
+        }
+    };
+
+    let scaled_coord = board_coord - origin;
+    if scaled_coord < 0 || scaled_coord >= scaled_size
+    {
+        return None;
+    }
+
+    //Nearest neighbor, sampled at the cell center
+    Some(((2 * scaled_coord + 1) * source_size / (2 * scaled_size)) as usize)
+}
+
+//The threshold that maximizes the variance between the dark and the bright pixels
+fn otsu_threshold(values: &[Option<f32>]) -> f32
+{
+    let mut histogram = [0u64; 256];
+    for value in values.iter().flatten()
+    {
+        histogram[(value.clamp(0.0, 1.0) * 255.0).round() as usize] += 1;
+    }
+
+    let total_count = histogram.iter().sum::<u64>() as f64;
+    let total_sum: f64 = histogram.iter().enumerate().map(|(level, count)| level as f64 * *count as f64).sum();
+
+    let mut best_level    = 0;
+    let mut best_variance = -1.0;
+
+    let mut dark_count = 0.0;
+    let mut dark_sum   = 0.0;
+    for (level, count) in histogram.iter().enumerate()
+    {
+        dark_count += *count as f64;
+        dark_sum   += level as f64 * *count as f64;
+
+        let bright_count = total_count - dark_count;
+        if dark_count == 0.0 || bright_count == 0.0
+        {
+            continue;
+        }
+
+        let dark_mean   = dark_sum / dark_count;
+        let bright_mean = (total_sum - dark_sum) / bright_count;
+
+        let between_variance = dark_count * bright_count * (dark_mean - bright_mean) * (dark_mean - bright_mean);
+        if between_variance > best_variance
+        {
+            best_variance = between_variance;
+            best_level    = level;
+        }
+    }
+
+    //Pixels at the best level belong to the dark class
+    (best_level as f32 + 0.5) / 255.0
+}
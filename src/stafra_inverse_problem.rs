@@ -0,0 +1,293 @@
+//Inverse problem: the initial board that turns into the target board after the given number of steps.
+//One step lights the same cells as clicking every lit cell in Lights Out, so each step back is a Lights Out puzzle whose clicks are the previous board.
+//If the click rule makes the step not invertible, some targets can't be reached. Each step back then drops the equations it can't satisfy,
+//and a greedy search flips single cells of the initial board while that decreases the number of wrong cells
+
+use std::cmp::{min, max};
+
+use super::stafra_board_state_bindings::ImageData;
+use super::stafra_lights_out::{LightsOutChase, click_rule_offsets};
+
+//Every step back is a separate Lights Out puzzle
+pub const MAX_INVERSE_PROBLEM_STEPS: u32 = 64;
+
+//Upper bound for the number of cell operations of the greedy search. The search stops with the best board found so far
+const INVERSE_PROBLEM_GREEDY_MAX_WORK: u64 = 1 << 30;
+
+pub struct InverseProblemSolution
+{
+    pub board_size:    u32,
+    pub step_count:    u32,
+    pub initial_board: Vec<u8>, //1 for the lit cells, one byte per cell
+    pub wrong_cells:   u64      //The cells that differ from the target after step_count steps, 0 for the exact solution
+}
+
+impl InverseProblemSolution
+{
+    //Lit cells are white on black, so the image can be loaded as a custom board with the default import options
+    pub fn initial_board_image(&self) -> ImageData
+    {
+        let mut pixel_data = Vec::with_capacity(self.initial_board.len() * 4);
+        for cell in &self.initial_board
+        {
+            let value = if *cell != 0 {255} else {0};
+            pixel_data.extend_from_slice(&[value, value, value, 255]);
+        }
+
+        ImageData
+        {
+            pixel_data,
+            image_width:  self.board_size,
+            image_height: self.board_size
+        }
+    }
+}
+
+//The spawn is ignored, the same as in the update period analysis
+pub fn solve_inverse_problem(board_size: u32, target: &[u8], restriction: &[u8], click_rule_data: &[u8; 32 * 32], step_count: u32) -> Result<InverseProblemSolution, String>
+{
+    let size = board_size as usize;
+    if target.len() != size * size || restriction.len() != size * size
+    {
+        return Err(format!("Board data doesn't match the board size {}", board_size));
+    }
+
+    if step_count == 0 || step_count > MAX_INVERSE_PROBLEM_STEPS
+    {
+        return Err(format!("The number of steps must be from 1 to {}", MAX_INVERSE_PROBLEM_STEPS));
+    }
+
+    let target_cells: Vec<u8> = target.iter().zip(restriction.iter()).map(|(cell, restriction_cell)| (*cell != 0 && *restriction_cell != 0) as u8).collect();
+
+    //Same as calc_next_frame, the board doesn't change with an empty click rule
+    let chase = match LightsOutChase::new(board_size, restriction, click_rule_data)
+    {
+        Some(chase) => chase,
+        None        => return Ok(InverseProblemSolution {board_size, step_count, initial_board: target_cells, wrong_cells: 0})
+    };
+
+    let mut initial_board = target_cells.clone();
+    let mut exact         = true;
+    for _ in 0..step_count
+    {
+        let (previous_board, cells) = chase.solve(initial_board)?;
+
+        exact         = exact && cells.iter().all(|cell| *cell == 0);
+        initial_board = previous_board;
+    }
+
+    if exact
+    {
+        return Ok(InverseProblemSolution {board_size, step_count, initial_board, wrong_cells: 0});
+    }
+
+    let step_operator = StepOperator {board_size: size, restriction, offsets: click_rule_offsets(click_rule_data)};
+
+    let mut wrong_board = initial_board.clone();
+    for _ in 0..step_count
+    {
+        wrong_board = step_operator.step(&wrong_board, 0, 0, size, size);
+    }
+
+    for (wrong_cell, target_cell) in wrong_board.iter_mut().zip(target_cells.iter())
+    {
+        *wrong_cell ^= *target_cell;
+    }
+
+    step_operator.greedy_search(&mut initial_board, &mut wrong_board, step_count);
+
+    let wrong_cells = wrong_board.iter().filter(|cell| **cell != 0).count() as u64;
+    Ok(InverseProblemSolution {board_size, step_count, initial_board, wrong_cells})
+}
+
+//The same step as calc_next_rows, on a rectangle of the board with one byte per cell
+struct StepOperator<'a>
+{
+    board_size:  usize,
+    restriction: &'a [u8],
+    offsets:     Vec<(i64, i64)>
+}
+
+impl<'a> StepOperator<'a>
+{
+    //The cells outside of the rectangle are treated as 0
+    fn step(&self, cells: &[u8], left: usize, top: usize, width: usize, height: usize) -> Vec<u8>
+    {
+        let mut next_cells = vec![0u8; width * height];
+        for y in 0..height
+        {
+            for x in 0..width
+            {
+                if self.restriction[(top + y) * self.board_size + left + x] == 0
+                {
+                    continue;
+                }
+
+                //next_board[x, y] = SUM(prev_board[x + xi, y + yi]) mod 2 for all enabled (xi, yi)
+                let mut cell_sum = 0;
+                for (offset_x, offset_y) in &self.offsets
+                {
+                    let (source_x, source_y) = (x as i64 + offset_x, y as i64 + offset_y);
+                    if source_x >= 0 && source_y >= 0 && source_x < width as i64 && source_y < height as i64
+                    {
+                        cell_sum ^= cells[source_y as usize * width + source_x as usize];
+                    }
+                }
+
+                next_cells[y * width + x] = cell_sum;
+            }
+        }
+
+        next_cells
+    }
+
+    //A lit cell can only change the cells within step_count * reach after step_count steps, so its effect is computed on that rectangle only.
+    //A cell is flipped if more than half of the cells it changes are wrong. Passes are repeated until no cell is flipped
+    fn greedy_search(&self, initial_board: &mut [u8], wrong_board: &mut [u8], step_count: u32)
+    {
+        let size  = self.board_size;
+        let reach = self.offsets.iter().map(|(offset_x, offset_y)| max(offset_x.abs(), offset_y.abs())).max().unwrap_or(0) as usize;
+        let range = reach * step_count as usize;
+
+        let mut work         = 0;
+        let mut cell_flipped = true;
+        while cell_flipped
+        {
+            cell_flipped = false;
+            for (cell_index, restriction_cell) in self.restriction.iter().enumerate()
+            {
+                if *restriction_cell == 0
+                {
+                    continue;
+                }
+
+                let (cell_x, cell_y) = (cell_index % size, cell_index / size);
+                let (left, top)      = (cell_x.saturating_sub(range), cell_y.saturating_sub(range));
+                let (right, bottom)  = (min(cell_x + range, size - 1), min(cell_y + range, size - 1));
+                let (width, height)  = (right - left + 1, bottom - top + 1);
+
+                work += (width * height) as u64;
+                if work > INVERSE_PROBLEM_GREEDY_MAX_WORK
+                {
+                    return;
+                }
+
+                let wrong_in_range = (top..=bottom).any(|y| wrong_board[y * size + left..=y * size + right].iter().any(|cell| *cell != 0));
+                if !wrong_in_range
+                {
+                    continue;
+                }
+
+                work += (width * height) as u64 * self.offsets.len() as u64 * step_count as u64;
+                if work > INVERSE_PROBLEM_GREEDY_MAX_WORK
+                {
+                    return;
+                }
+
+                let mut changed_cells = vec![0u8; width * height];
+                changed_cells[(cell_y - top) * width + cell_x - left] = 1;
+                for _ in 0..step_count
+                {
+                    changed_cells = self.step(&changed_cells, left, top, width, height);
+                }
+
+                let mut changed_count = 0;
+                let mut fixed_count   = 0;
+                for (index, changed_cell) in changed_cells.iter().enumerate()
+                {
+                    if *changed_cell != 0
+                    {
+                        changed_count += 1;
+                        fixed_count   += wrong_board[(top + index / width) * size + left + index % width] as u32;
+                    }
+                }
+
+                if fixed_count * 2 <= changed_count
+                {
+                    continue;
+                }
+
+                initial_board[cell_index] ^= 1;
+                for (index, changed_cell) in changed_cells.iter().enumerate()
+                {
+                    wrong_board[(top + index / width) * size + left + index % width] ^= *changed_cell;
+                }
+
+                cell_flipped = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::stafra_test_helpers::{click_rule_from_cells, cross_click_rule, random_board};
+
+    //Steps the board forward the same way calc_next_frame does
+    fn step_forward(board_size: u32, board: &[u8], restriction: &[u8], click_rule_data: &[u8; 32 * 32], step_count: u32) -> Vec<u8>
+    {
+        let size    = board_size as i64;
+        let offsets = click_rule_offsets(click_rule_data);
+
+        let mut cells = board.to_vec();
+        for _ in 0..step_count
+        {
+            let mut next_cells = vec![0u8; cells.len()];
+            for (index, next_cell) in next_cells.iter_mut().enumerate().filter(|(index, _)| restriction[*index] != 0)
+            {
+                let (x, y) = (index as i64 % size, index as i64 / size);
+                for (offset_x, offset_y) in &offsets
+                {
+                    let (source_x, source_y) = (x + offset_x, y + offset_y);
+                    if source_x >= 0 && source_y >= 0 && source_x < size && source_y < size
+                    {
+                        *next_cell ^= cells[(source_y * size + source_x) as usize];
+                    }
+                }
+            }
+
+            cells = next_cells;
+        }
+
+        cells
+    }
+
+    #[test]
+    fn exact_solution_steps_into_the_target()
+    {
+        let skewed_click_rule = click_rule_from_cells(&[(15, 15), (17, 15), (14, 16), (15, 13)]);
+
+        //Both rules have an invertible step on 7x7, so every target has an exact solution
+        let restriction = vec![1u8; 49];
+        for click_rule_data in [cross_click_rule(), skewed_click_rule]
+        {
+            for step_count in 1..=4
+            {
+                let target   = random_board(49, step_count as u64 * 17);
+                let solution = solve_inverse_problem(7, &target, &restriction, &click_rule_data, step_count).unwrap();
+
+                assert_eq!(solution.wrong_cells, 0);
+                assert_eq!(step_forward(7, &solution.initial_board, &restriction, &click_rule_data, step_count), target);
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_cells_match_the_stepped_board()
+    {
+        //5x5 isn't invertible for the cross rule, so most targets are only approximated
+        let click_rule_data = cross_click_rule();
+        let restriction     = vec![1u8; 25];
+        for seed in 1..10
+        {
+            let target   = random_board(25, seed);
+            let solution = solve_inverse_problem(5, &target, &restriction, &click_rule_data, 2).unwrap();
+
+            let stepped_board = step_forward(5, &solution.initial_board, &restriction, &click_rule_data, 2);
+            let wrong_cells   = stepped_board.iter().zip(target.iter()).filter(|(stepped_cell, target_cell)| stepped_cell != target_cell).count() as u64;
+            assert_eq!(solution.wrong_cells, wrong_cells);
+        }
+    }
+}
@@ -0,0 +1,638 @@
+//Lights Out play mode. A move at a cell toggles every cell the click rule covers around it: the cell (x - xi, y - yi) for each enabled (xi, yi).
+//These are the same cells a single lit cell toggles on the next step. Restricted cells are never toggled. The goal is to turn all the lights off
+
+use std::cmp::{min, max};
+
+use super::stafra_board_state_bindings::ImageData;
+
+//A single move, the clicked cell of the board
+#[derive(Copy, Clone, PartialEq)]
+pub struct LightsOutMove
+{
+    pub x: u32,
+    pub y: u32
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct LightsOutStatus
+{
+    pub move_count: u32,
+    pub lit_cells:  Option<u64> //None while the board is being read back
+}
+
+impl LightsOutStatus
+{
+    pub fn solved(&self) -> bool
+    {
+        self.lit_cells == Some(0)
+    }
+}
+
+//The largest distance between the clicked cell and a toggled cell, for click rules with offsets from -15 to 16
+pub const LIGHTS_OUT_MAX_REACH: u32 = 16;
+
+//Upper bound for the number of 64-bit word operations of the solver. Larger puzzles are rejected instead of freezing the page
+const LIGHTS_OUT_SOLVER_MAX_WORK: u64 = 1 << 32;
+
+//The cells to click to turn all the lights off. Clicks commute, so the order doesn't matter
+pub struct LightsOutSolution
+{
+    pub board_size:  u32,
+    pub clicks:      Vec<u8>, //1 for the clicked cells, one byte per cell
+    pub click_count: u64
+}
+
+impl LightsOutSolution
+{
+    //Clicked cells are drawn over the board, the rest is transparent
+    pub fn overlay_image(&self) -> ImageData
+    {
+        self.solution_image([0, 0, 0, 0], [255, 64, 64, 192])
+    }
+
+    //Clicked cells are white on black
+    pub fn export_image(&self) -> ImageData
+    {
+        self.solution_image([0, 0, 0, 255], [255, 255, 255, 255])
+    }
+
+    //A second click at the same cell cancels the first one, so the solution stays valid after each move if the move is toggled in it
+    pub fn toggle_click(&mut self, x: u32, y: u32)
+    {
+        if x >= self.board_size || y >= self.board_size
+        {
+            return;
+        }
+
+        let click = &mut self.clicks[(y * self.board_size + x) as usize];
+        *click = 1 - *click;
+
+        if *click != 0
+        {
+            self.click_count += 1;
+        }
+        else
+        {
+            self.click_count -= 1;
+        }
+    }
+
+    fn solution_image(&self, background_color: [u8; 4], click_color: [u8; 4]) -> ImageData
+    {
+        let mut pixel_data = Vec::with_capacity(self.clicks.len() * 4);
+        for click in &self.clicks
+        {
+            pixel_data.extend_from_slice(if *click != 0 {&click_color} else {&background_color});
+        }
+
+        ImageData
+        {
+            pixel_data,
+            image_width:  self.board_size,
+            image_height: self.board_size
+        }
+    }
+}
+
+//Finds the clicks that turn all the lights off, or None if there are none. Restricted cells can't be clicked and are never toggled
+pub fn solve_lights_out(board_size: u32, board: &[u8], restriction: &[u8], click_rule_data: &[u8; 32 * 32]) -> Result<Option<LightsOutSolution>, String>
+{
+    let size = board_size as usize;
+    if board.len() != size * size || restriction.len() != size * size
+    {
+        return Err(format!("Board data doesn't match the board size {}", board_size));
+    }
+
+    let lit_cells: Vec<u8> = board.iter().zip(restriction.iter()).map(|(cell, restriction_cell)| (*cell != 0 && *restriction_cell != 0) as u8).collect();
+
+    //Clicks don't change anything with an empty click rule
+    let chase = match LightsOutChase::new(board_size, restriction, click_rule_data)
+    {
+        Some(chase) => chase,
+        None =>
+        {
+            if lit_cells.iter().any(|cell| *cell != 0)
+            {
+                return Ok(None);
+            }
+
+            return Ok(Some(LightsOutSolution {board_size, clicks: vec![0; size * size], click_count: 0}));
+        }
+    };
+
+    let (clicks, cells) = chase.solve(lit_cells)?;
+    if cells.iter().any(|cell| *cell != 0)
+    {
+        return Ok(None);
+    }
+
+    let click_count = clicks.iter().filter(|click| **click != 0).count() as u64;
+    Ok(Some(LightsOutSolution {board_size, clicks, click_count}))
+}
+
+//The same offsets as in bake_click_rule.wgsl
+pub fn click_rule_offsets(click_rule_data: &[u8; 32 * 32]) -> Vec<(i64, i64)>
+{
+    let click_rule_size = 32;
+    let max_radius      = (click_rule_size as i64 - 1) / 2;
+
+    click_rule_data.iter().enumerate()
+        .filter(|(_, click_rule_cell)| **click_rule_cell != 0)
+        .map(|(index, _)| ((index % click_rule_size) as i64 - max_radius, (index / click_rule_size) as i64 - max_radius))
+        .collect()
+}
+
+//Light chasing over GF(2): clicks are decided in row-major order, and each click turns off the first cell it toggles, so most clicks follow from the previous ones.
+//The clicks that have no such cell are unknowns. Every cell is kept as a linear function of the unknowns, and the cells no click is responsible for give the equations.
+//Once the unknowns are known, the chase is repeated with the actual values
+pub struct LightsOutChase<'a>
+{
+    pub unknown_count: usize,
+
+    board_size:  usize,
+    restriction: &'a [u8],
+    offsets:     Vec<(i64, i64)>,
+
+    pivot_x:      i64, //The click at (x, y) turns off the cell (x - pivot_x, y - pivot_y)
+    pivot_y:      i64,
+    min_offset_y: i64,
+
+    enabled_count:  u64,
+    equation_count: u64
+}
+
+impl<'a> LightsOutChase<'a>
+{
+    //None for an empty click rule, clicks don't change anything then
+    pub fn new(board_size: u32, restriction: &'a [u8], click_rule_data: &[u8; 32 * 32]) -> Option<Self>
+    {
+        let offsets = click_rule_offsets(click_rule_data);
+
+        //The click at (x, y) toggles the cells (x - xi, y - yi). The first of them in row-major order is the one for the largest (yi, xi)
+        let (pivot_x, pivot_y) = *offsets.iter().max_by_key(|(offset_x, offset_y)| (*offset_y, *offset_x))?;
+        let min_offset_y       = offsets.iter().map(|(_, offset_y)| *offset_y).min().unwrap();
+
+        let mut chase = Self
+        {
+            unknown_count: 0,
+
+            board_size: board_size as usize,
+            restriction,
+            offsets,
+
+            pivot_x,
+            pivot_y,
+            min_offset_y,
+
+            enabled_count:  0,
+            equation_count: 0
+        };
+
+        for y in 0..board_size as i64
+        {
+            for x in 0..board_size as i64
+            {
+                if !chase.cell_enabled(x, y)
+                {
+                    continue;
+                }
+
+                chase.enabled_count += 1;
+                if !chase.cell_enabled(x - pivot_x, y - pivot_y)
+                {
+                    chase.unknown_count += 1;
+                }
+
+                if !chase.cell_enabled(x + pivot_x, y + pivot_y)
+                {
+                    chase.equation_count += 1;
+                }
+            }
+        }
+
+        Some(chase)
+    }
+
+    pub fn cell_enabled(&self, x: i64, y: i64) -> bool
+    {
+        let size = self.board_size as i64;
+        x >= 0 && y >= 0 && x < size && y < size && self.restriction[(y * size + x) as usize] != 0
+    }
+
+    //Each equation is a bit vector of the unknown coefficients followed by the constant term, the sum of the terms must be 0
+    pub fn equations(&self, lit_cells: &[u8]) -> Result<Vec<Vec<u64>>, String>
+    {
+        let size = self.board_size;
+
+        //One bit per unknown plus the constant term
+        let word_count    = (self.unknown_count + 1 + 63) / 64;
+        let constant_bit  = self.unknown_count;
+        let chase_work    = self.enabled_count * self.offsets.len() as u64 * word_count as u64;
+        let solution_work = self.equation_count * min(self.equation_count, self.unknown_count as u64) * word_count as u64;
+        if chase_work + solution_work > LIGHTS_OUT_SOLVER_MAX_WORK
+        {
+            return Err(format!("The puzzle is too large for the solver: {} unknown clicks and {} equations", self.unknown_count, self.equation_count));
+        }
+
+        //Only the rows the current row of clicks toggles are stored
+        let row_window = (self.pivot_y - self.min_offset_y + 1) as usize;
+        let cell_start = |x: i64, y: i64| ((y.rem_euclid(row_window as i64) as usize) * size + x as usize) * word_count;
+
+        let mut cell_values   = vec![0u64; row_window * size * word_count];
+        let mut click_value   = vec![0u64; word_count];
+        let mut equations     = Vec::with_capacity(self.equation_count as usize);
+        let mut unknown_index = 0;
+
+        let first_row = min(self.min_offset_y, 0);
+        let last_row  = max(size as i64 - 1 + self.pivot_y, size as i64 - 1);
+        for click_y in first_row..=last_row
+        {
+            //The slot of the row is freed by the row finished on the previous iteration
+            let load_y = click_y - self.min_offset_y;
+            if load_y >= 0 && load_y < size as i64
+            {
+                for x in 0..size as i64
+                {
+                    let start = cell_start(x, load_y);
+                    cell_values[start..start + word_count].fill(0);
+                    cell_values[start + constant_bit / 64] |= (lit_cells[load_y as usize * size + x as usize] as u64) << (constant_bit % 64);
+                }
+            }
+
+            if click_y >= 0 && click_y < size as i64
+            {
+                for click_x in 0..size as i64
+                {
+                    if !self.cell_enabled(click_x, click_y)
+                    {
+                        continue;
+                    }
+
+                    if self.cell_enabled(click_x - self.pivot_x, click_y - self.pivot_y)
+                    {
+                        //The click is needed if the first cell it toggles is still lit, every other click that toggles that cell is already decided
+                        let start = cell_start(click_x - self.pivot_x, click_y - self.pivot_y);
+                        click_value.copy_from_slice(&cell_values[start..start + word_count]);
+                    }
+                    else
+                    {
+                        click_value.fill(0);
+                        click_value[unknown_index / 64] |= 1 << (unknown_index % 64);
+                        unknown_index += 1;
+                    }
+
+                    for (offset_x, offset_y) in &self.offsets
+                    {
+                        let (x, y) = (click_x - offset_x, click_y - offset_y);
+                        if self.cell_enabled(x, y)
+                        {
+                            let start = cell_start(x, y);
+                            for (cell_word, click_word) in cell_values[start..start + word_count].iter_mut().zip(click_value.iter())
+                            {
+                                *cell_word ^= *click_word;
+                            }
+                        }
+                    }
+                }
+            }
+
+            //Every click that toggles this row is decided, the cells no click is responsible for must end up turned off
+            let final_y = click_y - self.pivot_y;
+            if final_y >= 0 && final_y < size as i64
+            {
+                for x in 0..size as i64
+                {
+                    if self.cell_enabled(x, final_y) && !self.cell_enabled(x + self.pivot_x, final_y + self.pivot_y)
+                    {
+                        let start = cell_start(x, final_y);
+                        equations.push(cell_values[start..start + word_count].to_vec());
+                    }
+                }
+            }
+        }
+
+        Ok(equations)
+    }
+
+    //Returns the clicks and the cells that stay lit. The equations that can't be satisfied together with the previous ones are dropped,
+    //so the cells stay lit only if the board can't be solved
+    pub fn solve(&self, lit_cells: Vec<u8>) -> Result<(Vec<u8>, Vec<u8>), String>
+    {
+        let mut equations = self.equations(&lit_cells)?;
+
+        //Unknowns that aren't fixed by the equations are set to 0
+        let pivot_unknowns = gf2_row_reduce(&mut equations, self.unknown_count);
+        let mut unknowns   = vec![false; self.unknown_count];
+        for (row, unknown) in pivot_unknowns.iter().enumerate()
+        {
+            unknowns[*unknown] = gf2_bit_set(&equations[row], self.unknown_count);
+        }
+
+        Ok(self.replay(lit_cells, &unknowns))
+    }
+
+    //Repeats the chase with the known unknowns. Returns the clicks and the resulting cells
+    pub fn replay(&self, lit_cells: Vec<u8>, unknowns: &[bool]) -> (Vec<u8>, Vec<u8>)
+    {
+        let size = self.board_size;
+
+        let mut cells  = lit_cells;
+        let mut clicks = vec![0u8; size * size];
+
+        let mut unknown_index = 0;
+        for click_y in 0..size as i64
+        {
+            for click_x in 0..size as i64
+            {
+                if !self.cell_enabled(click_x, click_y)
+                {
+                    continue;
+                }
+
+                let clicked = if self.cell_enabled(click_x - self.pivot_x, click_y - self.pivot_y)
+                {
+                    cells[(click_y - self.pivot_y) as usize * size + (click_x - self.pivot_x) as usize] != 0
+                }
+                else
+                {
+                    unknown_index += 1;
+                    unknowns[unknown_index - 1]
+                };
+
+                if !clicked
+                {
+                    continue;
+                }
+
+                clicks[click_y as usize * size + click_x as usize] = 1;
+                for (offset_x, offset_y) in &self.offsets
+                {
+                    let (x, y) = (click_x - offset_x, click_y - offset_y);
+                    if self.cell_enabled(x, y)
+                    {
+                        cells[y as usize * size + x as usize] ^= 1;
+                    }
+                }
+            }
+        }
+
+        (clicks, cells)
+    }
+}
+
+pub fn gf2_bit_set(row: &[u64], bit: usize) -> bool
+{
+    (row[bit / 64] >> (bit % 64)) & 1 != 0
+}
+
+//Brings the rows to the reduced row echelon form over GF(2), so each pivot column is set in a single row. Returns the pivot columns in row order.
+//The rows after the pivot rows have all the first column_count bits zeroed
+pub fn gf2_row_reduce(rows: &mut [Vec<u64>], column_count: usize) -> Vec<usize>
+{
+    let mut pivot_columns = Vec::new();
+    for column in 0..column_count
+    {
+        let pivot_row = pivot_columns.len();
+        let found_row = match (pivot_row..rows.len()).find(|row| gf2_bit_set(&rows[*row], column))
+        {
+            Some(found_row) => found_row,
+            None            => continue
+        };
+
+        rows.swap(pivot_row, found_row);
+
+        let pivot_values = rows[pivot_row].clone();
+        for (row, values) in rows.iter_mut().enumerate()
+        {
+            if row != pivot_row && gf2_bit_set(values, column)
+            {
+                for (word, pivot_word) in values.iter_mut().zip(pivot_values.iter())
+                {
+                    *word ^= *pivot_word;
+                }
+            }
+        }
+
+        pivot_columns.push(column);
+    }
+
+    pivot_columns
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::stafra_test_helpers::{click_rule_from_cells, cross_click_rule, random_board};
+
+    //Plays the clicks one by one: a click at (x, y) toggles (x - xi, y - yi) for every enabled (xi, yi) of the click rule centered at (15, 15)
+    fn apply_clicks(board_size: u32, board: &[u8], restriction: &[u8], click_rule_data: &[u8; 32 * 32], clicks: &[u8]) -> Vec<u8>
+    {
+        let size = board_size as i64;
+        let offsets: Vec<(i64, i64)> = click_rule_data.iter().enumerate()
+            .filter(|(_, click_rule_cell)| **click_rule_cell != 0)
+            .map(|(index, _)| ((index % 32) as i64 - 15, (index / 32) as i64 - 15))
+            .collect();
+
+        let mut cells: Vec<u8> = board.iter().zip(restriction.iter()).map(|(cell, restriction_cell)| (*cell != 0 && *restriction_cell != 0) as u8).collect();
+        for (index, _) in clicks.iter().enumerate().filter(|(_, click)| **click != 0)
+        {
+            let (x, y) = (index as i64 % size, index as i64 / size);
+            for (offset_x, offset_y) in &offsets
+            {
+                let (target_x, target_y) = (x - offset_x, y - offset_y);
+                if target_x >= 0 && target_y >= 0 && target_x < size && target_y < size && restriction[(target_y * size + target_x) as usize] != 0
+                {
+                    cells[(target_y * size + target_x) as usize] ^= 1;
+                }
+            }
+        }
+
+        cells
+    }
+
+    //Plain Gaussian elimination on the full system, independent of the chase: a row per cell, a column per click and the lit cells as the last column
+    fn solve_gaussian(board_size: u32, board: &[u8], restriction: &[u8], click_rule_data: &[u8; 32 * 32]) -> Option<Vec<u8>>
+    {
+        let cell_count = (board_size * board_size) as usize;
+
+        let mut rows = vec![vec![0u8; cell_count + 1]; cell_count];
+        for click in (0..cell_count).filter(|click| restriction[*click] != 0)
+        {
+            let mut single_click = vec![0u8; cell_count];
+            single_click[click] = 1;
+
+            let toggled = apply_clicks(board_size, &vec![0; cell_count], restriction, click_rule_data, &single_click);
+            for (row, toggled_cell) in rows.iter_mut().zip(toggled.iter())
+            {
+                row[click] = *toggled_cell;
+            }
+        }
+
+        for (cell, row) in rows.iter_mut().enumerate()
+        {
+            row[cell_count] = (board[cell] != 0 && restriction[cell] != 0) as u8;
+        }
+
+        let mut pivot_columns = Vec::new();
+        for column in 0..cell_count
+        {
+            let pivot_row = pivot_columns.len();
+            let found_row = match (pivot_row..cell_count).find(|row| rows[*row][column] != 0)
+            {
+                Some(found_row) => found_row,
+                None            => continue
+            };
+
+            rows.swap(pivot_row, found_row);
+
+            let pivot_values = rows[pivot_row].clone();
+            for (row, values) in rows.iter_mut().enumerate()
+            {
+                if row != pivot_row && values[column] != 0
+                {
+                    for (value, pivot_value) in values.iter_mut().zip(pivot_values.iter())
+                    {
+                        *value ^= *pivot_value;
+                    }
+                }
+            }
+
+            pivot_columns.push(column);
+        }
+
+        if rows[pivot_columns.len()..].iter().any(|row| row[cell_count] != 0)
+        {
+            return None;
+        }
+
+        let mut clicks = vec![0u8; cell_count];
+        for (row, pivot_column) in pivot_columns.iter().enumerate()
+        {
+            clicks[*pivot_column] = rows[row][cell_count];
+        }
+
+        Some(clicks)
+    }
+
+    #[test]
+    fn solution_clears_the_board()
+    {
+        let board_size      = 6;
+        let restriction     = vec![1u8; 36];
+        let click_rule_data = cross_click_rule();
+
+        for seed in 1..20
+        {
+            let board    = random_board(36, seed);
+            let solution = solve_lights_out(board_size, &board, &restriction, &click_rule_data).unwrap().expect("6x6 Lights Out is always solvable");
+
+            assert_eq!(solution.click_count, solution.clicks.iter().filter(|click| **click != 0).count() as u64);
+            assert!(apply_clicks(board_size, &board, &restriction, &click_rule_data, &solution.clicks).iter().all(|cell| *cell == 0));
+        }
+    }
+
+    #[test]
+    fn unsolvable_board()
+    {
+        let board_size      = 5;
+        let restriction     = vec![1u8; 25];
+        let click_rule_data = cross_click_rule();
+
+        //Clicking this pattern on 5x5 changes nothing, so the kernel is not trivial
+        let quiet_pattern: Vec<u8> = [1, 0, 1, 0, 1,
+                                      1, 0, 1, 0, 1,
+                                      0, 0, 0, 0, 0,
+                                      1, 0, 1, 0, 1,
+                                      1, 0, 1, 0, 1].to_vec();
+        assert!(apply_clicks(board_size, &[0; 25], &restriction, &click_rule_data, &quiet_pattern).iter().all(|cell| *cell == 0));
+
+        //A single corner light overlaps the quiet pattern in one cell, so no clicks turn it off
+        let mut board = vec![0u8; 25];
+        board[0] = 1;
+        assert!(solve_lights_out(board_size, &board, &restriction, &click_rule_data).unwrap().is_none());
+    }
+
+    #[test]
+    fn restricted_board()
+    {
+        let board_size      = 7;
+        let click_rule_data = cross_click_rule();
+
+        //A ring of restricted cells around the center, and a lit restricted cell that must be ignored
+        let mut restriction = vec![1u8; 49];
+        for (x, y) in [(2, 2), (3, 2), (4, 2), (2, 3), (4, 3), (2, 4), (3, 4), (4, 4)]
+        {
+            restriction[y * 7 + x] = 0;
+        }
+
+        for seed in 1..20
+        {
+            let mut board = random_board(49, seed);
+            board[2 * 7 + 2] = 1;
+
+            let gaussian_clicks = solve_gaussian(board_size, &board, &restriction, &click_rule_data);
+            match solve_lights_out(board_size, &board, &restriction, &click_rule_data).unwrap()
+            {
+                Some(solution) =>
+                {
+                    assert!(gaussian_clicks.is_some());
+                    assert!(solution.clicks.iter().zip(restriction.iter()).all(|(click, restriction_cell)| *click == 0 || *restriction_cell != 0));
+                    assert!(apply_clicks(board_size, &board, &restriction, &click_rule_data, &solution.clicks).iter().all(|cell| *cell == 0));
+                }
+                None => assert!(gaussian_clicks.is_none())
+            }
+        }
+    }
+
+    #[test]
+    fn empty_click_rule()
+    {
+        let click_rule_data = [0u8; 32 * 32];
+        let restriction     = vec![1u8; 9];
+
+        let solution = solve_lights_out(3, &[0; 9], &restriction, &click_rule_data).unwrap().expect("An empty board is solved with no clicks");
+        assert_eq!(solution.click_count, 0);
+        assert!(solution.clicks.iter().all(|click| *click == 0));
+
+        let mut board = vec![0u8; 9];
+        board[4] = 1;
+        assert!(solve_lights_out(3, &board, &restriction, &click_rule_data).unwrap().is_none());
+    }
+
+    #[test]
+    fn chasing_matches_gaussian_elimination()
+    {
+        //An asymmetric click rule with offsets on both sides of the center
+        let skewed_click_rule = click_rule_from_cells(&[(15, 15), (17, 15), (14, 16), (15, 13), (16, 17)]);
+
+        let restriction_5x5 = vec![1u8; 25];
+        let restriction_8x8 = vec![1u8; 64];
+        for seed in 1..40
+        {
+            for (board_size, restriction, click_rule_data) in [(5, &restriction_5x5, cross_click_rule()), (5, &restriction_5x5, skewed_click_rule), (8, &restriction_8x8, skewed_click_rule)]
+            {
+                let cell_count = (board_size * board_size) as usize;
+                let board      = random_board(cell_count, seed * 31 + board_size as u64);
+
+                let gaussian_clicks = solve_gaussian(board_size, &board, restriction, &click_rule_data);
+                let chase_solution  = solve_lights_out(board_size, &board, restriction, &click_rule_data).unwrap();
+                assert_eq!(gaussian_clicks.is_some(), chase_solution.is_some());
+
+                if let (Some(gaussian_clicks), Some(chase_solution)) = (gaussian_clicks, chase_solution)
+                {
+                    assert!(apply_clicks(board_size, &board, restriction, &click_rule_data, &gaussian_clicks).iter().all(|cell| *cell == 0));
+                    assert!(apply_clicks(board_size, &board, restriction, &click_rule_data, &chase_solution.clicks).iter().all(|cell| *cell == 0));
+                }
+            }
+        }
+
+        //6x6 has a unique solution for the cross rule, so both methods must find the same clicks
+        let restriction_6x6 = vec![1u8; 36];
+        for seed in 1..20
+        {
+            let board = random_board(36, seed);
+            assert_eq!(solve_gaussian(6, &board, &restriction_6x6, &cross_click_rule()), solve_lights_out(6, &board, &restriction_6x6, &cross_click_rule()).unwrap().map(|solution| solution.clicks));
+        }
+    }
+}
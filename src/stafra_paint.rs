@@ -0,0 +1,167 @@
+//Drawing tools for the board and the restriction. The same shapes are drawn by paint_stroke.wgsl and by the CPU state
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum PaintTool
+{
+    Pencil,    //A segment from the previous to the current cursor position
+    Line,
+    Rectangle,
+    Ellipse,   //Inscribed into the rectangle between the start and the end
+    Fill       //Flood fill of the 4-connected area around the start cell
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum PaintTarget
+{
+    Board,
+    Restriction
+}
+
+//A single paint command. All coordinates are in cells and can be outside of the board
+#[derive(Copy, Clone, PartialEq)]
+pub struct PaintStroke
+{
+    pub tool:    PaintTool,
+    pub target:  PaintTarget,
+    pub start_x: i32,
+    pub start_y: i32,
+    pub end_x:   i32,
+    pub end_y:   i32,
+    pub radius:  u32,  //Brush radius for pencil, lines and outlines, 0 for 1-cell wide lines
+    pub filled:  bool, //Rectangles and ellipses are outlines unless filled
+    pub enabled: bool  //The value for the painted cells. For the restriction, disabled cells are restricted
+}
+
+impl PaintStroke
+{
+    //Inclusive range of the board cells the stroke can change, or None if it doesn't touch the board.
+    //Flood fill can change the whole board
+    pub fn cell_bounds(&self, board_size: u32) -> Option<(u32, u32, u32, u32)>
+    {
+        let last_cell = board_size as i64 - 1;
+        let radius    = self.radius as i64;
+
+        let (min_x, min_y, max_x, max_y) = match self.tool
+        {
+            PaintTool::Fill =>
+            {
+                if self.start_x < 0 || self.start_y < 0 || self.start_x as i64 > last_cell || self.start_y as i64 > last_cell
+                {
+                    return None;
+                }
+
+                (0, 0, last_cell, last_cell)
+            }
+
+            _ =>
+            {
+                let min_x = std::cmp::min(self.start_x, self.end_x) as i64 - radius;
+                let min_y = std::cmp::min(self.start_y, self.end_y) as i64 - radius;
+                let max_x = std::cmp::max(self.start_x, self.end_x) as i64 + radius;
+                let max_y = std::cmp::max(self.start_y, self.end_y) as i64 + radius;
+                (min_x, min_y, max_x, max_y)
+            }
+        };
+
+        if max_x < 0 || max_y < 0 || min_x > last_cell || min_y > last_cell
+        {
+            return None;
+        }
+
+        Some((min_x.clamp(0, last_cell) as u32, min_y.clamp(0, last_cell) as u32, max_x.clamp(0, last_cell) as u32, max_y.clamp(0, last_cell) as u32))
+    }
+
+    //The same calculation as in paint_stroke.wgsl. Always false for flood fill, it depends on the board
+    pub fn cell_covered(&self, x: i32, y: i32) -> bool
+    {
+        let radius = self.radius as i32;
+
+        let min_x = std::cmp::min(self.start_x, self.end_x);
+        let min_y = std::cmp::min(self.start_y, self.end_y);
+        let max_x = std::cmp::max(self.start_x, self.end_x);
+        let max_y = std::cmp::max(self.start_y, self.end_y);
+
+        match self.tool
+        {
+            PaintTool::Pencil | PaintTool::Line =>
+            {
+                //Distance to the segment, measured from the cell centers
+                let segment_x = (self.end_x - self.start_x) as f32;
+                let segment_y = (self.end_y - self.start_y) as f32;
+                let point_x   = (x - self.start_x) as f32;
+                let point_y   = (y - self.start_y) as f32;
+
+                let segment_length_sq = segment_x * segment_x + segment_y * segment_y;
+                let t = if segment_length_sq > 0.0 {((point_x * segment_x + point_y * segment_y) / segment_length_sq).clamp(0.0, 1.0)} else {0.0};
+
+                let distance_x = point_x - segment_x * t;
+                let distance_y = point_y - segment_y * t;
+                let max_distance = radius as f32 + 0.5;
+                distance_x * distance_x + distance_y * distance_y <= max_distance * max_distance
+            }
+
+            PaintTool::Rectangle =>
+            {
+                let inside_outer = x >= min_x - radius && x <= max_x + radius && y >= min_y - radius && y <= max_y + radius;
+                let inside_inner = x >  min_x + radius && x <  max_x - radius && y >  min_y + radius && y <  max_y - radius;
+                inside_outer && (self.filled || !inside_inner)
+            }
+
+            PaintTool::Ellipse =>
+            {
+                let center_x = (min_x + max_x) as f32 * 0.5;
+                let center_y = (min_y + max_y) as f32 * 0.5;
+                let offset_x = x as f32 - center_x;
+                let offset_y = y as f32 - center_y;
+
+                let semi_axis_x = (max_x - min_x) as f32 * 0.5;
+                let semi_axis_y = (max_y - min_y) as f32 * 0.5;
+                let thickness   = radius as f32 + 0.5;
+
+                let inside_ellipse = |axis_x: f32, axis_y: f32|
+                {
+                    axis_x > 0.0 && axis_y > 0.0 && (offset_x / axis_x) * (offset_x / axis_x) + (offset_y / axis_y) * (offset_y / axis_y) <= 1.0
+                };
+
+                let inside_outer = inside_ellipse(semi_axis_x + thickness, semi_axis_y + thickness);
+                let inside_inner = inside_ellipse(semi_axis_x - thickness, semi_axis_y - thickness);
+                inside_outer && (self.filled || !inside_inner)
+            }
+
+            PaintTool::Fill => false
+        }
+    }
+
+    //Flood fill on a square board of one byte per cell, the same fill as paint_rows of the CPU state.
+    //The GPU state reads the board back to fill it here
+    pub fn flood_fill_cells(&self, cells: &mut [u8], board_size: usize)
+    {
+        if self.tool != PaintTool::Fill || self.cell_bounds(board_size as u32).is_none()
+        {
+            return;
+        }
+
+        let seed_x     = self.start_x as usize;
+        let seed_y     = self.start_y as usize;
+        let seed_value = cells[seed_y * board_size + seed_x] != 0;
+        if seed_value == self.enabled
+        {
+            return;
+        }
+
+        let mut pending_cells = vec![(seed_x, seed_y)];
+        cells[seed_y * board_size + seed_x] = self.enabled as u8;
+        while let Some((x, y)) = pending_cells.pop()
+        {
+            let neighbors = [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)];
+            for (neighbor_x, neighbor_y) in neighbors
+            {
+                if neighbor_x < board_size && neighbor_y < board_size && (cells[neighbor_y * board_size + neighbor_x] != 0) == seed_value
+                {
+                    cells[neighbor_y * board_size + neighbor_x] = self.enabled as u8;
+                    pending_cells.push((neighbor_x, neighbor_y));
+                }
+            }
+        }
+    }
+}
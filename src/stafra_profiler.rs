@@ -0,0 +1,362 @@
+use
+{
+    std::collections::VecDeque,
+    std::sync::Mutex,
+    std::sync::Arc,
+    std::sync::atomic::{AtomicBool, Ordering}
+};
+
+//The largest number of timestamps written between two resolves. Passes that don't fit are not measured
+const MAX_FRAME_TIMESTAMPS: u32 = 128;
+
+//The number of frames the averages are calculated over
+const ROLLING_AVERAGE_FRAMES: usize = 60;
+
+pub struct PassTiming
+{
+    pub name:       &'static str,
+    pub average_ms: f64
+}
+
+pub struct ProfilerReport
+{
+    pub timestamp_queries: bool, //GPU timestamps if true, CPU wall-clock time of recording the passes otherwise
+    pub passes:            Vec<PassTiming>
+}
+
+//Measures the time of the passes recorded each frame. Passes with the same name are summed up within a frame.
+//Uses timestamp queries if the adapter supports them, otherwise falls back to CPU wall-clock time
+pub struct PassProfiler
+{
+    enabled: bool,
+
+    timestamp_queries: Option<TimestampQueries>,
+
+    open_pass_name:     Option<&'static str>,
+    open_pass_start_ms: f64,
+    open_pass_recorded: bool,
+
+    frame_samples: Vec<(&'static str, f64)>,
+    averages:      Arc<Mutex<RollingAverages>>,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    time_origin: std::time::Instant
+}
+
+struct TimestampQueries
+{
+    query_set:        wgpu::QuerySet,
+    resolve_buffer:   wgpu::Buffer,
+    readback_buffer:  Arc<wgpu::Buffer>,
+    timestamp_period: f32,
+
+    query_count:        u32,
+    frame_pass_names:   Vec<&'static str>,
+    pending_pass_names: Option<Vec<&'static str>>,
+    readback_in_flight: Arc<AtomicBool>
+}
+
+struct RollingAverages
+{
+    pass_samples: Vec<(&'static str, VecDeque<f64>)>
+}
+
+impl PassProfiler
+{
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self
+    {
+        let mut profiler = Self::new_cpu();
+        if device.features().contains(wgpu::Features::TIMESTAMP_QUERY)
+        {
+            let timestamp_buffer_size = (MAX_FRAME_TIMESTAMPS as usize * std::mem::size_of::<u64>()) as u64;
+
+            profiler.timestamp_queries = Some(TimestampQueries
+            {
+                query_set: device.create_query_set(&wgpu::QuerySetDescriptor
+                {
+                    label: Some("Pass timestamp query set"),
+                    ty:    wgpu::QueryType::Timestamp,
+                    count: MAX_FRAME_TIMESTAMPS
+                }),
+
+                resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor
+                {
+                    label:              Some("Pass timestamp resolve buffer"),
+                    size:               timestamp_buffer_size,
+                    usage:              wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: false
+                }),
+
+                readback_buffer: Arc::new(device.create_buffer(&wgpu::BufferDescriptor
+                {
+                    label:              Some("Pass timestamp readback buffer"),
+                    size:               timestamp_buffer_size,
+                    usage:              wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false
+                })),
+
+                timestamp_period: queue.get_timestamp_period(),
+
+                query_count:        0,
+                frame_pass_names:   Vec::new(),
+                pending_pass_names: None,
+                readback_in_flight: Arc::new(AtomicBool::new(false))
+            });
+        }
+
+        profiler
+    }
+
+    //Only measures the CPU wall-clock time
+    pub fn new_cpu() -> Self
+    {
+        Self
+        {
+            enabled: false,
+
+            timestamp_queries: None,
+
+            open_pass_name:     None,
+            open_pass_start_ms: 0.0,
+            open_pass_recorded: false,
+
+            frame_samples: Vec::new(),
+            averages:      Arc::new(Mutex::new(RollingAverages{pass_samples: Vec::new()})),
+
+            #[cfg(not(target_arch = "wasm32"))]
+            time_origin: std::time::Instant::now()
+        }
+    }
+
+    pub fn set_enabled(&mut self, enable: bool)
+    {
+        self.enabled = enable;
+        if !enable
+        {
+            self.averages.lock().unwrap().pass_samples.clear();
+        }
+    }
+
+    pub fn begin_pass(&mut self, encoder: &mut wgpu::CommandEncoder, pass_name: &'static str)
+    {
+        if !self.enabled
+        {
+            return;
+        }
+
+        match &mut self.timestamp_queries
+        {
+            Some(timestamp_queries) =>
+            {
+                //The timestamps of the previous frame are still being read, or there's no space for more
+                self.open_pass_name     = Some(pass_name);
+                self.open_pass_recorded = !timestamp_queries.readback_in_flight.load(Ordering::Acquire) && timestamp_queries.query_count + 2 <= MAX_FRAME_TIMESTAMPS;
+                if self.open_pass_recorded
+                {
+                    encoder.write_timestamp(&timestamp_queries.query_set, timestamp_queries.query_count);
+                    timestamp_queries.query_count += 1;
+                    timestamp_queries.frame_pass_names.push(pass_name);
+                }
+            }
+
+            None =>
+            {
+                self.begin_cpu_pass(pass_name);
+            }
+        }
+    }
+
+    pub fn end_pass(&mut self, encoder: &mut wgpu::CommandEncoder)
+    {
+        if !self.enabled
+        {
+            return;
+        }
+
+        match &mut self.timestamp_queries
+        {
+            Some(timestamp_queries) =>
+            {
+                if self.open_pass_name.take().is_some() && self.open_pass_recorded
+                {
+                    encoder.write_timestamp(&timestamp_queries.query_set, timestamp_queries.query_count);
+                    timestamp_queries.query_count += 1;
+                }
+            }
+
+            None =>
+            {
+                self.end_cpu_pass();
+            }
+        }
+    }
+
+    pub fn begin_cpu_pass(&mut self, pass_name: &'static str)
+    {
+        if !self.enabled
+        {
+            return;
+        }
+
+        self.open_pass_name     = Some(pass_name);
+        self.open_pass_start_ms = self.now_ms();
+    }
+
+    pub fn end_cpu_pass(&mut self)
+    {
+        if !self.enabled
+        {
+            return;
+        }
+
+        if let Some(pass_name) = self.open_pass_name.take()
+        {
+            let pass_time_ms = self.now_ms() - self.open_pass_start_ms;
+            self.frame_samples.push((pass_name, pass_time_ms));
+        }
+    }
+
+    //Records the copy of all timestamps written since the last resolve into the readback buffer
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder)
+    {
+        if let Some(timestamp_queries) = &mut self.timestamp_queries
+        {
+            if timestamp_queries.query_count == 0
+            {
+                return;
+            }
+
+            let timestamp_bytes = (timestamp_queries.query_count as usize * std::mem::size_of::<u64>()) as u64;
+            encoder.resolve_query_set(&timestamp_queries.query_set, 0..timestamp_queries.query_count, &timestamp_queries.resolve_buffer, 0);
+            encoder.copy_buffer_to_buffer(&timestamp_queries.resolve_buffer, 0, &timestamp_queries.readback_buffer, 0, timestamp_bytes);
+
+            timestamp_queries.query_count        = 0;
+            timestamp_queries.pending_pass_names = Some(std::mem::take(&mut timestamp_queries.frame_pass_names));
+        }
+    }
+
+    //Must be called after the resolve is submitted. Starts reading the timestamps back, or adds the CPU timings of the frame
+    pub fn finish_frame(&mut self)
+    {
+        if !self.enabled
+        {
+            return;
+        }
+
+        match &mut self.timestamp_queries
+        {
+            Some(timestamp_queries) =>
+            {
+                let pass_names = match timestamp_queries.pending_pass_names.take()
+                {
+                    Some(pass_names) => pass_names,
+                    None             => return
+                };
+
+                timestamp_queries.readback_in_flight.store(true, Ordering::Release);
+
+                let readback_buffer    = timestamp_queries.readback_buffer.clone();
+                let readback_in_flight = timestamp_queries.readback_in_flight.clone();
+                let timestamp_period   = timestamp_queries.timestamp_period as f64;
+                let averages           = self.averages.clone();
+
+                let timestamp_bytes = (pass_names.len() * 2 * std::mem::size_of::<u64>()) as u64;
+                timestamp_queries.readback_buffer.slice(0..timestamp_bytes).map_async(wgpu::MapMode::Read, move |map_result|
+                {
+                    if map_result.is_ok()
+                    {
+                        let frame_samples =
+                        {
+                            let timestamp_data = readback_buffer.slice(0..timestamp_bytes).get_mapped_range();
+                            pass_names.iter().zip(timestamp_data.chunks_exact(2 * std::mem::size_of::<u64>())).map(|(pass_name, timestamp_pair)|
+                            {
+                                let start_timestamp = u64::from_le_bytes(timestamp_pair[0..8].try_into().unwrap());
+                                let end_timestamp   = u64::from_le_bytes(timestamp_pair[8..16].try_into().unwrap());
+
+                                //Timestamps are in units of timestamp_period nanoseconds
+                                (*pass_name, end_timestamp.saturating_sub(start_timestamp) as f64 * timestamp_period / 1_000_000.0)
+                            }).collect::<Vec<_>>()
+                        };
+
+                        readback_buffer.unmap();
+                        averages.lock().unwrap().add_frame(&frame_samples);
+                    }
+
+                    readback_in_flight.store(false, Ordering::Release);
+                });
+            }
+
+            None =>
+            {
+                let frame_samples = std::mem::take(&mut self.frame_samples);
+                if !frame_samples.is_empty()
+                {
+                    self.averages.lock().unwrap().add_frame(&frame_samples);
+                }
+            }
+        }
+    }
+
+    pub fn report(&self) -> ProfilerReport
+    {
+        let averages = self.averages.lock().unwrap();
+        ProfilerReport
+        {
+            timestamp_queries: self.timestamp_queries.is_some(),
+            passes:            averages.pass_samples.iter().map(|(pass_name, samples)| PassTiming
+            {
+                name:       pass_name,
+                average_ms: samples.iter().sum::<f64>() / std::cmp::max(samples.len(), 1) as f64
+            }).collect()
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn now_ms(&self) -> f64
+    {
+        self.time_origin.elapsed().as_secs_f64() * 1000.0
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn now_ms(&self) -> f64
+    {
+        web_sys::window().and_then(|window| window.performance()).map(|performance| performance.now()).unwrap_or(0.0)
+    }
+}
+
+impl RollingAverages
+{
+    fn add_frame(&mut self, frame_samples: &[(&'static str, f64)])
+    {
+        //The same pass can be recorded several times per frame, once for each tile
+        let mut frame_sums: Vec<(&'static str, f64)> = Vec::new();
+        for (pass_name, pass_time_ms) in frame_samples
+        {
+            match frame_sums.iter_mut().find(|(name, _)| name == pass_name)
+            {
+                Some((_, time_sum)) => *time_sum += pass_time_ms,
+                None                => frame_sums.push((pass_name, *pass_time_ms))
+            }
+        }
+
+        for (pass_name, pass_time_ms) in frame_sums
+        {
+            let pass_samples = match self.pass_samples.iter().position(|(name, _)| *name == pass_name)
+            {
+                Some(pass_index) => &mut self.pass_samples[pass_index].1,
+                None =>
+                {
+                    self.pass_samples.push((pass_name, VecDeque::with_capacity(ROLLING_AVERAGE_FRAMES)));
+                    &mut self.pass_samples.last_mut().unwrap().1
+                }
+            };
+
+            if pass_samples.len() == ROLLING_AVERAGE_FRAMES
+            {
+                pass_samples.pop_front();
+            }
+
+            pass_samples.push_back(pass_time_ms);
+        }
+    }
+}
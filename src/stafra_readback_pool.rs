@@ -0,0 +1,168 @@
+use
+{
+    std::sync::Mutex,
+    std::sync::MutexGuard,
+    std::sync::Arc,
+    std::sync::atomic::{AtomicBool, Ordering}
+};
+
+use super::stafra_board_state_bindings::ImageBuffer;
+
+pub const DEFAULT_READBACK_QUEUE_DEPTH: u32 = 4;
+pub const MAX_READBACK_QUEUE_DEPTH:     u32 = 16;
+
+//A ring of staging buffer sets for reading the data back from the GPU.
+//Each set holds one buffer per tile and is reused for the next requests after the previous one is read
+pub struct ReadbackPool
+{
+    slots:     Vec<Arc<ReadbackSlot>>,
+    next_slot: usize
+}
+
+pub struct ReadbackSlot
+{
+    buffers:   Mutex<Vec<ImageBuffer>>,
+    in_flight: AtomicBool
+}
+
+impl ReadbackPool
+{
+    pub fn new(depth: u32) -> Self
+    {
+        let mut pool = Self
+        {
+            slots:     Vec::new(),
+            next_slot: 0
+        };
+
+        pool.set_depth(depth);
+        pool
+    }
+
+    //Slots removed from the ring are kept alive by the pending requests until they are read
+    pub fn set_depth(&mut self, depth: u32)
+    {
+        let depth = std::cmp::max(depth, 1) as usize;
+
+        self.slots.truncate(depth);
+        while self.slots.len() < depth
+        {
+            self.slots.push(Arc::new(ReadbackSlot
+            {
+                buffers:   Mutex::new(Vec::new()),
+                in_flight: AtomicBool::new(false)
+            }));
+        }
+
+        self.next_slot %= depth;
+    }
+
+    pub fn is_full(&self) -> bool
+    {
+        self.slots.iter().all(|slot| slot.in_flight.load(Ordering::Acquire))
+    }
+
+    //Returns the next free slot of the ring, or None if all of them are still waiting to be read
+    pub fn acquire(&mut self) -> Option<Arc<ReadbackSlot>>
+    {
+        for slot_offset in 0..self.slots.len()
+        {
+            let slot_index = (self.next_slot + slot_offset) % self.slots.len();
+            let slot       = &self.slots[slot_index];
+
+            if slot.in_flight.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire).is_ok()
+            {
+                self.next_slot = (slot_index + 1) % self.slots.len();
+                return Some(slot.clone());
+            }
+        }
+
+        None
+    }
+
+    //Acquires slot_count slots at once for requests that read several textures. Either all of them are acquired or none
+    pub fn acquire_many(&mut self, slot_count: usize) -> Option<Vec<Arc<ReadbackSlot>>>
+    {
+        let mut acquired_slots = Vec::with_capacity(slot_count);
+        while acquired_slots.len() < slot_count
+        {
+            match self.acquire()
+            {
+                Some(slot) => acquired_slots.push(slot),
+                None =>
+                {
+                    for slot in acquired_slots
+                    {
+                        slot.in_flight.store(false, Ordering::Release);
+                    }
+
+                    return None;
+                }
+            }
+        }
+
+        Some(acquired_slots)
+    }
+}
+
+impl ReadbackSlot
+{
+    pub fn buffers(&self) -> MutexGuard<'_, Vec<ImageBuffer>>
+    {
+        self.buffers.lock().unwrap()
+    }
+
+    //Maps all buffers of the slot and calls on_mapped after the last one is mapped. Then the buffers are unmapped and the slot is free again.
+    //The lock on the buffer list is released before mapping: wgpu may call the map callback right away, and the last callback locks the list again
+    pub fn map_read(self: Arc<Self>, on_mapped: impl FnOnce(Result<&[ImageBuffer], String>) + Send + 'static)
+    {
+        let mapped_buffers: Vec<Arc<wgpu::Buffer>> = self.buffers().iter().map(|buffer| buffer.image_buffer.clone()).collect();
+        if mapped_buffers.is_empty()
+        {
+            on_mapped(Ok(&[]));
+            self.in_flight.store(false, Ordering::Release);
+            return;
+        }
+
+        //Only successfully mapped buffers get unmapped
+        let map_results  = Arc::new(Mutex::new(vec![None; mapped_buffers.len()]));
+        let on_mapped_rc = Arc::new(Mutex::new(Some(on_mapped)));
+        for (buffer_index, mapped_buffer) in mapped_buffers.iter().enumerate()
+        {
+            let slot_clone         = self.clone();
+            let map_results_clone  = map_results.clone();
+            let on_mapped_rc_clone = on_mapped_rc.clone();
+
+            mapped_buffer.slice(..).map_async(wgpu::MapMode::Read, move |map_result|
+            {
+                let mut map_results = map_results_clone.lock().unwrap();
+                map_results[buffer_index] = Some(map_result);
+                if map_results.iter().any(|map_result| map_result.is_none())
+                {
+                    return;
+                }
+
+                let buffers = slot_clone.buffers();
+                if let Some(on_mapped) = on_mapped_rc_clone.lock().unwrap().take()
+                {
+                    match map_results.iter().flatten().find_map(|map_result| map_result.as_ref().err())
+                    {
+                        Some(error) => on_mapped(Err(format!("Cannot map the readback buffer: {}", error))),
+                        None        => on_mapped(Ok(&buffers))
+                    }
+                }
+
+                for (buffer, map_result) in buffers.iter().zip(map_results.iter().flatten())
+                {
+                    if map_result.is_ok()
+                    {
+                        buffer.image_buffer.unmap();
+                    }
+                }
+
+                drop(buffers);
+                slot_clone.in_flight.store(false, Ordering::Release);
+            });
+        }
+    }
+}
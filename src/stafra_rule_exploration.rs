@@ -0,0 +1,306 @@
+//Batch exploration of click rules. Each rule is simulated on a small board from the corners for the same number of frames,
+//and the final images are tiled into a contact sheet with the base64 code of the rule under each tile.
+//The CSV lists the rule, the period of the board and the fraction of stable cells for each tile
+
+use std::sync::{Arc, Mutex};
+use std::fmt::Write;
+
+use super::app_state::{parse_click_rule_base64, encode_click_rule_base64, decode_base_64_char};
+use super::simulation_backend::SimulationBackend;
+use super::stafra_board_state_bindings::ImageData;
+use super::stafra_click_analysis::{UpdatePeriod, find_start_period};
+use super::stafra_state::{StandardResetBoardType, BatchParams};
+
+//Empty space around the tiles and their labels, in pixels
+const CONTACT_SHEET_MARGIN: u32 = 4;
+
+const CONTACT_SHEET_BACKGROUND: [u8; 4] = [32,  32,  32,  255];
+const CONTACT_SHEET_LABEL:      [u8; 4] = [255, 255, 255, 255];
+
+//One empty pixel between the characters and two between the lines
+const GLYPH_WIDTH:     u32 = 5;
+const GLYPH_HEIGHT:    u32 = 7;
+const GLYPH_ADVANCE_X: u32 = GLYPH_WIDTH  + 1;
+const GLYPH_ADVANCE_Y: u32 = GLYPH_HEIGHT + 2;
+
+//In the order of the base64 alphabet, so decode_base_64_char gives the glyph index. The highest of the 5 bits is the leftmost pixel
+const LABEL_GLYPHS: [[u8; GLYPH_HEIGHT as usize]; 64] =
+[
+    [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001], //A
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110], //B
+    [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110], //C
+    [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110], //D
+    [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111], //E
+    [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000], //F
+    [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111], //G
+    [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001], //H
+    [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110], //I
+    [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100], //J
+    [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001], //K
+    [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111], //L
+    [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001], //M
+    [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001], //N
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110], //O
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000], //P
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101], //Q
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001], //R
+    [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110], //S
+    [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100], //T
+    [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110], //U
+    [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100], //V
+    [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010], //W
+    [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001], //X
+    [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100], //Y
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111], //Z
+    [0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111], //a
+    [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110], //b
+    [0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110], //c
+    [0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111], //d
+    [0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110], //e
+    [0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000], //f
+    [0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110], //g
+    [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001], //h
+    [0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110], //i
+    [0b00010, 0b00000, 0b00110, 0b00010, 0b00010, 0b10010, 0b01100], //j
+    [0b10000, 0b10000, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010], //k
+    [0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110], //l
+    [0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10001, 0b10001], //m
+    [0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001], //n
+    [0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110], //o
+    [0b00000, 0b00000, 0b11110, 0b10001, 0b11110, 0b10000, 0b10000], //p
+    [0b00000, 0b00000, 0b01101, 0b10011, 0b01111, 0b00001, 0b00001], //q
+    [0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000], //r
+    [0b00000, 0b00000, 0b01110, 0b10000, 0b01110, 0b00001, 0b11110], //s
+    [0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110], //t
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b10011, 0b01101], //u
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100], //v
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b10101, 0b10101, 0b01010], //w
+    [0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001], //x
+    [0b00000, 0b00000, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110], //y
+    [0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111], //z
+    [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110], //0
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110], //1
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111], //2
+    [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110], //3
+    [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010], //4
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110], //5
+    [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110], //6
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000], //7
+    [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110], //8
+    [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100], //9
+    [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000], //-
+    [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000], //+
+];
+
+pub struct ExplorationParams
+{
+    pub board_size:  u32,
+    pub frame_count: u32,
+    pub columns:     u32 //Tiles per row of the contact sheet, 0 for a square grid
+}
+
+pub struct ExplorationResult
+{
+    pub click_rule_code: String,
+    pub period:          Option<UpdatePeriod>, //None if the board doesn't repeat within the simulated frames
+    pub stable_fraction: f64,
+    pub final_image:     ImageData
+}
+
+//One base64 code per line. Empty lines and lines starting with # are skipped
+pub fn parse_click_rule_list(click_rule_list: &str) -> Result<Vec<[u8; 32 * 32]>, String>
+{
+    let mut click_rules = Vec::new();
+    for (line_index, line) in click_rule_list.lines().enumerate()
+    {
+        let click_rule_code = line.trim();
+        if click_rule_code.is_empty() || click_rule_code.starts_with('#')
+        {
+            continue;
+        }
+
+        if !click_rule_code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '+')
+        {
+            return Err(format!("Line {}: {} is not a base64 click rule", line_index + 1, click_rule_code));
+        }
+
+        click_rules.push(parse_click_rule_base64(click_rule_code));
+    }
+
+    Ok(click_rules)
+}
+
+//The boards of a batch are read back at once, so bigger batches need more memory for the readback
+const EXPLORATION_BATCH_SIZE: usize = 1024;
+
+//The spawn is disabled, so the period is the one of the board itself. The click rules are simulated in batches, on_result is called after each click rule.
+//The main board of the backend is not affected
+pub fn explore_click_rules(backend: &mut dyn SimulationBackend, click_rules: &[[u8; 32 * 32]], params: &ExplorationParams, mut on_result: impl FnMut(&ExplorationResult)) -> Result<Vec<ExplorationResult>, String>
+{
+    let mut results = Vec::with_capacity(click_rules.len());
+    for click_rule_batch in click_rules.chunks(EXPLORATION_BATCH_SIZE)
+    {
+        let batch_params = BatchParams
+        {
+            board_size:  params.board_size,
+            frame_count: params.frame_count,
+            reset_type:  StandardResetBoardType::Corners,
+            click_rules: click_rule_batch.to_vec()
+        };
+
+        let stabilities = read_batch(backend, batch_params)?;
+        for (click_rule_data, stability) in click_rule_batch.iter().zip(stabilities.iter())
+        {
+            let result = exploration_result(click_rule_data, stability, params.frame_count)?;
+
+            on_result(&result);
+            results.push(result);
+        }
+    }
+
+    Ok(results)
+}
+
+//The result for the stability of the board simulated from the corners for frame_count frames
+pub fn exploration_result(click_rule_data: &[u8; 32 * 32], stability: &ImageData, frame_count: u32) -> Result<ExplorationResult, String>
+{
+    let board_size  = stability.image_width;
+    let restriction = vec![1u8; (board_size * board_size) as usize];
+
+    let period = find_start_period(board_size, &restriction, click_rule_data, StandardResetBoardType::Corners, frame_count)?;
+    Ok(exploration_result_with_period(click_rule_data, stability, period))
+}
+
+//The same result, for the period that is already found
+pub fn exploration_result_with_period(click_rule_data: &[u8; 32 * 32], stability: &ImageData, period: Option<UpdatePeriod>) -> ExplorationResult
+{
+    let stable_cells = stability.pixel_data.iter().filter(|stability_value| **stability_value == 0).count();
+    ExplorationResult
+    {
+        click_rule_code: encode_click_rule_base64(click_rule_data),
+        period,
+        stable_fraction: stable_cells as f64 / stability.pixel_data.len() as f64,
+        final_image:     stability_image(stability)
+    }
+}
+
+//Both backends call the callback before wait_for_completion returns
+pub fn read_batch(backend: &mut dyn SimulationBackend, batch_params: BatchParams) -> Result<Vec<ImageData>, String>
+{
+    let stabilities_arc       = Arc::new(Mutex::new(None));
+    let stabilities_arc_clone = stabilities_arc.clone();
+    backend.post_batch_request(batch_params, Box::new(move |stabilities_result|
+    {
+        *stabilities_arc_clone.lock().unwrap() = Some(stabilities_result);
+    }));
+
+    backend.wait_for_completion();
+
+    let stabilities_result = stabilities_arc.lock().unwrap().take();
+    stabilities_result.unwrap_or(Err("The readback didn't finish".to_string()))
+}
+
+//The same colors as final_state_transform.wgsl gives with the spawn disabled: the cells that never changed are lit
+fn stability_image(stability: &ImageData) -> ImageData
+{
+    let mut pixel_data = Vec::with_capacity(stability.pixel_data.len() * 4);
+    for stability_value in &stability.pixel_data
+    {
+        let value = if *stability_value == 0 {255} else {0};
+        pixel_data.extend_from_slice(&[value, 0, value, 255]);
+    }
+
+    ImageData
+    {
+        pixel_data,
+        image_width:  stability.image_width,
+        image_height: stability.image_height
+    }
+}
+
+//The labels are wrapped to the tile width, and every label gets as many lines as the longest one
+pub fn contact_sheet_image(results: &[ExplorationResult], columns: u32) -> Result<ImageData, String>
+{
+    if results.is_empty()
+    {
+        return Err("The contact sheet needs at least one result".to_string());
+    }
+
+    let tile_size = results.iter().map(|result| result.final_image.image_width).max().unwrap_or(0);
+
+    let label_characters = std::cmp::max((tile_size + 1) / GLYPH_ADVANCE_X, 1) as usize;
+    let label_lines      = results.iter().map(|result| result.click_rule_code.len().div_ceil(label_characters)).max().unwrap_or(0) as u32;
+    let label_height     = (label_lines * GLYPH_ADVANCE_Y).saturating_sub(GLYPH_ADVANCE_Y - GLYPH_HEIGHT);
+
+    let columns = if columns != 0 {columns} else {(results.len() as f64).sqrt().ceil() as u32};
+    let rows    = (results.len() as u32).div_ceil(columns);
+
+    let cell_width  = tile_size + CONTACT_SHEET_MARGIN;
+    let cell_height = tile_size + CONTACT_SHEET_MARGIN + label_height + CONTACT_SHEET_MARGIN;
+
+    let image_width  = CONTACT_SHEET_MARGIN + columns * cell_width;
+    let image_height = CONTACT_SHEET_MARGIN + rows    * cell_height;
+
+    let mut pixel_data = CONTACT_SHEET_BACKGROUND.repeat((image_width * image_height) as usize);
+    for (result_index, result) in results.iter().enumerate()
+    {
+        let tile_left = CONTACT_SHEET_MARGIN + (result_index as u32 % columns) * cell_width;
+        let tile_top  = CONTACT_SHEET_MARGIN + (result_index as u32 / columns) * cell_height;
+
+        let final_image = &result.final_image;
+        for (row_index, image_row) in final_image.pixel_data.chunks(final_image.image_width as usize * 4).enumerate()
+        {
+            let row_start = (((tile_top + row_index as u32) * image_width + tile_left) * 4) as usize;
+            pixel_data[row_start..row_start + image_row.len()].copy_from_slice(image_row);
+        }
+
+        let label_top = tile_top + tile_size + CONTACT_SHEET_MARGIN;
+        for (character_index, character) in result.click_rule_code.chars().enumerate()
+        {
+            let glyph_left = tile_left + (character_index % label_characters) as u32 * GLYPH_ADVANCE_X;
+            let glyph_top  = label_top + (character_index / label_characters) as u32 * GLYPH_ADVANCE_Y;
+
+            let glyph = &LABEL_GLYPHS[decode_base_64_char(character) as usize];
+            for (glyph_y, glyph_row) in glyph.iter().enumerate()
+            {
+                for glyph_x in 0..GLYPH_WIDTH
+                {
+                    if (glyph_row >> (GLYPH_WIDTH - 1 - glyph_x)) & 0x1 == 0
+                    {
+                        continue;
+                    }
+
+                    let pixel_start = (((glyph_top + glyph_y as u32) * image_width + glyph_left + glyph_x) * 4) as usize;
+                    pixel_data[pixel_start..pixel_start + 4].copy_from_slice(&CONTACT_SHEET_LABEL);
+                }
+            }
+        }
+    }
+
+    Ok(ImageData
+    {
+        pixel_data,
+        image_width,
+        image_height
+    })
+}
+
+//The period and the transient are empty for the boards that don't repeat within the simulated frames
+pub fn results_to_csv(results: &[ExplorationResult]) -> String
+{
+    let mut csv = String::new();
+
+    writeln!(csv, "click_rule,period,transient,stable_fraction").unwrap();
+    for result in results
+    {
+        let (period, transient) = match result.period
+        {
+            Some(period) => (period.period.to_string(), period.transient.to_string()),
+            None         => (String::new(), String::new())
+        };
+
+        writeln!(csv, "{},{},{},{:.6}", result.click_rule_code, period, transient, result.stable_fraction).unwrap();
+    }
+
+    csv
+}
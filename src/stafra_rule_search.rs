@@ -0,0 +1,469 @@
+//Evolutionary search for click rules. Each generation keeps the best rules, and fills the rest of the population with the children of the rules
+//picked by tournaments. A child takes the cells on one side of a random cut from one parent and the rest from the other, then each cell
+//within the radius flips with the mutation rate. All boards are simulated from the corners, the same as in the exploration
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use super::app_state::encode_click_rule_base64;
+use super::simulation_backend::SimulationBackend;
+use super::stafra_board_state_bindings::ImageData;
+use super::stafra_click_analysis::{UpdatePeriod, find_start_period};
+use super::stafra_rule_exploration::{ExplorationParams, ExplorationResult, read_batch};
+use super::stafra_state::{StandardResetBoardType, BatchParams, pcg_hash, MAX_RANDOM_CLICK_RULE_RADIUS};
+
+//The whole population is simulated in a single batch
+pub const MAX_SEARCH_POPULATION: u32 = 1024;
+
+//The best rules go to the next generation unchanged
+const SEARCH_ELITE_COUNT: usize = 2;
+
+//The fittest of this many random rules becomes a parent
+const SEARCH_TOURNAMENT_SIZE: usize = 3;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum SearchMetric
+{
+    FractalDimension, //Box-counting dimension of the border of the stable region
+    StableFraction,   //Fraction of stable cells at the last frame
+    Period,           //Period of the board, 0 if it doesn't repeat within the simulated frames
+    Symmetry          //Fraction of cells that match their mirror images, 0 if all cells are stable or all are unstable
+}
+
+pub struct SearchParams
+{
+    pub metric:           SearchMetric,
+    pub population_size:  u32,
+    pub generation_count: u32, //Generations after the first one
+    pub mutation_rate:    f32, //Probability to flip each cell within the radius
+    pub radius:           u32, //Cells farther from the center are never enabled, from 1 to MAX_RANDOM_CLICK_RULE_RADIUS
+    pub best_count:       u32, //Number of the best rules to keep over all generations
+    pub seed:             u32
+}
+
+pub struct SearchCandidate
+{
+    pub click_rule_data: [u8; 32 * 32],
+    pub fitness:         f64,
+    pub stability:       ImageData,
+    pub start_period:    Option<Option<UpdatePeriod>> //Only computed for the period metric. The inner None if the board doesn't repeat within the simulated frames
+}
+
+pub struct GenerationSummary
+{
+    pub generation:      u32,
+    pub best_fitness:    f64,
+    pub mean_fitness:    f64,
+    pub best_click_rule: String
+}
+
+pub struct SearchResult
+{
+    pub generations: Vec<GenerationSummary>,
+    pub best_rules:  Vec<SearchCandidate> //The fittest distinct rules of all generations, the fittest first
+}
+
+impl SearchMetric
+{
+    pub fn from_name(name: &str) -> Option<Self>
+    {
+        match name.to_lowercase().replace('-', "_").as_str()
+        {
+            "fractal_dimension" => Some(SearchMetric::FractalDimension),
+            "stable_fraction"   => Some(SearchMetric::StableFraction),
+            "period"            => Some(SearchMetric::Period),
+            "symmetry"          => Some(SearchMetric::Symmetry),
+            _                   => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            SearchMetric::FractalDimension => "fractal_dimension",
+            SearchMetric::StableFraction   => "stable_fraction",
+            SearchMetric::Period           => "period",
+            SearchMetric::Symmetry         => "symmetry"
+        }
+    }
+
+    //The period metric takes the start period computed by evaluate
+    fn fitness(&self, stability: &ImageData, start_period: Option<Option<UpdatePeriod>>) -> f64
+    {
+        let board_size = stability.image_width as usize;
+        let stable     = |x: usize, y: usize| stability.pixel_data[y * board_size + x] == 0;
+
+        match self
+        {
+            SearchMetric::FractalDimension =>
+            {
+                //Stable cells next to an unstable one. The border of the board doesn't count
+                let mut border_cells = vec![false; board_size * board_size];
+                for y in 0..board_size
+                {
+                    for x in 0..board_size
+                    {
+                        border_cells[y * board_size + x] = stable(x, y) && ((x > 0              && !stable(x - 1, y)) || (y > 0              && !stable(x, y - 1))
+                                                                        ||  (x + 1 < board_size && !stable(x + 1, y)) || (y + 1 < board_size && !stable(x, y + 1)));
+                    }
+                }
+
+                box_counting_dimension(&border_cells, board_size)
+            }
+
+            SearchMetric::StableFraction =>
+            {
+                let stable_cells = stability.pixel_data.iter().filter(|stability_value| **stability_value == 0).count();
+                stable_cells as f64 / stability.pixel_data.len() as f64
+            }
+
+            SearchMetric::Period => start_period.flatten().map_or(0.0, |period| period.period as f64),
+
+            SearchMetric::Symmetry =>
+            {
+                let stable_cells = stability.pixel_data.iter().filter(|stability_value| **stability_value == 0).count();
+                if stable_cells == 0 || stable_cells == stability.pixel_data.len()
+                {
+                    return 0.0;
+                }
+
+                //Mirrored horizontally, vertically and along the diagonal
+                let last = board_size - 1;
+
+                let mut matching_cells = 0;
+                for y in 0..board_size
+                {
+                    for x in 0..board_size
+                    {
+                        let cell_stable = stable(x, y);
+                        matching_cells += (cell_stable == stable(last - x, y)) as u64 + (cell_stable == stable(x, last - y)) as u64 + (cell_stable == stable(y, x)) as u64;
+                    }
+                }
+
+                matching_cells as f64 / (3 * board_size * board_size) as f64
+            }
+        }
+    }
+}
+
+//The slope of log(boxes with cells) against log(1 / box size), for box sizes from 1 to a quarter of the board. 0 for empty boards
+fn box_counting_dimension(cells: &[bool], board_size: usize) -> f64
+{
+    let mut log_points = Vec::new();
+
+    let mut box_size = 1;
+    while box_size * 4 <= board_size + 1
+    {
+        let boxes_per_side = (board_size + box_size - 1) / box_size;
+
+        let mut filled_boxes = vec![false; boxes_per_side * boxes_per_side];
+        for (cell_index, cell) in cells.iter().enumerate()
+        {
+            if *cell
+            {
+                filled_boxes[(cell_index / board_size / box_size) * boxes_per_side + (cell_index % board_size) / box_size] = true;
+            }
+        }
+
+        let filled_box_count = filled_boxes.iter().filter(|filled| **filled).count();
+        if filled_box_count == 0
+        {
+            return 0.0;
+        }
+
+        log_points.push((-(box_size as f64).ln(), (filled_box_count as f64).ln()));
+        box_size *= 2;
+    }
+
+    if log_points.len() < 2
+    {
+        return 0.0;
+    }
+
+    //Least squares
+    let point_count = log_points.len() as f64;
+    let mean_x      = log_points.iter().map(|(x, _)| x).sum::<f64>() / point_count;
+    let mean_y      = log_points.iter().map(|(_, y)| y).sum::<f64>() / point_count;
+
+    let covariance = log_points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>();
+    let variance   = log_points.iter().map(|(x, _)| (x - mean_x) * (x - mean_x)).sum::<f64>();
+    covariance / variance
+}
+
+//The same hash as the random boards and click rules, applied to a counter
+struct SearchRandom
+{
+    seed_hash: u32,
+    counter:   u32
+}
+
+impl SearchRandom
+{
+    fn new(seed: u32) -> Self
+    {
+        Self
+        {
+            seed_hash: pcg_hash(seed),
+            counter:   0
+        }
+    }
+
+    fn next_u32(&mut self) -> u32
+    {
+        self.counter = self.counter.wrapping_add(1);
+        pcg_hash(self.counter.wrapping_add(self.seed_hash))
+    }
+
+    fn next_below(&mut self, bound: u32) -> u32
+    {
+        self.next_u32() % bound
+    }
+
+    fn next_f32(&mut self) -> f32
+    {
+        (self.next_u32() as f64 / u32::MAX as f64) as f32
+    }
+}
+
+//initial_population is cut or filled with its mutated copies to the population size. on_generation is called after each generation, starting from the initial one
+pub fn search_click_rules(backend: &mut dyn SimulationBackend, initial_population: &[[u8; 32 * 32]], exploration_params: &ExplorationParams, search_params: &SearchParams, mut on_generation: impl FnMut(&GenerationSummary)) -> Result<SearchResult, String>
+{
+    if search_params.population_size < 2 || search_params.population_size > MAX_SEARCH_POPULATION
+    {
+        return Err(format!("The population size must be from 2 to {}", MAX_SEARCH_POPULATION));
+    }
+
+    if initial_population.is_empty()
+    {
+        return Err("The initial population is empty".to_string());
+    }
+
+    if search_params.radius == 0 || search_params.radius > MAX_RANDOM_CLICK_RULE_RADIUS
+    {
+        return Err(format!("The click rule radius must be from 1 to {}", MAX_RANDOM_CLICK_RULE_RADIUS));
+    }
+
+    let population_size = search_params.population_size as usize;
+
+    let mut random = SearchRandom::new(search_params.seed);
+
+    let mut first_generation: Vec<[u8; 32 * 32]> = initial_population.iter().take(population_size).map(|click_rule_data| restrict_to_radius(click_rule_data, search_params.radius)).collect();
+    while first_generation.len() < population_size
+    {
+        let mut click_rule_data = first_generation[first_generation.len() % initial_population.len()];
+        mutate(&mut click_rule_data, search_params.mutation_rate, search_params.radius, &mut random);
+        first_generation.push(click_rule_data);
+    }
+
+    let mut result = SearchResult
+    {
+        generations: Vec::with_capacity(search_params.generation_count as usize + 1),
+        best_rules:  Vec::new()
+    };
+
+    let mut population = evaluate(backend, first_generation, exploration_params, search_params.metric)?;
+    for generation in 0..=search_params.generation_count
+    {
+        if generation != 0
+        {
+            let mut children = Vec::with_capacity(population_size - SEARCH_ELITE_COUNT);
+            while children.len() + SEARCH_ELITE_COUNT < population_size
+            {
+                let first_parent  = tournament(&population, &mut random);
+                let second_parent = tournament(&population, &mut random);
+
+                let mut child = crossover(&first_parent.click_rule_data, &second_parent.click_rule_data, search_params.radius, &mut random);
+                mutate(&mut child, search_params.mutation_rate, search_params.radius, &mut random);
+                children.push(child);
+            }
+
+            population.truncate(SEARCH_ELITE_COUNT);
+            population.extend(evaluate(backend, children, exploration_params, search_params.metric)?);
+        }
+
+        population.sort_by(|first, second| second.fitness.total_cmp(&first.fitness));
+        update_best_rules(&mut result.best_rules, &population, search_params.best_count as usize);
+
+        let summary = GenerationSummary
+        {
+            generation,
+            best_fitness:    population[0].fitness,
+            mean_fitness:    population.iter().map(|candidate| candidate.fitness).sum::<f64>() / population.len() as f64,
+            best_click_rule: encode_click_rule_base64(&population[0].click_rule_data)
+        };
+
+        on_generation(&summary);
+        result.generations.push(summary);
+    }
+
+    Ok(result)
+}
+
+fn evaluate(backend: &mut dyn SimulationBackend, click_rules: Vec<[u8; 32 * 32]>, exploration_params: &ExplorationParams, metric: SearchMetric) -> Result<Vec<SearchCandidate>, String>
+{
+    let batch_params = BatchParams
+    {
+        board_size:  exploration_params.board_size,
+        frame_count: exploration_params.frame_count,
+        reset_type:  StandardResetBoardType::Corners,
+        click_rules: click_rules.clone()
+    };
+
+    let stabilities = read_batch(backend, batch_params)?;
+
+    let restriction = vec![1u8; (exploration_params.board_size * exploration_params.board_size) as usize];
+
+    let mut candidates = Vec::with_capacity(click_rules.len());
+    for (click_rule_data, stability) in click_rules.into_iter().zip(stabilities)
+    {
+        let start_period = match metric
+        {
+            SearchMetric::Period => Some(find_start_period(exploration_params.board_size, &restriction, &click_rule_data, StandardResetBoardType::Corners, exploration_params.frame_count)?),
+            _                    => None
+        };
+
+        let fitness = metric.fitness(&stability, start_period);
+        candidates.push(SearchCandidate {click_rule_data, fitness, stability, start_period});
+    }
+
+    Ok(candidates)
+}
+
+//The sorted population is merged into the best rules, skipping the rules that are already there
+fn update_best_rules(best_rules: &mut Vec<SearchCandidate>, population: &[SearchCandidate], best_count: usize)
+{
+    let mut known_rules: HashSet<[u8; 32 * 32]> = best_rules.iter().map(|candidate| candidate.click_rule_data).collect();
+    for candidate in population.iter().take(best_count)
+    {
+        if !known_rules.insert(candidate.click_rule_data)
+        {
+            continue;
+        }
+
+        best_rules.push(SearchCandidate
+        {
+            click_rule_data: candidate.click_rule_data,
+            fitness:         candidate.fitness,
+            stability:       ImageData
+            {
+                pixel_data:   candidate.stability.pixel_data.clone(),
+                image_width:  candidate.stability.image_width,
+                image_height: candidate.stability.image_height
+            },
+            start_period:    candidate.start_period
+        });
+    }
+
+    best_rules.sort_by(|first, second| second.fitness.total_cmp(&first.fitness));
+    best_rules.truncate(best_count);
+}
+
+fn tournament<'a>(population: &'a [SearchCandidate], random: &mut SearchRandom) -> &'a SearchCandidate
+{
+    let mut winner = &population[random.next_below(population.len() as u32) as usize];
+    for _ in 1..SEARCH_TOURNAMENT_SIZE
+    {
+        let contender = &population[random.next_below(population.len() as u32) as usize];
+        if contender.fitness > winner.fitness
+        {
+            winner = contender;
+        }
+    }
+
+    winner
+}
+
+//The cut is a random row or column within the radius. The cells before it come from the first parent
+fn crossover(first_parent: &[u8; 32 * 32], second_parent: &[u8; 32 * 32], radius: u32, random: &mut SearchRandom) -> [u8; 32 * 32]
+{
+    let click_rule_size = 32;
+    let center_cell     = (click_rule_size - 1) / 2;
+
+    assert!(radius <= MAX_RANDOM_CLICK_RULE_RADIUS, "The radius {} doesn't fit into the click rule", radius);
+
+    let vertical_cut = random.next_below(2) == 0;
+    let cut_position = center_cell - radius as usize + random.next_below(radius * 2 + 2) as usize;
+
+    let mut child = *second_parent;
+    for (cell_index, child_cell) in child.iter_mut().enumerate()
+    {
+        let cell_position = if vertical_cut {cell_index % click_rule_size} else {cell_index / click_rule_size};
+        if cell_position < cut_position
+        {
+            *child_cell = first_parent[cell_index];
+        }
+    }
+
+    child
+}
+
+fn mutate(click_rule_data: &mut [u8; 32 * 32], mutation_rate: f32, radius: u32, random: &mut SearchRandom)
+{
+    let click_rule_size = 32;
+    let center_cell     = (click_rule_size - 1) / 2;
+
+    assert!(radius <= MAX_RANDOM_CLICK_RULE_RADIUS, "The radius {} doesn't fit into the click rule", radius);
+
+    for y in center_cell - radius as usize..=center_cell + radius as usize
+    {
+        for x in center_cell - radius as usize..=center_cell + radius as usize
+        {
+            if random.next_f32() < mutation_rate
+            {
+                click_rule_data[y * click_rule_size + x] ^= 1;
+            }
+        }
+    }
+}
+
+fn restrict_to_radius(click_rule_data: &[u8; 32 * 32], radius: u32) -> [u8; 32 * 32]
+{
+    let click_rule_size = 32;
+    let center_cell     = (click_rule_size - 1) / 2;
+
+    let mut restricted_data = [0u8; 32 * 32];
+    for (cell_index, restricted_cell) in restricted_data.iter_mut().enumerate()
+    {
+        let (x, y) = (cell_index % click_rule_size, cell_index / click_rule_size);
+        if x.abs_diff(center_cell) <= radius as usize && y.abs_diff(center_cell) <= radius as usize
+        {
+            *restricted_cell = (click_rule_data[cell_index] != 0) as u8;
+        }
+    }
+
+    restricted_data
+}
+
+//The period and the transient are empty for the boards that don't repeat within the simulated frames
+pub fn best_rules_to_csv(best_rules: &[SearchCandidate], results: &[ExplorationResult]) -> String
+{
+    let mut csv = String::new();
+
+    writeln!(csv, "click_rule,fitness,period,transient,stable_fraction").unwrap();
+    for (candidate, result) in best_rules.iter().zip(results.iter())
+    {
+        let (period, transient) = match result.period
+        {
+            Some(period) => (period.period.to_string(), period.transient.to_string()),
+            None         => (String::new(), String::new())
+        };
+
+        writeln!(csv, "{},{:.6},{},{},{:.6}", result.click_rule_code, candidate.fitness, period, transient, result.stable_fraction).unwrap();
+    }
+
+    csv
+}
+
+pub fn generations_to_csv(generations: &[GenerationSummary]) -> String
+{
+    let mut csv = String::new();
+
+    writeln!(csv, "generation,best_fitness,mean_fitness,best_click_rule").unwrap();
+    for summary in generations
+    {
+        writeln!(csv, "{},{:.6},{:.6},{}", summary.generation, summary.best_fitness, summary.mean_fitness, summary.best_click_rule).unwrap();
+    }
+
+    csv
+}
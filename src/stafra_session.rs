@@ -0,0 +1,534 @@
+use super::stafra_state::{StandardResetBoardType, ResetBoardType, RandomBoardParams, RandomBoardSymmetry, RandomBoardShape, PatternBoardParams, PatternBoardKind};
+use super::stafra_board_state_bindings::ImageData;
+use super::stafra_expression::BoardExpression;
+use super::stafra_paint::{PaintStroke, PaintTool, PaintTarget};
+
+//Session file layout, all numbers are little-endian u32:
+//  magic "STAFRA\0\0", version,
+//  board size, frame number, spawn period, flags (bit 0 is smooth transform), last reset type (0 corners, 1 edges, 2 center, 3 custom, 4 random, 5 pattern, 6 expression),
+//  for random reset type (since version 2): seed, density (f32), symmetry (0 none, 1 mirror x, 2 4-fold, 3 8-fold), shape (0 full, 1 disc, 2 square), shape size (f32),
+//  for pattern reset type (since version 3): kind (0 horizontal line, 1 vertical line, 2 cross, 3 diagonals, 4 checkerboard, 5 concentric squares, 6 concentric circles, 7 single cell), size, x, y,
+//  for expression reset type (since version 4): seed, expression length in bytes, UTF-8 expression text,
+//  click rule (32x32 bytes),
+//  board (1 bit per cell), stability (1 byte per cell), restriction (1 bit per cell), all row-major with no padding,
+//  custom initial image flag, and if it's set: width, height and RGBA data,
+//  (since version 5) the number of board paint strokes, and for each one: tool (0 pencil, 1 line, 2 rectangle, 3 ellipse, 4 fill), start x, start y, end x, end y (i32), radius,
+//  flags (bit 0 is filled, bit 1 is enabled)
+const SESSION_MAGIC: &[u8; 8] = b"STAFRA\0\0";
+
+pub const SESSION_VERSION: u32 = 5;
+
+//The largest board size of the size list. Bigger boards in the file are rejected before reading the cells
+const MAX_SESSION_BOARD_SIZE: u32 = 32767;
+
+//The whole state of the simulation. Board, stability and restriction have one byte per cell
+pub struct StafraSession
+{
+    pub board_size:   u32,
+    pub frame_number: u32,
+
+    pub spawn_period:             u32,
+    pub smooth_transform_enabled: bool,
+
+    pub last_reset_type: ResetBoardType,
+    pub click_rule_data: [u8; 32 * 32],
+
+    pub board:       Vec<u8>,
+    pub stability:   Vec<u8>,
+    pub restriction: Vec<u8>,
+
+    pub initial_image:    Option<ImageData>,
+    pub board_expression: Option<BoardExpression>,
+    pub board_strokes:    Vec<PaintStroke>
+}
+
+struct SessionReader<'a>
+{
+    data:     &'a [u8],
+    position: usize
+}
+
+impl StafraSession
+{
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let cell_count = (self.board_size as usize) * (self.board_size as usize);
+        debug_assert!(self.board.len() == cell_count && self.stability.len() == cell_count && self.restriction.len() == cell_count, "The session cells don't match the board size {}", self.board_size);
+
+        let mut session_data = Vec::with_capacity(SESSION_MAGIC.len() + 32 * 32 + cell_count * 2);
+        session_data.extend_from_slice(SESSION_MAGIC);
+        session_data.extend_from_slice(&SESSION_VERSION.to_le_bytes());
+
+        let reset_type_index: u32 = match self.last_reset_type
+        {
+            ResetBoardType::Standard {reset_type: StandardResetBoardType::Corners} => 0,
+            ResetBoardType::Standard {reset_type: StandardResetBoardType::Edges}   => 1,
+            ResetBoardType::Standard {reset_type: StandardResetBoardType::Center}  => 2,
+            ResetBoardType::Custom                                                 => 3,
+            ResetBoardType::Random {..}                                            => 4,
+            ResetBoardType::Pattern {..}                                           => 5,
+            ResetBoardType::Expression                                             => 6
+        };
+
+        let flags: u32 = if self.smooth_transform_enabled {0x01} else {0x00};
+        for value in [self.board_size, self.frame_number, self.spawn_period, flags, reset_type_index]
+        {
+            session_data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        if let ResetBoardType::Random {params} = self.last_reset_type
+        {
+            let symmetry_index: u32 = match params.symmetry
+            {
+                RandomBoardSymmetry::None      => 0,
+                RandomBoardSymmetry::MirrorX   => 1,
+                RandomBoardSymmetry::FourFold  => 2,
+                RandomBoardSymmetry::EightFold => 3
+            };
+
+            let shape_index: u32 = match params.shape
+            {
+                RandomBoardShape::Full   => 0,
+                RandomBoardShape::Disc   => 1,
+                RandomBoardShape::Square => 2
+            };
+
+            for value in [params.seed, params.density.to_bits(), symmetry_index, shape_index, params.shape_size.to_bits()]
+            {
+                session_data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        if let ResetBoardType::Pattern {params} = self.last_reset_type
+        {
+            let kind_index: u32 = match params.kind
+            {
+                PatternBoardKind::HorizontalLine    => 0,
+                PatternBoardKind::VerticalLine      => 1,
+                PatternBoardKind::Cross             => 2,
+                PatternBoardKind::Diagonals         => 3,
+                PatternBoardKind::Checkerboard      => 4,
+                PatternBoardKind::ConcentricSquares => 5,
+                PatternBoardKind::ConcentricCircles => 6,
+                PatternBoardKind::SingleCell        => 7
+            };
+
+            for value in [kind_index, params.size, params.x, params.y]
+            {
+                session_data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        if let (ResetBoardType::Expression, Some(board_expression)) = (self.last_reset_type, &self.board_expression)
+        {
+            session_data.extend_from_slice(&board_expression.seed().to_le_bytes());
+            session_data.extend_from_slice(&(board_expression.source().len() as u32).to_le_bytes());
+            session_data.extend_from_slice(board_expression.source().as_bytes());
+        }
+
+        session_data.extend_from_slice(&self.click_rule_data);
+
+        session_data.extend(pack_cell_bits(&self.board));
+        session_data.extend_from_slice(&self.stability);
+        session_data.extend(pack_cell_bits(&self.restriction));
+
+        match &self.initial_image
+        {
+            Some(initial_image) =>
+            {
+                session_data.extend_from_slice(&1u32.to_le_bytes());
+                session_data.extend_from_slice(&initial_image.image_width.to_le_bytes());
+                session_data.extend_from_slice(&initial_image.image_height.to_le_bytes());
+                session_data.extend_from_slice(&initial_image.pixel_data);
+            }
+
+            None =>
+            {
+                session_data.extend_from_slice(&0u32.to_le_bytes());
+            }
+        }
+
+        session_data.extend_from_slice(&(self.board_strokes.len() as u32).to_le_bytes());
+        for stroke in &self.board_strokes
+        {
+            let tool_index: u32 = match stroke.tool
+            {
+                PaintTool::Pencil    => 0,
+                PaintTool::Line      => 1,
+                PaintTool::Rectangle => 2,
+                PaintTool::Ellipse   => 3,
+                PaintTool::Fill      => 4
+            };
+
+            let stroke_flags: u32 = (stroke.filled as u32) | ((stroke.enabled as u32) << 1);
+            for value in [tool_index, stroke.start_x as u32, stroke.start_y as u32, stroke.end_x as u32, stroke.end_y as u32, stroke.radius, stroke_flags]
+            {
+                session_data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        session_data
+    }
+
+    pub fn from_bytes(session_data: &[u8]) -> Result<Self, String>
+    {
+        let mut reader = SessionReader{data: session_data, position: 0};
+
+        if reader.read_bytes(SESSION_MAGIC.len())? != SESSION_MAGIC
+        {
+            return Err("Not a stafra session file".to_string());
+        }
+
+        let version = reader.read_u32()?;
+        if version == 0 || version > SESSION_VERSION
+        {
+            return Err(format!("Unsupported session version {}, the latest supported version is {}", version, SESSION_VERSION));
+        }
+
+        let board_size   = reader.read_u32()?;
+        let frame_number = reader.read_u32()?;
+        let spawn_period = reader.read_u32()?;
+        let flags        = reader.read_u32()?;
+
+        if board_size == 0 || board_size > MAX_SESSION_BOARD_SIZE || board_size.checked_add(1).map(u32::count_ones) != Some(1)
+        {
+            return Err(format!("Invalid board size {} in the session", board_size));
+        }
+
+        let last_reset_type = match reader.read_u32()?
+        {
+            0 => ResetBoardType::Standard {reset_type: StandardResetBoardType::Corners},
+            1 => ResetBoardType::Standard {reset_type: StandardResetBoardType::Edges},
+            2 => ResetBoardType::Standard {reset_type: StandardResetBoardType::Center},
+            3 => ResetBoardType::Custom,
+            4 => ResetBoardType::Random {params: reader.read_random_board_params()?},
+            5 => ResetBoardType::Pattern {params: reader.read_pattern_board_params()?},
+            6 => ResetBoardType::Expression,
+            reset_type_index => return Err(format!("Invalid reset type {} in the session", reset_type_index))
+        };
+
+        let board_expression = match last_reset_type
+        {
+            ResetBoardType::Expression => Some(reader.read_board_expression()?),
+            _                          => None
+        };
+
+        let mut click_rule_data = [0u8; 32 * 32];
+        click_rule_data.copy_from_slice(reader.read_bytes(32 * 32)?);
+
+        let cell_count = (board_size as usize).checked_mul(board_size as usize).ok_or(format!("Invalid board size {} in the session", board_size))?;
+        let board       = unpack_cell_bits(reader.read_bytes((cell_count + 7) / 8)?, cell_count);
+        let stability   = reader.read_bytes(cell_count)?.to_vec();
+        let restriction = unpack_cell_bits(reader.read_bytes((cell_count + 7) / 8)?, cell_count);
+
+        let initial_image = match reader.read_u32()?
+        {
+            0 => None,
+            _ =>
+            {
+                let image_width  = reader.read_u32()?;
+                let image_height = reader.read_u32()?;
+                if image_width == 0 || image_height == 0
+                {
+                    return Err(format!("Invalid image size {}x{} in the session", image_width, image_height));
+                }
+
+                let image_bytes  = (image_width as usize).checked_mul(image_height as usize).and_then(|pixel_count| pixel_count.checked_mul(4));
+                let pixel_data   = reader.read_bytes(image_bytes.ok_or(format!("Invalid image size {}x{} in the session", image_width, image_height))?)?.to_vec();
+
+                Some(ImageData{pixel_data, image_width, image_height})
+            }
+        };
+
+        if last_reset_type == ResetBoardType::Custom && initial_image.is_none()
+        {
+            return Err("The session has a custom reset type but no initial image".to_string());
+        }
+
+        let mut board_strokes = Vec::new();
+        if version >= 5
+        {
+            let stroke_count = reader.read_u32()?;
+            for _ in 0..stroke_count
+            {
+                board_strokes.push(reader.read_paint_stroke()?);
+            }
+        }
+
+        Ok(Self
+        {
+            board_size,
+            frame_number,
+
+            spawn_period,
+            smooth_transform_enabled: flags & 0x01 != 0,
+
+            last_reset_type,
+            click_rule_data,
+
+            board,
+            stability,
+            restriction,
+
+            initial_image,
+            board_expression,
+            board_strokes
+        })
+    }
+
+    //Restriction as an image the restriction shaders can read: white for enabled cells, black for disabled ones
+    pub fn restriction_image(&self) -> ImageData
+    {
+        ImageData
+        {
+            pixel_data:   self.restriction.iter().flat_map(|cell| if *cell != 0 {[255u8, 255, 255, 255]} else {[0u8, 0, 0, 255]}).collect(),
+            image_width:  self.board_size,
+            image_height: self.board_size
+        }
+    }
+
+    pub fn is_restricted(&self) -> bool
+    {
+        self.restriction.contains(&0)
+    }
+}
+
+impl<'a> SessionReader<'a>
+{
+    fn read_bytes(&mut self, byte_count: usize) -> Result<&'a [u8], String>
+    {
+        if self.data.len() - self.position < byte_count
+        {
+            return Err("The session file is truncated".to_string());
+        }
+
+        let bytes = &self.data[self.position..self.position + byte_count];
+        self.position += byte_count;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String>
+    {
+        let bytes = self.read_bytes(std::mem::size_of::<u32>())?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_random_board_params(&mut self) -> Result<RandomBoardParams, String>
+    {
+        let seed    = self.read_u32()?;
+        let density = f32::from_bits(self.read_u32()?);
+
+        let symmetry = match self.read_u32()?
+        {
+            0 => RandomBoardSymmetry::None,
+            1 => RandomBoardSymmetry::MirrorX,
+            2 => RandomBoardSymmetry::FourFold,
+            3 => RandomBoardSymmetry::EightFold,
+            symmetry_index => return Err(format!("Invalid random board symmetry {} in the session", symmetry_index))
+        };
+
+        let shape = match self.read_u32()?
+        {
+            0 => RandomBoardShape::Full,
+            1 => RandomBoardShape::Disc,
+            2 => RandomBoardShape::Square,
+            shape_index => return Err(format!("Invalid random board shape {} in the session", shape_index))
+        };
+
+        let shape_size = f32::from_bits(self.read_u32()?);
+        Ok(RandomBoardParams{seed, density, symmetry, shape, shape_size})
+    }
+
+    fn read_board_expression(&mut self) -> Result<BoardExpression, String>
+    {
+        let seed          = self.read_u32()?;
+        let source_length = self.read_u32()?;
+        let source_bytes  = self.read_bytes(source_length as usize)?;
+
+        let expression_source = std::str::from_utf8(source_bytes).map_err(|_| "The board expression in the session is not valid UTF-8".to_string())?;
+        BoardExpression::parse(expression_source, seed).map_err(|message| format!("Invalid board expression in the session: {}", message))
+    }
+
+    fn read_paint_stroke(&mut self) -> Result<PaintStroke, String>
+    {
+        let tool = match self.read_u32()?
+        {
+            0 => PaintTool::Pencil,
+            1 => PaintTool::Line,
+            2 => PaintTool::Rectangle,
+            3 => PaintTool::Ellipse,
+            4 => PaintTool::Fill,
+            tool_index => return Err(format!("Invalid paint tool {} in the session", tool_index))
+        };
+
+        let start_x = self.read_u32()? as i32;
+        let start_y = self.read_u32()? as i32;
+        let end_x   = self.read_u32()? as i32;
+        let end_y   = self.read_u32()? as i32;
+        let radius  = self.read_u32()?;
+        let flags   = self.read_u32()?;
+
+        Ok(PaintStroke{tool, target: PaintTarget::Board, start_x, start_y, end_x, end_y, radius, filled: flags & 0x01 != 0, enabled: flags & 0x02 != 0})
+    }
+
+    fn read_pattern_board_params(&mut self) -> Result<PatternBoardParams, String>
+    {
+        let kind = match self.read_u32()?
+        {
+            0 => PatternBoardKind::HorizontalLine,
+            1 => PatternBoardKind::VerticalLine,
+            2 => PatternBoardKind::Cross,
+            3 => PatternBoardKind::Diagonals,
+            4 => PatternBoardKind::Checkerboard,
+            5 => PatternBoardKind::ConcentricSquares,
+            6 => PatternBoardKind::ConcentricCircles,
+            7 => PatternBoardKind::SingleCell,
+            kind_index => return Err(format!("Invalid pattern board kind {} in the session", kind_index))
+        };
+
+        let size = self.read_u32()?;
+        let x    = self.read_u32()?;
+        let y    = self.read_u32()?;
+        Ok(PatternBoardParams{kind, size, x, y})
+    }
+}
+
+fn pack_cell_bits(cells: &[u8]) -> Vec<u8>
+{
+    cells.chunks(8).map(|cell_chunk|
+    {
+        cell_chunk.iter().enumerate().fold(0u8, |packed_cells, (bit_index, cell)| packed_cells | (((*cell != 0) as u8) << bit_index))
+    }).collect()
+}
+
+fn unpack_cell_bits(packed_cells: &[u8], cell_count: usize) -> Vec<u8>
+{
+    (0..cell_count).map(|cell_index| (packed_cells[cell_index / 8] >> (cell_index % 8)) & 0x01).collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const TEST_BOARD_SIZE: u32 = 15;
+
+    fn test_session(last_reset_type: ResetBoardType) -> StafraSession
+    {
+        let cell_count = (TEST_BOARD_SIZE * TEST_BOARD_SIZE) as usize;
+
+        let mut click_rule_data = [0u8; 32 * 32];
+        for (x, y) in [(15, 15), (14, 15), (16, 15), (15, 14), (15, 16)]
+        {
+            click_rule_data[y * 32 + x] = 1;
+        }
+
+        StafraSession
+        {
+            board_size:   TEST_BOARD_SIZE,
+            frame_number: 42,
+
+            spawn_period:             7,
+            smooth_transform_enabled: true,
+
+            last_reset_type,
+            click_rule_data,
+
+            board:       (0..cell_count).map(|cell_index| (cell_index % 3 == 0) as u8).collect(),
+            stability:   (0..cell_count).map(|cell_index| (cell_index % 5) as u8).collect(),
+            restriction: (0..cell_count).map(|cell_index| (cell_index % 11 != 0) as u8).collect(),
+
+            initial_image:    None,
+            board_expression: None,
+            board_strokes:    Vec::new()
+        }
+    }
+
+    //The older versions only lack the paint strokes at the end, so the file of a session without strokes is rewritten to the older version
+    fn session_bytes_as_version(session: &StafraSession, version: u32) -> Vec<u8>
+    {
+        assert!(session.board_strokes.is_empty());
+
+        let mut session_data = session.to_bytes();
+        session_data[SESSION_MAGIC.len()..SESSION_MAGIC.len() + 4].copy_from_slice(&version.to_le_bytes());
+        if version < 5
+        {
+            session_data.truncate(session_data.len() - std::mem::size_of::<u32>());
+        }
+
+        session_data
+    }
+
+    //Loads the file of the given version and checks that saving it again gives the same session in the latest version
+    fn check_round_trip(session: &StafraSession, version: u32)
+    {
+        let session_data   = session_bytes_as_version(session, version);
+        let loaded_session = StafraSession::from_bytes(&session_data).unwrap_or_else(|message| panic!("Version {}: {}", version, message));
+
+        assert!(loaded_session.board       == session.board);
+        assert!(loaded_session.stability   == session.stability);
+        assert!(loaded_session.restriction == session.restriction);
+        assert!(loaded_session.last_reset_type == session.last_reset_type);
+        assert!(loaded_session.to_bytes() == session.to_bytes());
+    }
+
+    #[test]
+    fn round_trip_version_1()
+    {
+        let mut session = test_session(ResetBoardType::Custom);
+        session.initial_image = Some(ImageData{pixel_data: (0..3 * 2 * 4).map(|byte_index| byte_index as u8).collect(), image_width: 3, image_height: 2});
+
+        check_round_trip(&session, 1);
+    }
+
+    #[test]
+    fn round_trip_version_2()
+    {
+        let params = RandomBoardParams{seed: 1234, density: 0.25, symmetry: RandomBoardSymmetry::FourFold, shape: RandomBoardShape::Disc, shape_size: 0.75};
+        check_round_trip(&test_session(ResetBoardType::Random {params}), 2);
+    }
+
+    #[test]
+    fn round_trip_version_3()
+    {
+        let params = PatternBoardParams{kind: PatternBoardKind::ConcentricCircles, size: 3, x: 4, y: 9};
+        check_round_trip(&test_session(ResetBoardType::Pattern {params}), 3);
+    }
+
+    #[test]
+    fn round_trip_version_4()
+    {
+        let mut session = test_session(ResetBoardType::Expression);
+        session.board_expression = Some(BoardExpression::parse("(x + y) % 3 == 0", 99).unwrap());
+
+        check_round_trip(&session, 4);
+        assert!(StafraSession::from_bytes(&session.to_bytes()).unwrap().board_expression.map(|board_expression| (board_expression.source().to_string(), board_expression.seed())) == Some(("(x + y) % 3 == 0".to_string(), 99)));
+    }
+
+    #[test]
+    fn round_trip_version_5()
+    {
+        let mut session = test_session(ResetBoardType::Standard {reset_type: StandardResetBoardType::Edges});
+        check_round_trip(&session, 5);
+
+        session.board_strokes = vec!
+        [
+            PaintStroke{tool: PaintTool::Line, target: PaintTarget::Board, start_x: -2, start_y: 3, end_x: 10, end_y: 12, radius: 1, filled: false, enabled: true},
+            PaintStroke{tool: PaintTool::Fill, target: PaintTarget::Board, start_x: 5,  start_y: 5, end_x: 5,  end_y: 5,  radius: 0, filled: true,  enabled: false}
+        ];
+
+        let session_data   = session.to_bytes();
+        let loaded_session = StafraSession::from_bytes(&session_data).unwrap();
+        assert!(loaded_session.board_strokes == session.board_strokes);
+        assert!(loaded_session.to_bytes() == session_data);
+    }
+
+    #[test]
+    fn empty_initial_image_is_rejected()
+    {
+        let mut session = test_session(ResetBoardType::Custom);
+        session.initial_image = Some(ImageData{pixel_data: Vec::new(), image_width: 0, image_height: 4});
+
+        assert!(StafraSession::from_bytes(&session.to_bytes()).is_err());
+    }
+}
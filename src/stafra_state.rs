@@ -2,16 +2,29 @@ use
 {
     std::num::NonZeroU32,
     std::cmp::min,
-    std::sync::Mutex,
-    std::sync::Arc
+    std::sync::{Arc, Mutex},
+    std::collections::VecDeque,
+    std::future::Future,
+    std::pin::Pin,
+    futures::FutureExt
 };
 
 use
 {
     super::stafra_static_state::StafraStaticState,
     super::stafra_static_state_bindings::StafraStaticBindings,
-    super::stafra_board_state_bindings::StafraBoardBindings,
-    super::stafra_initial_state_bindings::StafraInitialStateBindings
+    super::stafra_board_state_bindings::{StafraBoardBindings, BoardSnapshot, ImageData},
+    super::stafra_initial_state_bindings::StafraInitialStateBindings,
+    super::stafra_batch_state_bindings::StafraBatchBindings,
+    super::stafra_readback_pool::{ReadbackPool, DEFAULT_READBACK_QUEUE_DEPTH},
+    super::stafra_profiler::{PassProfiler, ProfilerReport},
+    super::stafra_session::StafraSession,
+    super::stafra_expression::BoardExpression,
+    super::stafra_image_import::{ImageImportOptions, import_board_size, import_image},
+    super::stafra_history::{FrameHistory, DEFAULT_HISTORY_FRAMES, DEFAULT_KEYFRAME_PERIOD},
+    super::stafra_paint::{PaintStroke, PaintTarget, PaintTool},
+    super::stafra_lights_out::{LightsOutMove, LightsOutStatus},
+    super::simulation_backend::{SimulationBackend, ImageCallback, SessionCallback, BatchCallback}
 };
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -20,6 +33,12 @@ use
     winit::window::Window
 };
 
+#[cfg(target_arch = "wasm32")]
+use
+{
+    wasm_bindgen::JsValue
+};
+
 #[derive(Copy, Clone, PartialEq)]
 pub enum StandardResetBoardType
 {
@@ -29,36 +48,409 @@ pub enum StandardResetBoardType
 }
 
 #[derive(Copy, Clone, PartialEq)]
-enum ResetBoardType
+pub enum RandomBoardSymmetry
+{
+    None,
+    MirrorX,
+    FourFold,
+    EightFold
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum RandomBoardShape
+{
+    Full,
+    Disc,
+    Square
+}
+
+//Seeded random board. The same parameters always give the same board, both on the GPU and on the CPU
+#[derive(Copy, Clone, PartialEq)]
+pub struct RandomBoardParams
+{
+    pub seed:       u32,
+    pub density:    f32, //Fraction of enabled cells, from 0 to 1
+    pub symmetry:   RandomBoardSymmetry,
+    pub shape:      RandomBoardShape,
+    pub shape_size: f32  //Size of the central disc or square relative to the board, from 0 to 1
+}
+
+//Offsets from -15 to 15 keep every symmetry inside the 32x32 click rule
+pub const MAX_RANDOM_CLICK_RULE_RADIUS: u32 = 15;
+
+//Seeded random click rule inside the square with the given radius. The same parameters always give the same click rule
+#[derive(Copy, Clone, PartialEq)]
+pub struct RandomClickRuleParams
+{
+    pub seed:     u32,
+    pub radius:   u32, //The largest offset of an enabled cell, from 1 to MAX_RANDOM_CLICK_RULE_RADIUS
+    pub density:  f32, //Fraction of enabled cells, from 0 to 1
+    pub symmetry: RandomBoardSymmetry,
+    pub center:   bool //The center cell doesn't depend on the density
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum PatternBoardKind
+{
+    HorizontalLine,
+    VerticalLine,
+    Cross,
+    Diagonals,
+    Checkerboard,
+    ConcentricSquares,
+    ConcentricCircles,
+    SingleCell
+}
+
+//Geometric board, centered on the board except for the single cell
+#[derive(Copy, Clone, PartialEq)]
+pub struct PatternBoardParams
+{
+    pub kind: PatternBoardKind,
+    pub size: u32, //Half-width of the lines, side of the checkerboard squares or distance between the rings
+    pub x:    u32, //Position of the single cell
+    pub y:    u32
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ResetBoardType
 {
     Standard {reset_type: StandardResetBoardType},
+    Random {params: RandomBoardParams},
+    Pattern {params: PatternBoardParams},
+    Expression,
     Custom
 }
 
+//Many boards of the same size simulated at once, each one with its own click rule.
+//All boards start from the same standard reset and run for the same number of frames, without the restriction
+#[derive(Clone, PartialEq)]
+pub struct BatchParams
+{
+    pub board_size:  u32,
+    pub frame_count: u32,
+    pub reset_type:  StandardResetBoardType,
+    pub click_rules: Vec<[u8; 32 * 32]>
+}
+
+impl StandardResetBoardType
+{
+    //The cells enabled by the reset, as (x, y)
+    pub fn enabled_cells(&self, board_size: u32) -> Vec<(usize, usize)>
+    {
+        let last   = board_size as usize - 1;
+        let center = last / 2;
+
+        match self
+        {
+            StandardResetBoardType::Corners => vec![(0, 0), (last, 0), (0, last), (last, last)],
+            StandardResetBoardType::Edges   => vec![(0, center), (last, center), (center, 0), (center, last)],
+            StandardResetBoardType::Center  => vec![(center, center)]
+        }
+    }
+}
+
+impl RandomBoardParams
+{
+    //Cells with the hash below the threshold are enabled
+    pub fn density_threshold(&self) -> u32
+    {
+        (self.density.clamp(0.0, 1.0) as f64 * u32::MAX as f64) as u32
+    }
+
+    //Radius of the disc or half-size of the square in cells, not counting the center cell
+    pub fn shape_radius(&self, board_size: u32) -> u32
+    {
+        (self.shape_size.clamp(0.0, 1.0) as f64 * ((board_size - 1) / 2) as f64).round() as u32
+    }
+
+    //The same calculation as in clear_random.wgsl
+    pub fn cell_enabled(&self, board_size: u32, x: u32, y: u32) -> bool
+    {
+        let last_cell   = board_size - 1;
+        let center_cell = last_cell / 2;
+
+        let center_distance_x = x.abs_diff(center_cell);
+        let center_distance_y = y.abs_diff(center_cell);
+
+        let shape_radius = self.shape_radius(board_size);
+        let inside_shape = match self.shape
+        {
+            RandomBoardShape::Full   => true,
+            RandomBoardShape::Disc   => center_distance_x * center_distance_x + center_distance_y * center_distance_y <= shape_radius * shape_radius,
+            RandomBoardShape::Square => std::cmp::max(center_distance_x, center_distance_y) <= shape_radius
+        };
+
+        if !inside_shape
+        {
+            return false;
+        }
+
+        //Symmetric cells share the hash
+        let (hash_x, hash_y) = match self.symmetry
+        {
+            RandomBoardSymmetry::None      => (x, y),
+            RandomBoardSymmetry::MirrorX   => (min(x, last_cell - x), y),
+            RandomBoardSymmetry::FourFold  => (min(x, last_cell - x), min(y, last_cell - y)),
+            RandomBoardSymmetry::EightFold =>
+            {
+                let folded_x = min(x, last_cell - x);
+                let folded_y = min(y, last_cell - y);
+                (min(folded_x, folded_y), std::cmp::max(folded_x, folded_y))
+            }
+        };
+
+        let cell_hash = pcg_hash(hash_x.wrapping_add(pcg_hash(hash_y.wrapping_add(pcg_hash(self.seed)))));
+        cell_hash < self.density_threshold()
+    }
+}
+
+impl RandomClickRuleParams
+{
+    //Symmetric cells share the hash, the same way as on the random board. A smaller radius with the same seed gives a part of the same click rule
+    pub fn click_rule_data(&self) -> [u8; 32 * 32]
+    {
+        let click_rule_size = 32;
+        let center_cell     = (click_rule_size - 1) / 2;
+
+        let radius            = self.radius.clamp(1, MAX_RANDOM_CLICK_RULE_RADIUS) as i32;
+        let density_threshold = (self.density.clamp(0.0, 1.0) as f64 * u32::MAX as f64) as u32;
+
+        let mut click_rule_data = [0u8; 32 * 32];
+        for offset_y in -radius..=radius
+        {
+            for offset_x in -radius..=radius
+            {
+                let (hash_x, hash_y) = match self.symmetry
+                {
+                    RandomBoardSymmetry::None      => (offset_x, offset_y),
+                    RandomBoardSymmetry::MirrorX   => (offset_x.abs(), offset_y),
+                    RandomBoardSymmetry::FourFold  => (offset_x.abs(), offset_y.abs()),
+                    RandomBoardSymmetry::EightFold => (min(offset_x.abs(), offset_y.abs()), std::cmp::max(offset_x.abs(), offset_y.abs()))
+                };
+
+                let cell_enabled = if offset_x == 0 && offset_y == 0
+                {
+                    self.center
+                }
+                else
+                {
+                    let cell_hash = pcg_hash(((hash_x + center_cell) as u32).wrapping_add(pcg_hash(((hash_y + center_cell) as u32).wrapping_add(pcg_hash(self.seed)))));
+                    cell_hash < density_threshold
+                };
+
+                click_rule_data[((center_cell + offset_y) * click_rule_size + center_cell + offset_x) as usize] = cell_enabled as u8;
+            }
+        }
+
+        click_rule_data
+    }
+}
+
+impl PatternBoardParams
+{
+    //The same calculation as in clear_pattern.wgsl
+    pub fn cell_enabled(&self, board_size: u32, x: u32, y: u32) -> bool
+    {
+        let center_cell = (board_size - 1) / 2;
+        let size        = std::cmp::max(self.size, 1);
+
+        let center_distance_x = x.abs_diff(center_cell);
+        let center_distance_y = y.abs_diff(center_cell);
+
+        match self.kind
+        {
+            PatternBoardKind::HorizontalLine    => center_distance_y < size,
+            PatternBoardKind::VerticalLine      => center_distance_x < size,
+            PatternBoardKind::Cross             => center_distance_x < size || center_distance_y < size,
+            PatternBoardKind::Diagonals         => center_distance_x.abs_diff(center_distance_y) < size,
+            PatternBoardKind::Checkerboard      => (center_distance_x / size + center_distance_y / size) % 2 == 0,
+            PatternBoardKind::ConcentricSquares => std::cmp::max(center_distance_x, center_distance_y) % size == 0,
+            PatternBoardKind::ConcentricCircles => integer_sqrt(center_distance_x * center_distance_x + center_distance_y * center_distance_y) % size == 0,
+            PatternBoardKind::SingleCell        => x == std::cmp::min(self.x, board_size - 1) && y == std::cmp::min(self.y, board_size - 1)
+        }
+    }
+}
+
+//Largest number whose square is not bigger than the value
+fn integer_sqrt(value: u32) -> u32
+{
+    let mut root = (value as f64).sqrt() as u32;
+    while root * root > value
+    {
+        root -= 1;
+    }
+
+    while (root + 1) * (root + 1) <= value
+    {
+        root += 1;
+    }
+
+    root
+}
+
+pub fn pcg_hash(value: u32) -> u32
+{
+    let state = value.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word  = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+//Initial images are cropped to the largest square with sides of 2^n - 1
+fn initial_image_size(initial_image: &ImageData) -> u32
+{
+    (min(initial_image.image_width, initial_image.image_height) + 2).next_power_of_two() / 2 - 1
+}
+
+//The same conversion as in initial_state_transform.wgsl: the cropped image is centered on the board, and the cells brighter than 0.15 are lit
+fn initial_image_cells(initial_image: &ImageData, board_width: u32, board_height: u32) -> Vec<u8>
+{
+    let image_size = initial_image_size(initial_image) as i64;
+    let offset_x   = (image_size - board_width  as i64) / 2;
+    let offset_y   = (image_size - board_height as i64) / 2;
+
+    let mut cells = vec![0u8; board_width as usize * board_height as usize];
+    for (cell_index, cell) in cells.iter_mut().enumerate()
+    {
+        let image_x = (cell_index % board_width as usize) as i64 + offset_x;
+        let image_y = (cell_index / board_width as usize) as i64 + offset_y;
+        if image_x < 0 || image_y < 0 || image_x >= image_size || image_y >= image_size
+        {
+            continue;
+        }
+
+        let texel_start = (image_y as usize * initial_image.image_width as usize + image_x as usize) * 4;
+        let texel       = &initial_image.pixel_data[texel_start..texel_start + 4];
+
+        let luma = (0.2126 * texel[0] as f32 + 0.7152 * texel[1] as f32 + 0.0722 * texel[2] as f32) / 255.0;
+        *cell = (luma > 0.15) as u8;
+    }
+
+    cells
+}
+
+//Board, stability and restriction are read back separately for each session request
+const SESSION_READBACK_SLOTS: usize = 3;
+
+//The board and the restriction are read back for the Lights Out play mode independently
+const LIGHTS_OUT_READBACK_SLOTS: u32 = 2;
+
+//Flood fills wait for each other in the pending operations, so only one of them reads back at a time
+const FILL_READBACK_SLOTS: u32 = 1;
+
+//A batch reads back all of its boards at once, with one buffer per board
+const BATCH_READBACK_SLOTS: u32 = 1;
+
+//The result of an out of memory error scope, resolved immediately on native and asynchronously on WebGPU
+type ErrorScopeFuture = Pin<Box<dyn Future<Output = Option<wgpu::Error>>>>;
+
+//Receives the allocation error of the operation, if there was one
+type PendingOperationRun = Box<dyn FnOnce(&mut StafraState, Option<wgpu::Error>)>;
+
+enum CheckedAllocation<T>
+{
+    Ready(Result<T, String>),
+    Pending(PendingAllocation<T>)
+}
+
+//Resources that can't be used until the error scope of their allocation resolves
+struct PendingAllocation<T>
+{
+    description: String,
+    allocated:   T,
+    error_scope: ErrorScopeFuture
+}
+
+//An operation waiting for the allocations queued before it. Requests have no error scope and run as soon as they are at the front.
+//Flood fills wait for their readback the same way, with a future that never reports an error
+struct PendingOperation
+{
+    error_scope: Option<ErrorScopeFuture>,
+    run:         PendingOperationRun
+}
+
 pub struct StafraState
 {
-    main_surface:       wgpu::Surface,
-    click_rule_surface: wgpu::Surface,
+    //Headless states have no surfaces
+    main_surface:       Option<wgpu::Surface>,
+    click_rule_surface: Option<wgpu::Surface>,
     device:             wgpu::Device,
     queue:              wgpu::Queue,
 
     swapchain_format: wgpu::TextureFormat,
     frame_number:     u32,
 
+    main_surface_width:  u32,
+    main_surface_height: u32,
+
     last_reset_type: ResetBoardType,
 
+    //Kept for saving sessions, the GPU versions can't be read back as they were uploaded
+    click_rule_data: [u8; 32 * 32],
+    initial_image:   Option<ImageData>,
+
+    //The expression pipeline is rebuilt each time the board expression changes
+    board_expression:          Option<BoardExpression>,
+    clear_expression_pipeline: Option<wgpu::ComputePipeline>,
+
     initial_restriction_tex: Option<wgpu::Texture>,
 
+    image_import_options: ImageImportOptions,
+
+    //Strokes painted on top of the last reset, replayed every time the board is reset unchanged.
+    //Flood fill is done on the CPU, the board after the latest fill is kept together with the number of strokes painted on it.
+    //The generation changes every time the strokes are cleared, to drop the fills requested for the old ones.
+    //The fill waiting for the readback is not requested again by the replays in the meantime
+    board_strokes:            Vec<PaintStroke>,
+    board_fill_snapshot:      Option<(usize, BoardSnapshot)>,
+    board_strokes_generation: u64,
+    requested_board_fill:     Option<(u64, usize)>,
+
+    //The board is displayed instead of the stability. The moves are kept until the board changes in any other way.
+    //Simulation steps only mark the board as stepped, the puzzle is restarted once the next Lights Out call sees it
+    lights_out_enabled: bool,
+    lights_out_moves:   Vec<LightsOutMove>,
+    board_stepped:      bool,
+
+    //The board is read back after each change to count the lit cells, and the restriction to reject the clicks on restricted cells.
+    //Each readback keeps the version of the board or the restriction it was made for
+    board_version:                 u64,
+    restriction_version:           u64,
+    requested_board_version:       Option<u64>,
+    requested_restriction_version: Option<u64>,
+    lights_out_lit_cells:          Arc<Mutex<Option<(u64, u64)>>>,
+    lights_out_restriction:        Arc<Mutex<Option<(u64, ImageData)>>>,
+
+    png_readback_pool:         ReadbackPool,
+    stability_readback_pool:   ReadbackPool,
+    video_frame_readback_pool: ReadbackPool,
+    session_readback_pool:     ReadbackPool,
+    lights_out_readback_pool:  ReadbackPool,
+    batch_readback_pool:       ReadbackPool,
+    fill_readback_pool:        ReadbackPool,
+
+    profiler: PassProfiler,
+
+    history: FrameHistory<BoardSnapshot>,
+
     static_state:           StafraStaticState,
     static_bindings:        StafraStaticBindings,
     initial_state_bindings: StafraInitialStateBindings,
     board_bindings:         StafraBoardBindings,
+
+    //Created by the first batch request and recreated when the board size or the number of boards changes
+    batch_bindings: Option<StafraBatchBindings>,
+
+    //WebGPU reports allocation errors only asynchronously. Until then the old resources are used,
+    //and the readback requests posted in the meantime wait for the allocation so they read the new board
+    pending_operations: VecDeque<PendingOperation>,
+    allocation_error:   Option<String>
 }
 
 impl StafraState
 {
     #[cfg(not(target_arch = "wasm32"))]
-    pub async fn new_native(main_window: &Window, click_rule_window: &Window, width: u32, height: u32) -> Self
+    pub async fn new_native(main_window: &Window, click_rule_window: &Window, width: u32, height: u32) -> Result<Self, String>
     {
         let window_size     = main_window.inner_size();
         let click_rule_size = click_rule_window.inner_size();
@@ -67,11 +459,19 @@ impl StafraState
         let main_surface       = unsafe{wgpu_instance.create_surface(main_window)};
         let click_rule_surface = unsafe{wgpu_instance.create_surface(click_rule_window)};
 
-        StafraState::new_impl(wgpu_instance, main_surface, click_rule_surface, window_size.width, window_size.height, click_rule_size.width, click_rule_size.height, width, height).await
+        StafraState::new_impl(wgpu_instance, Some(main_surface), Some(click_rule_surface), window_size.width, window_size.height, click_rule_size.width, click_rule_size.height, width, height).await
+    }
+
+    //Simulates without presenting anything, for benchmarks and offline processing
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn new_headless(width: u32, height: u32) -> Result<Self, String>
+    {
+        let wgpu_instance = wgpu::Instance::new(wgpu::Backends::PRIMARY);
+        StafraState::new_impl(wgpu_instance, None, None, 1, 1, 1, 1, width, height).await
     }
 
     #[cfg(target_arch = "wasm32")]
-    pub async fn new_web(main_canvas: &web_sys::HtmlCanvasElement, click_rule_canvas: &web_sys::HtmlCanvasElement, width: u32, height: u32) -> Self
+    pub async fn new_web(main_canvas: &web_sys::HtmlCanvasElement, click_rule_canvas: &web_sys::HtmlCanvasElement, width: u32, height: u32) -> Result<Self, String>
     {
         let canvas_width  = main_canvas.width();
         let canvas_height = main_canvas.height();
@@ -79,74 +479,130 @@ impl StafraState
         let click_rule_width  = click_rule_canvas.width();
         let click_rule_height = click_rule_canvas.height();
 
+        //Creating a surface panics if the browser doesn't support WebGPU
+        let webgpu_supported = web_sys::window().map(|window| js_sys::Reflect::has(&window.navigator(), &JsValue::from_str("gpu")).unwrap_or(false)).unwrap_or(false);
+        if !webgpu_supported
+        {
+            return Err("Wgpu is not supported: the browser doesn't support WebGPU".to_string());
+        }
+
         let wgpu_instance      = wgpu::Instance::new(wgpu::Backends::PRIMARY);
         let main_surface       = wgpu_instance.create_surface_from_canvas(main_canvas);
         let click_rule_surface = wgpu_instance.create_surface_from_canvas(click_rule_canvas);
 
-        StafraState::new_impl(wgpu_instance, main_surface, click_rule_surface, canvas_width as u32, canvas_height as u32, click_rule_width as u32, click_rule_height as u32, width, height).await
+        StafraState::new_impl(wgpu_instance, Some(main_surface), Some(click_rule_surface), canvas_width as u32, canvas_height as u32, click_rule_width as u32, click_rule_height as u32, width, height).await
     }
 
-    async fn new_impl(instance: wgpu::Instance, main_surface: wgpu::Surface, click_rule_surface: wgpu::Surface, window_width: u32, window_height: u32, click_rule_width: u32, click_rule_height: u32, board_width: u32, board_height: u32) -> Self
+    async fn new_impl(instance: wgpu::Instance, main_surface: Option<wgpu::Surface>, click_rule_surface: Option<wgpu::Surface>, window_width: u32, window_height: u32, click_rule_width: u32, click_rule_height: u32, requested_board_width: u32, requested_board_height: u32) -> Result<Self, String>
     {
         let adapter_option = instance.request_adapter(&wgpu::RequestAdapterOptions
         {
             power_preference:       wgpu::PowerPreference::default(),
             force_fallback_adapter: false,
-            compatible_surface:     Some(&main_surface),
+            compatible_surface:     main_surface.as_ref(),
         }).await;
 
-        #[cfg(target_arch = "wasm32")]
-        if let None = adapter_option
+        let adapter = match adapter_option
+        {
+            Some(adapter) => adapter,
+            None          => return Err("Wgpu is not supported: no compatible adapter found".to_string())
+        };
+
+        //Board textures are written from compute shaders
+        for board_format in [wgpu::TextureFormat::R32Uint, wgpu::TextureFormat::Rgba8Unorm]
         {
-            web_sys::window().unwrap().alert_with_message("Wgpu is not supported").unwrap();
+            let format_features = adapter.get_texture_format_features(board_format);
+            if !format_features.allowed_usages.contains(wgpu::TextureUsages::STORAGE_BINDING)
+            {
+                return Err(format!("Wgpu is not supported: the adapter doesn't support {:?} storage textures", board_format));
+            }
         }
 
-        let adapter = adapter_option.unwrap();
-        let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor
+        //Timestamp queries are only used for profiling, so they are optional
+        let device_limits = Self::required_limits(&adapter.limits());
+        let (device, queue) = match adapter.request_device(&wgpu::DeviceDescriptor
         {
-            features: wgpu::Features::default(),
-            limits:   wgpu::Limits::default(),
+            features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+            limits:   device_limits.clone(),
             label:    Some("Device"),
         },
-        None).await.unwrap();
+        None).await
+        {
+            Ok(device_and_queue) => device_and_queue,
+            Err(error)           => return Err(format!("Cannot create the device: {}", error))
+        };
+
+        //Boards bigger than the largest supported size are shrunk to it
+        let max_board_size = StafraBoardBindings::max_board_size(&device_limits);
+        let board_width    = min(requested_board_width,  max_board_size);
+        let board_height   = min(requested_board_height, max_board_size);
 
         device.on_uncaptured_error(|error|
         {
             println!("Wgpu error: {}", error);
         });
 
-        let swapchain_formats = main_surface.get_supported_formats(&adapter);
-        if swapchain_formats.is_empty()
+        //Without surfaces the render pipelines are only used for video frames
+        let swapchain_format = match &main_surface
         {
-            panic!("Error: the surface is incompatible with the adapter.");
-        }
+            Some(main_surface) =>
+            {
+                let swapchain_formats = main_surface.get_supported_formats(&adapter);
+                if swapchain_formats.is_empty()
+                {
+                    return Err("Error: the surface is incompatible with the adapter.".to_string());
+                }
+
+                swapchain_formats[0]
+            }
 
-        let swapchain_format = swapchain_formats[0];
+            None => wgpu::TextureFormat::Rgba8Unorm
+        };
 
-        main_surface.configure(&device, &wgpu::SurfaceConfiguration
+        if let Some(main_surface) = &main_surface
         {
-            usage:        wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format:       swapchain_format,
-            width:        window_width,
-            height:       window_height,
-            present_mode: wgpu::PresentMode::Fifo
-        });
+            main_surface.configure(&device, &wgpu::SurfaceConfiguration
+            {
+                usage:        wgpu::TextureUsages::RENDER_ATTACHMENT,
+                format:       swapchain_format,
+                width:        window_width,
+                height:       window_height,
+                present_mode: wgpu::PresentMode::Fifo
+            });
+        }
 
-        click_rule_surface.configure(&device, &wgpu::SurfaceConfiguration
+        if let Some(click_rule_surface) = &click_rule_surface
         {
-            usage:        wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format:       swapchain_format,
-            width:        click_rule_width,
-            height:       click_rule_height,
-            present_mode: wgpu::PresentMode::Fifo
-        });
+            click_rule_surface.configure(&device, &wgpu::SurfaceConfiguration
+            {
+                usage:        wgpu::TextureUsages::RENDER_ATTACHMENT,
+                format:       swapchain_format,
+                width:        click_rule_width,
+                height:       click_rule_height,
+                present_mode: wgpu::PresentMode::Fifo
+            });
+        }
+
+        //The initial state texture only needs to hold the custom image, which can't be bigger than the max texture size
+        let max_texture_dimension = device_limits.max_texture_dimension_2d;
+        let initial_state_width   = min(board_width,  max_texture_dimension);
+        let initial_state_height  = min(board_height, max_texture_dimension);
+
+        let profiler = PassProfiler::new(&device, &queue);
 
-        let static_state           = StafraStaticState::new(&device, swapchain_format);
-        let static_bindings        = StafraStaticBindings::new(&device, &static_state);
-        let initial_state_bindings = StafraInitialStateBindings::new(&device, board_width, board_height);
+        let static_state    = StafraStaticState::new(&device, swapchain_format);
+        let static_bindings = StafraStaticBindings::new(&device, &static_state);
+
+        //The board can be too big for the video memory even if it fits into the limits
+        device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+        let initial_state_bindings = StafraInitialStateBindings::new(&device, initial_state_width, initial_state_height);
         let board_bindings         = StafraBoardBindings::new(&device, &static_state, &static_bindings, &initial_state_bindings, board_width, board_height);
+        if let Some(error) = device.pop_error_scope().await
+        {
+            return Err(format!("Cannot allocate the board of size {}x{}: {}", board_width, board_height, error));
+        }
 
-        Self
+        Ok(Self
         {
             main_surface,
             click_rule_surface,
@@ -156,187 +612,1207 @@ impl StafraState
             swapchain_format,
             frame_number: 0,
 
+            main_surface_width:  window_width,
+            main_surface_height: window_height,
+
             last_reset_type: ResetBoardType::Standard{reset_type: StandardResetBoardType::Corners},
 
+            click_rule_data: [0u8; 32 * 32],
+            initial_image:   None,
+
+            board_expression:          None,
+            clear_expression_pipeline: None,
+
             initial_restriction_tex: None,
 
+            image_import_options: ImageImportOptions::default(),
+
+            board_strokes:            Vec::new(),
+            board_fill_snapshot:      None,
+            board_strokes_generation: 0,
+            requested_board_fill:     None,
+
+            lights_out_enabled: false,
+            lights_out_moves:   Vec::new(),
+            board_stepped:      false,
+
+            board_version:                 0,
+            restriction_version:           0,
+            requested_board_version:       None,
+            requested_restriction_version: None,
+            lights_out_lit_cells:          Arc::new(Mutex::new(None)),
+            lights_out_restriction:        Arc::new(Mutex::new(None)),
+
+            png_readback_pool:         ReadbackPool::new(DEFAULT_READBACK_QUEUE_DEPTH),
+            stability_readback_pool:   ReadbackPool::new(DEFAULT_READBACK_QUEUE_DEPTH),
+            video_frame_readback_pool: ReadbackPool::new(DEFAULT_READBACK_QUEUE_DEPTH),
+            session_readback_pool:     ReadbackPool::new(SESSION_READBACK_SLOTS as u32),
+            lights_out_readback_pool:  ReadbackPool::new(LIGHTS_OUT_READBACK_SLOTS),
+            batch_readback_pool:       ReadbackPool::new(BATCH_READBACK_SLOTS),
+            fill_readback_pool:        ReadbackPool::new(FILL_READBACK_SLOTS),
+
+            profiler,
+
+            history: FrameHistory::new(DEFAULT_HISTORY_FRAMES, DEFAULT_KEYFRAME_PERIOD),
+
             static_state,
             static_bindings,
             initial_state_bindings,
-            board_bindings
+            board_bindings,
+
+            batch_bindings: None,
+
+            pending_operations: VecDeque::new(),
+            allocation_error:   None
+        })
+    }
+
+    //The adapter's own limits, with the texture and buffer sizes capped at what the project can use
+    fn required_limits(adapter_limits: &wgpu::Limits) -> wgpu::Limits
+    {
+        //The board of the largest size 32767x32767 fits into a single texture of 16384x16384 quads
+        let max_useful_texture_dimension = 16384;
+
+        let mut limits = adapter_limits.clone();
+        limits.max_texture_dimension_2d = min(adapter_limits.max_texture_dimension_2d, max_useful_texture_dimension);
+        limits.max_buffer_size          = min(adapter_limits.max_buffer_size, (max_useful_texture_dimension * max_useful_texture_dimension) as u64 * std::mem::size_of::<u32>() as u64);
+        limits
+    }
+
+    //Catches the out of memory errors of the allocations. Native wgpu reports the errors immediately, WebGPU only asynchronously.
+    //Allocations are also pending while the earlier ones are, so they are applied in order
+    fn allocate_checked<T>(&self, description: &str, allocate: impl FnOnce(&Self) -> T) -> CheckedAllocation<T>
+    {
+        self.device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+        let allocated = allocate(self);
+
+        let mut error_scope: ErrorScopeFuture = Box::pin(self.device.pop_error_scope());
+        if self.pending_operations.is_empty()
+        {
+            match error_scope.as_mut().now_or_never()
+            {
+                Some(None)        => return CheckedAllocation::Ready(Ok(allocated)),
+                Some(Some(error)) => return CheckedAllocation::Ready(Err(format!("Cannot allocate {}: {}", description, error))),
+                None              => {}
+            }
         }
+
+        CheckedAllocation::Pending(PendingAllocation
+        {
+            description: description.to_string(),
+            allocated,
+            error_scope
+        })
     }
 
-    pub fn frame_number(&self) -> u32
+    //Applies the allocation right away if it's checked, otherwise after its error scope resolves.
+    //The old resources are kept if the allocation fails. Failures found later are reported by take_allocation_error
+    fn apply_checked<T: 'static>(&mut self, allocation: CheckedAllocation<T>, apply: impl FnOnce(&mut Self, T) + 'static) -> Result<(), String>
     {
-        self.frame_number
+        match allocation
+        {
+            CheckedAllocation::Ready(allocation_result) =>
+            {
+                apply(self, allocation_result?);
+                Ok(())
+            }
+
+            CheckedAllocation::Pending(pending_allocation) =>
+            {
+                self.defer_allocation(pending_allocation, move |state, allocation_result|
+                {
+                    match allocation_result
+                    {
+                        Ok(allocated) => apply(state, allocated),
+                        Err(message)  => state.allocation_error = Some(message)
+                    }
+                });
+
+                Ok(())
+            }
+        }
+    }
+
+    fn defer_allocation<T: 'static>(&mut self, pending_allocation: PendingAllocation<T>, apply: impl FnOnce(&mut Self, Result<T, String>) + 'static)
+    {
+        let PendingAllocation {description, allocated, error_scope} = pending_allocation;
+        self.pending_operations.push_back(PendingOperation
+        {
+            error_scope: Some(error_scope),
+            run: Box::new(move |state, allocation_error|
+            {
+                match allocation_error
+                {
+                    None        => apply(state, Ok(allocated)),
+                    Some(error) => apply(state, Err(format!("Cannot allocate {}: {}", description, error)))
+                }
+            })
+        });
+    }
+
+    //Runs the request right away if no allocation is pending
+    fn run_after_allocations(&mut self, request: impl FnOnce(&mut Self) + 'static)
+    {
+        if self.pending_operations.is_empty()
+        {
+            request(self);
+            return;
+        }
+
+        self.pending_operations.push_back(PendingOperation
+        {
+            error_scope: None,
+            run:         Box::new(move |state, _| request(state))
+        });
+    }
+
+    fn run_pending_operations(&mut self)
+    {
+        while let Some(pending_operation) = self.pending_operations.front_mut()
+        {
+            let allocation_error = match pending_operation.error_scope.as_mut()
+            {
+                Some(error_scope) => match error_scope.as_mut().now_or_never()
+                {
+                    Some(allocation_error) => allocation_error,
+                    None                   => return
+                },
+
+                None => None
+            };
+
+            let pending_operation = self.pending_operations.pop_front().unwrap();
+            (pending_operation.run)(self, allocation_error);
+        }
+    }
+
+    //The initial state texture holds the cropped image, or nothing if the image is larger than a single texture
+    fn create_initial_state_bindings(&self, initial_image: &ImageData) -> StafraInitialStateBindings
+    {
+        let image_size = initial_image_size(initial_image);
+        if image_size > self.device.limits().max_texture_dimension_2d
+        {
+            return StafraInitialStateBindings::new(&self.device, 1, 1);
+        }
+
+        let initial_state_bindings = StafraInitialStateBindings::new(&self.device, image_size, image_size);
+        initial_state_bindings.upload_texture(&self.queue, initial_image.pixel_data.clone(), initial_image.image_width, initial_image.image_height);
+        initial_state_bindings
+    }
+
+    fn apply_resized_board(&mut self, board_bindings: StafraBoardBindings)
+    {
+        self.board_bindings = board_bindings;
+
+        //The strokes are drawn in the cells of the old board size
+        self.clear_board_strokes();
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Resize board encoder")});
+
+        if let Some(initial_restriction_tex) = &self.initial_restriction_tex
+        {
+            let initial_restriction_view = initial_restriction_tex.create_view(&wgpu::TextureViewDescriptor::default());
+            self.board_bindings.initial_transform_restriction(&self.device, &mut encoder, &initial_restriction_view, &self.static_state);
+        }
+        else
+        {
+            self.board_bindings.clear_restriction(&mut encoder, &self.static_state);
+        }
+
+        self.reset_board_unchanged_impl(&mut encoder);
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    fn post_batch_request_impl(&mut self, params: BatchParams, callback: BatchCallback)
+    {
+        if !(params.board_size + 1).is_power_of_two() || params.board_size < 3
+        {
+            return callback(Err(format!("Batch board size {} is not 2^n - 1", params.board_size)));
+        }
+
+        let max_layers = StafraBatchBindings::max_layers(&self.device.limits(), params.board_size);
+        if max_layers == 0
+        {
+            return callback(Err(format!("Batch board size {} is not supported", params.board_size)));
+        }
+
+        if params.click_rules.is_empty()
+        {
+            return callback(Ok(Vec::new()));
+        }
+
+        let layer_count = min(max_layers, params.click_rules.len() as u32);
+        let bindings_match_batch = self.batch_bindings.as_ref().is_some_and(|batch_bindings| batch_bindings.board_size() == params.board_size && batch_bindings.layer_count() == layer_count);
+        if !bindings_match_batch
+        {
+            let batch_allocation = self.allocate_checked(&format!("{} boards of size {}x{}", layer_count, params.board_size, params.board_size), |state|
+            {
+                StafraBatchBindings::new(&state.device, &state.queue, &state.static_state, params.board_size, layer_count)
+            });
+
+            match batch_allocation
+            {
+                CheckedAllocation::Ready(allocation_result) => match allocation_result.and_then(|batch_result| batch_result)
+                {
+                    Ok(batch_bindings) => self.batch_bindings = Some(batch_bindings),
+                    Err(message)       => return callback(Err(message))
+                },
+
+                CheckedAllocation::Pending(pending_allocation) =>
+                {
+                    return self.defer_allocation(pending_allocation, move |state, allocation_result|
+                    {
+                        match allocation_result.and_then(|batch_result| batch_result)
+                        {
+                            Ok(batch_bindings) =>
+                            {
+                                state.batch_bindings = Some(batch_bindings);
+                                state.run_batch(params, layer_count, callback);
+                            }
+
+                            Err(message) => callback(Err(message))
+                        }
+                    });
+                }
+            }
+        }
+
+        self.run_batch(params, layer_count, callback);
+    }
+
+    //Simulates the boards of the batch in chunks of layer_count boards with the current batch bindings
+    fn run_batch(&mut self, params: BatchParams, layer_count: u32, callback: BatchCallback)
+    {
+        let readback_slot = match self.batch_readback_pool.acquire()
+        {
+            Some(readback_slot) => readback_slot,
+            None                => return callback(Err("The previous batch is still waiting for the readback".to_string()))
+        };
+
+        let batch_bindings = self.batch_bindings.as_ref().unwrap();
+
+        let mut stability_buffers = readback_slot.buffers();
+        batch_bindings.create_readback_buffers(&self.device, &mut stability_buffers, params.click_rules.len());
+
+        //Each chunk of boards is uploaded right before its submission, so the uploads don't overwrite the boards of the previous chunks
+        for (click_rule_chunk, stability_buffer_chunk) in params.click_rules.chunks(layer_count as usize).zip(stability_buffers.chunks(layer_count as usize))
+        {
+            batch_bindings.reset_layers(&self.queue, click_rule_chunk, params.reset_type);
+
+            let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Batch encoder")});
+            for frame_number in 0..params.frame_count
+            {
+                batch_bindings.calc_next_frame(&mut encoder, &self.static_state, frame_number);
+            }
+
+            batch_bindings.copy_stability_data(&mut encoder, stability_buffer_chunk, params.frame_count);
+            self.queue.submit(std::iter::once(encoder.finish()));
+        }
+
+        drop(stability_buffers);
+
+        let board_size = params.board_size;
+        readback_slot.map_read(move |map_result|
+        {
+            callback(map_result.map(|stability_buffers|
+            {
+                stability_buffers.iter().map(|stability_buffer| StafraBoardBindings::get_cell_buffers_mapped_data(std::slice::from_ref(stability_buffer), board_size, board_size)).collect()
+            }));
+        });
+    }
+
+    fn post_save_png_request_impl(&mut self, callback: ImageCallback)
+    {
+        let readback_slot = match self.png_readback_pool.acquire()
+        {
+            Some(readback_slot) => readback_slot,
+            None                => return callback(Err("Too many PNG requests are waiting for the readback".to_string()))
+        };
+
+        let mut buffer_copy_encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("PNG buffer copy encoder")});
+        self.board_bindings.copy_image_data(&self.device, &mut buffer_copy_encoder, &mut readback_slot.buffers());
+        self.queue.submit(std::iter::once(buffer_copy_encoder.finish()));
+
+        let image_width  = self.board_bindings.board_width();
+        let image_height = self.board_bindings.board_height();
+
+        readback_slot.map_read(move |map_result|
+        {
+            callback(map_result.map(|save_png_buffers| StafraBoardBindings::get_image_buffers_mapped_data(save_png_buffers, image_width, image_height)));
+        });
+    }
+
+    fn post_stability_request_impl(&mut self, callback: ImageCallback)
+    {
+        let readback_slot = match self.stability_readback_pool.acquire()
+        {
+            Some(readback_slot) => readback_slot,
+            None                => return callback(Err("Too many stability requests are waiting for the readback".to_string()))
+        };
+
+        let mut buffer_copy_encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Stability buffer copy encoder")});
+        self.board_bindings.copy_stability_data(&self.device, &mut buffer_copy_encoder, &mut readback_slot.buffers(), self.frame_number);
+        self.queue.submit(std::iter::once(buffer_copy_encoder.finish()));
+
+        let board_width  = self.board_bindings.board_width();
+        let board_height = self.board_bindings.board_height();
+
+        readback_slot.map_read(move |map_result|
+        {
+            callback(map_result.map(|stability_buffers| StafraBoardBindings::get_cell_buffers_mapped_data(stability_buffers, board_width, board_height)));
+        });
+    }
+
+    fn post_save_session_request_impl(&mut self, callback: SessionCallback)
+    {
+        let readback_slots = match self.session_readback_pool.acquire_many(SESSION_READBACK_SLOTS)
+        {
+            Some(readback_slots) => readback_slots,
+            None                 => return callback(Err("The previous session is still being saved".to_string()))
+        };
+
+        let mut buffer_copy_encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Session buffer copy encoder")});
+        self.board_bindings.copy_board_data(&self.device,       &mut buffer_copy_encoder, &mut readback_slots[0].buffers(), self.frame_number);
+        self.board_bindings.copy_stability_data(&self.device,   &mut buffer_copy_encoder, &mut readback_slots[1].buffers(), self.frame_number);
+        self.board_bindings.copy_restriction_data(&self.device, &mut buffer_copy_encoder, &mut readback_slots[2].buffers());
+        self.queue.submit(std::iter::once(buffer_copy_encoder.finish()));
+
+        let session = StafraSession
+        {
+            board_size:   self.board_bindings.board_width(),
+            frame_number: self.frame_number,
+
+            spawn_period:             self.static_bindings.spawn_period(),
+            smooth_transform_enabled: self.static_bindings.smooth_transform_enabled(),
+
+            last_reset_type: self.last_reset_type,
+            click_rule_data: self.click_rule_data,
+
+            board:       Vec::new(),
+            stability:   Vec::new(),
+            restriction: Vec::new(),
+
+            initial_image: self.initial_image.as_ref().map(|initial_image| ImageData
+            {
+                pixel_data:   initial_image.pixel_data.clone(),
+                image_width:  initial_image.image_width,
+                image_height: initial_image.image_height
+            }),
+
+            board_expression: self.board_expression.clone(),
+            board_strokes:    self.board_strokes.clone()
+        };
+
+        //The session is completed by whichever readback finishes last
+        let board_size     = session.board_size;
+        let pending_reads  = Arc::new(Mutex::new((Some(session), SESSION_READBACK_SLOTS, None)));
+        let callback_mutex = Arc::new(Mutex::new(Some(callback)));
+        for (slot_index, readback_slot) in readback_slots.into_iter().enumerate()
+        {
+            let pending_reads_clone  = pending_reads.clone();
+            let callback_mutex_clone = callback_mutex.clone();
+            readback_slot.map_read(move |map_result|
+            {
+                let mut pending_reads = pending_reads_clone.lock().unwrap();
+                let (pending_session, remaining_reads, read_error) = &mut *pending_reads;
+
+                match (map_result, pending_session.as_mut())
+                {
+                    (Ok(cell_buffers), Some(session)) =>
+                    {
+                        let cell_data = StafraBoardBindings::get_cell_buffers_mapped_data(cell_buffers, board_size, board_size).pixel_data;
+                        match slot_index
+                        {
+                            0 => session.board       = cell_data,
+                            1 => session.stability   = cell_data,
+                            _ => session.restriction = cell_data
+                        }
+                    }
+
+                    (Err(message), _) => *read_error = Some(message),
+                    (Ok(_), None)     => {}
+                }
+
+                *remaining_reads -= 1;
+                if *remaining_reads > 0
+                {
+                    return;
+                }
+
+                if let Some(callback) = callback_mutex_clone.lock().unwrap().take()
+                {
+                    match read_error.take()
+                    {
+                        Some(message) => callback(Err(message)),
+                        None          => callback(pending_session.take().ok_or("The session is already read".to_string()))
+                    }
+                }
+            });
+        }
+    }
+
+    fn post_video_frame_request_impl(&mut self, callback: ImageCallback)
+    {
+        let readback_slot = match self.video_frame_readback_pool.acquire()
+        {
+            Some(readback_slot) => readback_slot,
+            None                => return callback(Err("The video frame queue is full".to_string()))
+        };
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Video frame copy encoder")});
+        self.board_bindings.render_video_frame(&mut encoder, &self.static_state, &mut self.profiler);
+        self.board_bindings.copy_video_frame_data(&self.device, &mut encoder, &mut readback_slot.buffers());
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        readback_slot.map_read(move |map_result|
+        {
+            callback(map_result.map(|video_frame_buffers| StafraBoardBindings::get_video_frame_buffer_mapped_data(&video_frame_buffers[0].image_buffer, video_frame_buffers[0].raw_width, video_frame_buffers[0].raw_height)));
+        });
+    }
+
+    fn reset_board_unchanged_impl(&mut self, encoder: &mut wgpu::CommandEncoder)
+    {
+        match self.last_reset_type
+        {
+            ResetBoardType::Standard {reset_type} =>
+            {
+                self.reset_board_standard_impl(encoder, reset_type);
+            }
+
+            ResetBoardType::Random {params} =>
+            {
+                self.reset_board_random_impl(encoder, params);
+            }
+
+            ResetBoardType::Pattern {params} =>
+            {
+                self.reset_board_pattern_impl(encoder, params);
+            }
+
+            ResetBoardType::Expression =>
+            {
+                self.reset_board_expression_impl(encoder);
+            }
+
+            ResetBoardType::Custom =>
+            {
+                self.reset_board_custom_impl(encoder);
+            }
+        }
+    }
+
+    fn reset_board_standard_impl(&mut self, encoder: &mut wgpu::CommandEncoder, reset_type: StandardResetBoardType)
+    {
+        match reset_type
+        {
+            StandardResetBoardType::Corners => {self.board_bindings.reset_board_standard_corners(encoder, &self.static_state);}
+            StandardResetBoardType::Edges   => {self.board_bindings.reset_board_standard_edges(encoder, &self.static_state);}
+            StandardResetBoardType::Center  => {self.board_bindings.reset_board_standard_center(encoder, &self.static_state);}
+        }
+
+        self.last_reset_type = ResetBoardType::Standard {reset_type};
+        self.finish_reset_impl(encoder);
+    }
+
+    fn reset_board_random_impl(&mut self, encoder: &mut wgpu::CommandEncoder, params: RandomBoardParams)
+    {
+        self.static_bindings.set_random_board_params(&self.queue, &params, self.board_bindings.board_width());
+        self.board_bindings.reset_board_random(encoder, &self.static_state, &self.static_bindings);
+
+        self.last_reset_type = ResetBoardType::Random {params};
+        self.finish_reset_impl(encoder);
+    }
+
+    fn reset_board_pattern_impl(&mut self, encoder: &mut wgpu::CommandEncoder, params: PatternBoardParams)
+    {
+        self.static_bindings.set_pattern_board_params(&self.queue, &params);
+        self.board_bindings.reset_board_pattern(encoder, &self.static_state, &self.static_bindings);
+
+        self.last_reset_type = ResetBoardType::Pattern {params};
+        self.finish_reset_impl(encoder);
+    }
+
+    fn reset_board_expression_impl(&mut self, encoder: &mut wgpu::CommandEncoder)
+    {
+        if let Some(clear_expression_pipeline) = &self.clear_expression_pipeline
+        {
+            self.board_bindings.reset_board_expression(encoder, &self.static_state, clear_expression_pipeline);
+        }
+
+        self.last_reset_type = ResetBoardType::Expression;
+        self.finish_reset_impl(encoder);
+    }
+
+    fn reset_board_custom_impl(&mut self, encoder: &mut wgpu::CommandEncoder)
+    {
+        //The images larger than a single texture have no initial state texture, their cells are written into the tiles directly
+        let max_texture_dimension = self.device.limits().max_texture_dimension_2d;
+        match &self.initial_image
+        {
+            Some(initial_image) if initial_image_size(initial_image) > max_texture_dimension =>
+            {
+                let cells = initial_image_cells(initial_image, self.board_bindings.board_width(), self.board_bindings.board_height());
+                self.board_bindings.upload_board_cells(&self.queue, &cells);
+            }
+
+            _ => self.board_bindings.reset_board_custom(encoder, &self.static_state)
+        }
+
+        self.last_reset_type = ResetBoardType::Custom;
+        self.finish_reset_impl(encoder);
+    }
+
+    //Replays the painted strokes on top of the freshly reset board and makes it the frame 0.
+    //The strokes up to the latest flood fill are restored from its snapshot, the replay stops before the next fill until it's done
+    fn finish_reset_impl(&mut self, encoder: &mut wgpu::CommandEncoder)
+    {
+        let first_stroke = match &self.board_fill_snapshot
+        {
+            Some((stroke_count, fill_snapshot)) =>
+            {
+                self.board_bindings.restore_snapshot(encoder, fill_snapshot);
+                *stroke_count
+            }
+
+            None => 0
+        };
+
+        let unfilled_stroke = self.first_unfilled_stroke();
+        for stroke in &self.board_strokes[first_stroke..unfilled_stroke.unwrap_or(self.board_strokes.len())]
+        {
+            self.board_bindings.paint_stroke(&self.device, encoder, &self.static_state, stroke);
+        }
+
+        self.board_bindings.filter_restriction(encoder, &self.static_state);
+        self.board_bindings.clear_stability(encoder, &self.static_state);
+
+        self.frame_number = 0;
+        self.generate_final_image_impl(encoder, 0);
+
+        //The restriction may have changed before the reset
+        self.restriction_version += 1;
+        self.restart_lights_out();
+
+        self.restart_history(encoder);
+
+        if let Some(stroke_index) = unfilled_stroke
+        {
+            self.queue_fill(self.board_strokes[stroke_index], Some(stroke_index));
+        }
+    }
+
+    //The fills of the old strokes are not used anymore
+    fn clear_board_strokes(&mut self)
+    {
+        self.board_strokes.clear();
+        self.board_fill_snapshot       = None;
+        self.board_strokes_generation += 1;
+    }
+
+    //The first board fill that is not in the fill snapshot yet
+    fn first_unfilled_stroke(&self) -> Option<usize>
+    {
+        let filled_strokes = self.board_fill_snapshot.as_ref().map_or(0, |(stroke_count, _)| *stroke_count);
+        self.board_strokes.iter().skip(filled_strokes).position(|stroke| stroke.tool == PaintTool::Fill).map(|position| filled_strokes + position)
+    }
+
+    //Flood fill reads the board or the restriction back, fills it on the CPU the same way as the CPU state and uploads it again.
+    //The readback is posted on the next run of the pending operations, after the strokes before the fill are submitted
+    fn queue_fill(&mut self, stroke: PaintStroke, board_stroke_index: Option<usize>)
+    {
+        let strokes_generation = self.board_strokes_generation;
+        if let Some(stroke_index) = board_stroke_index
+        {
+            if self.requested_board_fill == Some((strokes_generation, stroke_index))
+            {
+                return;
+            }
+
+            self.requested_board_fill = Some((strokes_generation, stroke_index));
+        }
+
+        self.pending_operations.push_back(PendingOperation
+        {
+            error_scope: None,
+            run:         Box::new(move |state, _| state.post_fill_readback(stroke, board_stroke_index, strokes_generation))
+        });
+    }
+
+    fn post_fill_readback(&mut self, stroke: PaintStroke, board_stroke_index: Option<usize>, strokes_generation: u64)
+    {
+        if strokes_generation != self.board_strokes_generation || board_stroke_index.is_some_and(|stroke_index| self.first_unfilled_stroke() != Some(stroke_index))
+        {
+            return;
+        }
+
+        let readback_slot = match self.fill_readback_pool.acquire()
+        {
+            Some(readback_slot) => readback_slot,
+            None                => return self.finish_fill_request(board_stroke_index, strokes_generation)
+        };
+
+        let mut buffer_copy_encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Flood fill copy encoder")});
+        match stroke.target
+        {
+            PaintTarget::Board =>
+            {
+                //The board may have changed since the replay, it's replayed up to the fill again
+                self.reset_board_unchanged_impl(&mut buffer_copy_encoder);
+                self.board_bindings.copy_unfiltered_board_data(&self.device, &mut buffer_copy_encoder, &mut readback_slot.buffers());
+            }
+
+            PaintTarget::Restriction =>
+            {
+                self.board_bindings.copy_restriction_data(&self.device, &mut buffer_copy_encoder, &mut readback_slot.buffers());
+            }
+        }
+
+        self.queue.submit(std::iter::once(buffer_copy_encoder.finish()));
+
+        let board_size = self.board_bindings.board_width();
+        let fill_cells = Arc::new(Mutex::new(None));
+
+        let (fill_sender, fill_receiver) = futures::channel::oneshot::channel();
+        let fill_cells_clone             = fill_cells.clone();
+        readback_slot.map_read(move |map_result|
+        {
+            *fill_cells_clone.lock().unwrap() = Some(map_result.map(|cell_buffers| StafraBoardBindings::get_cell_buffers_mapped_data(cell_buffers, board_size, board_size).pixel_data));
+            let _ = fill_sender.send(());
+        });
+
+        //Runs before the operations queued in the meantime, they may depend on the filled board
+        self.pending_operations.push_front(PendingOperation
+        {
+            error_scope: Some(Box::pin(fill_receiver.map(|_| None))),
+            run:         Box::new(move |state, _|
+            {
+                state.finish_fill_request(board_stroke_index, strokes_generation);
+
+                let read_cells = fill_cells.lock().unwrap().take();
+                match read_cells
+                {
+                    Some(Ok(cells))    => state.apply_fill(stroke, board_stroke_index, strokes_generation, cells),
+                    Some(Err(message)) => log::error!("Flood fill failed: {}", message),
+                    None               => {}
+                }
+            })
+        });
+    }
+
+    //The next replay requests the board fill again if it's still not done
+    fn finish_fill_request(&mut self, board_stroke_index: Option<usize>, strokes_generation: u64)
+    {
+        if board_stroke_index.is_some() && self.requested_board_fill == board_stroke_index.map(|stroke_index| (strokes_generation, stroke_index))
+        {
+            self.requested_board_fill = None;
+        }
+    }
+
+    fn apply_fill(&mut self, stroke: PaintStroke, board_stroke_index: Option<usize>, strokes_generation: u64, mut cells: Vec<u8>)
+    {
+        if strokes_generation != self.board_strokes_generation || board_stroke_index.is_some_and(|stroke_index| self.first_unfilled_stroke() != Some(stroke_index))
+        {
+            return;
+        }
+
+        stroke.flood_fill_cells(&mut cells, self.board_bindings.board_width() as usize);
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Flood fill encoder")});
+        match board_stroke_index
+        {
+            Some(stroke_index) =>
+            {
+                //The queue writes the cells before the snapshot copies them
+                self.board_bindings.upload_board_cells(&self.queue, &cells);
+
+                let fill_snapshot = match self.board_fill_snapshot.take()
+                {
+                    Some((_, fill_snapshot)) => fill_snapshot,
+                    None                     => self.board_bindings.create_snapshot(&self.device)
+                };
+
+                self.board_bindings.save_snapshot(&mut encoder, &fill_snapshot, 0);
+                self.board_fill_snapshot = Some((stroke_index + 1, fill_snapshot));
+            }
+
+            None =>
+            {
+                self.board_bindings.upload_restriction_cells(&self.queue, &cells);
+            }
+        }
+
+        //Replays the strokes after the fill, and filters the board by the filled restriction
+        self.reset_board_unchanged_impl(&mut encoder);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    //In the Lights Out play mode the latest board is displayed instead of the stability
+    fn generate_final_image_impl(&mut self, encoder: &mut wgpu::CommandEncoder, stability_frame_number: u32)
+    {
+        if self.lights_out_enabled
+        {
+            self.board_bindings.generate_board_image(encoder, &self.static_state, &mut self.profiler, self.frame_number);
+        }
+        else
+        {
+            self.board_bindings.generate_final_image(encoder, &self.static_state, &mut self.profiler, stability_frame_number);
+        }
+    }
+
+    //Any change of the board other than a move starts the puzzle again
+    fn restart_lights_out(&mut self)
+    {
+        self.lights_out_moves.clear();
+        self.board_version += 1;
+        self.board_stepped = false;
+    }
+
+    fn restart_stepped_lights_out(&mut self)
+    {
+        if self.board_stepped
+        {
+            self.restart_lights_out();
+        }
+    }
+
+    fn lights_out_toggle(&mut self, lights_out_move: LightsOutMove)
+    {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Lights Out move encoder")});
+
+        self.board_bindings.lights_out_click(&self.device, &mut encoder, &self.static_state, &self.static_bindings, lights_out_move, self.frame_number);
+        self.board_bindings.clear_stability(&mut encoder, &self.static_state);
+        self.generate_final_image_impl(&mut encoder, self.frame_number.saturating_sub(1));
+        self.restart_history(&mut encoder);
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        self.board_version += 1;
+    }
+
+    //Until the restriction is read back, the clicks on restricted cells count as moves that don't toggle anything
+    fn lights_out_cell_restricted(&self, x: u32, y: u32) -> bool
+    {
+        match &*self.lights_out_restriction.lock().unwrap()
+        {
+            Some((version, restriction)) if *version == self.restriction_version => restriction.pixel_data[(y * restriction.image_width + x) as usize] == 0,
+            _                                                                     => false
+        }
+    }
+
+    //Older readbacks never overwrite the newer ones
+    fn request_lights_out_readbacks(&mut self)
+    {
+        let board_width  = self.board_bindings.board_width();
+        let board_height = self.board_bindings.board_height();
+
+        if self.requested_board_version != Some(self.board_version)
+        {
+            if let Some(readback_slot) = self.lights_out_readback_pool.acquire()
+            {
+                let mut buffer_copy_encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Lights Out board copy encoder")});
+                self.board_bindings.copy_board_data(&self.device, &mut buffer_copy_encoder, &mut readback_slot.buffers(), self.frame_number);
+                self.queue.submit(std::iter::once(buffer_copy_encoder.finish()));
+
+                let board_version        = self.board_version;
+                let lights_out_lit_cells = self.lights_out_lit_cells.clone();
+                readback_slot.map_read(move |map_result|
+                {
+                    if let Ok(board_buffers) = map_result
+                    {
+                        let board     = StafraBoardBindings::get_cell_buffers_mapped_data(board_buffers, board_width, board_height);
+                        let lit_cells = board.pixel_data.iter().filter(|cell| **cell != 0).count() as u64;
+
+                        let mut lit_cells_data = lights_out_lit_cells.lock().unwrap();
+                        if lit_cells_data.is_none_or(|(version, _)| version < board_version)
+                        {
+                            *lit_cells_data = Some((board_version, lit_cells));
+                        }
+                    }
+                });
+
+                self.requested_board_version = Some(self.board_version);
+            }
+        }
+
+        if self.requested_restriction_version != Some(self.restriction_version)
+        {
+            if let Some(readback_slot) = self.lights_out_readback_pool.acquire()
+            {
+                let mut buffer_copy_encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Lights Out restriction copy encoder")});
+                self.board_bindings.copy_restriction_data(&self.device, &mut buffer_copy_encoder, &mut readback_slot.buffers());
+                self.queue.submit(std::iter::once(buffer_copy_encoder.finish()));
+
+                let restriction_version    = self.restriction_version;
+                let lights_out_restriction = self.lights_out_restriction.clone();
+                readback_slot.map_read(move |map_result|
+                {
+                    if let Ok(restriction_buffers) = map_result
+                    {
+                        let restriction = StafraBoardBindings::get_cell_buffers_mapped_data(restriction_buffers, board_width, board_height);
+
+                        let mut restriction_data = lights_out_restriction.lock().unwrap();
+                        if restriction_data.as_ref().is_none_or(|(version, _)| *version < restriction_version)
+                        {
+                            *restriction_data = Some((restriction_version, restriction));
+                        }
+                    }
+                });
+
+                self.requested_restriction_version = Some(self.restriction_version);
+            }
+        }
+    }
+
+    //Drops the history and starts it again from the current frame
+    fn restart_history(&mut self, encoder: &mut wgpu::CommandEncoder)
+    {
+        self.history.reset(self.board_bindings.snapshot_bytes());
+        self.record_history(encoder);
+    }
+
+    fn record_history(&mut self, encoder: &mut wgpu::CommandEncoder)
+    {
+        let device         = &self.device;
+        let board_bindings = &self.board_bindings;
+        let frame_number   = self.frame_number;
+
+        self.history.record(frame_number, |reused_snapshot|
+        {
+            let snapshot = reused_snapshot.unwrap_or_else(|| board_bindings.create_snapshot(device));
+            board_bindings.save_snapshot(encoder, &snapshot, frame_number);
+            snapshot
+        });
+    }
+
+    fn upload_restriction_impl(&mut self, image_array: Vec<u8>, image_width: u32, image_height: u32)
+    {
+        //Restriction images bigger than the max texture size are cropped
+        let max_texture_dimension = self.device.limits().max_texture_dimension_2d;
+        let width  = min(image_width,  max_texture_dimension);
+        let height = min(image_height, max_texture_dimension);
+
+        let restriction_texture_descriptor = wgpu::TextureDescriptor
+        {
+            label: Some("Restriction texture"),
+            size:  wgpu::Extent3d
+            {
+                width,
+                height,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count:    1,
+            dimension:       wgpu::TextureDimension::D2,
+            format:          wgpu::TextureFormat::Rgba8Unorm,
+            usage:           wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+        };
+
+        let restriction_tex = self.device.create_texture(&restriction_texture_descriptor);
+        self.queue.write_texture(wgpu::ImageCopyTexture
+        {
+            texture:   &restriction_tex,
+            mip_level: 0,
+            origin:    wgpu::Origin3d::ZERO,
+            aspect:    wgpu::TextureAspect::All
+        },
+        image_array.as_slice(),
+        wgpu::ImageDataLayout
+        {
+            offset:         0,
+            bytes_per_row:  NonZeroU32::new(image_width * 4),
+            rows_per_image: NonZeroU32::new(image_height)
+        },
+        wgpu::Extent3d
+        {
+            width,
+            height,
+            depth_or_array_layers: 1
+        });
+
+        self.initial_restriction_tex = Some(restriction_tex);
+    }
+}
+
+impl SimulationBackend for StafraState
+{
+    fn max_board_size(&self) -> u32
+    {
+        StafraBoardBindings::max_board_size(&self.device.limits())
+    }
+
+    fn frame_number(&self) -> u32
+    {
+        self.frame_number
+    }
+
+    fn board_size(&self) -> u32
+    {
+        self.board_bindings.board_width()
+    }
+
+    fn set_readback_queue_depth(&mut self, depth: u32)
+    {
+        self.png_readback_pool.set_depth(depth);
+        self.stability_readback_pool.set_depth(depth);
+        self.video_frame_readback_pool.set_depth(depth);
+    }
+
+    fn video_frame_queue_full(&self) -> bool
+    {
+        self.video_frame_readback_pool.is_full() || !self.pending_operations.is_empty()
     }
 
-    pub fn video_frame_queue_full(&self) -> bool
+    fn take_allocation_error(&mut self) -> Option<String>
     {
-        false
+        self.allocation_error.take()
     }
 
-    pub fn resize(&mut self, new_width: u32, new_height: u32)
+    fn resize(&mut self, new_width: u32, new_height: u32)
     {
-        self.main_surface.configure(&self.device, &wgpu::SurfaceConfiguration
+        self.main_surface_width  = new_width;
+        self.main_surface_height = new_height;
+
+        if let Some(main_surface) = &self.main_surface
         {
-            usage:        wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format:       self.swapchain_format,
-            width:        new_width,
-            height:       new_height,
-            present_mode: wgpu::PresentMode::Fifo
-        });
+            main_surface.configure(&self.device, &wgpu::SurfaceConfiguration
+            {
+                usage:        wgpu::TextureUsages::RENDER_ATTACHMENT,
+                format:       self.swapchain_format,
+                width:        new_width,
+                height:       new_height,
+                present_mode: wgpu::PresentMode::Fifo
+            });
+        }
     }
 
-    pub fn resize_click_rule(&mut self, new_width: u32, new_height: u32)
+    fn resize_click_rule(&mut self, new_width: u32, new_height: u32)
     {
-        self.click_rule_surface.configure(&self.device, &wgpu::SurfaceConfiguration
+        if let Some(click_rule_surface) = &self.click_rule_surface
         {
-            usage:        wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format:       self.swapchain_format,
-            width:        new_width,
-            height:       new_height,
-            present_mode: wgpu::PresentMode::Fifo
-        });
+            click_rule_surface.configure(&self.device, &wgpu::SurfaceConfiguration
+            {
+                usage:        wgpu::TextureUsages::RENDER_ATTACHMENT,
+                format:       self.swapchain_format,
+                width:        new_width,
+                height:       new_height,
+                present_mode: wgpu::PresentMode::Fifo
+            });
+        }
     }
 
-    pub fn post_save_png_request(&mut self, callback: impl FnOnce(Vec<u8>, u32, u32) + Send + 'static)
+    fn post_save_png_request(&mut self, callback: ImageCallback)
     {
-        let mut buffer_copy_encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("PNG buffer copy encoder")});
-        let save_png_buffer_rc = Arc::new(Mutex::new(self.board_bindings.create_image_data_buffer(&self.device, &mut buffer_copy_encoder)));
-        self.queue.submit(std::iter::once(buffer_copy_encoder.finish()));
+        self.run_after_allocations(move |state| state.post_save_png_request_impl(callback));
+    }
 
-        let save_png_buffer_rc_clone = save_png_buffer_rc.clone();
+    fn post_stability_request(&mut self, callback: ImageCallback)
+    {
+        self.run_after_allocations(move |state| state.post_stability_request_impl(callback));
+    }
 
-        let save_png_buffer = save_png_buffer_rc.lock().unwrap();
-        save_png_buffer.image_buffer.slice(..).map_async(wgpu::MapMode::Read, move |_|
-        {
-            let save_png_buffer = save_png_buffer_rc_clone.lock().unwrap();
-            let image_data = StafraBoardBindings::get_image_buffer_mapped_data(&save_png_buffer.image_buffer, save_png_buffer.raw_width, save_png_buffer.raw_height, save_png_buffer.row_pitch);
-            save_png_buffer.image_buffer.unmap();
+    fn post_batch_request(&mut self, params: BatchParams, callback: BatchCallback)
+    {
+        self.run_after_allocations(move |state| state.post_batch_request_impl(params, callback));
+    }
 
-            callback(image_data.pixel_data, image_data.image_width, image_data.image_height);
-        });
+    fn post_save_session_request(&mut self, callback: SessionCallback)
+    {
+        self.run_after_allocations(move |state| state.post_save_session_request_impl(callback));
     }
 
-    pub fn post_video_frame_request(&mut self, callback: impl FnOnce(Vec<u8>, u32, u32) + Send + 'static)
+    fn load_session(&mut self, session: &StafraSession) -> Result<(), String>
     {
-        if self.video_frame_queue_full()
+        if session.board_size > self.max_board_size()
         {
-            return;
+            return Err(format!("Board size {}x{} is not supported, the largest supported size is {}x{}", session.board_size, session.board_size, self.max_board_size(), self.max_board_size()));
         }
 
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Video frame copy encoder")});
-        self.board_bindings.render_video_frame(&mut encoder, &self.static_state);
+        //The cells and the initial image are uploaded to the new textures, which replace the old ones only if the allocation succeeds.
+        //The initial image is cropped the same way as in reset_board_custom
+        let board_allocation = self.allocate_checked(&format!("the board of size {}x{}", session.board_size, session.board_size), |state|
+        {
+            let initial_state_bindings = session.initial_image.as_ref().map(|initial_image| state.create_initial_state_bindings(initial_image));
 
-        let video_frame_buffer_rc = Arc::new(Mutex::new(self.board_bindings.create_video_frame_data_buffer(&self.device, &mut encoder)));
-        self.queue.submit(std::iter::once(encoder.finish()));
+            let board_bindings = StafraBoardBindings::new(&state.device, &state.static_state, &state.static_bindings, initial_state_bindings.as_ref().unwrap_or(&state.initial_state_bindings), session.board_size, session.board_size);
+            board_bindings.upload_board_state(&state.queue, &session.board, &session.stability, &session.restriction);
+            (initial_state_bindings, board_bindings)
+        });
 
-        let video_frame_buffer_rc_clone = video_frame_buffer_rc.clone();
+        let click_rule_data          = session.click_rule_data;
+        let spawn_period             = session.spawn_period;
+        let smooth_transform_enabled = session.smooth_transform_enabled;
+        let last_reset_type          = session.last_reset_type;
+        let frame_number             = session.frame_number;
+        let board_expression         = session.board_expression.clone();
+        let board_strokes            = session.board_strokes.clone();
+        let restriction_image        = if session.is_restricted() {Some(session.restriction_image())} else {None};
+
+        let initial_image = session.initial_image.as_ref().map(|initial_image| ImageData
+        {
+            pixel_data:   initial_image.pixel_data.clone(),
+            image_width:  initial_image.image_width,
+            image_height: initial_image.image_height
+        });
 
-        let video_frame_buffer = video_frame_buffer_rc.lock().unwrap();
-        video_frame_buffer.image_buffer.slice(..).map_async(wgpu::MapMode::Read, move |_|
+        self.apply_checked(board_allocation, move |state, (initial_state_bindings, board_bindings)|
         {
-            let video_frame_buffer = video_frame_buffer_rc_clone.lock().unwrap();
+            state.reset_click_rule(&click_rule_data);
+            state.static_bindings.set_spawn_period(spawn_period);
+            state.static_bindings.set_smooth_transform_enabled(smooth_transform_enabled);
 
-            let video_frame_data = StafraBoardBindings::get_video_frame_buffer_mapped_data(&video_frame_buffer.image_buffer, video_frame_buffer.raw_width, video_frame_buffer.raw_height);
-            video_frame_buffer.image_buffer.unmap();
+            if let Some(initial_state_bindings) = initial_state_bindings
+            {
+                state.initial_state_bindings = initial_state_bindings;
+            }
 
-            callback(video_frame_data.pixel_data, video_frame_data.image_width, video_frame_data.image_height);
-        });
+            state.initial_image = initial_image;
+
+            state.clear_expression_pipeline = board_expression.as_ref().map(|board_expression| state.static_state.create_clear_expression_pipeline(&state.device, board_expression));
+            state.board_expression          = board_expression;
+
+            state.board_bindings = board_bindings;
+
+            //Keep the restriction to re-apply it after resizing the board
+            match restriction_image
+            {
+                Some(restriction_image) => state.upload_restriction_impl(restriction_image.pixel_data, restriction_image.image_width, restriction_image.image_height),
+                None                    => state.initial_restriction_tex = None
+            }
+
+            state.clear_board_strokes();
+
+            state.last_reset_type = last_reset_type;
+            state.frame_number    = frame_number;
+            state.board_strokes   = board_strokes;
+
+            state.static_bindings.update_draw_state(&state.queue);
+
+            state.restriction_version += 1;
+            state.restart_lights_out();
+
+            let mut encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Load session encoder")});
+            state.generate_final_image_impl(&mut encoder, state.frame_number);
+            state.restart_history(&mut encoder);
+            state.queue.submit(std::iter::once(encoder.finish()));
+        })
+    }
+
+    fn post_video_frame_request(&mut self, callback: ImageCallback)
+    {
+        self.run_after_allocations(move |state| state.post_video_frame_request_impl(callback));
     }
 
-    pub fn set_click_rule_grid_enabled(&mut self, enable: bool)
+    fn set_click_rule_grid_enabled(&mut self, enable: bool)
     {
         self.static_bindings.set_click_rule_grid_enabled(enable);
     }
 
-    pub fn set_click_rule_read_only(&mut self, is_read_only: bool)
+    fn set_click_rule_read_only(&mut self, is_read_only: bool)
     {
         self.static_bindings.set_click_rule_read_only(is_read_only);
     }
 
-    pub fn reset_board_unchanged(&mut self)
+    fn reset_board_unchanged(&mut self)
     {
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Reset board unchanged encoder")});
         self.reset_board_unchanged_impl(&mut encoder);
         self.queue.submit(std::iter::once(encoder.finish()));
     }
 
-    fn reset_board_unchanged_impl(&mut self, encoder: &mut wgpu::CommandEncoder)
+    fn reset_board_standard(&mut self, reset_type: StandardResetBoardType)
     {
-        match self.last_reset_type
-        {
-            ResetBoardType::Standard {reset_type} =>
-            {
-                self.reset_board_standard_impl(encoder, reset_type);
-            }
+        self.clear_board_strokes();
 
-            ResetBoardType::Custom =>
-            {
-                self.reset_board_custom_impl(encoder);
-            }
-        }
-    }
-
-    pub fn reset_board_standard(&mut self, reset_type: StandardResetBoardType)
-    {
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Reset board standard encoder")});
         self.reset_board_standard_impl(&mut encoder, reset_type);
         self.queue.submit(std::iter::once(encoder.finish()));
     }
 
-    fn reset_board_standard_impl(&mut self, encoder: &mut wgpu::CommandEncoder, reset_type: StandardResetBoardType)
+    fn reset_board_random(&mut self, params: RandomBoardParams)
     {
-        match reset_type
-        {
-            StandardResetBoardType::Corners => {self.board_bindings.reset_board_standard_corners(encoder, &self.static_state);}
-            StandardResetBoardType::Edges   => {self.board_bindings.reset_board_standard_edges(encoder, &self.static_state);}
-            StandardResetBoardType::Center  => {self.board_bindings.reset_board_standard_center(encoder, &self.static_state);}
-        }
+        self.clear_board_strokes();
 
-        self.board_bindings.filter_restriction(encoder, &self.static_state);
-        self.board_bindings.clear_stability(encoder, &self.static_state);
-        self.board_bindings.generate_final_image(encoder, &self.static_state, 0);
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Reset board random encoder")});
+        self.reset_board_random_impl(&mut encoder, params);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
 
-        self.last_reset_type = ResetBoardType::Standard {reset_type};
-        self.frame_number = 0;
+    fn reset_board_pattern(&mut self, params: PatternBoardParams)
+    {
+        self.clear_board_strokes();
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Reset board pattern encoder")});
+        self.reset_board_pattern_impl(&mut encoder, params);
+        self.queue.submit(std::iter::once(encoder.finish()));
     }
 
-    pub fn reset_board_custom(&mut self, image_array: Vec<u8>, width: u32, height: u32) -> u32
+    fn reset_board_expression(&mut self, expression_source: &str, seed: u32) -> Result<(), String>
     {
-        //Crop to the largest possible square with sides of 2^n - 1
-        let cropped_size = (min(width, height) + 2).next_power_of_two() / 2 - 1;
-        self.initial_state_bindings = StafraInitialStateBindings::new(&self.device, cropped_size, cropped_size);
-        self.board_bindings = StafraBoardBindings::new(&self.device, &self.static_state, &self.static_bindings, &self.initial_state_bindings, cropped_size, cropped_size);
+        let board_expression = BoardExpression::parse(expression_source, seed)?;
 
-        self.initial_state_bindings.upload_texture(&self.queue, image_array, width, height);
+        self.clear_expression_pipeline = Some(self.static_state.create_clear_expression_pipeline(&self.device, &board_expression));
+        self.board_expression          = Some(board_expression);
 
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Reset board custom encoder")});
-        self.reset_board_custom_impl(&mut encoder);
+        self.clear_board_strokes();
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Reset board expression encoder")});
+        self.reset_board_expression_impl(&mut encoder);
         self.queue.submit(std::iter::once(encoder.finish()));
 
-        cropped_size
+        Ok(())
     }
 
-    pub fn reset_board_custom_impl(&mut self, encoder: &mut wgpu::CommandEncoder)
+    fn reset_board_custom(&mut self, image_array: Vec<u8>, width: u32, height: u32) -> Result<u32, String>
     {
-        self.board_bindings.reset_board_custom(encoder, &self.static_state);
+        let image_bytes = (width as usize).checked_mul(height as usize).and_then(|pixel_count| pixel_count.checked_mul(4));
+        if width == 0 || height == 0 || image_bytes.is_none_or(|image_bytes| image_array.len() < image_bytes)
+        {
+            return Err(format!("Invalid initial state image of size {}x{}", width, height));
+        }
 
-        self.board_bindings.filter_restriction(encoder, &self.static_state);
-        self.board_bindings.clear_stability(encoder, &self.static_state);
-        self.board_bindings.generate_final_image(encoder, &self.static_state, 0);
+        //The largest possible square with sides of 2^n - 1 that fits into the largest tiled board
+        let cropped_size = import_board_size(width, height, &self.image_import_options, self.max_board_size());
 
-        self.last_reset_type = ResetBoardType::Custom;
-        self.frame_number = 0;
+        //The image is uploaded to the new textures, which replace the old ones only if the allocation succeeds
+        let initial_image = ImageData
+        {
+            pixel_data:   import_image(&image_array, width, height, cropped_size, false, &self.image_import_options),
+            image_width:  cropped_size,
+            image_height: cropped_size
+        };
+
+        let board_allocation = self.allocate_checked(&format!("the board of size {}x{}", cropped_size, cropped_size), |state|
+        {
+            let initial_state_bindings = state.create_initial_state_bindings(&initial_image);
+            let board_bindings         = StafraBoardBindings::new(&state.device, &state.static_state, &state.static_bindings, &initial_state_bindings, cropped_size, cropped_size);
+            (initial_state_bindings, board_bindings)
+        });
+
+        self.apply_checked(board_allocation, move |state, (initial_state_bindings, board_bindings)|
+        {
+            state.initial_state_bindings = initial_state_bindings;
+            state.board_bindings         = board_bindings;
+
+            state.initial_image = Some(initial_image);
+            state.clear_board_strokes();
+
+            let mut encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Reset board custom encoder")});
+            state.reset_board_custom_impl(&mut encoder);
+            state.queue.submit(std::iter::once(encoder.finish()));
+        })?;
+
+        Ok(cropped_size)
     }
 
-    pub fn upload_restriction(&mut self, image_array: Vec<u8>, width: u32, height: u32)
+    fn upload_restriction(&mut self, image_array: Vec<u8>, width: u32, height: u32)
     {
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Upload restriction encoder")});
 
-        self.upload_restriction_impl(image_array, width, height);
+        //Everything outside the image is unrestricted
+        let board_size  = self.board_bindings.board_width();
+        let image_array = import_image(&image_array, width, height, board_size, true, &self.image_import_options);
+        self.upload_restriction_impl(image_array, board_size, board_size);
 
         let initial_restriction_view = self.initial_restriction_tex.as_ref().unwrap().create_view(&wgpu::TextureViewDescriptor::default());
         self.board_bindings.initial_transform_restriction(&self.device, &mut encoder, &initial_restriction_view, &self.static_state);
@@ -345,7 +1821,7 @@ impl StafraState
         self.queue.submit(std::iter::once(encoder.finish()));
     }
 
-    pub fn clear_restriction(&mut self)
+    fn clear_restriction(&mut self)
     {
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Clear restriction encoder")});
 
@@ -355,76 +1831,200 @@ impl StafraState
         self.queue.submit(std::iter::once(encoder.finish()));
     }
 
-    pub fn resize_board(&mut self, new_width: u32, new_height: u32)
+    fn paint(&mut self, stroke: PaintStroke)
     {
-        let cropped_size = (min(new_width, new_height) + 2).next_power_of_two() / 2 - 1;
-        self.board_bindings = StafraBoardBindings::new(&self.device, &self.static_state, &self.static_bindings, &self.initial_state_bindings, cropped_size, cropped_size);
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Paint encoder")});
 
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Resize board encoder")});
+        match stroke.target
+        {
+            PaintTarget::Board =>
+            {
+                //Flood fill is queued by the replay, and the strokes after an unfinished fill wait for it
+                self.board_strokes.push(stroke);
+                if self.frame_number == 0 && self.first_unfilled_stroke().is_none()
+                {
+                    //The pre-filter board of the frame 0 is still in place, only the new stroke needs to be drawn
+                    self.board_bindings.paint_stroke(&self.device, &mut encoder, &self.static_state, &stroke);
+                    self.board_bindings.filter_restriction(&mut encoder, &self.static_state);
+                    self.board_bindings.clear_stability(&mut encoder, &self.static_state);
+                    self.generate_final_image_impl(&mut encoder, 0);
+                    self.restart_lights_out();
+                    self.restart_history(&mut encoder);
+                }
+                else
+                {
+                    self.reset_board_unchanged_impl(&mut encoder);
+                }
+            }
+
+            PaintTarget::Restriction if stroke.tool == PaintTool::Fill =>
+            {
+                self.queue_fill(stroke, None);
+            }
+
+            PaintTarget::Restriction =>
+            {
+                self.board_bindings.paint_stroke(&self.device, &mut encoder, &self.static_state, &stroke);
+                self.reset_board_unchanged_impl(&mut encoder);
+            }
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    fn set_lights_out_enabled(&mut self, enable: bool)
+    {
+        self.lights_out_enabled = enable;
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Lights Out mode encoder")});
+        self.generate_final_image_impl(&mut encoder, self.frame_number.saturating_sub(1));
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    fn lights_out_click(&mut self, x: u32, y: u32) -> bool
+    {
+        self.restart_stepped_lights_out();
 
-        if let Some(_) = &self.initial_restriction_tex
+        let board_size = self.board_bindings.board_width();
+        if !self.lights_out_enabled || x >= board_size || y >= board_size || self.lights_out_cell_restricted(x, y)
         {
-            let initial_restriction_view = self.initial_restriction_tex.as_ref().unwrap().create_view(&wgpu::TextureViewDescriptor::default());
-            self.board_bindings.initial_transform_restriction(&self.device, &mut encoder, &initial_restriction_view, &self.static_state);
+            return false;
         }
-        else
+
+        let lights_out_move = LightsOutMove{x, y};
+        self.lights_out_toggle(lights_out_move);
+        self.lights_out_moves.push(lights_out_move);
+        true
+    }
+
+    fn lights_out_undo(&mut self) -> bool
+    {
+        self.restart_stepped_lights_out();
+
+        match self.lights_out_moves.pop()
         {
-            self.board_bindings.clear_restriction(&mut encoder, &self.static_state);
+            Some(lights_out_move) =>
+            {
+                self.lights_out_toggle(lights_out_move);
+                true
+            }
+
+            None => false
         }
+    }
 
-        self.reset_board_unchanged_impl(&mut encoder);
+    fn lights_out_status(&mut self) -> LightsOutStatus
+    {
+        self.restart_stepped_lights_out();
 
-        self.queue.submit(std::iter::once(encoder.finish()));
+        if self.lights_out_enabled
+        {
+            self.request_lights_out_readbacks();
+        }
+
+        let lit_cells = match *self.lights_out_lit_cells.lock().unwrap()
+        {
+            Some((version, lit_cells)) if version == self.board_version => Some(lit_cells),
+            _                                                           => None
+        };
+
+        LightsOutStatus
+        {
+            move_count: self.lights_out_moves.len() as u32,
+            lit_cells
+        }
+    }
+
+    fn resize_board(&mut self, new_width: u32, new_height: u32) -> Result<(), String>
+    {
+        let cropped_size = (min(new_width, new_height) + 2).next_power_of_two() / 2 - 1;
+        if cropped_size > self.max_board_size()
+        {
+            return Err(format!("Board size {}x{} is not supported, the largest supported size is {}x{}", cropped_size, cropped_size, self.max_board_size(), self.max_board_size()));
+        }
+
+        let board_allocation = self.allocate_checked(&format!("the board of size {}x{}", cropped_size, cropped_size), |state|
+        {
+            StafraBoardBindings::new(&state.device, &state.static_state, &state.static_bindings, &state.initial_state_bindings, cropped_size, cropped_size)
+        });
+
+        self.apply_checked(board_allocation, |state, board_bindings| state.apply_resized_board(board_bindings))
     }
 
-    pub fn reset_click_rule(&mut self, click_rule_data: &[u8; 32 * 32])
+    fn reset_click_rule(&mut self, click_rule_data: &[u8; 32 * 32])
     {
+        self.click_rule_data = *click_rule_data;
+
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Reset click rule encoder")});
         self.static_bindings.reset_click_rule(&self.queue, &mut encoder, &self.static_state, click_rule_data);
         self.queue.submit(std::iter::once(encoder.finish()));
+
+        //The moves made with the old click rule can't be undone with the new one
+        self.restart_lights_out();
     }
 
-    pub fn set_spawn_period(&mut self, spawn_period: u32)
+    fn set_spawn_period(&mut self, spawn_period: u32)
     {
         self.static_bindings.set_spawn_period(spawn_period);
     }
 
-    pub fn set_smooth_transform_enabled(&mut self, enable: bool)
+    fn set_smooth_transform_enabled(&mut self, enable: bool)
     {
         self.static_bindings.set_smooth_transform_enabled(enable);
     }
 
-    pub fn update(&mut self)
+    fn set_image_import_options(&mut self, options: ImageImportOptions)
+    {
+        self.image_import_options = options;
+    }
+
+    fn update(&mut self)
     {
+        self.run_pending_operations();
+
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Update encoder")});
 
-        self.board_bindings.calc_next_frame(&mut encoder, &self.static_state, self.frame_number);
-        self.board_bindings.generate_final_image(&mut encoder, &self.static_state, self.frame_number);
+        self.board_bindings.calc_next_frame(&mut encoder, &self.static_state, &mut self.profiler, self.frame_number);
 
         self.frame_number += 1;
+        self.generate_final_image_impl(&mut encoder, self.frame_number - 1);
+        self.board_stepped = true;
+        self.record_history(&mut encoder);
 
         self.queue.submit(std::iter::once(encoder.finish()));
     }
 
-    pub fn update_visual_info(&mut self)
+    fn update_visual_info(&mut self)
     {
         self.static_bindings.update_draw_state(&self.queue);
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError>
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>
     {
-        let main_frame       = self.main_surface.get_current_texture()?;
-        let click_rule_frame = self.click_rule_surface.get_current_texture()?;
+        self.run_pending_operations();
+
+        let (main_surface, click_rule_surface) = match (&self.main_surface, &self.click_rule_surface)
+        {
+            (Some(main_surface), Some(click_rule_surface)) => (main_surface, click_rule_surface),
+            _                                              => return Ok(())
+        };
+
+        let main_frame       = main_surface.get_current_texture()?;
+        let click_rule_frame = click_rule_surface.get_current_texture()?;
 
         let main_frame_view       = main_frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
         let click_rule_frame_view = click_rule_frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Render encoder")});
 
-        self.board_bindings.draw_main_state(&mut encoder, &main_frame_view, &self.static_state);
+        self.board_bindings.draw_main_state(&mut encoder, &main_frame_view, self.main_surface_width, self.main_surface_height, &self.static_state, &mut self.profiler);
         self.static_bindings.draw_click_rule(&mut encoder, &click_rule_frame_view, &self.static_state);
 
+        //All passes since the last render are resolved together
+        self.profiler.resolve(&mut encoder);
+
         self.queue.submit(std::iter::once(encoder.finish()));
+        self.profiler.finish_frame();
 
         main_frame.present();
         click_rule_frame.present();
@@ -432,46 +2032,188 @@ impl StafraState
         Ok(())
     }
 
-    fn upload_restriction_impl(&mut self, image_array: Vec<u8>, width: u32, height: u32)
+    fn set_history_depth(&mut self, history_frames: u32, keyframe_period: u32)
     {
-        let restriction_texture_descriptor = wgpu::TextureDescriptor
+        self.history.set_depth(history_frames, keyframe_period, self.board_bindings.snapshot_bytes());
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Restart history encoder")});
+        self.record_history(&mut encoder);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    fn step_back(&mut self) -> Result<(), String>
+    {
+        if self.frame_number == 0
         {
-            label: Some("Restriction texture"),
-            size:  wgpu::Extent3d
+            return Err("Cannot step back from the first frame".to_string());
+        }
+
+        self.seek_frame(self.frame_number - 1)
+    }
+
+    fn seek_frame(&mut self, frame_number: u32) -> Result<(), String>
+    {
+        while self.frame_number < frame_number
+        {
+            self.update();
+        }
+
+        if self.frame_number == frame_number
+        {
+            return Ok(());
+        }
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("Seek frame encoder")});
+
+        let snapshot_frame = match self.history.nearest_snapshot(frame_number)
+        {
+            Some((snapshot_frame, snapshot)) =>
             {
-                width,
-                height,
-                depth_or_array_layers: 1
-            },
-            mip_level_count: 1,
-            sample_count:    1,
-            dimension:       wgpu::TextureDimension::D2,
-            format:          wgpu::TextureFormat::Rgba8Unorm,
-            usage:           wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+                self.board_bindings.restore_snapshot(&mut encoder, snapshot);
+                snapshot_frame
+            }
+
+            None =>
+            {
+                let first_frame = self.history.first_frame().unwrap_or(self.frame_number);
+                return Err(format!("Frame {} is not in the history, the earliest frame is {}", frame_number, first_frame));
+            }
         };
 
-        let restriction_tex = self.device.create_texture(&restriction_texture_descriptor);
-        self.queue.write_texture(wgpu::ImageCopyTexture
+        //Replay the frames after the snapshot, recording them again
+        self.history.truncate_after(snapshot_frame);
+        self.frame_number = snapshot_frame;
+        while self.frame_number < frame_number
         {
-            texture:   &restriction_tex,
-            mip_level: 0,
-            origin:    wgpu::Origin3d::ZERO,
-            aspect:    wgpu::TextureAspect::All
-        },
-        image_array.as_slice(),
-        wgpu::ImageDataLayout
+            self.board_bindings.calc_next_frame(&mut encoder, &self.static_state, &mut self.profiler, self.frame_number);
+            self.frame_number += 1;
+            self.record_history(&mut encoder);
+        }
+
+        self.generate_final_image_impl(&mut encoder, self.frame_number.saturating_sub(1));
+        self.restart_lights_out();
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        Ok(())
+    }
+
+    fn timeline_range(&self) -> (u32, u32)
+    {
+        self.history.timeline_range(self.frame_number)
+    }
+
+    fn wait_for_completion(&self)
+    {
+        self.device.poll(wgpu::Maintain::Wait);
+    }
+
+    fn set_profiling_enabled(&mut self, enable: bool)
+    {
+        self.profiler.set_enabled(enable);
+    }
+
+    fn profiler_report(&self) -> ProfilerReport
+    {
+        self.profiler.report()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::stafra_cpu_state::StafraCpuState;
+
+    const SPIRAL_BOARD_SIZE: u32 = 127;
+
+    //The walls of a square spiral with a corridor one cell wide between them, winding from (1, 1) to the center
+    fn spiral_strokes(board_size: u32) -> Vec<PaintStroke>
+    {
+        let mut corners = vec![(0, 0)];
+        let (mut low, mut high) = (0, board_size as i32 - 1);
+        while low < high
         {
-            offset:         0,
-            bytes_per_row:  NonZeroU32::new(width * 4),
-            rows_per_image: NonZeroU32::new(height)
-        },
-        wgpu::Extent3d
+            corners.extend_from_slice(&[(high, low), (high, high), (low, high), (low, low + 2)]);
+            low  += 2;
+            high -= 2;
+        }
+
+        corners.windows(2).map(|segment| PaintStroke
         {
-            width,
-            height,
-            depth_or_array_layers: 1
+            tool:    PaintTool::Line,
+            target:  PaintTarget::Board,
+            start_x: segment[0].0,
+            start_y: segment[0].1,
+            end_x:   segment[1].0,
+            end_y:   segment[1].1,
+            radius:  0,
+            filled:  false,
+            enabled: true
+        }).collect()
+    }
+
+    //Paints the spiral on an empty board and fills its corridor
+    fn paint_filled_spiral(state: &mut dyn SimulationBackend)
+    {
+        let empty_image = vec![0u8; (SPIRAL_BOARD_SIZE * SPIRAL_BOARD_SIZE * 4) as usize];
+        state.reset_board_custom(empty_image, SPIRAL_BOARD_SIZE, SPIRAL_BOARD_SIZE).unwrap();
+
+        for stroke in spiral_strokes(SPIRAL_BOARD_SIZE)
+        {
+            state.paint(stroke);
+        }
+
+        state.paint(PaintStroke
+        {
+            tool:    PaintTool::Fill,
+            target:  PaintTarget::Board,
+            start_x: 1,
+            start_y: 1,
+            end_x:   1,
+            end_y:   1,
+            radius:  0,
+            filled:  false,
+            enabled: true
         });
+    }
 
-        self.initial_restriction_tex = Some(restriction_tex);
+    fn read_board(state: &mut dyn SimulationBackend) -> Vec<u8>
+    {
+        let (session_sender, session_receiver) = std::sync::mpsc::channel();
+        state.post_save_session_request(Box::new(move |session_result|
+        {
+            session_sender.send(session_result).unwrap();
+        }));
+
+        state.wait_for_completion();
+        session_receiver.recv().unwrap().unwrap().board
+    }
+
+    #[test]
+    fn gpu_fill_matches_cpu_fill()
+    {
+        let mut cpu_state = StafraCpuState::new(SPIRAL_BOARD_SIZE, SPIRAL_BOARD_SIZE);
+        paint_filled_spiral(&mut cpu_state);
+
+        let cpu_board = read_board(&mut cpu_state);
+        assert!(cpu_board.iter().all(|cell| *cell != 0), "The corridor of the spiral is not filled completely");
+
+        //Nothing to compare with on the machines without a GPU
+        let mut gpu_state = match futures::executor::block_on(StafraState::new_headless(SPIRAL_BOARD_SIZE, SPIRAL_BOARD_SIZE))
+        {
+            Ok(gpu_state) => gpu_state,
+            Err(_)        => return
+        };
+
+        paint_filled_spiral(&mut gpu_state);
+
+        //The fill waits for the readback of the board and is applied by the pending operations
+        while !gpu_state.pending_operations.is_empty()
+        {
+            gpu_state.wait_for_completion();
+            gpu_state.run_pending_operations();
+        }
+
+        assert!(read_board(&mut gpu_state) == cpu_board);
     }
 }
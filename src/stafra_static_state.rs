@@ -1,4 +1,16 @@
 use std::num::NonZeroU64;
+use std::borrow::Cow;
+use super::stafra_expression::BoardExpression;
+
+//The textures read and written by one simulation step
+pub struct NextStepViews<'a>
+{
+    pub current_board:     &'a wgpu::TextureView,
+    pub current_stability: &'a wgpu::TextureView,
+    pub next_board:        &'a wgpu::TextureView,
+    pub next_stability:    &'a wgpu::TextureView,
+    pub restriction:       &'a wgpu::TextureView
+}
 
 //Binding layout and pipeline library for the main stafra state. Contains all meta-state that needs to be initialized only once
 pub struct StafraStaticState
@@ -6,6 +18,8 @@ pub struct StafraStaticState
     main_render_bind_group_layout:                   wgpu::BindGroupLayout,
     click_rule_render_bind_group_layout:             wgpu::BindGroupLayout,
     clear_default_bind_group_layout:                 wgpu::BindGroupLayout,
+    clear_random_bind_group_layout:                  wgpu::BindGroupLayout,
+    clear_pattern_bind_group_layout:                 wgpu::BindGroupLayout,
     clear_stability_bind_group_layout:               wgpu::BindGroupLayout,
     clear_restriction_bind_group_layout:             wgpu::BindGroupLayout,
     initial_state_transform_bind_group_layout:       wgpu::BindGroupLayout,
@@ -15,12 +29,20 @@ pub struct StafraStaticState
     bake_click_rule_bind_group_layout:               wgpu::BindGroupLayout,
     final_state_transform_bind_group_layout:         wgpu::BindGroupLayout,
     generate_mip_bind_group_layout:                  wgpu::BindGroupLayout,
+    tile_info_bind_group_layout:                     wgpu::BindGroupLayout,
+    paint_stroke_bind_group_layout:                  wgpu::BindGroupLayout,
+    paint_stroke_data_bind_group_layout:             wgpu::BindGroupLayout,
+    board_image_transform_bind_group_layout:         wgpu::BindGroupLayout,
+    lights_out_click_bind_group_layout:              wgpu::BindGroupLayout,
+    lights_out_click_data_bind_group_layout:         wgpu::BindGroupLayout,
 
     main_render_pipeline:                   wgpu::RenderPipeline,
     click_rule_render_pipeline:             wgpu::RenderPipeline,
     clear_4_corners_pipeline:               wgpu::ComputePipeline,
     clear_4_sides_pipeline:                 wgpu::ComputePipeline,
     clear_center_pipeline:                  wgpu::ComputePipeline,
+    clear_random_pipeline:                  wgpu::ComputePipeline,
+    clear_pattern_pipeline:                 wgpu::ComputePipeline,
     clear_stability_pipeline:               wgpu::ComputePipeline,
     clear_restriction_pipeline:             wgpu::ComputePipeline,
     initial_state_transform_pipeline:       wgpu::ComputePipeline,
@@ -30,6 +52,9 @@ pub struct StafraStaticState
     bake_click_rule_pipeline:               wgpu::ComputePipeline,
     final_state_transform_pipeline:         wgpu::ComputePipeline,
     generate_mip_pipeline:                  wgpu::ComputePipeline,
+    paint_stroke_pipeline:                  wgpu::ComputePipeline,
+    board_image_transform_pipeline:         wgpu::ComputePipeline,
+    lights_out_click_pipeline:              wgpu::ComputePipeline,
 
     #[allow(dead_code)]
     render_state_sampler: wgpu::Sampler
@@ -43,6 +68,8 @@ impl StafraStaticState
         let click_rule_render_bind_group_layout = create_click_rule_render_bind_group_layout(device);
 
         let clear_default_bind_group_layout     = create_clear_default_bind_group_layout(device);
+        let clear_random_bind_group_layout      = create_clear_random_bind_group_layout(device);
+        let clear_pattern_bind_group_layout     = create_clear_pattern_bind_group_layout(device);
         let clear_stability_bind_group_layout   = create_clear_stability_bind_group_layout(device);
         let clear_restriction_bind_group_layout = create_clear_restriction_bind_group_layout(device);
 
@@ -56,29 +83,44 @@ impl StafraStaticState
         let final_state_transform_bind_group_layout = create_final_state_transform_bind_group_layout(device);
         let generate_mip_bind_group_layout          = create_generate_mip_bind_group_layout(device);
 
+        let tile_info_bind_group_layout = create_tile_info_bind_group_layout(device);
+
+        let paint_stroke_bind_group_layout      = create_paint_stroke_bind_group_layout(device);
+        let paint_stroke_data_bind_group_layout = create_paint_stroke_data_bind_group_layout(device);
 
-        let clear_default_pipeline_layout = create_clear_default_pipeline_layout(device, &clear_default_bind_group_layout);
+        let board_image_transform_bind_group_layout = create_board_image_transform_bind_group_layout(device);
+        let lights_out_click_bind_group_layout      = create_lights_out_click_bind_group_layout(device);
+        let lights_out_click_data_bind_group_layout = create_lights_out_click_data_bind_group_layout(device);
 
-        let main_render_pipeline                   = create_main_render_pipeline(device, &main_render_bind_group_layout, swapchain_format);
+        let clear_default_pipeline_layout = create_clear_default_pipeline_layout(device, &clear_default_bind_group_layout, &tile_info_bind_group_layout);
+
+        let main_render_pipeline                   = create_main_render_pipeline(device, &main_render_bind_group_layout, &tile_info_bind_group_layout, swapchain_format);
         let click_rule_render_pipeline             = create_click_rule_render_pipeline(device, &click_rule_render_bind_group_layout, swapchain_format);
         let clear_4_corners_pipeline               = create_clear_4_corners_pipeline(device, &clear_default_pipeline_layout);
         let clear_4_sides_pipeline                 = create_clear_4_sides_pipeline(device, &clear_default_pipeline_layout);
         let clear_center_pipeline                  = create_clear_center_pipeline(device, &clear_default_pipeline_layout);
-        let clear_stability_pipeline               = create_clear_stability_pipeline(device, &clear_stability_bind_group_layout);
-        let clear_restriction_pipeline             = create_clear_restriction_pipeline(device, &clear_restriction_bind_group_layout);
-        let initial_state_transform_pipeline       = create_initial_state_transform_pipeline(device, &initial_state_transform_bind_group_layout);
-        let initial_restriction_transform_pipeline = create_initial_restriction_transform_pipeline(device, &initial_restriction_transform_bind_group_layout);
+        let clear_random_pipeline                  = create_clear_random_pipeline(device, &clear_default_bind_group_layout, &tile_info_bind_group_layout, &clear_random_bind_group_layout);
+        let clear_pattern_pipeline                 = create_clear_pattern_pipeline(device, &clear_default_bind_group_layout, &tile_info_bind_group_layout, &clear_pattern_bind_group_layout);
+        let clear_stability_pipeline               = create_clear_stability_pipeline(device, &clear_stability_bind_group_layout, &tile_info_bind_group_layout);
+        let clear_restriction_pipeline             = create_clear_restriction_pipeline(device, &clear_restriction_bind_group_layout, &tile_info_bind_group_layout);
+        let initial_state_transform_pipeline       = create_initial_state_transform_pipeline(device, &initial_state_transform_bind_group_layout, &tile_info_bind_group_layout);
+        let initial_restriction_transform_pipeline = create_initial_restriction_transform_pipeline(device, &initial_restriction_transform_bind_group_layout, &tile_info_bind_group_layout);
         let filter_restriction_pipeline            = create_filter_restriction_pipeline(device, &filter_restriction_bind_group_layout);
-        let next_step_pipeline                     = create_next_step_pipeline(device, &next_step_bind_group_layout);
+        let next_step_pipeline                     = create_next_step_pipeline(device, &next_step_bind_group_layout, &tile_info_bind_group_layout);
         let bake_click_rule_pipeline               = create_bake_click_rule_pipeline(device, &bake_click_rule_bind_group_layout);
         let final_state_transform_pipeline         = create_final_state_transform_pipeline(device, &final_state_transform_bind_group_layout);
         let generate_mip_pipeline                  = create_generate_mip_pipeline(device, &generate_mip_bind_group_layout);
+        let paint_stroke_pipeline                  = create_paint_stroke_pipeline(device, &paint_stroke_bind_group_layout, &tile_info_bind_group_layout, &paint_stroke_data_bind_group_layout);
+        let board_image_transform_pipeline         = create_board_image_transform_pipeline(device, &board_image_transform_bind_group_layout);
+        let lights_out_click_pipeline              = create_lights_out_click_pipeline(device, &lights_out_click_bind_group_layout, &tile_info_bind_group_layout, &lights_out_click_data_bind_group_layout);
 
         Self
         {
             main_render_bind_group_layout,
             click_rule_render_bind_group_layout,
             clear_default_bind_group_layout,
+            clear_random_bind_group_layout,
+            clear_pattern_bind_group_layout,
             clear_stability_bind_group_layout,
             clear_restriction_bind_group_layout,
             initial_state_transform_bind_group_layout,
@@ -88,12 +130,20 @@ impl StafraStaticState
             bake_click_rule_bind_group_layout,
             final_state_transform_bind_group_layout,
             generate_mip_bind_group_layout,
+            tile_info_bind_group_layout,
+            paint_stroke_bind_group_layout,
+            paint_stroke_data_bind_group_layout,
+            board_image_transform_bind_group_layout,
+            lights_out_click_bind_group_layout,
+            lights_out_click_data_bind_group_layout,
 
             main_render_pipeline,
             click_rule_render_pipeline,
             clear_4_corners_pipeline,
             clear_4_sides_pipeline,
             clear_center_pipeline,
+            clear_random_pipeline,
+            clear_pattern_pipeline,
             clear_stability_pipeline,
             clear_restriction_pipeline,
             initial_state_transform_pipeline,
@@ -103,6 +153,9 @@ impl StafraStaticState
             bake_click_rule_pipeline,
             final_state_transform_pipeline,
             generate_mip_pipeline,
+            paint_stroke_pipeline,
+            board_image_transform_pipeline,
+            lights_out_click_pipeline,
 
             render_state_sampler: create_render_state_sampler(device)
         }
@@ -171,6 +224,40 @@ impl StafraStaticState
         })
     }
 
+    pub fn create_clear_random_bind_group(&self, device: &wgpu::Device, random_board_buffer: &wgpu::Buffer) -> wgpu::BindGroup
+    {
+        device.create_bind_group(&wgpu::BindGroupDescriptor
+        {
+            label: Some("Clear random bind group"),
+            layout: &self.clear_random_bind_group_layout,
+            entries:
+            &[
+                wgpu::BindGroupEntry
+                {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(random_board_buffer.as_entire_buffer_binding())
+                }
+            ]
+        })
+    }
+
+    pub fn create_clear_pattern_bind_group(&self, device: &wgpu::Device, pattern_board_buffer: &wgpu::Buffer) -> wgpu::BindGroup
+    {
+        device.create_bind_group(&wgpu::BindGroupDescriptor
+        {
+            label: Some("Clear pattern bind group"),
+            layout: &self.clear_pattern_bind_group_layout,
+            entries:
+            &[
+                wgpu::BindGroupEntry
+                {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(pattern_board_buffer.as_entire_buffer_binding())
+                }
+            ]
+        })
+    }
+
     pub fn create_clear_stability_bind_group(&self, device: &wgpu::Device, stability_view: &wgpu::TextureView) -> wgpu::BindGroup
     {
         device.create_bind_group(&wgpu::BindGroupDescriptor
@@ -280,7 +367,7 @@ impl StafraStaticState
         })
     }
 
-    pub fn create_next_step_bind_group(&self, device: &wgpu::Device, current_board_view: &wgpu::TextureView, current_stability_view: &wgpu::TextureView, next_board_view: &wgpu::TextureView, next_stability_view: &wgpu::TextureView, restriction_view: &wgpu::TextureView, click_rule_buffer_binding: wgpu::BufferBinding) -> wgpu::BindGroup
+    pub fn create_next_step_bind_group(&self, device: &wgpu::Device, views: NextStepViews, click_rule_buffer_binding: wgpu::BufferBinding) -> wgpu::BindGroup
     {
         device.create_bind_group(&wgpu::BindGroupDescriptor
         {
@@ -291,31 +378,31 @@ impl StafraStaticState
                 wgpu::BindGroupEntry
                 {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&current_board_view),
+                    resource: wgpu::BindingResource::TextureView(views.current_board),
                 },
 
                 wgpu::BindGroupEntry
                 {
                     binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&current_stability_view),
+                    resource: wgpu::BindingResource::TextureView(views.current_stability),
                 },
 
                 wgpu::BindGroupEntry
                 {
                     binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&next_board_view),
+                    resource: wgpu::BindingResource::TextureView(views.next_board),
                 },
 
                 wgpu::BindGroupEntry
                 {
                     binding: 3,
-                    resource: wgpu::BindingResource::TextureView(&next_stability_view),
+                    resource: wgpu::BindingResource::TextureView(views.next_stability),
                 },
 
                 wgpu::BindGroupEntry
                 {
                     binding: 4,
-                    resource: wgpu::BindingResource::TextureView(&restriction_view),
+                    resource: wgpu::BindingResource::TextureView(views.restriction),
                 },
 
                 wgpu::BindGroupEntry
@@ -402,6 +489,144 @@ impl StafraStaticState
         })
     }
 
+    pub fn create_tile_info_bind_group(&self, device: &wgpu::Device, tile_info_buffer: &wgpu::Buffer) -> wgpu::BindGroup
+    {
+        device.create_bind_group(&wgpu::BindGroupDescriptor
+        {
+            label: Some("Tile info bind group"),
+            layout: &self.tile_info_bind_group_layout,
+            entries:
+            &[
+                wgpu::BindGroupEntry
+                {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(tile_info_buffer.as_entire_buffer_binding())
+                },
+            ]
+        })
+    }
+
+    pub fn create_paint_stroke_bind_group(&self, device: &wgpu::Device, in_board_view: &wgpu::TextureView, out_board_view: &wgpu::TextureView) -> wgpu::BindGroup
+    {
+        device.create_bind_group(&wgpu::BindGroupDescriptor
+        {
+            label: Some("Paint stroke bind group"),
+            layout: &self.paint_stroke_bind_group_layout,
+            entries:
+            &[
+                wgpu::BindGroupEntry
+                {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&in_board_view)
+                },
+
+                wgpu::BindGroupEntry
+                {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&out_board_view)
+                }
+            ]
+        })
+    }
+
+    pub fn create_paint_stroke_data_bind_group(&self, device: &wgpu::Device, paint_stroke_buffer: &wgpu::Buffer) -> wgpu::BindGroup
+    {
+        device.create_bind_group(&wgpu::BindGroupDescriptor
+        {
+            label: Some("Paint stroke data bind group"),
+            layout: &self.paint_stroke_data_bind_group_layout,
+            entries:
+            &[
+                wgpu::BindGroupEntry
+                {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(paint_stroke_buffer.as_entire_buffer_binding())
+                }
+            ]
+        })
+    }
+
+    pub fn create_board_image_transform_bind_group(&self, device: &wgpu::Device, board_view: &wgpu::TextureView, final_state_view: &wgpu::TextureView) -> wgpu::BindGroup
+    {
+        device.create_bind_group(&wgpu::BindGroupDescriptor
+        {
+            label: Some("Board image transform bind group"),
+            layout: &self.board_image_transform_bind_group_layout,
+            entries:
+            &[
+                wgpu::BindGroupEntry
+                {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&board_view),
+                },
+
+                wgpu::BindGroupEntry
+                {
+                    binding:  1,
+                    resource: wgpu::BindingResource::TextureView(&final_state_view),
+                }
+            ]
+        })
+    }
+
+    pub fn create_lights_out_click_bind_group(&self, device: &wgpu::Device, in_board_view: &wgpu::TextureView, restriction_view: &wgpu::TextureView, click_restriction_view: &wgpu::TextureView, out_board_view: &wgpu::TextureView, click_rule_buffer_binding: wgpu::BufferBinding) -> wgpu::BindGroup
+    {
+        device.create_bind_group(&wgpu::BindGroupDescriptor
+        {
+            label: Some("Lights Out click bind group"),
+            layout: &self.lights_out_click_bind_group_layout,
+            entries:
+            &[
+                wgpu::BindGroupEntry
+                {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&in_board_view)
+                },
+
+                wgpu::BindGroupEntry
+                {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&restriction_view)
+                },
+
+                wgpu::BindGroupEntry
+                {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&click_restriction_view)
+                },
+
+                wgpu::BindGroupEntry
+                {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&out_board_view)
+                },
+
+                wgpu::BindGroupEntry
+                {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Buffer(click_rule_buffer_binding)
+                }
+            ]
+        })
+    }
+
+    pub fn create_lights_out_click_data_bind_group(&self, device: &wgpu::Device, lights_out_click_buffer: &wgpu::Buffer) -> wgpu::BindGroup
+    {
+        device.create_bind_group(&wgpu::BindGroupDescriptor
+        {
+            label: Some("Lights Out click data bind group"),
+            layout: &self.lights_out_click_data_bind_group_layout,
+            entries:
+            &[
+                wgpu::BindGroupEntry
+                {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(lights_out_click_buffer.as_entire_buffer_binding())
+                }
+            ]
+        })
+    }
+
     pub fn create_main_draw_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, main_frame_view: &'a wgpu::TextureView) -> wgpu::RenderPass<'a>
     {
         let mut main_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor
@@ -489,6 +714,48 @@ impl StafraStaticState
         pass
     }
 
+    pub fn create_clear_random_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::ComputePass<'a>
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {label: Some("Clear random pass")});
+        pass.set_pipeline(&self.clear_random_pipeline);
+        pass
+    }
+
+    pub fn create_clear_pattern_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::ComputePass<'a>
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {label: Some("Clear pattern pass")});
+        pass.set_pipeline(&self.clear_pattern_pipeline);
+        pass
+    }
+
+    //The expression pipeline is created for each new board expression
+    pub fn create_clear_expression_pipeline(&self, device: &wgpu::Device, board_expression: &BoardExpression) -> wgpu::ComputePipeline
+    {
+        let clear_expression_source = include_str!("shaders/clear_board/clear_expression.wgsl").replace("BOARD_EXPRESSION_FUNCTION", &board_expression.to_wgsl_function());
+        let clear_expression_module = device.create_shader_module(wgpu::ShaderModuleDescriptor
+        {
+            label:  Some("Clear expression shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(clear_expression_source))
+        });
+
+        let clear_expression_pipeline_layout = create_clear_default_pipeline_layout(device, &self.clear_default_bind_group_layout, &self.tile_info_bind_group_layout);
+
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor
+        {
+            label:       Some("Clear expression pipeline"),
+            layout:      Some(&clear_expression_pipeline_layout),
+            module:      &clear_expression_module,
+            entry_point: "main"
+        })
+    }
+
+    pub fn create_clear_expression_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, clear_expression_pipeline: &'a wgpu::ComputePipeline) -> wgpu::ComputePass<'a>
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {label: Some("Clear expression pass")});
+        pass.set_pipeline(clear_expression_pipeline);
+        pass
+    }
+
     pub fn create_initial_transform_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::ComputePass<'a>
     {
         let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {label: Some("Initial transform pass")});
@@ -537,6 +804,27 @@ impl StafraStaticState
         pass.set_pipeline(&self.generate_mip_pipeline);
         pass
     }
+
+    pub fn create_paint_stroke_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::ComputePass<'a>
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {label: Some("Paint stroke pass")});
+        pass.set_pipeline(&self.paint_stroke_pipeline);
+        pass
+    }
+
+    pub fn create_generate_board_image_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::ComputePass<'a>
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {label: Some("Generate board image pass")});
+        pass.set_pipeline(&self.board_image_transform_pipeline);
+        pass
+    }
+
+    pub fn create_lights_out_click_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::ComputePass<'a>
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {label: Some("Lights Out click pass")});
+        pass.set_pipeline(&self.lights_out_click_pipeline);
+        pass
+    }
 }
 
 macro_rules! initial_texture_binding
@@ -648,6 +936,44 @@ macro_rules! board_image_binding
     }
 }
 
+macro_rules! board_texture_array_binding
+{
+    ($bd:literal) =>
+    {
+        wgpu::BindGroupLayoutEntry
+        {
+            binding:    $bd,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty:         wgpu::BindingType::Texture
+            {
+                sample_type:    wgpu::TextureSampleType::Uint,
+                view_dimension: wgpu::TextureViewDimension::D2Array,
+                multisampled:   false,
+            },
+            count: None
+        }
+    }
+}
+
+macro_rules! board_image_array_binding
+{
+    ($bd:literal) =>
+    {
+        wgpu::BindGroupLayoutEntry
+        {
+            binding:    $bd,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty:         wgpu::BindingType::StorageTexture
+            {
+                access:         wgpu::StorageTextureAccess::WriteOnly,
+                format:         wgpu::TextureFormat::R32Uint,
+                view_dimension: wgpu::TextureViewDimension::D2Array,
+            },
+            count: None
+        }
+    }
+}
+
 macro_rules! final_texture_mip_binding
 {
     ($bd:literal) =>
@@ -705,6 +1031,82 @@ macro_rules! spawn_data_uniform_binding
     }
 }
 
+macro_rules! random_board_uniform_binding
+{
+    ($bd:literal) =>
+    {
+        wgpu::BindGroupLayoutEntry
+        {
+            binding:    $bd,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty:         wgpu::BindingType::Buffer
+            {
+                ty:                 wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size:   NonZeroU64::new(8 * std::mem::size_of::<u32>() as u64)
+            },
+            count: None
+        }
+    }
+}
+
+macro_rules! pattern_board_uniform_binding
+{
+    ($bd:literal) =>
+    {
+        wgpu::BindGroupLayoutEntry
+        {
+            binding:    $bd,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty:         wgpu::BindingType::Buffer
+            {
+                ty:                 wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size:   NonZeroU64::new(4 * std::mem::size_of::<u32>() as u64)
+            },
+            count: None
+        }
+    }
+}
+
+macro_rules! paint_stroke_uniform_binding
+{
+    ($bd:literal) =>
+    {
+        wgpu::BindGroupLayoutEntry
+        {
+            binding:    $bd,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty:         wgpu::BindingType::Buffer
+            {
+                ty:                 wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size:   NonZeroU64::new(12 * std::mem::size_of::<u32>() as u64)
+            },
+            count: None
+        }
+    }
+}
+
+macro_rules! lights_out_click_uniform_binding
+{
+    ($bd:literal) =>
+    {
+        wgpu::BindGroupLayoutEntry
+        {
+            binding:    $bd,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty:         wgpu::BindingType::Buffer
+            {
+                ty:                 wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size:   NonZeroU64::new(8 * std::mem::size_of::<u32>() as u64)
+            },
+            count: None
+        }
+    }
+}
+
 macro_rules! click_rule_uniform_binding
 {
     ($bd:literal) =>
@@ -765,6 +1167,48 @@ macro_rules! click_rule_render_flags_binding
     }
 }
 
+//One click rule for each layer of the board arrays
+macro_rules! click_rule_array_binding
+{
+    ($bd:literal) =>
+    {
+        wgpu::BindGroupLayoutEntry
+        {
+            binding:    $bd,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty:         wgpu::BindingType::Buffer
+            {
+                ty: wgpu::BufferBindingType::Storage
+                {
+                    read_only: true
+                },
+                has_dynamic_offset: false,
+                min_binding_size:   NonZeroU64::new(4 * std::mem::size_of::<i32>() as u64 + 32 * 32 * 2 * std::mem::size_of::<i32>() as u64)
+            },
+            count: None
+        }
+    }
+}
+
+macro_rules! tile_info_uniform_binding
+{
+    ($bd:literal) =>
+    {
+        wgpu::BindGroupLayoutEntry
+        {
+            binding:    $bd,
+            visibility: wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::FRAGMENT,
+            ty:         wgpu::BindingType::Buffer
+            {
+                ty:                 wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size:   NonZeroU64::new(8 * std::mem::size_of::<u32>() as u64)
+            },
+            count: None
+        }
+    }
+}
+
 fn create_main_render_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
 {
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor
@@ -803,6 +1247,30 @@ fn create_clear_default_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGr
     })
 }
 
+fn create_clear_random_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor
+    {
+        label: Some("Clear random bind group layout"),
+        entries:
+        &[
+            random_board_uniform_binding!(0)
+        ]
+    })
+}
+
+fn create_clear_pattern_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor
+    {
+        label: Some("Clear pattern bind group layout"),
+        entries:
+        &[
+            pattern_board_uniform_binding!(0)
+        ]
+    })
+}
+
 fn create_bake_click_rule_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
 {
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor
@@ -901,15 +1369,15 @@ fn create_next_step_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupL
         label: Some("Next step bind group layout"),
         entries:
         &[
-            board_texture_binding!(0),
-            board_texture_binding!(1),
+            board_texture_array_binding!(0),
+            board_texture_array_binding!(1),
 
-            board_image_binding!(2),
-            board_image_binding!(3),
+            board_image_array_binding!(2),
+            board_image_array_binding!(3),
 
             board_texture_binding!(4),
 
-            click_rule_uniform_binding!(5)
+            click_rule_array_binding!(5)
         ]
     })
 }
@@ -927,17 +1395,96 @@ fn create_generate_mip_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGro
     })
 }
 
-fn create_clear_default_pipeline_layout(device: &wgpu::Device, clear_default_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::PipelineLayout
+fn create_tile_info_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor
+    {
+        label: Some("Tile info bind group layout"),
+        entries:
+        &[
+            tile_info_uniform_binding!(0)
+        ]
+    })
+}
+
+fn create_paint_stroke_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor
+    {
+        label: Some("Paint stroke bind group layout"),
+        entries:
+        &[
+            board_texture_binding!(0),
+            board_image_binding!(1)
+        ]
+    })
+}
+
+fn create_paint_stroke_data_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor
+    {
+        label: Some("Paint stroke data bind group layout"),
+        entries:
+        &[
+            paint_stroke_uniform_binding!(0)
+        ]
+    })
+}
+
+fn create_board_image_transform_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor
+    {
+        label: Some("Board image transform bind group layout"),
+        entries:
+        &[
+            board_texture_binding!(0),
+            final_image_mip_binding!(1)
+        ]
+    })
+}
+
+fn create_lights_out_click_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor
+    {
+        label: Some("Lights Out click bind group layout"),
+        entries:
+        &[
+            board_texture_binding!(0),
+            board_texture_binding!(1),
+            board_texture_binding!(2),
+            board_image_binding!(3),
+
+            click_rule_uniform_binding!(4)
+        ]
+    })
+}
+
+fn create_lights_out_click_data_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout
+{
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor
+    {
+        label: Some("Lights Out click data bind group layout"),
+        entries:
+        &[
+            lights_out_click_uniform_binding!(0)
+        ]
+    })
+}
+
+fn create_clear_default_pipeline_layout(device: &wgpu::Device, clear_default_bind_group_layout: &wgpu::BindGroupLayout, tile_info_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::PipelineLayout
 {
     device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
     {
         label: Some("Clear default pipeline layout"),
-        bind_group_layouts: &[&clear_default_bind_group_layout],
+        bind_group_layouts: &[&clear_default_bind_group_layout, &tile_info_bind_group_layout],
         push_constant_ranges: &[]
     })
 }
 
-fn create_main_render_pipeline(device: &wgpu::Device, main_render_bind_group_layout: &wgpu::BindGroupLayout, swapchain_format: wgpu::TextureFormat) -> wgpu::RenderPipeline
+fn create_main_render_pipeline(device: &wgpu::Device, main_render_bind_group_layout: &wgpu::BindGroupLayout, tile_info_bind_group_layout: &wgpu::BindGroupLayout, swapchain_format: wgpu::TextureFormat) -> wgpu::RenderPipeline
 {
     let main_render_state_vs_module = device.create_shader_module(wgpu::include_wgsl!("shaders/render/render_state_vs.wgsl"));
     let main_render_state_fs_module = device.create_shader_module(wgpu::include_wgsl!("shaders/render/render_state_fs.wgsl"));
@@ -945,7 +1492,7 @@ fn create_main_render_pipeline(device: &wgpu::Device, main_render_bind_group_lay
     let main_render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
     {
         label: Some("Main render pipeline layout"),
-        bind_group_layouts: &[&main_render_bind_group_layout],
+        bind_group_layouts: &[&main_render_bind_group_layout, &tile_info_bind_group_layout],
         push_constant_ranges: &[],
     });
 
@@ -1090,14 +1637,54 @@ fn create_clear_center_pipeline(device: &wgpu::Device, clear_default_pipeline_la
     })
 }
 
-fn create_clear_stability_pipeline(device: &wgpu::Device, clear_stability_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
+fn create_clear_random_pipeline(device: &wgpu::Device, clear_default_bind_group_layout: &wgpu::BindGroupLayout, tile_info_bind_group_layout: &wgpu::BindGroupLayout, clear_random_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
+{
+    let clear_random_module = device.create_shader_module(wgpu::include_wgsl!("shaders/clear_board/clear_random.wgsl"));
+
+    let clear_random_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
+    {
+        label: Some("Clear random pipeline layout"),
+        bind_group_layouts: &[&clear_default_bind_group_layout, &tile_info_bind_group_layout, &clear_random_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor
+    {
+        label:       Some("Clear random pipeline"),
+        layout:      Some(&clear_random_pipeline_layout),
+        module:      &clear_random_module,
+        entry_point: "main"
+    })
+}
+
+fn create_clear_pattern_pipeline(device: &wgpu::Device, clear_default_bind_group_layout: &wgpu::BindGroupLayout, tile_info_bind_group_layout: &wgpu::BindGroupLayout, clear_pattern_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
+{
+    let clear_pattern_module = device.create_shader_module(wgpu::include_wgsl!("shaders/clear_board/clear_pattern.wgsl"));
+
+    let clear_pattern_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
+    {
+        label: Some("Clear pattern pipeline layout"),
+        bind_group_layouts: &[&clear_default_bind_group_layout, &tile_info_bind_group_layout, &clear_pattern_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor
+    {
+        label:       Some("Clear pattern pipeline"),
+        layout:      Some(&clear_pattern_pipeline_layout),
+        module:      &clear_pattern_module,
+        entry_point: "main"
+    })
+}
+
+fn create_clear_stability_pipeline(device: &wgpu::Device, clear_stability_bind_group_layout: &wgpu::BindGroupLayout, tile_info_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
 {
     let clear_stability_module = device.create_shader_module(wgpu::include_wgsl!("shaders/state_transform/clear_stability.wgsl"));
 
     let clear_stability_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
     {
         label: Some("Clear stability pipeline layout"),
-        bind_group_layouts: &[&clear_stability_bind_group_layout],
+        bind_group_layouts: &[&clear_stability_bind_group_layout, &tile_info_bind_group_layout],
         push_constant_ranges: &[],
     });
 
@@ -1110,14 +1697,14 @@ fn create_clear_stability_pipeline(device: &wgpu::Device, clear_stability_bind_g
     })
 }
 
-fn create_clear_restriction_pipeline(device: &wgpu::Device, clear_restriction_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
+fn create_clear_restriction_pipeline(device: &wgpu::Device, clear_restriction_bind_group_layout: &wgpu::BindGroupLayout, tile_info_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
 {
     let clear_restriction_module = device.create_shader_module(wgpu::include_wgsl!("shaders/state_transform/clear_restriction.wgsl"));
 
     let clear_restriction_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
     {
         label: Some("Clear stability pipeline layout"),
-        bind_group_layouts: &[&clear_restriction_bind_group_layout],
+        bind_group_layouts: &[&clear_restriction_bind_group_layout, &tile_info_bind_group_layout],
         push_constant_ranges: &[],
     });
 
@@ -1130,14 +1717,14 @@ fn create_clear_restriction_pipeline(device: &wgpu::Device, clear_restriction_bi
     })
 }
 
-fn create_initial_state_transform_pipeline(device: &wgpu::Device, initial_state_transform_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
+fn create_initial_state_transform_pipeline(device: &wgpu::Device, initial_state_transform_bind_group_layout: &wgpu::BindGroupLayout, tile_info_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
 {
     let initial_state_transform_module = device.create_shader_module(wgpu::include_wgsl!("shaders/state_transform/initial_state_transform.wgsl"));
 
     let initial_state_transform_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
     {
         label: Some("Initial transform pipeline layout"),
-        bind_group_layouts: &[&initial_state_transform_bind_group_layout],
+        bind_group_layouts: &[&initial_state_transform_bind_group_layout, &tile_info_bind_group_layout],
         push_constant_ranges: &[]
     });
 
@@ -1150,14 +1737,14 @@ fn create_initial_state_transform_pipeline(device: &wgpu::Device, initial_state_
     })
 }
 
-fn create_initial_restriction_transform_pipeline(device: &wgpu::Device, initial_restriction_transform_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
+fn create_initial_restriction_transform_pipeline(device: &wgpu::Device, initial_restriction_transform_bind_group_layout: &wgpu::BindGroupLayout, tile_info_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
 {
     let initial_restriction_transform_module = device.create_shader_module(wgpu::include_wgsl!("shaders/state_transform/initial_restriction_transform.wgsl"));
 
     let initial_restriction_transform_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
     {
         label: Some("Initial restriction transform pipeline layout"),
-        bind_group_layouts: &[&initial_restriction_transform_bind_group_layout],
+        bind_group_layouts: &[&initial_restriction_transform_bind_group_layout, &tile_info_bind_group_layout],
         push_constant_ranges: &[]
     });
 
@@ -1190,14 +1777,14 @@ fn create_filter_restriction_pipeline(device: &wgpu::Device, filter_restriction_
     })
 }
 
-fn create_next_step_pipeline(device: &wgpu::Device, next_step_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
+fn create_next_step_pipeline(device: &wgpu::Device, next_step_bind_group_layout: &wgpu::BindGroupLayout, tile_info_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
 {
     let next_step_module = device.create_shader_module(wgpu::include_wgsl!("shaders/next_step/next_step.wgsl"));
 
     let next_step_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
     {
         label: Some("Next step pipeline layout"),
-        bind_group_layouts: &[&next_step_bind_group_layout],
+        bind_group_layouts: &[&next_step_bind_group_layout, &tile_info_bind_group_layout],
         push_constant_ranges: &[],
     });
 
@@ -1270,6 +1857,66 @@ fn create_generate_mip_pipeline(device: &wgpu::Device, generate_mip_bind_group_l
     })
 }
 
+fn create_paint_stroke_pipeline(device: &wgpu::Device, paint_stroke_bind_group_layout: &wgpu::BindGroupLayout, tile_info_bind_group_layout: &wgpu::BindGroupLayout, paint_stroke_data_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
+{
+    let paint_stroke_module = device.create_shader_module(wgpu::include_wgsl!("shaders/paint/paint_stroke.wgsl"));
+
+    let paint_stroke_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
+    {
+        label: Some("Paint stroke pipeline layout"),
+        bind_group_layouts: &[&paint_stroke_bind_group_layout, &tile_info_bind_group_layout, &paint_stroke_data_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor
+    {
+        label:       Some("Paint stroke pipeline"),
+        layout:      Some(&paint_stroke_pipeline_layout),
+        module:      &paint_stroke_module,
+        entry_point: "main"
+    })
+}
+
+fn create_board_image_transform_pipeline(device: &wgpu::Device, board_image_transform_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
+{
+    let board_image_transform_module = device.create_shader_module(wgpu::include_wgsl!("shaders/state_transform/board_image_transform.wgsl"));
+
+    let board_image_transform_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
+    {
+        label: Some("Board image transform pipeline layout"),
+        bind_group_layouts: &[&board_image_transform_bind_group_layout],
+        push_constant_ranges: &[]
+    });
+
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor
+    {
+        label:       Some("Board image transform pipeline"),
+        layout:      Some(&board_image_transform_pipeline_layout),
+        module:      &board_image_transform_module,
+        entry_point: "main"
+    })
+}
+
+fn create_lights_out_click_pipeline(device: &wgpu::Device, lights_out_click_bind_group_layout: &wgpu::BindGroupLayout, tile_info_bind_group_layout: &wgpu::BindGroupLayout, lights_out_click_data_bind_group_layout: &wgpu::BindGroupLayout) -> wgpu::ComputePipeline
+{
+    let lights_out_click_module = device.create_shader_module(wgpu::include_wgsl!("shaders/lights_out/lights_out_click.wgsl"));
+
+    let lights_out_click_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
+    {
+        label: Some("Lights Out click pipeline layout"),
+        bind_group_layouts: &[&lights_out_click_bind_group_layout, &tile_info_bind_group_layout, &lights_out_click_data_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor
+    {
+        label:       Some("Lights Out click pipeline"),
+        layout:      Some(&lights_out_click_pipeline_layout),
+        module:      &lights_out_click_module,
+        entry_point: "main"
+    })
+}
+
 fn create_render_state_sampler(device: &wgpu::Device) -> wgpu::Sampler
 {
     device.create_sampler(&wgpu::SamplerDescriptor
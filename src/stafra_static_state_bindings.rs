@@ -1,11 +1,14 @@
 use std::num::NonZeroU32;
 use super::stafra_static_state::StafraStaticState;
+use super::stafra_state::{RandomBoardParams, RandomBoardSymmetry, RandomBoardShape, PatternBoardParams, PatternBoardKind};
 
-//All bindings for the main stafra state that only need to be initialized once: click rule info, spawn buffer data
+//All bindings for the main stafra state that only need to be initialized once: click rule info, spawn buffer data, random and pattern board parameters
 pub struct StafraStaticBindings
 {
     render_click_rule_bind_group: wgpu::BindGroup,
     bake_click_rule_bind_group:   wgpu::BindGroup,
+    clear_random_bind_group:      wgpu::BindGroup,
+    clear_pattern_bind_group:     wgpu::BindGroup,
 
     click_rule_texture: wgpu::Texture,
     click_rule_buffer:  wgpu::Buffer,
@@ -15,7 +18,10 @@ pub struct StafraStaticBindings
 
     spawn_period:      u32,
     spawn_data_flags:  u32,
-    spawn_data_buffer: wgpu::Buffer
+    spawn_data_buffer: wgpu::Buffer,
+
+    random_board_buffer:  wgpu::Buffer,
+    pattern_board_buffer: wgpu::Buffer
 }
 
 impl StafraStaticBindings
@@ -64,10 +70,28 @@ impl StafraStaticBindings
             mapped_at_creation: false
         };
 
+        let random_board_buffer_descriptor = wgpu::BufferDescriptor
+        {
+            label:              Some("Random board buffer"),
+            size:               8 * std::mem::size_of::<u32>() as u64,
+            usage:              wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        };
+
+        let pattern_board_buffer_descriptor = wgpu::BufferDescriptor
+        {
+            label:              Some("Pattern board buffer"),
+            size:               4 * std::mem::size_of::<u32>() as u64,
+            usage:              wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        };
+
         let click_rule_texture             = device.create_texture(&click_rule_texture_descriptor);
         let click_rule_buffer              = device.create_buffer(&click_rule_buffer_descriptor);
         let click_rule_render_flags_buffer = device.create_buffer(&click_rule_render_flags_buffer_descriptor);
         let spawn_data_buffer              = device.create_buffer(&spawn_data_buffer_descriptor);
+        let random_board_buffer            = device.create_buffer(&random_board_buffer_descriptor);
+        let pattern_board_buffer           = device.create_buffer(&pattern_board_buffer_descriptor);
 
         let click_rule_texture_view_descriptor = wgpu::TextureViewDescriptor
         {
@@ -85,11 +109,15 @@ impl StafraStaticBindings
 
         let render_click_rule_bind_group = static_state.create_render_click_rule_bind_group(device, &click_rule_texture_view, &click_rule_render_flags_buffer);
         let bake_click_rule_bind_group   = static_state.create_bake_click_rule_bind_group(device,   &click_rule_texture_view, &click_rule_buffer);
+        let clear_random_bind_group      = static_state.create_clear_random_bind_group(device, &random_board_buffer);
+        let clear_pattern_bind_group     = static_state.create_clear_pattern_bind_group(device, &pattern_board_buffer);
 
         Self
         {
             render_click_rule_bind_group,
             bake_click_rule_bind_group,
+            clear_random_bind_group,
+            clear_pattern_bind_group,
 
             click_rule_texture,
             click_rule_buffer,
@@ -99,7 +127,10 @@ impl StafraStaticBindings
 
             spawn_period:     u32::MAX,
             spawn_data_flags: 0,
-            spawn_data_buffer
+            spawn_data_buffer,
+
+            random_board_buffer,
+            pattern_board_buffer
         }
     }
 
@@ -159,6 +190,17 @@ impl StafraStaticBindings
         self.spawn_data_flags |= dirty_flag;
     }
 
+    pub fn spawn_period(&self) -> u32
+    {
+        self.spawn_period
+    }
+
+    pub fn smooth_transform_enabled(&self) -> bool
+    {
+        let smooth_transform_enable_flag = 0x01;
+        self.spawn_data_flags & smooth_transform_enable_flag != 0
+    }
+
     pub fn update_draw_state(&mut self, queue: &wgpu::Queue)
     {
         let dirty_flag = 0x80000000;
@@ -176,8 +218,8 @@ impl StafraStaticBindings
             let elem_size = std::mem::size_of::<u32>();
 
             let mut buffer_data = [0u8; std::mem::size_of::<u32>() * 2];
-            buffer_data[elem_size * 0..elem_size * 1].copy_from_slice(&self.spawn_period.to_le_bytes());
-            buffer_data[elem_size * 1..elem_size * 2].copy_from_slice(&self.spawn_data_flags.to_le_bytes());
+            buffer_data[0..elem_size].copy_from_slice(&self.spawn_period.to_le_bytes());
+            buffer_data[elem_size..elem_size * 2].copy_from_slice(&self.spawn_data_flags.to_le_bytes());
 
             queue.write_buffer(&self.spawn_data_buffer, 0, &buffer_data);
 
@@ -185,6 +227,72 @@ impl StafraStaticBindings
         }
     }
 
+    //Written immediately, since the random reset goes right after it
+    pub fn set_random_board_params(&self, queue: &wgpu::Queue, params: &RandomBoardParams, board_size: u32)
+    {
+        let symmetry_index: u32 = match params.symmetry
+        {
+            RandomBoardSymmetry::None      => 0,
+            RandomBoardSymmetry::MirrorX   => 1,
+            RandomBoardSymmetry::FourFold  => 2,
+            RandomBoardSymmetry::EightFold => 3
+        };
+
+        let shape_index: u32 = match params.shape
+        {
+            RandomBoardShape::Full   => 0,
+            RandomBoardShape::Disc   => 1,
+            RandomBoardShape::Square => 2
+        };
+
+        let elem_size = std::mem::size_of::<u32>();
+
+        let mut buffer_data = [0u8; std::mem::size_of::<u32>() * 8];
+        buffer_data[0..elem_size].copy_from_slice(&params.seed.to_le_bytes());
+        buffer_data[elem_size..elem_size * 2].copy_from_slice(&params.density_threshold().to_le_bytes());
+        buffer_data[elem_size * 2..elem_size * 3].copy_from_slice(&symmetry_index.to_le_bytes());
+        buffer_data[elem_size * 3..elem_size * 4].copy_from_slice(&shape_index.to_le_bytes());
+        buffer_data[elem_size * 4..elem_size * 5].copy_from_slice(&params.shape_radius(board_size).to_le_bytes());
+
+        queue.write_buffer(&self.random_board_buffer, 0, &buffer_data);
+    }
+
+    pub fn clear_random_bind_group(&self) -> &wgpu::BindGroup
+    {
+        &self.clear_random_bind_group
+    }
+
+    //Written immediately, the same as the random board parameters
+    pub fn set_pattern_board_params(&self, queue: &wgpu::Queue, params: &PatternBoardParams)
+    {
+        let kind_index: u32 = match params.kind
+        {
+            PatternBoardKind::HorizontalLine    => 0,
+            PatternBoardKind::VerticalLine      => 1,
+            PatternBoardKind::Cross             => 2,
+            PatternBoardKind::Diagonals         => 3,
+            PatternBoardKind::Checkerboard      => 4,
+            PatternBoardKind::ConcentricSquares => 5,
+            PatternBoardKind::ConcentricCircles => 6,
+            PatternBoardKind::SingleCell        => 7
+        };
+
+        let elem_size = std::mem::size_of::<u32>();
+
+        let mut buffer_data = [0u8; std::mem::size_of::<u32>() * 4];
+        buffer_data[0..elem_size].copy_from_slice(&kind_index.to_le_bytes());
+        buffer_data[elem_size..elem_size * 2].copy_from_slice(&std::cmp::max(params.size, 1).to_le_bytes());
+        buffer_data[elem_size * 2..elem_size * 3].copy_from_slice(&params.x.to_le_bytes());
+        buffer_data[elem_size * 3..elem_size * 4].copy_from_slice(&params.y.to_le_bytes());
+
+        queue.write_buffer(&self.pattern_board_buffer, 0, &buffer_data);
+    }
+
+    pub fn clear_pattern_bind_group(&self) -> &wgpu::BindGroup
+    {
+        &self.clear_pattern_bind_group
+    }
+
     pub fn click_rule_buffer_binding(&self) -> wgpu::BufferBinding
     {
         self.click_rule_buffer.as_entire_buffer_binding()
@@ -0,0 +1,32 @@
+//Boards and click rules shared by the unit tests
+
+//The click rule with the given cells enabled, the center is at (15, 15)
+pub fn click_rule_from_cells(cells: &[(usize, usize)]) -> [u8; 32 * 32]
+{
+    let mut click_rule_data = [0u8; 32 * 32];
+    for (x, y) in cells
+    {
+        click_rule_data[y * 32 + x] = 1;
+    }
+
+    click_rule_data
+}
+
+//The default click rule: the center cell and its four neighbors
+pub fn cross_click_rule() -> [u8; 32 * 32]
+{
+    click_rule_from_cells(&[(15, 15), (14, 15), (16, 15), (15, 14), (15, 16)])
+}
+
+//Xorshift, so the boards are the same on every run
+pub fn random_board(cell_count: usize, seed: u64) -> Vec<u8>
+{
+    let mut state = seed | 1;
+    (0..cell_count).map(|_|
+    {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state & 1) as u8
+    }).collect()
+}